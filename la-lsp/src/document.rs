@@ -0,0 +1,529 @@
+//! Analysis of open text documents.
+
+use crate::LineIndex;
+
+use la_parse::Cst;
+use la_parse::Element;
+use la_parse::Node;
+use la_parse::NodeKind;
+use la_parse::Scope;
+use la_parse::Span;
+use la_parse::SyntaxToken;
+use la_parse::TokenKind;
+use la_parse::parse_cst;
+use la_term::symbol::Symbols;
+
+////////////////////////////////////////////////////////////////////////////////
+// Documents
+
+/// Text document that the client has opened.
+pub struct Document
+{
+    /// The current contents of the document.
+    pub text: String,
+
+    /// The version number assigned by the client.
+    pub version: i64,
+
+    /// Lines of [`text`](`Document::text`).
+    pub line_index: LineIndex,
+}
+
+impl Document
+{
+    /// Create a document with the given contents.
+    pub fn new(text: String, version: i64) -> Self
+    {
+        let line_index = LineIndex::new(&text);
+        Self{text, version, line_index}
+    }
+
+    /// Parse the document.
+    pub fn cst(&self) -> Cst<'_>
+    {
+        parse_cst(&self.text)
+    }
+}
+
+/// Where the identifier at the given offset is, if there is one.
+///
+/// An offset just past the end of an identifier also counts,
+/// so that the identifier under a cursor placed after it is found.
+/// The name of the pattern variable in a blank, such as the `x`
+/// in `x_Integer`, also counts as an identifier.
+pub fn identifier_at(cst: &Cst, offset: usize) -> Option<Span>
+{
+    cst.root.descendant_tokens().into_iter()
+        .filter_map(|token| identifier_span(cst, token))
+        .find(|span| span.start <= offset && offset <= span.end)
+}
+
+/// Where the identifier in a token is, if it has one.
+fn identifier_span(cst: &Cst, token: &SyntaxToken) -> Option<Span>
+{
+    match token.kind {
+        TokenKind::Identifier => Some(token.span.clone()),
+        TokenKind::Blank => {
+            let name = cst.text(token.span.clone()).find('_')?;
+            let span = token.span.start .. token.span.start + name;
+            if span.is_empty() { None } else { Some(span) }
+        },
+        _ => None,
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Definitions
+
+/// Statement of the form `name := rhs` or `name(x, y) := rhs`.
+pub struct Definition
+{
+    /// The name being defined.
+    pub name: String,
+
+    /// Where the name is on the left-hand side.
+    pub name_span: Span,
+
+    /// Whether parameters are given on the left-hand side.
+    pub is_function: bool,
+
+    /// Documentation comments preceding the definition.
+    pub doc: Option<String>,
+}
+
+/// The definitions in a program.
+///
+/// Definitions whose left-hand side is malformed are left out.
+pub fn definitions(cst: &Cst) -> Vec<Definition>
+{
+    let mut definitions = Vec::new();
+    for (node, doc) in cst.statements() {
+        if node.kind != NodeKind::Definition {
+            continue;
+        }
+        let lhs = match node.nodes().next() {
+            Some(lhs) => lhs,
+            None => continue,
+        };
+        if let Some((name, parameters)) = definition_head(lhs) {
+            definitions.push(Definition{
+                name: cst.text(name.span.clone()).to_owned(),
+                name_span: name.span.clone(),
+                is_function: parameters.is_some(),
+                doc,
+            });
+        }
+    }
+    definitions
+}
+
+/// Find the name and parameters on the left-hand side of `:=`.
+///
+/// This accepts the same left-hand sides as the parser does,
+/// but works on the syntax tree so the tokens can be found.
+fn definition_head(lhs: &Node)
+    -> Option<(&SyntaxToken, Option<Vec<&SyntaxToken>>)>
+{
+    let lhs = unparenthesize(lhs);
+    match lhs.kind {
+        NodeKind::Name => Some((name_token(lhs)?, None)),
+        NodeKind::Application => {
+            let mut nodes = lhs.nodes();
+            let function = name_token(unparenthesize(nodes.next()?))?;
+            let parameters =
+                nodes.next()?.nodes()
+                .map(|argument| name_token(unparenthesize(argument)))
+                .collect::<Option<_>>()?;
+            Some((function, Some(parameters)))
+        },
+        _ => None,
+    }
+}
+
+fn unparenthesize(mut node: &Node) -> &Node
+{
+    while node.kind == NodeKind::Parenthesized {
+        match node.nodes().next() {
+            Some(inner) => node = inner,
+            None => break,
+        }
+    }
+    node
+}
+
+/// The span of the name in a [`Parameter`](`NodeKind::Parameter`) node.
+fn parameter_span(node: &Node) -> Option<Span>
+{
+    node.tokens()
+        .find(|token| token.kind == TokenKind::Identifier)
+        .map(|token| token.span.clone())
+}
+
+fn name_token(node: &Node) -> Option<&SyntaxToken>
+{
+    if node.kind != NodeKind::Name {
+        return None;
+    }
+    node.tokens().find(|token| token.kind == TokenKind::Identifier)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Bindings
+
+/// Occurrences of parameters in a program.
+///
+/// Parameters are bound by lambdas and by function definitions,
+/// variables are bound by `let`, `with`, and `where`,
+/// and pattern variables are bound by conditions and rules.
+/// Each occurrence of a parameter is paired with the parameter
+/// that it refers to, which is found by resolving the name
+/// through a [`Scope`], just like the parser does.
+/// Parameters themselves are paired with themselves.
+pub struct Bindings
+{
+    occurrences: Vec<(Span, Span)>,
+}
+
+impl Bindings
+{
+    /// Find the parameters in a program and their occurrences.
+    ///
+    /// Symbols for the names of parameters are taken from the table.
+    pub fn new(symbols: &Symbols, cst: &Cst) -> Self
+    {
+        let mut resolver = Resolver{
+            symbols,
+            cst,
+            binders: Vec::new(),
+            occurrences: Vec::new(),
+        };
+        let scope = Scope::new(None, []);
+        for (node, _) in cst.statements() {
+            resolver.statement(&scope, node);
+        }
+        let mut occurrences = resolver.occurrences;
+        occurrences.sort_by_key(|(occurrence, _)| occurrence.start);
+        Self{occurrences}
+    }
+
+    /// The parameter that the identifier at the given span refers to.
+    pub fn declaration(&self, span: &Span) -> Option<&Span>
+    {
+        self.occurrences.iter()
+            .find(|(occurrence, _)| occurrence == span)
+            .map(|(_, declaration)| declaration)
+    }
+
+    /// The occurrences of the given parameter,
+    /// including the parameter itself, in source order.
+    pub fn occurrences<'a>(&'a self, declaration: &'a Span)
+        -> impl Iterator<Item=&'a Span>
+    {
+        self.occurrences.iter()
+            .filter(move |(_, d)| d == declaration)
+            .map(|(occurrence, _)| occurrence)
+    }
+}
+
+/// State of the walk over the syntax tree that finds bindings.
+struct Resolver<'a>
+{
+    symbols: &'a Symbols,
+    cst: &'a Cst<'a>,
+
+    /// The parameters that are in scope, indexed by De Bruijn index.
+    binders: Vec<Span>,
+
+    occurrences: Vec<(Span, Span)>,
+}
+
+impl<'a> Resolver<'a>
+{
+    fn statement(&mut self, scope: &Scope, node: &Node)
+    {
+        let mut nodes = node.nodes();
+        let (lhs, rhs) = match (node.kind, nodes.next(), nodes.next()) {
+            (NodeKind::Definition, Some(lhs), Some(rhs)) => (lhs, rhs),
+            _ => return self.node(scope, node),
+        };
+        match definition_head(lhs) {
+            Some((_, Some(parameters))) => {
+                let parameters: Vec<_> =
+                    parameters.iter().map(|p| p.span.clone()).collect();
+                self.bind(scope, &parameters, rhs);
+            },
+            _ => {
+                self.node(scope, lhs);
+                self.node(scope, rhs);
+            },
+        }
+    }
+
+    fn node(&mut self, scope: &Scope, node: &Node)
+    {
+        match node.kind {
+
+            NodeKind::Lambda => {
+                let parameters: Vec<_> =
+                    node.nodes()
+                    .filter(|child| child.kind == NodeKind::Parameter)
+                    .filter_map(parameter_span)
+                    .collect();
+                for body in node.nodes() {
+                    if body.kind != NodeKind::Parameter {
+                        self.bind(scope, &parameters, body);
+                    }
+                }
+            },
+
+            NodeKind::Name => {
+                let token = match name_token(node) {
+                    Some(token) => token,
+                    None => return,
+                };
+                let name = self.cst.text(token.span.clone());
+                let name = self.symbols.get(name.as_bytes());
+                if let Some(de_bruijn) = scope.get(&name) {
+                    let index = de_bruijn.0 as usize;
+                    let declaration = self.binders[index].clone();
+                    self.occur(token.span.clone(), declaration);
+                }
+            },
+
+            NodeKind::Condition | NodeKind::Rule => {
+                let mut nodes = node.nodes();
+                let (pattern, body) = match (nodes.next(), nodes.next()) {
+                    (Some(pattern), Some(body)) => (pattern, body),
+                    _ => return,
+                };
+                self.node(scope, pattern);
+
+                // Later occurrences of a pattern variable
+                // refer to the first one.
+                let mut variables: Vec<Span> = Vec::new();
+                for span in self.pattern_variables(pattern) {
+                    let name = self.cst.text(span.clone());
+                    let first = variables.iter()
+                        .find(|first| self.cst.text((*first).clone()) == name);
+                    match first {
+                        Some(first) => self.occur(span, first.clone()),
+                        None => variables.push(span),
+                    }
+                }
+                self.bind(scope, &variables, body);
+            },
+
+            NodeKind::Let | NodeKind::Where => {
+                let mut nodes: Vec<_> = node.nodes().collect();
+                let is_where = node.kind == NodeKind::Where;
+                if nodes.is_empty() {
+                    return;
+                }
+                let body =
+                    if is_where { nodes.remove(0) }
+                    else { nodes.pop().unwrap() };
+                let is_rec = node.tokens().any(|t| t.kind == TokenKind::Rec);
+                let is_let = node.tokens().next()
+                    .map(|t| t.kind == TokenKind::Let) == Some(true);
+
+                if is_let && !is_rec {
+                    return self.sequential(scope, &nodes, body);
+                }
+
+                let binders: Vec<_> =
+                    nodes.iter()
+                    .filter_map(|binding| binding.nodes().next())
+                    .filter_map(parameter_span)
+                    .collect();
+                // The binders themselves are skipped.
+                let values = || nodes.iter().flat_map(|b| b.nodes().skip(1));
+                if !is_rec {
+                    for value in values() {
+                        self.node(scope, value);
+                    }
+                }
+                self.bind_with(scope, &binders, |this, inner| {
+                    if is_rec {
+                        for value in values() {
+                            this.node(inner, value);
+                        }
+                    }
+                    this.node(inner, body);
+                });
+            },
+
+            _ => for child in &node.children {
+                if let Element::Node(child) = child {
+                    self.node(scope, child);
+                }
+            },
+
+        }
+    }
+
+    /// The names of the blanks in a pattern, in source order,
+    /// like [`pattern_variables`](`la_parse::pattern_variables`).
+    fn pattern_variables(&self, pattern: &Node) -> Vec<Span>
+    {
+        let mut variables = Vec::new();
+        let mut stack = vec![pattern];
+        while let Some(node) = stack.pop() {
+            match node.kind {
+                NodeKind::Pattern => {
+                    let blank = node.tokens().next();
+                    let name = blank.and_then(|b| identifier_span(self.cst, b));
+                    variables.extend(name);
+                },
+                NodeKind::Condition => stack.extend(node.nodes().next()),
+                NodeKind::Lambda => (),
+                _ => {
+                    let children: Vec<_> = node.nodes().collect();
+                    stack.extend(children.into_iter().rev());
+                },
+            }
+        }
+        variables
+    }
+
+    /// Record an occurrence of a parameter, unless it was recorded already.
+    fn occur(&mut self, occurrence: Span, declaration: Span)
+    {
+        let pair = (occurrence, declaration);
+        if !self.occurrences.contains(&pair) {
+            self.occurrences.push(pair);
+        }
+    }
+
+    /// Walk the bindings and the body of a `let`,
+    /// each binding in the scope of the ones before it.
+    fn sequential(&mut self, scope: &Scope, bindings: &[&Node], body: &Node)
+    {
+        let (binding, rest) = match bindings.split_first() {
+            Some(split) => split,
+            None => return self.node(scope, body),
+        };
+        let mut nodes = binding.nodes();
+        let binder = nodes.next().and_then(parameter_span);
+        for value in nodes {
+            self.node(scope, value);
+        }
+        let binders: Vec<_> = binder.into_iter().collect();
+        self.bind_with(scope, &binders, |this, inner| {
+            this.sequential(inner, rest, body);
+        });
+    }
+
+    /// Walk the body of a lambda, function definition, or condition
+    /// with its parameters in scope.
+    fn bind(&mut self, scope: &Scope, parameters: &[Span], body: &Node)
+    {
+        self.bind_with(scope, parameters, |this, scope| this.node(scope, body));
+    }
+
+    /// Like [`bind`](`Self::bind`), but walk whatever the closure walks.
+    fn bind_with<F>(&mut self, scope: &Scope, parameters: &[Span], walk: F)
+        where F: FnOnce(&mut Self, &Scope)
+    {
+        let names = parameters.iter().map(|parameter| {
+            let name = self.cst.text(parameter.clone());
+            self.symbols.get(name.as_bytes())
+        });
+        let scope = Scope::new(Some(scope), names);
+
+        // The parameters are numbered before the ones already in scope.
+        let outer = self.binders.len();
+        self.binders.splice(0 .. 0, parameters.iter().cloned());
+        for parameter in parameters {
+            self.occur(parameter.clone(), parameter.clone());
+        }
+
+        walk(self, &scope);
+
+        let inner = self.binders.len() - outer;
+        self.binders.drain(0 .. inner);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn occurrences(source: &str, offset: usize) -> Vec<Span>
+    {
+        let symbols = Symbols::new();
+        let cst = parse_cst(source);
+        let bindings = Bindings::new(&symbols, &cst);
+        let span = identifier_at(&cst, offset).unwrap();
+        match bindings.declaration(&span) {
+            Some(declaration) =>
+                bindings.occurrences(declaration).cloned().collect(),
+            None => vec![],
+        }
+    }
+
+    #[test]
+    fn bindings()
+    {
+        let source = "f(x, y) := |y| x + y; g(x)";
+        assert_eq!(occurrences(source, 2), [2 .. 3, 15 .. 16]);
+        let unused_y: Span = 5 .. 6;
+        assert_eq!(occurrences(source, 5), [unused_y]);
+        assert_eq!(occurrences(source, 12), [12 .. 13, 19 .. 20]);
+        assert_eq!(occurrences(source, 20), [12 .. 13, 19 .. 20]);
+        assert_eq!(occurrences(source, 24), []);
+        assert_eq!(occurrences(source, 0), []);
+
+        let source = "|x, y| |z| f(x, y, z)";
+        assert_eq!(occurrences(source, 4), [4 .. 5, 16 .. 17]);
+        assert_eq!(occurrences(source, 8), [8 .. 9, 19 .. 20]);
+
+        // Bindings are found despite errors elsewhere.
+        let source = "|x| (x + ; x";
+        assert_eq!(occurrences(source, 5), [1 .. 2, 5 .. 6]);
+        assert_eq!(occurrences(source, 11), []);
+
+        let source = "f(x_, x_Integer, ys__) /; g(x, ys, |x| x) /; h(x)";
+        let x = [2 .. 3, 6 .. 7, 28 .. 29, 47 .. 48];
+        assert_eq!(occurrences(source, 2), x);
+        assert_eq!(occurrences(source, 7), x);
+        assert_eq!(occurrences(source, 47), x);
+        assert_eq!(occurrences(source, 18), [17 .. 19, 31 .. 33]);
+        assert_eq!(occurrences(source, 39), [36 .. 37, 39 .. 40]);
+        assert_eq!(identifier_at(&parse_cst(source), 9), None);
+
+        let source = "e /. f(x_) :> x + y /. x -> y";
+        assert_eq!(occurrences(source, 7), [7 .. 8, 14 .. 15]);
+        assert_eq!(occurrences(source, 23), []);
+
+        let source = "let x = 1, y = x in x + y";
+        assert_eq!(occurrences(source, 4), [4 .. 5, 15 .. 16, 20 .. 21]);
+        assert_eq!(occurrences(source, 24), [11 .. 12, 24 .. 25]);
+
+        let source = "|x| with x = x in x";
+        assert_eq!(occurrences(source, 13), [1 .. 2, 13 .. 14]);
+        assert_eq!(occurrences(source, 18), [9 .. 10, 18 .. 19]);
+
+        let source = "let rec f = |n| f(n) in f(1)";
+        assert_eq!(occurrences(source, 8), [8 .. 9, 16 .. 17, 24 .. 25]);
+
+        let source = "f(x) where x = g(x)";
+        assert_eq!(occurrences(source, 2), [2 .. 3, 11 .. 12]);
+        assert_eq!(occurrences(source, 17), []);
+    }
+
+    #[test]
+    fn definitions()
+    {
+        let source = "## A.\na := 1; (f)((x)) := x; f(1) := 2; g";
+        let cst = parse_cst(source);
+        let definitions = super::definitions(&cst);
+        let summary: Vec<_> =
+            definitions.iter()
+            .map(|d| (&d.name[..], d.name_span.clone(), d.is_function))
+            .collect();
+        assert_eq!(summary, [("a", 6 .. 7, false), ("f", 15 .. 16, true)]);
+        assert_eq!(definitions[0].doc.as_deref(), Some("A."));
+        assert_eq!(definitions[1].doc, None);
+    }
+}
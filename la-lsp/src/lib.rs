@@ -0,0 +1,18 @@
+//! Language server for Libre Algebra source code.
+//!
+//! The server speaks the Language Server Protocol over a byte stream,
+//! normally standard input and output; see [`run`].
+//! It reports parse errors, and it provides
+//! go-to-definition, hover, completion, and highlighting.
+
+#![warn(missing_docs)]
+
+pub use self::document::*;
+pub use self::protocol::*;
+pub use self::reference::*;
+pub use self::server::*;
+
+mod document;
+mod protocol;
+mod reference;
+mod server;
@@ -0,0 +1,285 @@
+//! Encoding of messages and positions in the Language Server Protocol.
+
+use la_parse::Span;
+use serde_json::Value;
+use serde_json::json;
+use std::io;
+use std::io::BufRead;
+use std::io::Read;
+use std::io::Write;
+
+////////////////////////////////////////////////////////////////////////////////
+// Messages
+
+/// The greatest `Content-Length` of a message that is read.
+///
+/// This is far more than any source file needs, but keeps
+/// a bad header from making the server run out of memory.
+pub const MAX_CONTENT_LENGTH: usize = 64 * 1024 * 1024;
+
+/// Read the body of a message.
+///
+/// Each message is preceded by headers, of which only `Content-Length`
+/// is interpreted. Returns [`None`] at the end of the input.
+/// Messages longer than [`MAX_CONTENT_LENGTH`] are rejected.
+pub fn read_message<R>(input: &mut R) -> io::Result<Option<Vec<u8>>>
+    where R: BufRead
+{
+    let mut content_length: Option<usize> = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                content_length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let content_length = content_length.ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidData,
+        "message without valid Content-Length header",
+    ))?;
+
+    if content_length > MAX_CONTENT_LENGTH {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "message with too large Content-Length header",
+        ));
+    }
+
+    // The body is read into a growing buffer,
+    // so that a message cut short does not allocate its full length.
+    let mut body = Vec::new();
+    input.by_ref().take(content_length as u64).read_to_end(&mut body)?;
+    if body.len() != content_length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(Some(body))
+}
+
+/// Write a message, preceded by its headers.
+pub fn write_message<W>(output: &mut W, message: &Value) -> io::Result<()>
+    where W: Write
+{
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+}
+
+/// Error that is sent in response to a request.
+#[derive(Debug)]
+pub struct ResponseError
+{
+    /// One of the error codes defined by JSON-RPC.
+    pub code: i64,
+
+    /// Human-readable description of the error.
+    pub message: String,
+}
+
+impl ResponseError
+{
+    /// The message is not valid JSON.
+    pub const PARSE_ERROR: i64 = -32700;
+
+    /// The message is not a valid request,
+    /// or the request is not valid in the current state.
+    pub const INVALID_REQUEST: i64 = -32600;
+
+    /// The request method is not supported.
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+
+    /// The request parameters are not valid.
+    pub const INVALID_PARAMS: i64 = -32602;
+
+    /// Create an error with the given code and message.
+    pub fn new(code: i64, message: impl Into<String>) -> Self
+    {
+        Self{code, message: message.into()}
+    }
+
+    /// Create an error about the request parameters.
+    pub fn invalid_params() -> Self
+    {
+        Self::new(Self::INVALID_PARAMS, "invalid parameters")
+    }
+}
+
+/// Create a response to the request with the given ID.
+pub fn response(id: Value, result: Result<Value, ResponseError>) -> Value
+{
+    match result {
+        Ok(result) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": result,
+        }),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": error.code, "message": error.message},
+        }),
+    }
+}
+
+/// Create a notification.
+pub fn notification(method: &str, params: Value) -> Value
+{
+    json!({
+        "jsonrpc": "2.0",
+        "method": method,
+        "params": params,
+    })
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Positions
+
+/// Location in a text document, as defined by the protocol.
+///
+/// Characters are counted in UTF-16 code units.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Position
+{
+    pub line: u32,
+    pub character: u32,
+}
+
+impl Position
+{
+    /// Decode a position.
+    pub fn from_json(value: &Value) -> Option<Self>
+    {
+        let line = value.get("line")?.as_u64()?.try_into().ok()?;
+        let character = value.get("character")?.as_u64()?.try_into().ok()?;
+        Some(Self{line, character})
+    }
+
+    /// Encode the position.
+    pub fn to_json(self) -> Value
+    {
+        json!({"line": self.line, "character": self.character})
+    }
+}
+
+/// Conversion between byte offsets and positions in a text document.
+pub struct LineIndex
+{
+    /// Byte offset of the start of each line.
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex
+{
+    /// Find the lines in a text.
+    pub fn new(text: &str) -> Self
+    {
+        let mut line_starts = vec![0];
+        line_starts.extend(text.match_indices('\n').map(|(i, _)| i + 1));
+        Self{line_starts}
+    }
+
+    /// The position at the given byte offset in the text.
+    pub fn position(&self, text: &str, offset: usize) -> Position
+    {
+        let offset = offset.min(text.len());
+        let line = self.line_starts.partition_point(|&s| s <= offset) - 1;
+        let start = self.line_starts[line];
+        let character = text[start .. offset].encode_utf16().count();
+        Position{line: line as u32, character: character as u32}
+    }
+
+    /// The byte offset at the given position in the text.
+    ///
+    /// Positions past the end of a line are clamped to the end of the line.
+    pub fn offset(&self, text: &str, position: Position) -> usize
+    {
+        let line = position.line as usize;
+        let start = match self.line_starts.get(line) {
+            Some(&start) => start,
+            None => return text.len(),
+        };
+        let end = self.line_starts.get(line + 1)
+            .map_or(text.len(), |&next| next - 1);
+
+        let mut units = 0;
+        for (i, char) in text[start .. end].char_indices() {
+            if units >= position.character as usize {
+                return start + i;
+            }
+            units += char.len_utf16();
+        }
+        end
+    }
+
+    /// Encode the range of text at the given span.
+    pub fn range(&self, text: &str, span: Span) -> Value
+    {
+        json!({
+            "start": self.position(text, span.start).to_json(),
+            "end": self.position(text, span.end).to_json(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use std::io::Cursor;
+
+    #[test]
+    fn messages()
+    {
+        let mut output = Vec::new();
+        write_message(&mut output, &json!({"a": 1})).unwrap();
+        write_message(&mut output, &json!([])).unwrap();
+        assert_eq!(
+            String::from_utf8(output.clone()).unwrap(),
+            "Content-Length: 7\r\n\r\n{\"a\":1}Content-Length: 2\r\n\r\n[]",
+        );
+
+        let mut input = Cursor::new(output);
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), b"{\"a\":1}");
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), b"[]");
+        assert_eq!(read_message(&mut input).unwrap(), None);
+
+        let error = |input: &str| {
+            read_message(&mut Cursor::new(input)).unwrap_err().kind()
+        };
+        assert_eq!(error("\r\n{}"), io::ErrorKind::InvalidData);
+        assert_eq!(error("Content-Length: 99999999999999\r\n\r\n{}"),
+                   io::ErrorKind::InvalidData);
+        assert_eq!(error("Content-Length: 3\r\n\r\n{}"),
+                   io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn positions()
+    {
+        let text = "ab\n\u{1D465} = c\n";
+        let index = LineIndex::new(text);
+        let cases = [
+            (0, Position{line: 0, character: 0}),
+            (2, Position{line: 0, character: 2}),
+            (3, Position{line: 1, character: 0}),
+            (7, Position{line: 1, character: 2}),
+            (11, Position{line: 1, character: 6}),
+            (12, Position{line: 2, character: 0}),
+        ];
+        for (offset, position) in cases {
+            assert_eq!(index.position(text, offset), position);
+            assert_eq!(index.offset(text, position), offset);
+        }
+        assert_eq!(index.offset(text, Position{line: 0, character: 9}), 2);
+        assert_eq!(index.offset(text, Position{line: 9, character: 0}), 12);
+    }
+}
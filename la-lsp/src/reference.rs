@@ -0,0 +1,217 @@
+//! Loading of the reference documentation.
+
+use la_parse::Lexer;
+use la_parse::Scope;
+use la_parse::Statement;
+use la_parse::parse_program;
+use la_term::Term;
+use la_term::View;
+use la_term::symbol::Symbols;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Entry in the reference documentation.
+///
+/// Entries are written in Libre Algebra itself, as terms of the form
+/// `List(Name("Power"), Syntax("x ^ y"), Meaning("...", Markup("x")))`.
+/// Components other than `Name`, `Syntax`, and `Meaning` are ignored,
+/// as are syntax examples that are not strings.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Entry
+{
+    /// The name of the symbol that is documented.
+    pub name: String,
+
+    /// Ways to write terms with the symbol.
+    pub syntax: Vec<String>,
+
+    /// Description of the symbol.
+    ///
+    /// Markup in the description is rendered as inline code.
+    pub meaning: String,
+}
+
+impl Entry
+{
+    /// Convert a term to an entry, if it is one.
+    pub fn from_term(term: &Term) -> Option<Self>
+    {
+        let components = application(term, b"List")?;
+
+        let mut name = None;
+        let mut syntax = Vec::new();
+        let mut meaning = String::new();
+        for component in components {
+            if let Some([value]) = application(component, b"Name") {
+                name = Some(string(value)?);
+            } else if let Some([value]) = application(component, b"Syntax") {
+                syntax.extend(string(value));
+            } else if let Some(parts) = application(component, b"Meaning") {
+                for part in parts {
+                    if let Some([markup]) = application(part, b"Markup") {
+                        meaning.push('`');
+                        meaning.push_str(&string(markup)?);
+                        meaning.push('`');
+                    } else {
+                        meaning.push_str(&string(part)?);
+                    }
+                }
+            }
+        }
+
+        Some(Self{name: name?, syntax, meaning})
+    }
+
+    /// Render the entry as Markdown, for display on hover.
+    pub fn to_markdown(&self) -> String
+    {
+        let mut markdown = String::new();
+        if !self.syntax.is_empty() {
+            markdown.push_str("```librealgebra\n");
+            for syntax in &self.syntax {
+                markdown.push_str(syntax);
+                markdown.push('\n');
+            }
+            markdown.push_str("```\n\n");
+        }
+        markdown.push_str(&self.meaning);
+        markdown
+    }
+}
+
+/// The arguments of an application of the symbol with the given name.
+fn application<'a>(term: &'a Term, name: &[u8]) -> Option<&'a [Term]>
+{
+    match term.view() {
+        View::Application(function, arguments) =>
+            function.as_symbol()
+                .filter(|symbol| symbol.name() == name)
+                .map(|_| arguments),
+        _ => None,
+    }
+}
+
+fn string(term: &Term) -> Option<String>
+{
+    match term.view() {
+        View::String(bytes) => Some(String::from_utf8_lossy(bytes).into()),
+        _ => None,
+    }
+}
+
+/// Reference documentation, indexed by symbol name.
+#[derive(Debug, Default)]
+pub struct Reference
+{
+    /// The entries, indexed by [`Entry::name`].
+    pub entries: BTreeMap<String, Entry>,
+}
+
+impl Reference
+{
+    /// Create a reference without entries.
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Add the entries in the `.la` files in the given directory.
+    ///
+    /// Files that do not parse are skipped.
+    pub fn load(&mut self, symbols: &Symbols, directory: &Path)
+        -> io::Result<()>
+    {
+        for file in fs::read_dir(directory)? {
+            let path = file?.path();
+            if path.extension().map(|e| e == "la") == Some(true) {
+                let source = fs::read_to_string(&path)?;
+                let _ = self.add_source(symbols, &source);
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the entries in the given source code.
+    ///
+    /// Statements that are not entries are ignored.
+    pub fn add_source(&mut self, symbols: &Symbols, source: &str)
+        -> la_parse::Result<()>
+    {
+        let scope = Scope::new(None, []);
+        let mut lex = Lexer::new(source);
+        for statement in parse_program(symbols, &scope, &mut lex)? {
+            if let Statement::Term(term) = statement {
+                if let Some(entry) = Entry::from_term(&term) {
+                    self.entries.insert(entry.name.clone(), entry);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The entry for the symbol with the given name, if any.
+    pub fn get(&self, name: &str) -> Option<&Entry>
+    {
+        self.entries.get(name)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use std::path::PathBuf;
+
+    #[test]
+    fn entries()
+    {
+        let symbols = Symbols::new();
+        let mut reference = Reference::new();
+        let source = r#"
+            List(
+                Name("Sqrt"),
+                Syntax("Sqrt(x)"),
+                Syntax(SqrtMarkup("x")),
+                Meaning("The square root of ", Markup("x"), "."),
+                Example(Sqrt(4)),
+            );
+            List(Name("Undocumented"));
+            List(Syntax("Nameless"));
+            Sqrt(4)
+        "#;
+        reference.add_source(&symbols, source).unwrap();
+
+        let names: Vec<_> = reference.entries.keys().collect();
+        assert_eq!(names, ["Sqrt", "Undocumented"]);
+
+        let entry = reference.get("Sqrt").unwrap();
+        assert_eq!(entry.syntax, ["Sqrt(x)"]);
+        assert_eq!(entry.meaning, "The square root of `x`.");
+        assert_eq!(
+            entry.to_markdown(),
+            "```librealgebra\nSqrt(x)\n```\n\nThe square root of `x`.",
+        );
+        assert_eq!(reference.get("Undocumented").unwrap().to_markdown(), "");
+    }
+
+    #[test]
+    fn load()
+    {
+        let symbols = Symbols::new();
+        let mut reference = Reference::new();
+        let directory: PathBuf =
+            [env!("CARGO_MANIFEST_DIR"), "..", "la-reference", "definition"]
+            .iter().collect();
+        reference.load(&symbols, &directory).unwrap();
+
+        let power = reference.get("Power").unwrap();
+        assert_eq!(power.syntax, ["Power(x, y)", "x ^ y"]);
+        assert_eq!(
+            power.meaning,
+            "Represents `x` raised to the power of `y`.",
+        );
+    }
+}
@@ -0,0 +1,782 @@
+//! Handling of requests and notifications.
+
+use crate::Bindings;
+use crate::Document;
+use crate::Position;
+use crate::Reference;
+use crate::ResponseError;
+use crate::definitions;
+use crate::identifier_at;
+use crate::notification;
+use crate::read_message;
+use crate::response;
+use crate::write_message;
+
+use la_parse::Scope;
+use la_parse::Span;
+use la_parse::TokenKind;
+use la_simplify::Constants;
+use la_simplify::builtins::Builtins;
+use la_term::symbol::Symbols;
+use serde_json::Value;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::io;
+use std::io::BufRead;
+use std::io::Write;
+use std::path::Path;
+
+/// Kinds of completion items, as defined by the protocol.
+const COMPLETION_FUNCTION: u32 = 3;
+const COMPLETION_CONSTANT: u32 = 21;
+
+/// Kinds of document highlights, as defined by the protocol.
+const HIGHLIGHT_TEXT: u32 = 1;
+const HIGHLIGHT_READ: u32 = 2;
+const HIGHLIGHT_WRITE: u32 = 3;
+
+/// Read messages from the input and write replies to the output,
+/// until the client sends the `exit` notification.
+///
+/// Returns the exit status the process should exit with:
+/// zero if the client asked the server to shut down before exiting,
+/// and nonzero otherwise, including if the input ends prematurely.
+pub fn run<R, W>(server: &mut Server, mut input: R, mut output: W)
+    -> io::Result<i32>
+    where R: BufRead
+        , W: Write
+{
+    while let Some(body) = read_message(&mut input)? {
+        let replies = match serde_json::from_slice(&body) {
+            Ok(message) => server.handle(&message),
+            Err(error) => {
+                let code = ResponseError::PARSE_ERROR;
+                let error = ResponseError::new(code, error.to_string());
+                vec![response(Value::Null, Err(error))]
+            },
+        };
+        for reply in &replies {
+            write_message(&mut output, reply)?;
+        }
+        output.flush()?;
+        if let Some(exit_code) = server.exit_code() {
+            return Ok(exit_code);
+        }
+    }
+    Ok(1)
+}
+
+/// State of the language server.
+///
+/// The server is independent of how messages are transported;
+/// [`run`] implements the transport over byte streams.
+pub struct Server
+{
+    symbols: Symbols,
+    constants: Constants,
+    builtins: Builtins,
+    reference: Reference,
+
+    /// The documents that are open, by URI.
+    documents: BTreeMap<String, Document>,
+
+    /// Whether the `shutdown` request was received.
+    shutdown: bool,
+
+    /// Whether the `exit` notification was received.
+    exit: bool,
+}
+
+impl Server
+{
+    /// Create a server without reference documentation.
+    pub fn new() -> Self
+    {
+        let symbols = Symbols::new();
+        let constants = Constants::new(&symbols);
+        let builtins = Builtins::new(&constants);
+        Self{
+            symbols,
+            constants,
+            builtins,
+            reference: Reference::new(),
+            documents: BTreeMap::new(),
+            shutdown: false,
+            exit: false,
+        }
+    }
+
+    /// Add the reference documentation in the given directory,
+    /// which is shown on hover.
+    pub fn load_reference(&mut self, directory: &Path) -> io::Result<()>
+    {
+        self.reference.load(&self.symbols, directory)
+    }
+
+    /// Add the reference documentation in the given source code.
+    pub fn add_reference(&mut self, source: &str) -> la_parse::Result<()>
+    {
+        self.reference.add_source(&self.symbols, source)
+    }
+
+    /// The exit status, once the client has sent `exit`.
+    pub fn exit_code(&self) -> Option<i32>
+    {
+        match (self.exit, self.shutdown) {
+            (false, _) => None,
+            (true, true) => Some(0),
+            (true, false) => Some(1),
+        }
+    }
+
+    /// Handle a message from the client.
+    ///
+    /// Returns the messages to send to the client in reply,
+    /// which consist of the response if the message is a request,
+    /// and any notifications.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value>
+    {
+        let method = message.get("method").and_then(Value::as_str);
+        let params = message.get("params").unwrap_or(&Value::Null);
+        match (message.get("id"), method) {
+            (Some(id), Some(method)) => {
+                let result = if self.shutdown {
+                    let code = ResponseError::INVALID_REQUEST;
+                    Err(ResponseError::new(code, "server is shut down"))
+                } else {
+                    self.request(method, params)
+                };
+                vec![response(id.clone(), result)]
+            },
+            (None, Some(method)) =>
+                self.notification(method, params),
+            (Some(id), None) => {
+                let code = ResponseError::INVALID_REQUEST;
+                let error = ResponseError::new(code, "missing method");
+                vec![response(id.clone(), Err(error))]
+            },
+            // Responses to requests from the server; it sends none.
+            (None, None) =>
+                vec![],
+        }
+    }
+
+    fn request(&mut self, method: &str, params: &Value)
+        -> Result<Value, ResponseError>
+    {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": 1,
+                    "definitionProvider": true,
+                    "hoverProvider": true,
+                    "completionProvider": {},
+                    "documentHighlightProvider": true,
+                },
+                "serverInfo": {"name": "librealgebra-lsp"},
+            })),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            },
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/completion" => self.completion(params),
+            "textDocument/documentHighlight" => self.highlight(params),
+            _ => {
+                let code = ResponseError::METHOD_NOT_FOUND;
+                let message = format!("unsupported method `{}`", method);
+                Err(ResponseError::new(code, message))
+            },
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value>
+    {
+        let uri = params["textDocument"]["uri"].as_str().map(str::to_owned);
+        let version = params["textDocument"]["version"].as_i64();
+        match (method, uri) {
+            ("exit", _) => {
+                self.exit = true;
+                vec![]
+            },
+            ("textDocument/didOpen", Some(uri)) => {
+                let text = &params["textDocument"]["text"];
+                let text = text.as_str().unwrap_or_default().to_owned();
+                let document = Document::new(text, version.unwrap_or(0));
+                self.documents.insert(uri.clone(), document);
+                vec![self.diagnostics(&uri)]
+            },
+            ("textDocument/didChange", Some(uri)) => {
+                // Only full synchronization is advertised,
+                // so the last change contains the entire text.
+                let changes = params["contentChanges"].as_array();
+                let text = changes.and_then(|c| c.last())
+                    .and_then(|change| change["text"].as_str());
+                match (self.documents.get_mut(&uri), text) {
+                    (Some(document), Some(text)) => {
+                        let version = version.unwrap_or(document.version);
+                        *document = Document::new(text.to_owned(), version);
+                        vec![self.diagnostics(&uri)]
+                    },
+                    _ => vec![],
+                }
+            },
+            ("textDocument/didClose", Some(uri)) => {
+                self.documents.remove(&uri);
+                vec![self.diagnostics(&uri)]
+            },
+            _ => vec![],
+        }
+    }
+
+    /// Create the notification that reports the errors in a document.
+    ///
+    /// Syntax errors are reported all at once, thanks to error recovery.
+    /// Only if there are none is the first remaining error reported,
+    /// such as a malformed left-hand side of `:=`.
+    fn diagnostics(&self, uri: &str) -> Value
+    {
+        let mut diagnostics = Vec::new();
+        let mut version = None;
+        if let Some(document) = self.documents.get(uri) {
+            let cst = document.cst();
+            let scope = Scope::new(None, []);
+            let mut errors = cst.errors.clone();
+            if errors.is_empty() {
+                errors.extend(cst.to_statements(&self.symbols, &scope).err());
+            }
+            for error in errors {
+                let range =
+                    document.line_index.range(&document.text, error.span);
+                diagnostics.push(json!({
+                    "range": range,
+                    "severity": 1,
+                    "source": "librealgebra",
+                    "message": error.message,
+                }));
+            }
+            version = Some(document.version);
+        }
+        notification("textDocument/publishDiagnostics", json!({
+            "uri": uri,
+            "version": version,
+            "diagnostics": diagnostics,
+        }))
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    // Language features
+
+    /// Go to the parameter that an identifier refers to,
+    /// or to the definitions of the symbol in the open documents.
+    fn definition(&self, params: &Value) -> Result<Value, ResponseError>
+    {
+        let (uri, document, offset) = self.document_position(params)?;
+        let cst = document.cst();
+        let span = match identifier_at(&cst, offset) {
+            Some(span) => span,
+            None => return Ok(Value::Null),
+        };
+
+        let bindings = Bindings::new(&self.symbols, &cst);
+        if let Some(declaration) = bindings.declaration(&span) {
+            return Ok(location(uri, document, declaration.clone()));
+        }
+
+        let name = cst.text(span.clone());
+        let mut locations = Vec::new();
+        for (uri, document) in &self.documents {
+            for definition in definitions(&document.cst()) {
+                if definition.name == name {
+                    let span = definition.name_span;
+                    locations.push(location(uri, document, span));
+                }
+            }
+        }
+        Ok(Value::Array(locations))
+    }
+
+    /// Describe the parameter or symbol under the cursor.
+    ///
+    /// Symbols are described by the reference documentation,
+    /// and by the documentation comments of their definitions.
+    fn hover(&self, params: &Value) -> Result<Value, ResponseError>
+    {
+        let (_, document, offset) = self.document_position(params)?;
+        let cst = document.cst();
+        let span = match identifier_at(&cst, offset) {
+            Some(span) => span,
+            None => return Ok(Value::Null),
+        };
+        let name = cst.text(span.clone());
+
+        let mut sections = Vec::new();
+        let bindings = Bindings::new(&self.symbols, &cst);
+        if bindings.declaration(&span).is_some() {
+            sections.push(format!("`{}` is a parameter.", name));
+        } else {
+            if let Some(entry) = self.reference.get(name) {
+                sections.push(entry.to_markdown());
+            }
+            for document in self.documents.values() {
+                for definition in definitions(&document.cst()) {
+                    if definition.name == name {
+                        sections.extend(definition.doc);
+                    }
+                }
+            }
+        }
+
+        if sections.is_empty() {
+            return Ok(Value::Null);
+        }
+        let range =
+            document.line_index.range(&document.text, span.clone());
+        Ok(json!({
+            "contents": {
+                "kind": "markdown",
+                "value": sections.join("\n\n---\n\n"),
+            },
+            "range": range,
+        }))
+    }
+
+    /// Complete the identifier under the cursor
+    /// with the names of builtins, constants, and definitions.
+    fn completion(&self, params: &Value) -> Result<Value, ResponseError>
+    {
+        let (_, document, offset) = self.document_position(params)?;
+        let cst = document.cst();
+        let prefix = match identifier_at(&cst, offset) {
+            Some(span) => cst.text(span.start .. offset),
+            None => "",
+        };
+
+        let mut items = BTreeMap::new();
+        let builtins = self.builtins.iter().map(|(symbol, _)| symbol);
+        for symbol in builtins {
+            let label = String::from_utf8_lossy(symbol.name()).into_owned();
+            items.insert(label, (COMPLETION_FUNCTION, None));
+        }
+        for symbol in self.constants.symbols() {
+            let label = String::from_utf8_lossy(symbol.name()).into_owned();
+            items.entry(label).or_insert((COMPLETION_CONSTANT, None));
+        }
+        for document in self.documents.values() {
+            for definition in definitions(&document.cst()) {
+                let kind = if definition.is_function {
+                    COMPLETION_FUNCTION
+                } else {
+                    COMPLETION_CONSTANT
+                };
+                let item = items.entry(definition.name).or_insert((kind, None));
+                if item.1.is_none() {
+                    item.1 = definition.doc;
+                }
+            }
+        }
+
+        let items: Vec<_> =
+            items.into_iter()
+            .filter(|(label, _)| label.starts_with(prefix))
+            .map(|(label, (kind, doc))| {
+                let entry = self.reference.get(&label);
+                let doc = entry.map(|entry| entry.to_markdown()).or(doc);
+                let mut item = json!({"label": label, "kind": kind});
+                if let Some(doc) = doc {
+                    item["documentation"] =
+                        json!({"kind": "markdown", "value": doc});
+                }
+                item
+            })
+            .collect();
+        Ok(Value::Array(items))
+    }
+
+    /// Highlight the occurrences of the parameter under the cursor,
+    /// or of the symbol under the cursor.
+    fn highlight(&self, params: &Value) -> Result<Value, ResponseError>
+    {
+        let (_, document, offset) = self.document_position(params)?;
+        let cst = document.cst();
+        let span = match identifier_at(&cst, offset) {
+            Some(span) => span,
+            None => return Ok(Value::Null),
+        };
+
+        let bindings = Bindings::new(&self.symbols, &cst);
+        let highlights: Vec<(Span, u32)> =
+            match bindings.declaration(&span) {
+                Some(declaration) =>
+                    bindings.occurrences(declaration)
+                    .map(|span| {
+                        let kind = if span == declaration {
+                            HIGHLIGHT_WRITE
+                        } else {
+                            HIGHLIGHT_READ
+                        };
+                        (span.clone(), kind)
+                    })
+                    .collect(),
+                None => {
+                    let name = cst.text(span.clone());
+                    cst.root.descendant_tokens().into_iter()
+                        .filter(|t| t.kind == TokenKind::Identifier)
+                        .filter(|t| cst.text(t.span.clone()) == name)
+                        .filter(|t| bindings.declaration(&t.span).is_none())
+                        .map(|t| (t.span.clone(), HIGHLIGHT_TEXT))
+                        .collect()
+                },
+            };
+
+        let highlights = highlights.into_iter().map(|(span, kind)| json!({
+            "range": document.line_index.range(&document.text, span),
+            "kind": kind,
+        }));
+        Ok(highlights.collect())
+    }
+
+    /// Find the document and offset that a request is about.
+    fn document_position<'a>(&'a self, params: &'a Value)
+        -> Result<(&'a str, &'a Document, usize), ResponseError>
+    {
+        let uri = params["textDocument"]["uri"].as_str()
+            .ok_or_else(ResponseError::invalid_params)?;
+        let position = Position::from_json(&params["position"])
+            .ok_or_else(ResponseError::invalid_params)?;
+        let document = self.documents.get(uri).ok_or_else(|| {
+            let code = ResponseError::INVALID_PARAMS;
+            let message = format!("document `{}` is not open", uri);
+            ResponseError::new(code, message)
+        })?;
+        let offset = document.line_index.offset(&document.text, position);
+        Ok((uri, document, offset))
+    }
+}
+
+impl Default for Server
+{
+    fn default() -> Self
+    {
+        Self::new()
+    }
+}
+
+fn location(uri: &str, document: &Document, span: Span) -> Value
+{
+    json!({
+        "uri": uri,
+        "range": document.line_index.range(&document.text, span),
+    })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    /// Client that talks to a server in the same process.
+    struct Client
+    {
+        server: Server,
+        next_id: i64,
+    }
+
+    impl Client
+    {
+        fn new() -> Self
+        {
+            let mut client = Self{server: Server::new(), next_id: 0};
+            client.request("initialize", json!({"capabilities": {}}));
+            client.notify("initialized", json!({}));
+            client
+        }
+
+        /// Send a request and return its response.
+        fn request(&mut self, method: &str, params: Value) -> Value
+        {
+            self.next_id += 1;
+            let request = json!({
+                "jsonrpc": "2.0",
+                "id": self.next_id,
+                "method": method,
+                "params": params,
+            });
+            let mut replies = self.server.handle(&request);
+            assert_eq!(replies.len(), 1);
+            assert_eq!(replies[0]["id"], self.next_id);
+            replies.remove(0)
+        }
+
+        /// Send a notification and return the notifications in reply.
+        fn notify(&mut self, method: &str, params: Value) -> Vec<Value>
+        {
+            self.server.handle(&notification(method, params))
+        }
+
+        fn open(&mut self, uri: &str, text: &str) -> Value
+        {
+            let params = json!({"textDocument": {
+                "uri": uri,
+                "languageId": "librealgebra",
+                "version": 1,
+                "text": text,
+            }});
+            let mut replies = self.notify("textDocument/didOpen", params);
+            assert_eq!(replies.len(), 1);
+            replies.remove(0)
+        }
+
+        /// Send a request about a position and return its result.
+        fn at(&mut self, method: &str, uri: &str, line: u32, character: u32)
+            -> Value
+        {
+            let params = json!({
+                "textDocument": {"uri": uri},
+                "position": {"line": line, "character": character},
+            });
+            self.request(method, params)["result"].take()
+        }
+    }
+
+    fn range(a: (u32, u32), b: (u32, u32)) -> Value
+    {
+        json!({
+            "start": {"line": a.0, "character": a.1},
+            "end": {"line": b.0, "character": b.1},
+        })
+    }
+
+    #[test]
+    fn diagnostics()
+    {
+        let mut client = Client::new();
+
+        let published = client.open("file:///a.la", "f(x\n+ ;\ng(1) := 2");
+        assert_eq!(published["method"], "textDocument/publishDiagnostics");
+        assert_eq!(published["params"]["uri"], "file:///a.la");
+        assert_eq!(published["params"]["version"], 1);
+        let diagnostics = published["params"]["diagnostics"].as_array();
+        let ranges: Vec<_> =
+            diagnostics.unwrap().iter()
+            .map(|diagnostic| diagnostic["range"].clone())
+            .collect();
+        assert_eq!(ranges, [range((1, 2), (1, 3))]);
+
+        // Once the syntax errors are fixed, other errors are reported.
+        let params = json!({
+            "textDocument": {"uri": "file:///a.la", "version": 2},
+            "contentChanges": [{"text": "f(x);\ng(1) := 2"}],
+        });
+        let published = client.notify("textDocument/didChange", params);
+        let diagnostic = &published[0]["params"]["diagnostics"][0];
+        assert_eq!(diagnostic["range"], range((1, 0), (1, 4)));
+        assert_eq!(diagnostic["severity"], 1);
+        assert_eq!(published[0]["params"]["version"], 2);
+
+        let params = json!({
+            "textDocument": {"uri": "file:///a.la"},
+            "contentChanges": [{"text": "f(x)"}],
+        });
+        let published = client.notify("textDocument/didChange", params);
+        assert_eq!(published[0]["params"]["diagnostics"], json!([]));
+
+        let params = json!({"textDocument": {"uri": "file:///a.la"}});
+        let published = client.notify("textDocument/didClose", params);
+        assert_eq!(published[0]["params"]["diagnostics"], json!([]));
+        assert_eq!(published[0]["params"]["version"], Value::Null);
+    }
+
+    #[test]
+    fn definition()
+    {
+        let mut client = Client::new();
+        client.open("file:///a.la", "## Squares.\nf(x) := x ^ 2;\nf(3)");
+        client.open("file:///b.la", "g := f(f(1))");
+
+        let locations =
+            client.at("textDocument/definition", "file:///b.la", 0, 5);
+        assert_eq!(locations, json!([
+            {"uri": "file:///a.la", "range": range((1, 0), (1, 1))},
+        ]));
+
+        // Parameters are defined where they are bound.
+        let location =
+            client.at("textDocument/definition", "file:///a.la", 1, 8);
+        assert_eq!(location, json!(
+            {"uri": "file:///a.la", "range": range((1, 2), (1, 3))}
+        ));
+
+        let locations =
+            client.at("textDocument/definition", "file:///b.la", 0, 0);
+        assert_eq!(locations, json!([
+            {"uri": "file:///b.la", "range": range((0, 0), (0, 1))},
+        ]));
+
+        let locations =
+            client.at("textDocument/definition", "file:///b.la", 0, 3);
+        assert_eq!(locations, Value::Null);
+    }
+
+    #[test]
+    fn hover()
+    {
+        let mut client = Client::new();
+        let directory: PathBuf =
+            [env!("CARGO_MANIFEST_DIR"), "..", "la-reference", "definition"]
+            .iter().collect();
+        client.server.load_reference(&directory).unwrap();
+        client.open("file:///a.la", "## Cubes.\nf(x) := Power(x, 3)");
+
+        let hover = client.at("textDocument/hover", "file:///a.la", 1, 10);
+        assert_eq!(hover["contents"]["kind"], "markdown");
+        assert_eq!(
+            hover["contents"]["value"],
+            "```librealgebra\nPower(x, y)\nx ^ y\n```\n\n\
+             Represents `x` raised to the power of `y`.",
+        );
+        assert_eq!(hover["range"], range((1, 8), (1, 13)));
+
+        let hover = client.at("textDocument/hover", "file:///a.la", 1, 0);
+        assert_eq!(hover["contents"]["value"], "Cubes.");
+
+        let hover = client.at("textDocument/hover", "file:///a.la", 1, 14);
+        assert_eq!(hover["contents"]["value"], "`x` is a parameter.");
+
+        let hover = client.at("textDocument/hover", "file:///a.la", 1, 17);
+        assert_eq!(hover, Value::Null);
+    }
+
+    #[test]
+    fn completion()
+    {
+        let mut client = Client::new();
+        let reference = r#"List(
+            Name("Sin"),
+            Syntax("Sin(x)"),
+            Meaning("The sine of ", Markup("x"), "."),
+        )"#;
+        client.server.add_reference(reference).unwrap();
+        client.open("file:///a.la", "## Sine squared.\nSin2(x) := Si");
+
+        let items = client.at("textDocument/completion", "file:///a.la", 1, 13);
+        let labels: Vec<_> =
+            items.as_array().unwrap().iter()
+            .map(|item| (item["label"].clone(), item["kind"].clone()))
+            .collect();
+        assert_eq!(labels, [
+            (json!("Sin"), json!(COMPLETION_FUNCTION)),
+            (json!("Sin2"), json!(COMPLETION_FUNCTION)),
+        ]);
+        assert_eq!(
+            items[0]["documentation"]["value"],
+            "```librealgebra\nSin(x)\n```\n\nThe sine of `x`.",
+        );
+        assert_eq!(items[1]["documentation"]["value"], "Sine squared.");
+
+        let items = client.at("textDocument/completion", "file:///a.la", 0, 0);
+        let labels: Vec<_> =
+            items.as_array().unwrap().iter()
+            .map(|item| item["label"].as_str().unwrap())
+            .collect();
+        assert!(labels.contains(&"Add"));
+        assert!(labels.contains(&"Pi"));
+        assert!(labels.contains(&"Sin2"));
+    }
+
+    #[test]
+    fn highlight()
+    {
+        let mut client = Client::new();
+        client.open("file:///a.la", "|x, y| Add(x, |x| x, y, x)");
+
+        let highlights =
+            client.at("textDocument/documentHighlight", "file:///a.la", 0, 1);
+        assert_eq!(highlights, json!([
+            {"range": range((0, 1), (0, 2)), "kind": 3},
+            {"range": range((0, 11), (0, 12)), "kind": 2},
+            {"range": range((0, 24), (0, 25)), "kind": 2},
+        ]));
+
+        let highlights =
+            client.at("textDocument/documentHighlight", "file:///a.la", 0, 18);
+        assert_eq!(highlights, json!([
+            {"range": range((0, 15), (0, 16)), "kind": 3},
+            {"range": range((0, 18), (0, 19)), "kind": 2},
+        ]));
+
+        let highlights =
+            client.at("textDocument/documentHighlight", "file:///a.la", 0, 8);
+        assert_eq!(highlights, json!([
+            {"range": range((0, 7), (0, 10)), "kind": 1},
+        ]));
+    }
+
+    #[test]
+    fn errors()
+    {
+        let mut client = Client::new();
+
+        let reply = client.request("workspace/symbol", json!({}));
+        assert_eq!(reply["error"]["code"], ResponseError::METHOD_NOT_FOUND);
+
+        let reply = client.request("textDocument/hover", json!({}));
+        assert_eq!(reply["error"]["code"], ResponseError::INVALID_PARAMS);
+
+        let params = json!({
+            "textDocument": {"uri": "file:///closed.la"},
+            "position": {"line": 0, "character": 0},
+        });
+        let reply = client.request("textDocument/hover", params);
+        assert_eq!(reply["error"]["code"], ResponseError::INVALID_PARAMS);
+
+        assert_eq!(client.server.exit_code(), None);
+        let reply = client.request("shutdown", Value::Null);
+        assert_eq!(reply["result"], Value::Null);
+        let reply = client.request("textDocument/hover", json!({}));
+        assert_eq!(reply["error"]["code"], ResponseError::INVALID_REQUEST);
+        client.notify("exit", Value::Null);
+        assert_eq!(client.server.exit_code(), Some(0));
+    }
+
+    #[test]
+    fn run()
+    {
+        let mut input = Vec::new();
+        let messages = [
+            json!({"jsonrpc": "2.0", "id": 1, "method": "initialize",
+                   "params": {}}),
+            json!({"jsonrpc": "2.0", "method": "textDocument/didOpen",
+                   "params": {"textDocument": {"uri": "file:///a.la",
+                                               "version": 1, "text": "("}}}),
+        ];
+        for message in &messages {
+            write_message(&mut input, message).unwrap();
+        }
+        input.extend(b"Content-Length: 3\r\n\r\n{,}");
+        write_message(&mut input, &notification("exit", Value::Null)).unwrap();
+
+        let mut server = Server::new();
+        let mut output = Vec::new();
+        let status = super::run(&mut server, Cursor::new(input), &mut output);
+        assert_eq!(status.unwrap(), 1);
+
+        let mut output = Cursor::new(output);
+        let mut replies = Vec::new();
+        while let Some(body) = read_message(&mut output).unwrap() {
+            replies.push(serde_json::from_slice::<Value>(&body).unwrap());
+        }
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0]["id"], 1);
+        assert_eq!(replies[0]["result"]["capabilities"]["hoverProvider"], true);
+        assert_eq!(replies[1]["params"]["diagnostics"][0]["message"],
+                   "expected `(`, `-`, `<|`, `[`, `let`, `with`, \
+                    `{`, `|`, `π`, `√`, blank, identifier, \
+                    integer or string, found end of input");
+        assert_eq!(replies[2]["error"]["code"], ResponseError::PARSE_ERROR);
+    }
+}
@@ -0,0 +1,73 @@
+use la_term::Term;
+use la_term::lambda::Parameter;
+use la_term::symbol::Symbol;
+use la_term::symbol::Symbols;
+use std::rc::Rc;
+
+/// Kind of local binding construct.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Binding
+{
+    /// `let x = a, y = b in body`.
+    ///
+    /// Each value is in the scope of the binders before it,
+    /// and the body is in the scope of all binders.
+    Sequential,
+
+    /// `with x = a, y = b in body` or `body where x = a, y = b`.
+    ///
+    /// The values are in the enclosing scope,
+    /// and the body is in the scope of all binders.
+    Parallel,
+
+    /// `let rec x = a, y = b in body`.
+    ///
+    /// The values and the body are in the scope of all binders.
+    Recursive,
+}
+
+/// Create the term for a local binding construct,
+/// as described at [`parse_term`](`crate::parse_term`).
+///
+/// The values and the body must have been parsed
+/// in the scopes described by the kind of construct.
+pub(crate) fn binding_term(
+    symbols: &Symbols,
+    kind: Binding,
+    binders: Vec<Parameter>,
+    values: Vec<Term>,
+    body: Term,
+) -> Term
+{
+    match kind {
+
+        Binding::Sequential =>
+            binders.into_iter().zip(values).rev()
+            .fold(body, |body, (binder, value)| {
+                Term::application(Term::lambda([binder].into(), body), [value])
+            }),
+
+        Binding::Parallel =>
+            Term::application(Term::lambda(binders.into(), body), values),
+
+        Binding::Recursive => {
+            let binders: Rc<[Parameter]> = binders.into();
+            let list = symbols.get(b"List").term();
+            let values = Term::application(list, values);
+            let values = Term::lambda(binders.clone(), values);
+            let body = Term::lambda(binders, body);
+            let let_rec = symbols.get(b"LetRec").term();
+            Term::application(let_rec, [values, body])
+        },
+
+    }
+}
+
+/// Error message about a binder that is bound twice in one construct.
+pub(crate) fn duplicate_binder(name: &Symbol) -> String
+{
+    format!(
+        "variable `{}` is bound more than once",
+        String::from_utf8_lossy(name.name()),
+    )
+}
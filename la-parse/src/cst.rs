@@ -0,0 +1,1844 @@
+use crate::Associativity;
+use crate::Binding;
+use crate::Error;
+use crate::Infix;
+use crate::Lexer;
+use crate::Notation;
+use crate::Operand;
+use crate::PRECEDENCE_NEGATE;
+use crate::Result;
+use crate::Scope;
+use crate::Span;
+use crate::Statement;
+use crate::TERM;
+use crate::Token;
+use crate::binding_term;
+use crate::blank_pattern;
+use crate::condition_pattern;
+use crate::definition_head;
+use crate::duplicate_binder;
+use crate::is_symbol;
+use crate::make_operand;
+use crate::negate;
+use crate::notation_associativity;
+use crate::notation_level;
+use crate::notation_operator;
+use crate::optional_pattern;
+use crate::power;
+use crate::replace_head;
+use crate::rule_head;
+use crate::rule_pattern;
+use crate::square_root;
+
+use la_term::Term;
+use la_term::View;
+use la_term::lambda::Parameter;
+use la_term::lambda::Strictness;
+use la_term::symbol::Symbols;
+use logos::Logos;
+use std::collections::HashMap;
+
+////////////////////////////////////////////////////////////////////////////////
+// Trees
+
+/// Kind of a token in a concrete syntax tree.
+///
+/// The kinds correspond to the variants of [`Token`],
+/// except that literals do not carry their values,
+/// and that there is an additional kind for whitespace.
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum TokenKind
+{
+    Whitespace,
+    Comment,
+    DocComment,
+    Comma,
+    Semicolon,
+    Colon,
+    ColonEqual,
+    ColonGreater,
+    Equal,
+    Pipe,
+    LessPipe,
+    PipeGreater,
+    Tilde,
+    LeftParenthesis,
+    RightParenthesis,
+    LeftBracket,
+    LeftDoubleBracket,
+    RightBracket,
+    LeftBrace,
+    RightBrace,
+    Plus,
+    Minus,
+    MinusGreater,
+    Asterisk,
+    Slash,
+    SlashSemicolon,
+    SlashDot,
+    SlashSlashDot,
+    Caret,
+    SquareRoot,
+    Pi,
+    EqualEqual,
+    ExclamationEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    In,
+    Infix,
+    Infixl,
+    Infixr,
+    Let,
+    Rec,
+    Where,
+    With,
+    Integer,
+    Superscript,
+    String,
+    Identifier,
+    Operator,
+    Blank,
+    Error,
+}
+
+impl TokenKind
+{
+    /// The kind of the given token.
+    pub fn of_token(token: &Token) -> Self
+    {
+        match token {
+            Token::Error => Self::Error,
+            Token::Comment => Self::Comment,
+            Token::DocComment(..) => Self::DocComment,
+            Token::Comma => Self::Comma,
+            Token::Semicolon => Self::Semicolon,
+            Token::Colon => Self::Colon,
+            Token::ColonEqual => Self::ColonEqual,
+            Token::ColonGreater => Self::ColonGreater,
+            Token::Equal => Self::Equal,
+            Token::Pipe => Self::Pipe,
+            Token::LessPipe => Self::LessPipe,
+            Token::PipeGreater => Self::PipeGreater,
+            Token::Tilde => Self::Tilde,
+            Token::LeftParenthesis => Self::LeftParenthesis,
+            Token::RightParenthesis => Self::RightParenthesis,
+            Token::LeftBracket => Self::LeftBracket,
+            Token::LeftDoubleBracket => Self::LeftDoubleBracket,
+            Token::RightBracket => Self::RightBracket,
+            Token::LeftBrace => Self::LeftBrace,
+            Token::RightBrace => Self::RightBrace,
+            Token::Plus => Self::Plus,
+            Token::Minus => Self::Minus,
+            Token::MinusGreater => Self::MinusGreater,
+            Token::Asterisk => Self::Asterisk,
+            Token::Slash => Self::Slash,
+            Token::SlashSemicolon => Self::SlashSemicolon,
+            Token::SlashDot => Self::SlashDot,
+            Token::SlashSlashDot => Self::SlashSlashDot,
+            Token::Caret => Self::Caret,
+            Token::SquareRoot => Self::SquareRoot,
+            Token::Pi => Self::Pi,
+            Token::EqualEqual => Self::EqualEqual,
+            Token::ExclamationEqual => Self::ExclamationEqual,
+            Token::Less => Self::Less,
+            Token::LessEqual => Self::LessEqual,
+            Token::Greater => Self::Greater,
+            Token::GreaterEqual => Self::GreaterEqual,
+            Token::In => Self::In,
+            Token::Infix => Self::Infix,
+            Token::Infixl => Self::Infixl,
+            Token::Infixr => Self::Infixr,
+            Token::Let => Self::Let,
+            Token::Rec => Self::Rec,
+            Token::Where => Self::Where,
+            Token::With => Self::With,
+            Token::Integer(..) => Self::Integer,
+            Token::Superscript(..) => Self::Superscript,
+            Token::String(..) => Self::String,
+            Token::Identifier(..) => Self::Identifier,
+            Token::Operator(..) => Self::Operator,
+            Token::Blank(..) => Self::Blank,
+        }
+    }
+
+    /// Whether tokens of this kind are whitespace or comments.
+    pub fn is_trivia(self) -> bool
+    {
+        matches!(self, Self::Whitespace | Self::Comment | Self::DocComment)
+    }
+}
+
+/// Kind of a node in a concrete syntax tree.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum NodeKind
+{
+    /// Statements separated by `;`.
+    Program,
+
+    /// `lhs := rhs`.
+    Definition,
+
+    /// `infixl 6 "⊕" = DirectSum`.
+    Notation,
+
+    /// `a ∘ b`, or a chain `a ∘ b ∘ c` that the parser
+    /// collects into a single application; see [`Infix::flat`].
+    Infix,
+
+    /// `-a`.
+    Negate,
+
+    /// `√a`.
+    SquareRoot,
+
+    /// `(a)`.
+    Parenthesized,
+
+    /// `|x, y| body`.
+    Lambda,
+
+    /// `x` or `~x` in the parameter list of a lambda,
+    /// or as the binder of a binding.
+    Parameter,
+
+    /// `let x = a in body`, `let rec x = a in body`
+    /// or `with x = a in body`.
+    Let,
+
+    /// `body where x = a`.
+    Where,
+
+    /// `x = a` in `let`, `with` or `where`.
+    Binding,
+
+    /// `f(a, b)`.
+    Application,
+
+    /// `(a, b)` in an application.
+    Arguments,
+
+    /// `{a, b}` or `[a, b]`.
+    List,
+
+    /// `<| k -> v, ... |>`.
+    Association,
+
+    /// `m[[i, j]]`.
+    Part,
+
+    /// `[[i, j]]` in a part extraction.
+    Indices,
+
+    /// `a²`.
+    Superscript,
+
+    /// Integer or string.
+    Literal,
+
+    /// Identifier, which stands for a symbol or a variable,
+    /// or `π`, which stands for `Pi`.
+    Name,
+
+    /// Blank such as `x_`, or `x_ : default`.
+    Pattern,
+
+    /// `pattern /; test`.
+    Condition,
+
+    /// `lhs -> rhs` or `lhs :> rhs`.
+    Rule,
+
+    /// `term /. rules` or `term //. rules`.
+    Replace,
+
+    /// Input that could not be parsed.
+    ///
+    /// This node is empty where something was missing.
+    Error,
+}
+
+/// Token in a concrete syntax tree.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SyntaxToken
+{
+    /// What the token is.
+    pub kind: TokenKind,
+
+    /// Where in the source code the token is.
+    pub span: Span,
+}
+
+/// Child of a node in a concrete syntax tree.
+#[allow(missing_docs)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Element
+{
+    Node(Node),
+    Token(SyntaxToken),
+}
+
+impl Element
+{
+    /// Where in the source code the element is.
+    pub fn span(&self) -> Span
+    {
+        match self {
+            Self::Node(node) => node.span.clone(),
+            Self::Token(token) => token.span.clone(),
+        }
+    }
+}
+
+/// Node in a concrete syntax tree.
+///
+/// Whitespace and comments before the first token
+/// and after the last token of a node
+/// are children of an ancestor of the node.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Node
+{
+    /// What the node is.
+    pub kind: NodeKind,
+
+    /// Where in the source code the node is.
+    pub span: Span,
+
+    /// The nodes and tokens that make up the node, in source order.
+    pub children: Vec<Element>,
+}
+
+impl Node
+{
+    /// Create a node, computing its span from its children.
+    ///
+    /// If there are no children, the span is empty and starts at `at`.
+    pub fn new(kind: NodeKind, children: Vec<Element>, at: usize) -> Self
+    {
+        let span = match (children.first(), children.last()) {
+            (Some(first), Some(last)) => first.span().start .. last.span().end,
+            _ => at .. at,
+        };
+        Self{kind, span, children}
+    }
+
+    /// The child nodes of the node.
+    pub fn nodes(&self) -> impl Iterator<Item=&Node>
+    {
+        self.children.iter().filter_map(|child| match child {
+            Element::Node(node) => Some(node),
+            Element::Token(..) => None,
+        })
+    }
+
+    /// The child tokens of the node that are not trivia.
+    pub fn tokens(&self) -> impl Iterator<Item=&SyntaxToken>
+    {
+        self.children.iter().filter_map(|child| match child {
+            Element::Token(token) if !token.kind.is_trivia() => Some(token),
+            _ => None,
+        })
+    }
+
+    /// All tokens in the node, including trivia and those of descendants,
+    /// in source order.
+    pub fn descendant_tokens(&self) -> Vec<&SyntaxToken>
+    {
+        fn go<'a>(node: &'a Node, tokens: &mut Vec<&'a SyntaxToken>)
+        {
+            for child in &node.children {
+                match child {
+                    Element::Node(node) => go(node, tokens),
+                    Element::Token(token) => tokens.push(token),
+                }
+            }
+        }
+        let mut tokens = Vec::new();
+        go(self, &mut tokens);
+        tokens
+    }
+
+    /// The innermost descendant node, or the node itself,
+    /// whose span contains the given offset.
+    pub fn node_at(&self, offset: usize) -> &Node
+    {
+        self.nodes()
+            .find(|node| node.span.contains(&offset))
+            .map_or(self, |node| node.node_at(offset))
+    }
+}
+
+/// Concrete syntax tree of a program.
+///
+/// The tree is lossless: its tokens, including whitespace and comments,
+/// cover the source code exactly, so the source code
+/// can be reproduced from the tree.
+/// The tree is built even if the program contains errors.
+#[derive(Clone, Debug)]
+pub struct Cst<'a>
+{
+    /// The source code the tree was parsed from.
+    pub source: &'a str,
+
+    /// The [`NodeKind::Program`] node.
+    pub root: Node,
+
+    /// Errors encountered while parsing, in source order.
+    pub errors: Vec<Error>,
+
+    /// The infix operators in the program, by the offset of their tokens.
+    ///
+    /// Each is resolved with the operators declared where it occurs,
+    /// as a later declaration may redeclare it.
+    pub infixes: HashMap<usize, Infix>,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Parsing
+
+/// Parse a program into a concrete syntax tree.
+///
+/// The grammar is that of [`parse_program`].
+/// Errors do not stop the parser: it records them,
+/// wraps the offending input in [`NodeKind::Error`] nodes,
+/// and carries on.
+///
+/// [`parse_program`]: `crate::parse_program`
+pub fn parse_cst(source: &str) -> Cst<'_>
+{
+    let mut lex = Lexer::with_trivia(source);
+    let mut builder = Builder::new(&mut lex);
+    let root = builder.program();
+    let Builder{errors, infixes, ..} = builder;
+    Cst{source, root, errors, infixes}
+}
+
+/// Parse a term from a token stream into a tree.
+///
+/// This is how [`parse_term`] finds the structure of a term.
+/// The tree of the term is the root of the returned tree.
+///
+/// [`parse_term`]: `crate::parse_term`
+pub(crate) fn parse_term_cst<'a>(lex: &mut Lexer<'a>) -> Cst<'a>
+{
+    parse_node(lex, |builder| builder.term())
+}
+
+/// Parse a statement from a token stream into a tree.
+///
+/// This is how [`parse_statement`] finds the structure of a statement.
+/// The tree of the statement is the root of the returned tree.
+///
+/// [`parse_statement`]: `crate::parse_statement`
+pub(crate) fn parse_statement_cst<'a>(lex: &mut Lexer<'a>) -> Cst<'a>
+{
+    parse_node(lex, |builder| builder.statement())
+}
+
+fn parse_node<'l, 'a, F>(lex: &'l mut Lexer<'a>, parse: F) -> Cst<'a>
+    where F: FnOnce(&mut Builder<'l, 'a>) -> Node
+{
+    let mut builder = Builder::new(lex);
+    let root = parse(&mut builder);
+    let source = builder.lex.source();
+    let Builder{errors, infixes, ..} = builder;
+    Cst{source, root, errors, infixes}
+}
+
+/// State of the parser for concrete syntax trees.
+///
+/// Each method parses a node and returns it.
+/// Whitespace and comments in front of a child node
+/// are added to the parent before the child node is parsed.
+struct Builder<'l, 'a>
+{
+    lex: &'l mut Lexer<'a>,
+    errors: Vec<Error>,
+    infixes: HashMap<usize, Infix>,
+}
+
+impl<'l, 'a> Builder<'l, 'a>
+{
+    fn new(lex: &'l mut Lexer<'a>) -> Self
+    {
+        Self{lex, errors: Vec::new(), infixes: HashMap::new()}
+    }
+
+    fn program(&mut self) -> Node
+    {
+        let mut children = Vec::new();
+        loop {
+            self.trivia(&mut children);
+            match self.lex.peek() {
+                None => break,
+                Some(Token::Semicolon) => self.bump(&mut children),
+                Some(_) => {
+                    self.node(&mut children, Self::statement);
+                    let next = self.lex.peek();
+                    if !matches!(next, None | Some(Token::Semicolon)) {
+                        self.error(&["`;`"]);
+                        self.node(&mut children, Self::skip_statement);
+                    }
+                },
+            }
+        }
+        let span = 0 .. self.lex.source().len();
+        Node{kind: NodeKind::Program, span, children}
+    }
+
+    fn statement(&mut self) -> Node
+    {
+        if self.lex.peek().and_then(notation_associativity).is_some() {
+            return self.notation();
+        }
+        let lhs = self.term();
+        if !matches!(self.lex.peek(), Some(Token::ColonEqual)) {
+            return lhs;
+        }
+        let mut children = vec![Element::Node(lhs)];
+        self.bump(&mut children);
+        self.node(&mut children, |this| this.term());
+        self.finish(NodeKind::Definition, children)
+    }
+
+    /// Parse a notation declaration.
+    ///
+    /// The operator is declared if the declaration is valid.
+    fn notation(&mut self) -> Node
+    {
+        let mut children = Vec::new();
+        self.bump(&mut children);
+        let complete =
+            self.expect(&mut children, |t| matches!(t, Token::Integer(..)),
+                        "integer")
+            && self.expect(&mut children, |t| matches!(t, Token::String(..)),
+                           "string")
+            && self.expect(&mut children, |t| matches!(t, Token::Equal),
+                           "`=`")
+            && self.expect(&mut children,
+                           |t| matches!(t, Token::Identifier(..)),
+                           "identifier");
+        let node = self.finish(NodeKind::Notation, children);
+        if complete {
+            match notation(self.lex.source(), &node) {
+                Ok(notation) => self.lex.notations_mut().declare(notation),
+                Err(error) => self.push_error(error),
+            }
+        }
+        node
+    }
+
+    /// Wrap the remainder of a statement in an error node.
+    fn skip_statement(&mut self) -> Node
+    {
+        let mut children = Vec::new();
+        while !matches!(self.lex.peek(), None | Some(Token::Semicolon)) {
+            self.bump(&mut children);
+        }
+        self.finish(NodeKind::Error, children)
+    }
+
+    /// Parse a term that may be followed by `where`;
+    /// see [`parse_term`](`crate::parse_term`) for the grammar.
+    fn term(&mut self) -> Node
+    {
+        let body = self.replace();
+        if !matches!(self.lex.peek(), Some(Token::Where)) {
+            return body;
+        }
+        let mut children = vec![Element::Node(body)];
+        self.bump(&mut children);
+        loop {
+            self.node(&mut children, Self::binding);
+            if !self.eat(&mut children, |t| matches!(t, Token::Comma)) {
+                break;
+            }
+        }
+        self.finish(NodeKind::Where, children)
+    }
+
+    /// Parse a term that may be a replacement,
+    /// but is not followed by `where`.
+    fn replace(&mut self) -> Node
+    {
+        let mut term = self.rule();
+        while self.lex.peek().and_then(replace_head).is_some() {
+            let mut children = vec![Element::Node(term)];
+            self.bump(&mut children);
+            self.node(&mut children, Self::rule);
+            term = self.finish(NodeKind::Replace, children);
+        }
+        term
+    }
+
+    /// Parse a term that may be a rule, but not a replacement.
+    fn rule(&mut self) -> Node
+    {
+        let lhs = self.condition();
+        if self.lex.peek().and_then(rule_head).is_none() {
+            return lhs;
+        }
+        let mut children = vec![Element::Node(lhs)];
+        self.bump(&mut children);
+        self.node(&mut children, Self::rule);
+        self.finish(NodeKind::Rule, children)
+    }
+
+    /// Parse a term that may be a condition,
+    /// but not a rule or replacement.
+    fn condition(&mut self) -> Node
+    {
+        let mut term = self.expression(0);
+        while matches!(self.lex.peek(), Some(Token::SlashSemicolon)) {
+            let mut children = vec![Element::Node(term)];
+            self.bump(&mut children);
+            self.node(&mut children, |this| this.expression(0));
+            term = self.finish(NodeKind::Condition, children);
+        }
+        term
+    }
+
+    /// Parse a term in which each infix operator
+    /// that is not in parentheses or in an argument list
+    /// has at least the given precedence.
+    ///
+    /// This is a Pratt parser; the operators are described by [`Infix`].
+    fn expression(&mut self, min_precedence: u32) -> Node
+    {
+        let mut lhs = self.prefix();
+
+        while let Some(infix) = self.peek_infix(min_precedence) {
+            let mut children = vec![Element::Node(lhs)];
+
+            let rhs_precedence = match infix.associativity {
+                Associativity::Left | Associativity::None =>
+                    infix.precedence + 1,
+                Associativity::Right =>
+                    infix.precedence,
+            };
+
+            self.infix(&mut children, infix.clone());
+            self.node(&mut children, |this| this.expression(rhs_precedence));
+
+            // Collect a chain such as `a + b - c` into a single node.
+            if infix.flat {
+                let same_head =
+                    |next: &Infix| next.flat && next.head == infix.head;
+                while let Some(next) =
+                    self.peek_infix(infix.precedence).filter(same_head)
+                {
+                    self.infix(&mut children, next);
+                    self.node(
+                        &mut children,
+                        |this| this.expression(rhs_precedence),
+                    );
+                }
+            }
+
+            if infix.associativity == Associativity::None {
+                if let Some(next) = self.peek_infix(infix.precedence) {
+                    if next.precedence == infix.precedence {
+                        let span = self.lex.peek_span();
+                        let message = format!(
+                            "`{}` cannot be chained; use parentheses",
+                            &self.lex.source()[span.clone()],
+                        );
+                        let error = self.lex.error_at(span, message);
+                        self.push_error(error);
+                    }
+                }
+            }
+
+            lhs = self.finish(NodeKind::Infix, children);
+        }
+
+        lhs
+    }
+
+    /// Find the infix operator of the next token,
+    /// if it has at least the given precedence.
+    fn peek_infix(&mut self, min_precedence: u32) -> Option<Infix>
+    {
+        self.lex.peek_infix()
+            .filter(|infix| infix.precedence >= min_precedence)
+    }
+
+    /// Add the next token, which is the given infix operator,
+    /// and record the operator for [`Cst::infixes`].
+    fn infix(&mut self, children: &mut Vec<Element>, infix: Infix)
+    {
+        self.bump(children);
+        if let Some(Element::Token(token)) = children.last() {
+            self.infixes.insert(token.span.start, infix);
+        }
+    }
+
+    /// Parse a term that may be preceded by unary `-`.
+    ///
+    /// The operand of `-` extends over any `^`,
+    /// so `-2 ^ 2` means `-(2 ^ 2)`.
+    fn prefix(&mut self) -> Node
+    {
+        if !matches!(self.lex.peek(), Some(Token::Minus)) {
+            return self.application();
+        }
+        let mut children = Vec::new();
+        self.bump(&mut children);
+        self.node(&mut children, |this| this.expression(PRECEDENCE_NEGATE));
+        self.finish(NodeKind::Negate, children)
+    }
+
+    fn application(&mut self) -> Node
+    {
+        let mut term = self.primary();
+        loop {
+            let (kind, parse): (_, fn(&mut Self) -> Node) =
+                match self.lex.peek() {
+                    Some(Token::LeftParenthesis) =>
+                        (NodeKind::Application, Self::arguments),
+                    Some(Token::LeftDoubleBracket) =>
+                        (NodeKind::Part, Self::indices),
+                    Some(Token::Superscript(..)) => {
+                        let mut children = vec![Element::Node(term)];
+                        self.bump(&mut children);
+                        term = self.finish(NodeKind::Superscript, children);
+                        continue;
+                    },
+                    _ => return term,
+                };
+            let mut children = vec![Element::Node(term)];
+            self.node(&mut children, parse);
+            term = self.finish(kind, children);
+        }
+    }
+
+    /// Parse an application that may be preceded by unary `-`,
+    /// as in `√-x` or the default `x_ : -1` of a pattern.
+    fn signed_application(&mut self) -> Node
+    {
+        if !matches!(self.lex.peek(), Some(Token::Minus)) {
+            return self.application();
+        }
+        let mut children = Vec::new();
+        self.bump(&mut children);
+        self.node(&mut children, Self::signed_application);
+        self.finish(NodeKind::Negate, children)
+    }
+
+    fn arguments(&mut self) -> Node
+    {
+        let mut children = Vec::new();
+        self.bump(&mut children);
+        self.comma_list(
+            &mut children,
+            |token| matches!(token, Token::RightParenthesis), "`)`",
+            |this| this.term(),
+        );
+        self.finish(NodeKind::Arguments, children)
+    }
+
+    /// Parse the indices of a part extraction, of which there is
+    /// at least one, so `m[[ ]]` is an error.
+    fn indices(&mut self) -> Node
+    {
+        let mut children = Vec::new();
+        self.bump(&mut children);
+        let is_terminator = |t: &Token| matches!(t, Token::RightBracket);
+        self.node(&mut children, |this| this.term());
+        if self.eat(&mut children, |token| matches!(token, Token::Comma)) {
+            self.comma_list(&mut children, is_terminator, "`]`", Self::term);
+        } else if !self.eat(&mut children, is_terminator) {
+            self.error(&["`,`", "`]`"]);
+        }
+        self.expect(
+            &mut children,
+            |token| matches!(token, Token::RightBracket), "`]`",
+        );
+        self.finish(NodeKind::Indices, children)
+    }
+
+    /// Parse a binding such as `x = a` in `let`, `with` or `where`.
+    ///
+    /// The value may not itself be followed by `where`.
+    fn binding(&mut self) -> Node
+    {
+        let mut children = Vec::new();
+        self.node(&mut children, Self::parameter);
+        let is_equal = |token: &Token| matches!(token, Token::Equal);
+        if self.expect(&mut children, is_equal, "`=`") {
+            self.node(&mut children, Self::replace);
+        }
+        self.finish(NodeKind::Binding, children)
+    }
+
+    fn primary(&mut self) -> Node
+    {
+        let mut children = Vec::new();
+        match self.lex.peek() {
+
+            Some(Token::Pipe) => {
+                self.bump(&mut children);
+                self.comma_list(
+                    &mut children,
+                    |token| matches!(token, Token::Pipe), "`|`",
+                    Self::parameter,
+                );
+                self.node(&mut children, |this| this.term());
+                self.finish(NodeKind::Lambda, children)
+            },
+
+            Some(Token::LeftParenthesis) => {
+                self.bump(&mut children);
+                self.node(&mut children, |this| this.term());
+                self.expect(
+                    &mut children,
+                    |token| matches!(token, Token::RightParenthesis), "`)`",
+                );
+                self.finish(NodeKind::Parenthesized, children)
+            },
+
+            Some(Token::Let | Token::With) => {
+                let is_let = matches!(self.lex.peek(), Some(Token::Let));
+                self.bump(&mut children);
+                if is_let {
+                    self.eat(&mut children, |t| matches!(t, Token::Rec));
+                }
+                self.comma_list(
+                    &mut children,
+                    |token| matches!(token, Token::In), "`in`",
+                    Self::binding,
+                );
+                self.node(&mut children, |this| this.term());
+                self.finish(NodeKind::Let, children)
+            },
+
+            Some(Token::LeftBrace) => {
+                self.bump(&mut children);
+                self.comma_list(
+                    &mut children,
+                    |token| matches!(token, Token::RightBrace), "`}`",
+                    |this| this.term(),
+                );
+                self.finish(NodeKind::List, children)
+            },
+
+            Some(Token::LeftBracket) => {
+                self.bump(&mut children);
+                self.comma_list(
+                    &mut children,
+                    |token| matches!(token, Token::RightBracket), "`]`",
+                    |this| this.term(),
+                );
+                self.finish(NodeKind::List, children)
+            },
+
+            Some(Token::LessPipe) => {
+                self.bump(&mut children);
+                self.comma_list(
+                    &mut children,
+                    |token| matches!(token, Token::PipeGreater), "`|>`",
+                    |this| this.term(),
+                );
+                self.finish(NodeKind::Association, children)
+            },
+
+            Some(Token::SquareRoot) => {
+                self.bump(&mut children);
+                self.node(&mut children, Self::signed_application);
+                self.finish(NodeKind::SquareRoot, children)
+            },
+
+            Some(Token::Integer(..) | Token::String(..)) => {
+                self.bump(&mut children);
+                self.finish(NodeKind::Literal, children)
+            },
+
+            Some(Token::Identifier(..) | Token::Pi) => {
+                self.bump(&mut children);
+                self.finish(NodeKind::Name, children)
+            },
+
+            Some(Token::Blank(..)) => {
+                self.bump(&mut children);
+                if self.eat(&mut children, |t| matches!(t, Token::Colon)) {
+                    self.node(&mut children, Self::signed_application);
+                }
+                self.finish(NodeKind::Pattern, children)
+            },
+
+            _ => {
+                self.error(TERM);
+                self.recover(&mut children);
+                self.finish(NodeKind::Error, children)
+            },
+
+        }
+    }
+
+    fn parameter(&mut self) -> Node
+    {
+        let mut children = Vec::new();
+        if matches!(self.lex.peek(), Some(Token::Tilde)) {
+            self.bump(&mut children);
+        }
+        let is_identifier =
+            |token: &Token| matches!(token, Token::Identifier(..));
+        if !self.expect(&mut children, is_identifier, "identifier") {
+            self.recover(&mut children);
+        }
+        self.finish(NodeKind::Parameter, children)
+    }
+
+    /// Parse a comma-separated list terminated by the given terminator.
+    /// A trailing comma is permitted at the end of the list.
+    ///
+    /// The description of the terminator is used in error messages.
+    fn comma_list<F, G>(
+        &mut self,
+        children: &mut Vec<Element>,
+        is_terminator: F,
+        terminator: &'static str,
+        mut parse_element: G,
+    )
+        where F: Fn(&Token) -> bool
+            , G: FnMut(&mut Self) -> Node
+    {
+        if self.eat(children, &is_terminator) {
+            return;
+        }
+        loop {
+            self.node(children, &mut parse_element);
+            if self.eat(children, |token| matches!(token, Token::Comma)) {
+                if self.eat(children, &is_terminator) {
+                    break;
+                }
+                continue;
+            }
+            if !self.eat(children, &is_terminator) {
+                self.error(&["`,`", terminator]);
+            }
+            break;
+        }
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    // Building blocks
+
+    /// Add the whitespace and comments in front of the next token.
+    fn trivia(&mut self, children: &mut Vec<Element>)
+    {
+        self.lex.peek();
+        for (kind, span) in self.lex.take_trivia() {
+            children.push(Element::Token(SyntaxToken{kind, span}));
+        }
+    }
+
+    /// Add the next token, if any.
+    fn bump(&mut self, children: &mut Vec<Element>)
+    {
+        self.trivia(children);
+        if let Some((token, span)) = self.lex.next() {
+            let kind = TokenKind::of_token(&token);
+            children.push(Element::Token(SyntaxToken{kind, span}));
+        }
+    }
+
+    /// Add the next token if it matches the predicate.
+    fn eat<F>(&mut self, children: &mut Vec<Element>, pred: F) -> bool
+        where F: FnOnce(&Token) -> bool
+    {
+        match self.lex.peek() {
+            Some(token) if pred(token) => { self.bump(children); true },
+            _ => false,
+        }
+    }
+
+    /// Add the next token if it matches the predicate,
+    /// and record an error otherwise.
+    fn expect<F>(
+        &mut self,
+        children: &mut Vec<Element>,
+        pred: F,
+        expected: &'static str,
+    ) -> bool
+        where F: FnOnce(&Token) -> bool
+    {
+        let matched = self.eat(children, pred);
+        if !matched {
+            self.error(&[expected]);
+        }
+        matched
+    }
+
+    /// Add the next token unless a parent node
+    /// is likely to continue with it.
+    fn recover(&mut self, children: &mut Vec<Element>)
+    {
+        let stop = matches!(
+            self.lex.peek(),
+            None | Some(
+                Token::Comma | Token::Semicolon | Token::ColonEqual
+                    | Token::Pipe | Token::RightParenthesis
+                    | Token::RightBracket | Token::RightBrace
+                    | Token::PipeGreater | Token::MinusGreater
+                    | Token::ColonGreater | Token::SlashSemicolon
+                    | Token::SlashDot | Token::SlashSlashDot
+                    | Token::Equal | Token::In | Token::Where
+            ),
+        );
+        if !stop {
+            self.bump(children);
+        }
+    }
+
+    /// Parse a child node, after adding the trivia in front of it.
+    fn node<F>(&mut self, children: &mut Vec<Element>, parse: F)
+        where F: FnOnce(&mut Self) -> Node
+    {
+        self.trivia(children);
+        let node = parse(self);
+        children.push(Element::Node(node));
+    }
+
+    fn finish(&mut self, kind: NodeKind, children: Vec<Element>) -> Node
+    {
+        let at = self.lex.peek_span().start;
+        Node::new(kind, children, at)
+    }
+
+    /// Record an error about the next token not being one of those expected.
+    fn error(&mut self, expected: &[&'static str])
+    {
+        let span = self.lex.peek_span();
+        let error = if self.lex.peek().is_some() {
+            self.lex.unexpected_at(span, expected)
+        } else {
+            Error::unexpected(span, None, expected)
+        };
+        self.push_error(error);
+    }
+
+    /// Record an error, unless one was already recorded at that location.
+    fn push_error(&mut self, error: Error)
+    {
+        let last = self.errors.last();
+        if last.map(|last| last.span.start) != Some(error.span.start) {
+            self.errors.push(error);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Conversion to terms
+
+impl<'a> Cst<'a>
+{
+    /// The source code of a node or token.
+    pub fn text(&self, span: Span) -> &'a str
+    {
+        &self.source[span]
+    }
+
+    /// Convert the program to statements,
+    /// as [`parse_program`] would have parsed them.
+    ///
+    /// Fails with the first error in [`errors`], if any.
+    ///
+    /// [`errors`]: `Cst::errors`
+    /// [`parse_program`]: `crate::parse_program`
+    pub fn to_statements(&self, symbols: &Symbols, scope: &Scope)
+        -> Result<Vec<Statement>>
+    {
+        if let Some(error) = self.errors.first() {
+            return Err(error.clone());
+        }
+
+        self.statements().into_iter()
+            .map(|(node, doc)| self.to_statement(symbols, scope, node, doc))
+            .collect()
+    }
+
+    /// The statements in the program,
+    /// each with the documentation comments preceding it.
+    pub fn statements(&self) -> Vec<(&Node, Option<String>)>
+    {
+        let mut statements = Vec::new();
+        let mut doc: Option<String> = None;
+        for child in &self.root.children {
+            match child {
+                Element::Token(token) if token.kind == TokenKind::DocComment =>
+                    self.push_doc(&mut doc, token),
+                Element::Token(token) if token.kind.is_trivia() =>
+                    (),
+                Element::Token(..) =>
+                    doc = None,
+                Element::Node(node) =>
+                    statements.push((node, doc.take())),
+            }
+        }
+        statements
+    }
+
+    /// Append the text of a documentation comment,
+    /// like [`Lexer::take_doc`] does.
+    fn push_doc(&self, doc: &mut Option<String>, token: &SyntaxToken)
+    {
+        let text = match self.lex(token) {
+            Some(Token::DocComment(text)) if !text.is_empty() => text,
+            _ => return,
+        };
+        match doc {
+            Some(doc) => {
+                doc.push('\n');
+                doc.push_str(&text);
+            },
+            None => *doc = Some(text.into_owned()),
+        }
+    }
+
+    /// Convert a child of the program node to a statement.
+    ///
+    /// Fails with the first error in [`errors`], if any,
+    /// unless the statement is a definition whose left-hand side
+    /// precedes the error and is wrong, in which case
+    /// it fails with the error about the left-hand side.
+    ///
+    /// [`errors`]: `Cst::errors`
+    pub fn to_statement(
+        &self,
+        symbols: &Symbols,
+        scope: &Scope,
+        node: &Node,
+        doc: Option<String>,
+    ) -> Result<Statement>
+    {
+        if node.kind == NodeKind::Definition {
+            let (lhs, _) = self.operands(node)?;
+            if !self.errors.iter().any(|e| e.span.start < lhs.span.end) {
+                let head = self.to_term(symbols, scope, lhs)?;
+                definition_head(&head)
+                    .map_err(|message| self.error(lhs, message))?;
+            }
+        }
+
+        if let Some(error) = self.errors.first() {
+            return Err(error.clone());
+        }
+
+        if node.kind == NodeKind::Notation {
+            return notation(self.source, node).map(Statement::Notation);
+        }
+
+        if node.kind != NodeKind::Definition {
+            let term = self.to_term(symbols, scope, node)?;
+            return Ok(Statement::Term(term));
+        }
+
+        let (lhs, rhs) = self.operands(node)?;
+        let head = self.to_term(symbols, scope, lhs)?;
+        let (name, parameters) = definition_head(&head)
+            .map_err(|message| self.error(lhs, message))?;
+
+        let definition = match parameters {
+            None => self.to_term(symbols, scope, rhs)?,
+            Some(parameters) => {
+                let body = {
+                    let parameters = parameters.iter().map(|p| p.name.clone());
+                    let scope = Scope::new(Some(scope), parameters);
+                    self.to_term(symbols, &scope, rhs)?
+                };
+                Term::lambda(parameters.into(), body)
+            },
+        };
+
+        Ok(Statement::Definition{name, definition, doc})
+    }
+
+    /// Convert a node that is part of a term to a term,
+    /// as [`parse_term`] would have parsed it.
+    ///
+    /// The scope must contain the parameters of the lambdas
+    /// that the node is in.
+    /// Infix operators are looked up in [`infixes`].
+    ///
+    /// [`infixes`]: `Cst::infixes`
+    /// [`parse_term`]: `crate::parse_term`
+    pub fn to_term(&self, symbols: &Symbols, scope: &Scope, node: &Node)
+        -> Result<Term>
+    {
+        match node.kind {
+
+            NodeKind::Infix => {
+                let mut nodes = node.nodes();
+                let mut operands = Vec::new();
+                let first = nodes.next().ok_or_else(|| self.invalid(node))?;
+                operands.push(self.to_term(symbols, scope, first)?);
+
+                let mut head = None;
+                for (operator, operand) in node.tokens().zip(nodes) {
+                    let infix =
+                        self.infixes.get(&operator.span.start)
+                        .ok_or_else(|| self.invalid(node))?;
+                    let operand = match infix.operand {
+                        Operand::Negated =>
+                            self.negated(symbols, scope, operand)?,
+                        _ => {
+                            let operand =
+                                self.to_term(symbols, scope, operand)?;
+                            make_operand(symbols, infix.operand, operand)
+                        },
+                    };
+                    operands.push(operand);
+                    head = Some(&infix.head);
+                }
+
+                let head = head.ok_or_else(|| self.invalid(node))?;
+                let head = symbols.get(head.as_bytes()).term();
+                Ok(Term::application(head, operands))
+            },
+
+            NodeKind::Negate => {
+                let operand = self.only_node(node)?;
+                self.negated(symbols, scope, operand)
+            },
+
+            NodeKind::SquareRoot => {
+                let operand = self.only_node(node)?;
+                let operand = self.to_term(symbols, scope, operand)?;
+                Ok(square_root(symbols, operand))
+            },
+
+            NodeKind::Parenthesized => {
+                let term = self.only_node(node)?;
+                self.to_term(symbols, scope, term)
+            },
+
+            NodeKind::Lambda => {
+                let mut parameters = Vec::new();
+                let mut body = None;
+                for child in node.nodes() {
+                    match child.kind {
+                        NodeKind::Parameter =>
+                            parameters.push(self.parameter(symbols, child)?),
+                        _ => body = Some(child),
+                    }
+                }
+                let body = body.ok_or_else(|| self.invalid(node))?;
+                let body = {
+                    let parameters = parameters.iter().map(|p| p.name.clone());
+                    let scope = Scope::new(Some(scope), parameters);
+                    self.to_term(symbols, &scope, body)?
+                };
+                Ok(Term::lambda(parameters.into(), body))
+            },
+
+            NodeKind::Let => {
+                let kind = match node.tokens().next().map(|t| t.kind) {
+                    Some(TokenKind::Let)
+                        if node.tokens().any(|t| t.kind == TokenKind::Rec) =>
+                        Binding::Recursive,
+                    Some(TokenKind::Let) => Binding::Sequential,
+                    Some(TokenKind::With) => Binding::Parallel,
+                    _ => return Err(self.invalid(node)),
+                };
+                let mut bindings: Vec<_> = node.nodes().collect();
+                let body = bindings.pop().ok_or_else(|| self.invalid(node))?;
+                self.binding_term(symbols, scope, kind, &bindings, body)
+            },
+
+            NodeKind::Where => {
+                let mut nodes = node.nodes();
+                let body = nodes.next().ok_or_else(|| self.invalid(node))?;
+                let bindings: Vec<_> = nodes.collect();
+                let kind = Binding::Parallel;
+                self.binding_term(symbols, scope, kind, &bindings, body)
+            },
+
+            NodeKind::Application => {
+                let (function, arguments) = self.operands(node)?;
+                let function = self.to_term(symbols, scope, function)?;
+                let arguments =
+                    arguments.nodes()
+                    .map(|argument| self.to_term(symbols, scope, argument))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Term::application(function, arguments))
+            },
+
+            NodeKind::List => {
+                let elements =
+                    node.nodes()
+                    .map(|element| self.to_term(symbols, scope, element))
+                    .collect::<Result<Vec<_>>>()?;
+                let list = symbols.get(b"List").term();
+                Ok(Term::application(list, elements))
+            },
+
+            NodeKind::Association => {
+                let entries =
+                    node.nodes()
+                    .map(|entry| {
+                        let term = self.to_term(symbols, scope, entry)?;
+                        if !is_rule(&term) {
+                            let message = "association entry must be \
+                                           a rule, as in `key -> value`";
+                            return Err(self.error(entry, message.into()));
+                        }
+                        Ok(term)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let association = symbols.get(b"Association").term();
+                Ok(Term::application(association, entries))
+            },
+
+            NodeKind::Part => {
+                let (term, indices) = self.operands(node)?;
+                let mut operands = vec![self.to_term(symbols, scope, term)?];
+                for index in indices.nodes() {
+                    operands.push(self.to_term(symbols, scope, index)?);
+                }
+                let part = symbols.get(b"Part").term();
+                Ok(Term::application(part, operands))
+            },
+
+            NodeKind::Superscript => {
+                let base = self.only_node(node)?;
+                let base = self.to_term(symbols, scope, base)?;
+                match node.tokens().next().and_then(|t| self.lex(t)) {
+                    Some(Token::Superscript(exponent)) => {
+                        let exponent = Term::integer_i32(exponent);
+                        Ok(power(symbols, base, exponent))
+                    },
+                    _ => Err(self.invalid(node)),
+                }
+            },
+
+            NodeKind::Literal => {
+                let token = node.tokens().next()
+                    .ok_or_else(|| self.invalid(node))?;
+                match self.lex(token) {
+                    Some(Token::Integer(value)) => {
+                        let value = i32::try_from(value).map_err(|_| {
+                            let message = "integer is too large".to_owned();
+                            self.error(node, message)
+                        })?;
+                        Ok(Term::integer_i32(value))
+                    },
+                    Some(Token::String(value)) =>
+                        Ok(Term::string(value.iter().copied())),
+                    _ => Err(self.invalid(node)),
+                }
+            },
+
+            NodeKind::Name => {
+                let token = node.tokens().next()
+                    .ok_or_else(|| self.invalid(node))?;
+                if token.kind == TokenKind::Pi {
+                    return Ok(symbols.get(b"Pi").term());
+                }
+                let name = self.text(token.span.clone());
+                let name = symbols.get(name.as_bytes());
+                match scope.get(&name) {
+                    Some(de_bruijn) => Ok(Term::variable(de_bruijn)),
+                    None => Ok(Term::symbol(name)),
+                }
+            },
+
+            NodeKind::Pattern => {
+                let token = node.tokens().next()
+                    .ok_or_else(|| self.invalid(node))?;
+                let pattern = match self.lex(token) {
+                    Some(Token::Blank(blank)) => blank_pattern(symbols, &blank),
+                    _ => return Err(self.invalid(node)),
+                };
+                match node.nodes().next() {
+                    None => Ok(pattern),
+                    Some(default) => {
+                        let default = self.to_term(symbols, scope, default)?;
+                        Ok(optional_pattern(symbols, pattern, default))
+                    },
+                }
+            },
+
+            NodeKind::Condition => {
+                let (pattern, test) = self.operands(node)?;
+                let pattern = self.to_term(symbols, scope, pattern)?;
+                condition_pattern(symbols, scope, pattern, |scope| {
+                    self.to_term(symbols, scope, test)
+                })
+            },
+
+            NodeKind::Rule => {
+                let (lhs, rhs) = self.operands(node)?;
+                let head = node.tokens().next()
+                    .and_then(|token| self.lex(token))
+                    .as_ref().and_then(rule_head)
+                    .ok_or_else(|| self.invalid(node))?;
+                let lhs = self.to_term(symbols, scope, lhs)?;
+                rule_pattern(symbols, scope, head, lhs, |scope| {
+                    self.to_term(symbols, scope, rhs)
+                })
+            },
+
+            NodeKind::Replace => {
+                let (term, rules) = self.operands(node)?;
+                let head = node.tokens().next()
+                    .and_then(|token| self.lex(token))
+                    .as_ref().and_then(replace_head)
+                    .ok_or_else(|| self.invalid(node))?;
+                let term = self.to_term(symbols, scope, term)?;
+                let rules = self.to_term(symbols, scope, rules)?;
+                let head = symbols.get(head.as_bytes()).term();
+                Ok(Term::application(head, [term, rules]))
+            },
+
+            NodeKind::Program | NodeKind::Definition | NodeKind::Notation
+                | NodeKind::Arguments | NodeKind::Indices
+                | NodeKind::Parameter | NodeKind::Binding | NodeKind::Error =>
+                Err(self.invalid(node)),
+
+        }
+    }
+
+    /// Convert a node to a term and negate it.
+    ///
+    /// If the node is an integer literal, the result is
+    /// the negative integer, which may be one greater in magnitude
+    /// than the greatest integer, as in `-2147483648`.
+    fn negated(&self, symbols: &Symbols, scope: &Scope, node: &Node)
+        -> Result<Term>
+    {
+        if node.kind == NodeKind::Literal {
+            let token = node.tokens().next().and_then(|t| self.lex(t));
+            if let Some(Token::Integer(value)) = token {
+                let value = 0i64 - i64::from(value);
+                if let Ok(value) = i32::try_from(value) {
+                    return Ok(Term::integer_i32(value));
+                }
+            }
+        }
+        let term = self.to_term(symbols, scope, node)?;
+        Ok(negate(symbols, term))
+    }
+
+    /// Convert the bindings and the body of a local binding construct.
+    fn binding_term(
+        &self,
+        symbols: &Symbols,
+        scope: &Scope,
+        kind: Binding,
+        bindings: &[&Node],
+        body: &Node,
+    ) -> Result<Term>
+    {
+        let mut binders = Vec::new();
+        for binding in bindings {
+            let binder = binding.nodes().next()
+                .ok_or_else(|| self.invalid(binding))?;
+            let parameter = self.parameter(symbols, binder)?;
+            if binders.iter().any(|b: &Parameter| b.name == parameter.name) {
+                let message = duplicate_binder(&parameter.name);
+                return Err(self.error(binder, message));
+            }
+            binders.push(parameter);
+        }
+        let names = binders.iter().map(|b| b.name.clone());
+        let inner = Scope::new(Some(scope), names);
+
+        let (values, body) = match kind {
+            Binding::Sequential =>
+                return self.sequential(symbols, scope, bindings, body),
+            Binding::Parallel =>
+                (self.values(symbols, scope, bindings)?,
+                 self.to_term(symbols, &inner, body)?),
+            Binding::Recursive =>
+                (self.values(symbols, &inner, bindings)?,
+                 self.to_term(symbols, &inner, body)?),
+        };
+        Ok(binding_term(symbols, kind, binders, values, body))
+    }
+
+    /// Convert the bindings and the body of a `let`,
+    /// each binding in the scope of the ones before it.
+    fn sequential(
+        &self,
+        symbols: &Symbols,
+        scope: &Scope,
+        bindings: &[&Node],
+        body: &Node,
+    ) -> Result<Term>
+    {
+        let (binding, rest) = match bindings.split_first() {
+            Some(split) => split,
+            None => return self.to_term(symbols, scope, body),
+        };
+        let (binder, value) = self.operands(binding)?;
+        let binder = self.parameter(symbols, binder)?;
+        let value = self.to_term(symbols, scope, value)?;
+        let body = {
+            let scope = Scope::new(Some(scope), [binder.name.clone()]);
+            self.sequential(symbols, &scope, rest, body)?
+        };
+        let kind = Binding::Sequential;
+        Ok(binding_term(symbols, kind, vec![binder], vec![value], body))
+    }
+
+    /// Convert the values of bindings.
+    fn values(&self, symbols: &Symbols, scope: &Scope, bindings: &[&Node])
+        -> Result<Vec<Term>>
+    {
+        bindings.iter()
+            .map(|binding| {
+                let (_, value) = self.operands(binding)?;
+                self.to_term(symbols, scope, value)
+            })
+            .collect()
+    }
+
+    fn parameter(&self, symbols: &Symbols, node: &Node) -> Result<Parameter>
+    {
+        let mut strictness = Strictness::Strict;
+        let mut name = None;
+        for token in node.tokens() {
+            match token.kind {
+                TokenKind::Tilde => strictness = Strictness::NonStrict,
+                TokenKind::Identifier => name = Some(token),
+                _ => return Err(self.invalid(node)),
+            }
+        }
+        let name = name.ok_or_else(|| self.invalid(node))?;
+        let name = symbols.get(self.text(name.span.clone()).as_bytes());
+        Ok(Parameter{strictness, name})
+    }
+
+    /// The two child nodes of a node.
+    fn operands<'n>(&self, node: &'n Node) -> Result<(&'n Node, &'n Node)>
+    {
+        let mut nodes = node.nodes();
+        match (nodes.next(), nodes.next(), nodes.next()) {
+            (Some(a), Some(b), None) => Ok((a, b)),
+            _ => Err(self.invalid(node)),
+        }
+    }
+
+    /// The only child node of a node.
+    fn only_node<'n>(&self, node: &'n Node) -> Result<&'n Node>
+    {
+        let mut nodes = node.nodes();
+        match (nodes.next(), nodes.next()) {
+            (Some(only), None) => Ok(only),
+            _ => Err(self.invalid(node)),
+        }
+    }
+
+    /// Lex a token again to find its value.
+    fn lex(&self, token: &SyntaxToken) -> Option<Token<'a>>
+    {
+        Token::lexer(self.text(token.span.clone())).next()
+    }
+
+    fn error(&self, node: &Node, message: String) -> Error
+    {
+        let found = self.text(node.span.clone());
+        Error::new(node.span.clone(), Some(found), message)
+    }
+
+    /// Error for a node that is malformed.
+    ///
+    /// The parser records an error for every malformed node it creates,
+    /// so this is only returned for trees that were not built by the parser.
+    fn invalid(&self, node: &Node) -> Error
+    {
+        self.error(node, format!("invalid {:?} node", node.kind))
+    }
+}
+
+/// Whether a term is a rule, as an association entry must be.
+pub(crate) fn is_rule(term: &Term) -> bool
+{
+    match term.view() {
+        View::Application(function, [_, _]) =>
+            is_symbol(function, b"Rule") || is_symbol(function, b"RuleDelayed"),
+        _ => false,
+    }
+}
+
+/// Convert a [`NodeKind::Notation`] node to the declaration it contains.
+fn notation(source: &str, node: &Node) -> Result<Notation>
+{
+    let error = |span: Span, message| {
+        let found = &source[span.clone()];
+        Error::new(span, Some(found), message)
+    };
+
+    let tokens: Vec<_> =
+        node.tokens()
+        .map(|token| (Token::lexer(&source[token.span.clone()]).next(),
+                      token.span.clone()))
+        .collect();
+
+    match tokens.as_slice() {
+        [
+            (Some(keyword), _),
+            (Some(Token::Integer(level)), level_span),
+            (Some(Token::String(operator)), operator_span),
+            (Some(Token::Equal), _),
+            (Some(Token::Identifier(head)), _),
+        ] => {
+            let associativity = notation_associativity(keyword)
+                .ok_or_else(|| error(node.span.clone(),
+                                     "invalid Notation node".into()))?;
+            let level = notation_level(*level)
+                .map_err(|message| error(level_span.clone(), message))?;
+            let operator = notation_operator(operator)
+                .map_err(|message| error(operator_span.clone(), message))?;
+            let head = String::from_utf8_lossy(head).into_owned();
+            Ok(Notation{operator, level, associativity, head})
+        },
+        _ => Err(error(node.span.clone(), "invalid Notation node".into())),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use crate::parse_program;
+    use crate::parse_term;
+    use std::fmt::Write;
+
+    /// Write the tree with one element per line,
+    /// leaving out whitespace.
+    fn dump(cst: &Cst) -> String
+    {
+        fn go(cst: &Cst, node: &Node, depth: usize, out: &mut String)
+        {
+            writeln!(out, "{:depth$}{:?}", "", node.kind, depth = depth)
+                .unwrap();
+            for child in &node.children {
+                match child {
+                    Element::Node(node) => go(cst, node, depth + 2, out),
+                    Element::Token(token) => {
+                        if token.kind == TokenKind::Whitespace {
+                            continue;
+                        }
+                        let text = cst.text(token.span.clone());
+                        writeln!(out, "{:depth$}{:?} {:?}", "", token.kind,
+                                 text, depth = depth + 2).unwrap();
+                    },
+                }
+            }
+        }
+        let mut out = String::new();
+        go(cst, &cst.root, 0, &mut out);
+        out
+    }
+
+    /// Assert that the tokens of the tree cover the source code exactly.
+    fn assert_lossless(source: &str)
+    {
+        let cst = parse_cst(source);
+        let mut end = 0;
+        for token in cst.root.descendant_tokens() {
+            assert_eq!(token.span.start, end, "{:?}", source);
+            end = token.span.end;
+        }
+        assert_eq!(end, source.len(), "{:?}", source);
+    }
+
+    #[test]
+    fn tree()
+    {
+        let cst = parse_cst("## Doc.\nf(x) := -x + 1; (* c *)\n");
+        assert!(cst.errors.is_empty());
+        assert_eq!(
+            dump(&cst),
+            "Program\n\
+            \x20 DocComment \"## Doc.\"\n\
+            \x20 Definition\n\
+            \x20   Application\n\
+            \x20     Name\n\
+            \x20       Identifier \"f\"\n\
+            \x20     Arguments\n\
+            \x20       LeftParenthesis \"(\"\n\
+            \x20       Name\n\
+            \x20         Identifier \"x\"\n\
+            \x20       RightParenthesis \")\"\n\
+            \x20   ColonEqual \":=\"\n\
+            \x20   Infix\n\
+            \x20     Negate\n\
+            \x20       Minus \"-\"\n\
+            \x20       Name\n\
+            \x20         Identifier \"x\"\n\
+            \x20     Plus \"+\"\n\
+            \x20     Literal\n\
+            \x20       Integer \"1\"\n\
+            \x20 Semicolon \";\"\n\
+            \x20 Comment \"(* c *)\"\n",
+        );
+        assert_eq!(cst.root.node_at(17).kind, NodeKind::Name);
+        assert_eq!(cst.root.node_at(19).kind, NodeKind::Infix);
+    }
+
+    #[test]
+    fn lossless()
+    {
+        let sources = [
+            "",
+            "  # comment\n",
+            "a-1 + f(x, \"s\") (** doc *) ;; |~x, y| x / y\n",
+            "Sin(x y); g(",
+            "|1| x",
+            "a < b < c",
+            ") := (",
+            "\"unterminated",
+            "a (* unterminated",
+            "$ % &",
+            "x_ : /; ",
+            "m[[1, {a, [b]}]] + <| k -> |x| x, \"v\" |>",
+            "m[[1 ; <| a -> |>",
+            "a -> ; /. b; x_ :> (x /. )",
+            "let x = in y; a where ; with x = 1 y; let rec x, = 2 in",
+            "√ ; x² ² + π(√) − ⁻; √√",
+            "infixl ; infix 1 \"⊕\" = ; a ⊕ b ⊕; infixr 2 \"+\" = f; a ⊗ b",
+        ];
+        for source in sources {
+            assert_lossless(source);
+        }
+    }
+
+    #[test]
+    fn recovery()
+    {
+        let cst = parse_cst("Sin(x y); g(; a := ; |1| b");
+        let errors: Vec<_> =
+            cst.errors.iter()
+            .map(|error| (error.span.clone(), error.message.as_str()))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (6 .. 7, "expected `)` or `,`, found `y`"),
+                (12 .. 13, "expected `(`, `-`, `<|`, `[`, `let`, `with`, \
+                            `{`, `|`, `π`, `√`, blank, identifier, \
+                            integer or string, found `;`"),
+                (19 .. 20, "expected `(`, `-`, `<|`, `[`, `let`, `with`, \
+                            `{`, `|`, `π`, `√`, blank, identifier, \
+                            integer or string, found `;`"),
+                (22 .. 23, "expected identifier, found `1`"),
+            ],
+        );
+
+        // Statements after the errors are intact.
+        let kinds: Vec<_> = cst.root.nodes().map(|node| node.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                NodeKind::Application,
+                NodeKind::Error,
+                NodeKind::Application,
+                NodeKind::Definition,
+                NodeKind::Lambda,
+            ],
+        );
+    }
+
+    #[test]
+    fn to_statements()
+    {
+        let sources = [
+            "a",
+            "f(x)(y, z,)",
+            "a + b - c * d / e ^ f ^ g",
+            "-1 - -a; a-1; (a + b) + c",
+            "a == b; a != b; a <= (b < c)",
+            "|x, ~y| |z| f(x, y, z, w)",
+            "\"a\\nb\"; 42",
+            "## The answer.\na := 42; f(x, y) := x + y",
+            "(** Doc. *) ; f := 1",
+            "f(x_, _Integer, ys__ : g(1), ___) /; x > 0 /; |y| y == x",
+            "|y| f(x_ /; x > y, z_) /; z == x",
+            "{}; [[a, b], {c}]; <|a -> 1, \"b\" -> {2},|>",
+            "|m| m[[1]][[-1, f(m)]](x); [1][[1]]",
+            "Sin(x) /. x -> Pi; e //. {a -> b, f(x_) :> x} /. c",
+            "x_ /; x > 0 -> y_ -> x + y; |y| f(x_) -> x + y",
+            "let x = 1, y = x, in let x = y in f(x, y); let in a",
+            "with ~x = 1, y = x in g(x, y) where g = f, x = 2",
+            "|x| x + y where y = x; (x where x = 1) where x = 2",
+            "let rec f = |n| g(n), g = |n| f(n) in f(x) where x = f",
+            "f(x_) -> y where y = 1",
+            "π × r² · 2 − √(a ≤ b) ≠ c; √f(x)²[[1]]⁻¹ ≥ −1; |θ| θ²",
+            "infixl 6 \"⊕\" = DirectSum; infixr 8 \"⊗\" = Tensor;\n\
+             -a ⊕ b ⊗ c ⊗ d ^ e ⊕ f * g; infix 4 \"≅\" = Iso; a ≅ b ⊕ c",
+            "infixl 6 \"⊕\" = A; a ⊕ b; infixr 2 \"⊕\" = B; a ⊕ b ⊕ c",
+        ];
+        for source in sources {
+            let symbols = Symbols::new();
+            let scope = Scope::new(None, []);
+            let expected = {
+                let mut lex = Lexer::new(source);
+                parse_program(&symbols, &scope, &mut lex).unwrap()
+            };
+            let actual = parse_cst(source).to_statements(&symbols, &scope);
+            assert_eq!(
+                format!("{:?}", actual.unwrap()),
+                format!("{:?}", expected),
+                "{:?}", source,
+            );
+        }
+    }
+
+    #[test]
+    fn to_statements_redeclared()
+    {
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, []);
+        let source = "infixl 6 \"⊕\" = A; a ⊕ b; infixl 6 \"⊕\" = B; a ⊕ b";
+        let statements =
+            parse_cst(source).to_statements(&symbols, &scope).unwrap();
+        let terms: Vec<_> =
+            statements.iter()
+            .filter_map(|statement| match statement {
+                Statement::Term(term) => Some(format!("{:?}", term)),
+                _ => None,
+            })
+            .collect();
+        let term = |source| {
+            let term = parse_term(&symbols, &scope, &mut Lexer::new(source));
+            format!("{:?}", term.unwrap())
+        };
+        assert_eq!(terms, [term("A(a, b)"), term("B(a, b)")]);
+    }
+
+    #[test]
+    fn to_statements_errors()
+    {
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, []);
+        let message = |source| {
+            let cst = parse_cst(source);
+            cst.to_statements(&symbols, &scope).unwrap_err().message
+        };
+        assert_eq!(message("a b"), "expected `;`, found `b`");
+        assert_eq!(message("a < b < c"),
+                   "`<` cannot be chained; use parentheses");
+        assert_eq!(message("f(x, x) := x"),
+                   "parameter `x` is bound more than once");
+        assert_eq!(message("let x = 1, x = 2 in x"),
+                   "variable `x` is bound more than once");
+        assert_eq!(message("a where x = 1, ~x = 2"),
+                   "variable `x` is bound more than once");
+        assert_eq!(message("1 := 2"),
+                   "left-hand side of `:=` must be a symbol, \
+                    or a symbol applied to parameters");
+        assert_eq!(message("a ⊕ b"), "operator `⊕` is not declared");
+        assert_eq!(message("infixl 6 \"⊕\" = f; infix 0 \"⊗\" f"),
+                   "expected `=`, found `f`");
+        assert_eq!(message("infix 4 \"≅\" = Iso; a ≅ b ≅ c"),
+                   "`≅` cannot be chained; use parentheses");
+        assert_eq!(message("infixr 12 \"⊕\" = f"),
+                   "precedence level must be from 0 to 9");
+    }
+}
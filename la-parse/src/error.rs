@@ -1,6 +1,186 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Write;
+use std::ops::Range;
+
 /// Result type for the parser.
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// Location in the source code, as a range of byte offsets.
+pub type Span = Range<usize>;
+
 /// Error type for the parser.
-#[derive(Debug)]
-pub struct Error;
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Error
+{
+    /// Location of the offending input.
+    pub span: Span,
+
+    /// Source code of the offending token,
+    /// or [`None`] if the end of the input was reached,
+    /// including inside an unterminated string or comment.
+    pub found: Option<String>,
+
+    /// Descriptions of the tokens that would have been accepted instead.
+    pub expected: BTreeSet<&'static str>,
+
+    /// Human-readable description of the error.
+    pub message: String,
+}
+
+impl Error
+{
+    /// Create an error with a custom message.
+    pub fn new(span: Span, found: Option<&str>, message: String) -> Self
+    {
+        let found = found.map(str::to_owned);
+        let expected = BTreeSet::new();
+        Self{span, found, expected, message}
+    }
+
+    /// Create an error for when a token was found
+    /// that is not one of the expected tokens.
+    pub fn unexpected(
+        span: Span,
+        found: Option<&str>,
+        expected: &[&'static str],
+    ) -> Self
+    {
+        let found = found.map(str::to_owned);
+        let expected: BTreeSet<_> = expected.iter().copied().collect();
+
+        let mut message = String::new();
+        if !expected.is_empty() {
+            message.push_str("expected ");
+            for (i, description) in expected.iter().enumerate() {
+                if i != 0 && i == expected.len() - 1 {
+                    message.push_str(" or ");
+                } else if i != 0 {
+                    message.push_str(", ");
+                }
+                message.push_str(description);
+            }
+            message.push_str(", ");
+        }
+        match &found {
+            Some(found) => write!(message, "found `{}`", found).unwrap(),
+            None => message.push_str("found end of input"),
+        }
+
+        Self{span, found, expected, message}
+    }
+
+    /// Create an error for a string or comment
+    /// that is still open at the end of the input.
+    pub fn unterminated(
+        span: Span,
+        what: &str,
+        terminator: &'static str,
+    ) -> Self
+    {
+        let expected = std::iter::once(terminator).collect();
+        let message = format!("unterminated {}", what);
+        Self{span, found: None, expected, message}
+    }
+
+    /// Whether the error is due to the input ending prematurely.
+    ///
+    /// Such input may become valid once more input is appended.
+    pub fn is_incomplete(&self) -> bool
+    {
+        self.found.is_none()
+    }
+
+    /// Render the error for display to the user.
+    ///
+    /// The rendering includes the message,
+    /// the line and column where the error occurred,
+    /// and the offending line of source code with a caret under the error.
+    /// The given source code must be the one that was parsed.
+    pub fn render(&self, source: &str) -> String
+    {
+        let start = self.span.start.min(source.len());
+        let line_start = source[.. start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end =
+            source[start ..].find('\n')
+            .map_or(source.len(), |i| start + i);
+        let line = &source[line_start .. line_end];
+
+        let line_number = source[.. line_start].matches('\n').count() + 1;
+        let column = source[line_start .. start].chars().count() + 1;
+
+        let end = self.span.end.clamp(start, line_end);
+        let carets = source[start .. end].chars().count().max(1);
+
+        let gutter = line_number.to_string().len();
+        let mut rendered = String::new();
+        writeln!(rendered, "error: {}", self.message).unwrap();
+        writeln!(rendered, "{:gutter$}--> {}:{}", "", line_number, column,
+                 gutter = gutter).unwrap();
+        writeln!(rendered, "{:gutter$} |", "", gutter = gutter).unwrap();
+        writeln!(rendered, "{} | {}", line_number, line).unwrap();
+        writeln!(rendered, "{:gutter$} | {:column$}{}", "", "",
+                 "^".repeat(carets),
+                 gutter = gutter, column = column - 1).unwrap();
+        rendered
+    }
+}
+
+impl fmt::Display for Error
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error
+{
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn message()
+    {
+        let error = Error::unexpected(3 .. 4, Some("]"), &["`)`"]);
+        assert_eq!(error.message, "expected `)`, found `]`");
+
+        let error = Error::unexpected(3 .. 3, None, &["`,`", "`)`", "`|`"]);
+        assert_eq!(error.message,
+                   "expected `)`, `,` or `|`, found end of input");
+    }
+
+    #[test]
+    fn render()
+    {
+        let source = "Add(\n  Sin(x]\n)";
+        let error = Error::unexpected(12 .. 13, Some("]"), &["`)`"]);
+        assert_eq!(
+            error.render(source),
+            "error: expected `)`, found `]`\n \
+             --> 2:8\n  \
+             |\n\
+             2 |   Sin(x]\n  \
+             |        ^\n",
+        );
+    }
+
+    #[test]
+    fn render_end_of_input()
+    {
+        let source = "Sin(x";
+        let error = Error::unexpected(5 .. 5, None, &["`)`"]);
+        assert_eq!(
+            error.render(source),
+            "error: expected `)`, found end of input\n \
+             --> 1:6\n  \
+             |\n\
+             1 | Sin(x\n  \
+             |      ^\n",
+        );
+    }
+}
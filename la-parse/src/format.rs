@@ -0,0 +1,975 @@
+use crate::Cst;
+use crate::Element;
+use crate::Node;
+use crate::NodeKind;
+use crate::Result;
+use crate::Span;
+use crate::SyntaxToken;
+use crate::TokenKind;
+use crate::parse_cst;
+
+use std::collections::HashMap;
+
+/// Options for [`format`].
+#[derive(Clone, Copy, Debug)]
+pub struct FormatOptions
+{
+    /// Lines are kept within this many columns where possible.
+    pub width: usize,
+
+    /// Number of spaces per level of indentation.
+    pub indent: usize,
+}
+
+impl Default for FormatOptions
+{
+    fn default() -> Self
+    {
+        Self{width: 80, indent: 4}
+    }
+}
+
+/// Rewrite a program into the canonical layout.
+///
+/// Each statement starts on a new line, and operators, `:=`, commas
+/// and lambda parameter lists are followed by a single space.
+/// Argument lists, list literals and the like that do not fit on the line
+/// are broken with one element per line and a trailing comma.
+/// Lists that contain strings, such as the prose of a `Meaning`,
+/// are broken only where the source breaks them,
+/// so that the fragments of a sentence stay together.
+/// Single blank lines between statements and between arguments are kept.
+/// Comments are kept, as are parentheses.
+///
+/// Fails with the first syntax error if the program has any,
+/// as programs with errors cannot be laid out reliably.
+pub fn format(source: &str, options: FormatOptions) -> Result<String>
+{
+    let cst = parse_cst(source);
+    if let Some(error) = cst.errors.first() {
+        return Err(error.clone());
+    }
+    let formatter = Formatter::new(&cst, options);
+    let doc = formatter.program(&cst.root);
+    Ok(render(&doc, options.width))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Documents
+
+/// Layout of text that is yet to be broken into lines.
+///
+/// This is the document algebra from Wadler's “A prettier printer”,
+/// with the extensions that are needed for formatting comments.
+#[derive(Debug)]
+enum Doc
+{
+    Text(String),
+
+    /// A space, or a line break if the enclosing group is broken.
+    Line,
+
+    /// Nothing, or a line break if the enclosing group is broken.
+    SoftLine,
+
+    /// A line break, which also breaks all enclosing groups.
+    /// Line breaks at the start of a line are ignored.
+    HardLine,
+
+    /// An empty line.
+    BlankLine,
+
+    /// Breaks all enclosing groups without producing any output.
+    BreakParent,
+
+    /// Text to be written at the end of the current line.
+    LineSuffix(String),
+
+    /// Increase the indentation of the line breaks in the document.
+    Nest(usize, Box<Doc>),
+
+    /// The line breaks in the document are broken if and only if
+    /// the document does not fit on the line.
+    Group(Box<Doc>),
+
+    /// The first document if the enclosing group is broken,
+    /// the second document otherwise.
+    IfBreak(Box<Doc>, Box<Doc>),
+
+    Concat(Vec<Doc>),
+}
+
+fn text(text: &str) -> Doc
+{
+    Doc::Text(text.to_owned())
+}
+
+fn nest(indent: usize, doc: Doc) -> Doc
+{
+    Doc::Nest(indent, Box::new(doc))
+}
+
+fn group(doc: Doc) -> Doc
+{
+    Doc::Group(Box::new(doc))
+}
+
+/// Concatenate documents with spaces between them.
+fn spaced(docs: Vec<Doc>) -> Doc
+{
+    let mut spaced = Vec::new();
+    for (i, doc) in docs.into_iter().enumerate() {
+        if i != 0 {
+            spaced.push(text(" "));
+        }
+        spaced.push(doc);
+    }
+    Doc::Concat(spaced)
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Mode
+{
+    Flat,
+    Break,
+}
+
+/// Render a document, breaking groups that do not fit in the width.
+fn render(doc: &Doc, width: usize) -> String
+{
+    let mut printer = Printer{
+        out: String::new(),
+        column: 0,
+        line_indent: 0,
+        at_line_start: true,
+        line_suffix: Vec::new(),
+    };
+
+    let mut stack = vec![(0, Mode::Break, doc)];
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) =>
+                printer.text(text),
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat =>
+                if let Doc::Line = doc { printer.text(" ") },
+            Doc::Line | Doc::SoftLine | Doc::HardLine =>
+                printer.newline(indent),
+            Doc::BlankLine =>
+                printer.blank_line(indent),
+            Doc::BreakParent =>
+                (),
+            Doc::LineSuffix(text) =>
+                printer.line_suffix.push(text.clone()),
+            Doc::Nest(extra, doc) =>
+                stack.push((indent + extra, mode, doc)),
+            Doc::Group(doc) => {
+                let remaining = width.saturating_sub(printer.column);
+                let flat = (indent, Mode::Flat, &**doc);
+                if fits(remaining, flat, &stack) {
+                    stack.push(flat);
+                } else {
+                    stack.push((indent, Mode::Break, doc));
+                }
+            },
+            Doc::IfBreak(broken, flat) => {
+                let doc = if mode == Mode::Break { broken } else { flat };
+                stack.push((indent, mode, doc));
+            },
+            Doc::Concat(docs) =>
+                stack.extend(docs.iter().rev().map(|doc| (indent, mode, doc))),
+        }
+    }
+
+    printer.flush_line_suffix();
+    printer.out
+}
+
+/// Whether the next command fits in the remaining width,
+/// up to the first line break that the remaining commands produce.
+fn fits(
+    mut remaining: usize,
+    next: (usize, Mode, &Doc),
+    rest: &[(usize, Mode, &Doc)],
+) -> bool
+{
+    let mut rest = rest.iter().rev();
+    let mut stack = vec![(next.1, next.2)];
+    loop {
+        let (mode, doc) = match stack.pop() {
+            Some(command) => command,
+            None => match rest.next() {
+                Some(&(_, mode, doc)) => (mode, doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => {
+                let width = text.chars().count();
+                if width > remaining {
+                    return false;
+                }
+                remaining -= width;
+            },
+            Doc::Line | Doc::SoftLine if mode == Mode::Flat => {
+                if let Doc::Line = doc {
+                    if remaining == 0 {
+                        return false;
+                    }
+                    remaining -= 1;
+                }
+            },
+            Doc::Line | Doc::SoftLine =>
+                return true,
+            Doc::HardLine | Doc::BlankLine | Doc::BreakParent =>
+                return mode == Mode::Break,
+            Doc::LineSuffix(..) =>
+                (),
+            Doc::Nest(_, doc) | Doc::Group(doc) =>
+                stack.push((mode, doc)),
+            Doc::IfBreak(broken, flat) => {
+                let doc = if mode == Mode::Break { broken } else { flat };
+                stack.push((mode, doc));
+            },
+            Doc::Concat(docs) =>
+                stack.extend(docs.iter().rev().map(|doc| (mode, doc))),
+        }
+    }
+}
+
+struct Printer
+{
+    out: String,
+
+    /// Column at which the next text is written,
+    /// including indentation that is yet to be written.
+    column: usize,
+
+    /// Indentation of the current line,
+    /// which is written before the first text on the line,
+    /// so that empty lines have no trailing whitespace.
+    line_indent: usize,
+
+    at_line_start: bool,
+    line_suffix: Vec<String>,
+}
+
+impl Printer
+{
+    fn text(&mut self, text: &str)
+    {
+        if self.at_line_start {
+            self.out.extend((0 .. self.line_indent).map(|_| ' '));
+            self.at_line_start = false;
+        }
+        self.out.push_str(text);
+        self.column += text.chars().count();
+    }
+
+    fn newline(&mut self, indent: usize)
+    {
+        self.flush_line_suffix();
+        if !self.at_line_start {
+            let trimmed = self.out.trim_end_matches(' ').len();
+            self.out.truncate(trimmed);
+            self.out.push('\n');
+            self.at_line_start = true;
+        }
+        self.column = indent;
+        self.line_indent = indent;
+    }
+
+    fn blank_line(&mut self, indent: usize)
+    {
+        self.newline(indent);
+        if !self.out.is_empty() && !self.out.ends_with("\n\n") {
+            self.out.push('\n');
+        }
+    }
+
+    fn flush_line_suffix(&mut self)
+    {
+        for suffix in std::mem::take(&mut self.line_suffix) {
+            self.text(&suffix);
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Layout of syntax trees
+
+/// Comment and where it appears relative to the tokens around it.
+struct Comment
+{
+    span: Span,
+
+    /// Whether the comment is on a line of its own.
+    own_line: bool,
+
+    /// Whether there is a blank line in front of the comment.
+    blank_before: bool,
+}
+
+struct Formatter<'a>
+{
+    cst: &'a Cst<'a>,
+    options: FormatOptions,
+
+    /// Comments on their own lines before each token,
+    /// by the start of the token.
+    leading: HashMap<usize, Vec<Comment>>,
+
+    /// Comments after each token, by the start of the token.
+    trailing: HashMap<usize, Vec<Comment>>,
+
+    /// Starts of the tokens that are preceded by a blank line.
+    blank_before: HashMap<usize, bool>,
+
+    /// Starts of the tokens that are preceded by a line break.
+    newline_before: HashMap<usize, bool>,
+
+    /// Comments in a program that has no tokens.
+    orphans: Vec<Comment>,
+}
+
+impl<'a> Formatter<'a>
+{
+    /// Attach each comment to the token it belongs to.
+    ///
+    /// A comment that is on a line of its own belongs to the next token.
+    /// Other comments belong to the previous token.
+    fn new(cst: &'a Cst<'a>, options: FormatOptions) -> Self
+    {
+        let mut this = Self{
+            cst,
+            options,
+            leading: HashMap::new(),
+            trailing: HashMap::new(),
+            blank_before: HashMap::new(),
+            newline_before: HashMap::new(),
+            orphans: Vec::new(),
+        };
+
+        let mut previous: Option<usize> = None;
+        let mut pending = Vec::new();
+        let mut newline = false;
+        let mut blank = false;
+        for token in cst.root.descendant_tokens() {
+            match token.kind {
+                TokenKind::Whitespace => {
+                    let newlines = cst.text(token.span.clone()).matches('\n');
+                    let newlines = newlines.count();
+                    newline |= newlines > 0;
+                    blank |= newlines > 1;
+                },
+                TokenKind::Comment | TokenKind::DocComment => {
+                    let comment = Comment{
+                        span: token.span.clone(),
+                        own_line: newline || previous.is_none(),
+                        blank_before: blank,
+                    };
+                    match previous {
+                        Some(previous) if !comment.own_line =>
+                            this.trailing.entry(previous).or_default()
+                                .push(comment),
+                        _ => pending.push(comment),
+                    }
+                    newline = false;
+                    blank = false;
+                },
+                _ => {
+                    let start = token.span.start;
+                    if !pending.is_empty() {
+                        let comments = std::mem::take(&mut pending);
+                        this.leading.insert(start, comments);
+                    }
+                    this.blank_before.insert(start, blank);
+                    this.newline_before.insert(start, newline);
+                    previous = Some(start);
+                    newline = false;
+                    blank = false;
+                },
+            }
+        }
+
+        match previous {
+            Some(previous) =>
+                this.trailing.entry(previous).or_default().extend(pending),
+            None => this.orphans = pending,
+        }
+
+        this
+    }
+
+    fn program(&self, node: &Node) -> Doc
+    {
+        let mut docs = Vec::new();
+
+        // Whether the last statement is not yet followed by `;`.
+        // Only that `;` is kept; the others separate empty statements.
+        let mut unterminated = false;
+
+        for child in &node.children {
+            match child {
+                Element::Node(node) => {
+                    if !docs.is_empty() && self.is_blank_before(node) {
+                        docs.push(Doc::BlankLine);
+                    }
+                    docs.push(Doc::HardLine);
+                    docs.push(self.node(node));
+                    unterminated = true;
+                },
+                Element::Token(token) if token.kind.is_trivia() =>
+                    (),
+                Element::Token(token) if unterminated => {
+                    docs.push(self.token(token));
+                    unterminated = false;
+                },
+                Element::Token(token) =>
+                    docs.push(self.comments(token)),
+            }
+        }
+
+        for (i, comment) in self.orphans.iter().enumerate() {
+            let blank = i != 0 && comment.blank_before;
+            docs.push(if blank { Doc::BlankLine } else { Doc::HardLine });
+            docs.push(text(self.cst.text(comment.span.clone())));
+        }
+
+        docs.push(Doc::HardLine);
+        Doc::Concat(docs)
+    }
+
+    fn node(&self, node: &Node) -> Doc
+    {
+        match node.kind {
+
+            NodeKind::Definition => {
+                let mut nodes = node.nodes();
+                let (lhs, rhs) = (nodes.next(), nodes.next());
+                let colon_equal = node.tokens().next();
+                Doc::Concat(vec![
+                    lhs.map_or(Doc::Concat(vec![]), |lhs| self.node(lhs)),
+                    text(" "),
+                    colon_equal.map_or(text(":="), |t| self.token(t)),
+                    text(" "),
+                    rhs.map_or(Doc::Concat(vec![]), |rhs| self.node(rhs)),
+                ])
+            },
+
+            NodeKind::Infix | NodeKind::Condition | NodeKind::Rule
+                | NodeKind::Replace => {
+                let mut nodes = node.nodes();
+                let first = nodes.next().map(|first| self.node(first));
+                let mut rest = Vec::new();
+                for (operator, operand) in node.tokens().zip(nodes) {
+                    rest.push(Doc::Line);
+                    rest.push(self.token(operator));
+                    rest.push(text(" "));
+                    rest.push(self.node(operand));
+                }
+                group(Doc::Concat(vec![
+                    first.unwrap_or(Doc::Concat(vec![])),
+                    nest(self.options.indent, Doc::Concat(rest)),
+                ]))
+            },
+
+            NodeKind::Parenthesized => {
+                let mut tokens = node.tokens();
+                let (open, close) = (tokens.next(), tokens.next());
+                let inner = node.nodes().next().map(|inner| self.node(inner));
+                group(Doc::Concat(vec![
+                    open.map_or(text("("), |t| self.token(t)),
+                    nest(self.options.indent, Doc::Concat(vec![
+                        Doc::SoftLine,
+                        inner.unwrap_or(Doc::Concat(vec![])),
+                    ])),
+                    Doc::SoftLine,
+                    close.map_or(text(")"), |t| self.token(t)),
+                ]))
+            },
+
+            NodeKind::Lambda => {
+                let mut docs = Vec::new();
+                let mut parameters = 0;
+                let mut pipes = 0;
+                for child in &node.children {
+                    match child {
+                        Element::Node(child)
+                            if child.kind == NodeKind::Parameter =>
+                        {
+                            if parameters != 0 {
+                                docs.push(text(", "));
+                            }
+                            docs.push(self.node(child));
+                            parameters += 1;
+                        },
+                        Element::Node(body) => {
+                            docs.push(text(" "));
+                            docs.push(self.node(body));
+                        },
+                        Element::Token(token) if token.kind.is_trivia() =>
+                            (),
+                        Element::Token(token)
+                            if token.kind == TokenKind::Pipe =>
+                        {
+                            docs.push(self.token(token));
+                            pipes += 1;
+                        },
+                        // Commas are written between parameters above,
+                        // so only their comments are kept here.
+                        Element::Token(token) =>
+                            docs.push(self.comments(token)),
+                    }
+                }
+                debug_assert_eq!(pipes, 2);
+                Doc::Concat(docs)
+            },
+
+            NodeKind::Pattern => {
+                let docs = node.children.iter().map(|child| match child {
+                    Element::Node(default) => self.node(default),
+                    Element::Token(token) if token.kind.is_trivia() =>
+                        Doc::Concat(vec![]),
+                    Element::Token(token) if token.kind == TokenKind::Colon =>
+                        Doc::Concat(
+                            vec![text(" "), self.token(token), text(" ")],
+                        ),
+                    Element::Token(token) => self.token(token),
+                });
+                Doc::Concat(docs.collect())
+            },
+
+            NodeKind::Let => {
+                let (head, bindings, tail) = self.bindings(node);
+                group(Doc::Concat(vec![
+                    spaced(head),
+                    nest(self.options.indent, bindings),
+                    Doc::Line,
+                    spaced(tail),
+                ]))
+            },
+
+            NodeKind::Where => {
+                let (head, bindings, _) = self.bindings(node);
+                let mut head = head.into_iter();
+                let body = head.next().unwrap_or(Doc::Concat(vec![]));
+                let rest = Doc::Concat(head.collect());
+                group(Doc::Concat(vec![
+                    body,
+                    nest(self.options.indent, Doc::Concat(vec![
+                        Doc::Line,
+                        rest,
+                        nest(self.options.indent, bindings),
+                    ])),
+                ]))
+            },
+
+            NodeKind::Binding | NodeKind::Notation => {
+                let docs = node.children.iter().filter_map(|child| match child {
+                    Element::Node(node) => Some(self.node(node)),
+                    Element::Token(token) if token.kind.is_trivia() => None,
+                    Element::Token(token) => Some(self.token(token)),
+                });
+                spaced(docs.collect())
+            },
+
+            NodeKind::Arguments | NodeKind::Indices | NodeKind::List
+                | NodeKind::Association =>
+                self.arguments(node),
+
+            NodeKind::Program =>
+                self.program(node),
+
+            NodeKind::Negate | NodeKind::SquareRoot | NodeKind::Parameter
+                | NodeKind::Application | NodeKind::Part
+                | NodeKind::Superscript | NodeKind::Literal | NodeKind::Name
+                | NodeKind::Error =>
+                self.verbatim(node),
+
+        }
+    }
+
+    /// Write the tokens and child nodes of a node with no spaces between.
+    fn verbatim(&self, node: &Node) -> Doc
+    {
+        let docs = node.children.iter().filter_map(|child| match child {
+            Element::Node(node) => Some(self.node(node)),
+            Element::Token(token) if token.kind.is_trivia() => None,
+            Element::Token(token) => Some(self.token(token)),
+        });
+        Doc::Concat(docs.collect())
+    }
+
+    /// Split a `let` or `where` node into the parts before its bindings,
+    /// the bindings themselves, and the parts after its bindings.
+    ///
+    /// Each binding is preceded by a line, and followed by a comma
+    /// if another binding follows.
+    fn bindings(&self, node: &Node) -> (Vec<Doc>, Doc, Vec<Doc>)
+    {
+        let elements: Vec<&Element> =
+            node.children.iter()
+            .filter(|child| match child {
+                Element::Token(token) => !token.kind.is_trivia(),
+                Element::Node(..) => true,
+            })
+            .collect();
+
+        let is_binding = |element: Option<&&Element>| matches!(
+            element,
+            Some(Element::Node(node)) if node.kind == NodeKind::Binding,
+        );
+
+        let mut head = Vec::new();
+        let mut bindings = Vec::new();
+        let mut tail = Vec::new();
+        for (i, element) in elements.iter().enumerate() {
+            let doc = match element {
+                Element::Node(node) if node.kind == NodeKind::Binding => {
+                    bindings.push(Doc::Line);
+                    bindings.push(self.node(node));
+                    continue;
+                },
+                Element::Token(token) if token.kind == TokenKind::Comma => {
+                    // A trailing comma is dropped, but not its comments.
+                    bindings.push(if is_binding(elements.get(i + 1)) {
+                        self.token(token)
+                    } else {
+                        self.comments(token)
+                    });
+                    continue;
+                },
+                Element::Node(node) => self.node(node),
+                Element::Token(token) => self.token(token),
+            };
+            if bindings.is_empty() {
+                head.push(doc);
+            } else {
+                tail.push(doc);
+            }
+        }
+        (head, Doc::Concat(bindings), tail)
+    }
+
+    /// Write an argument list, or another comma-separated list
+    /// such as a list literal, between its delimiters.
+    fn arguments(&self, node: &Node) -> Doc
+    {
+        let mut open = Vec::new();
+        let mut close = Vec::new();
+        let mut arguments: Vec<(&Node, Option<&SyntaxToken>)> = Vec::new();
+        for child in &node.children {
+            match child {
+                Element::Node(node) =>
+                    arguments.push((node, None)),
+                Element::Token(token) if token.kind.is_trivia() =>
+                    (),
+                Element::Token(token) if token.kind == TokenKind::Comma =>
+                    if let Some(last) = arguments.last_mut() {
+                        last.1 = Some(token);
+                    },
+                Element::Token(token) if arguments.is_empty() =>
+                    open.push(self.token(token)),
+                Element::Token(token) =>
+                    close.push(self.token(token)),
+            }
+        }
+
+        // The closing delimiter of an empty list
+        // comes before any node, like the opening delimiter.
+        if arguments.is_empty() {
+            close = open.split_off(open.len().min(1));
+        }
+        let open = Doc::Concat(open);
+        let close = Doc::Concat(close);
+        if arguments.is_empty() {
+            return Doc::Concat(vec![open, close]);
+        }
+
+        let prose = arguments.iter().any(|(argument, _)| is_string(argument));
+        let mut docs = Vec::new();
+        let count = arguments.len();
+        for (i, (argument, comma)) in arguments.into_iter().enumerate() {
+            if i == 0 {
+                docs.push(Doc::SoftLine);
+            } else if self.is_blank_before(argument) {
+                docs.push(Doc::BlankLine);
+            } else if prose && !self.is_newline_before(argument) {
+                docs.push(text(" "));
+            } else {
+                docs.push(Doc::Line);
+            }
+            docs.push(self.node(argument));
+            let comma_doc = || comma.map_or(text(","), |t| self.token(t));
+            if i + 1 < count {
+                docs.push(comma_doc());
+            } else {
+                // The trailing comma is written if and only if
+                // the argument list is broken over multiple lines.
+                let dropped = comma.map_or(Doc::Concat(vec![]),
+                                           |t| self.comments(t));
+                docs.push(Doc::IfBreak(Box::new(comma_doc()),
+                                       Box::new(dropped)));
+            }
+        }
+
+        group(Doc::Concat(vec![
+            open,
+            nest(self.options.indent, Doc::Concat(docs)),
+            Doc::SoftLine,
+            close,
+        ]))
+    }
+
+    /// Whether there is a blank line in front of the node,
+    /// or in front of the comments that precede it.
+    fn is_blank_before(&self, node: &Node) -> bool
+    {
+        let start = node.span.start;
+        match self.leading.get(&start).and_then(|c| c.first()) {
+            Some(comment) => comment.blank_before,
+            None => self.blank_before.get(&start).copied().unwrap_or(false),
+        }
+    }
+
+    /// Whether the node starts on a new line,
+    /// or is preceded by comments on lines of their own.
+    fn is_newline_before(&self, node: &Node) -> bool
+    {
+        let start = node.span.start;
+        self.leading.contains_key(&start)
+            || self.newline_before.get(&start).copied().unwrap_or(false)
+    }
+
+    /// Write a token along with its comments.
+    fn token(&self, token: &SyntaxToken) -> Doc
+    {
+        let text = text(self.cst.text(token.span.clone()));
+        self.with_comments(token, text)
+    }
+
+    /// Write only the comments of a token.
+    fn comments(&self, token: &SyntaxToken) -> Doc
+    {
+        self.with_comments(token, Doc::Concat(vec![]))
+    }
+
+    fn with_comments(&self, token: &SyntaxToken, doc: Doc) -> Doc
+    {
+        let start = token.span.start;
+        let mut docs = Vec::new();
+
+        // Blank lines in front of the first comment
+        // are up to the node that the token starts.
+        let leading = self.leading.get(&start).map_or(&[][..], Vec::as_slice);
+        for (i, comment) in leading.iter().enumerate() {
+            let blank = i != 0 && comment.blank_before;
+            docs.push(if blank { Doc::BlankLine } else { Doc::HardLine });
+            docs.push(text(self.cst.text(comment.span.clone())));
+            docs.push(Doc::HardLine);
+        }
+        if !leading.is_empty() && self.blank_before[&start] {
+            docs.push(Doc::BlankLine);
+        }
+
+        docs.push(doc);
+
+        for comment in self.trailing.get(&start).into_iter().flatten() {
+            let comment_text = self.cst.text(comment.span.clone());
+            if comment.own_line {
+                let blank = comment.blank_before;
+                docs.push(if blank { Doc::BlankLine } else { Doc::HardLine });
+                docs.push(text(comment_text));
+            } else if comment_text.starts_with('#') {
+                docs.push(Doc::LineSuffix(format!(" {}", comment_text)));
+                docs.push(Doc::BreakParent);
+            } else {
+                docs.push(text(" "));
+                docs.push(text(comment_text));
+            }
+        }
+
+        Doc::Concat(docs)
+    }
+}
+
+/// Whether the node is a string literal.
+fn is_string(node: &Node) -> bool
+{
+    node.kind == NodeKind::Literal
+        && node.tokens().any(|token| token.kind == TokenKind::String)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use crate::Lexer;
+    use crate::Scope;
+    use crate::parse_program;
+    use la_term::symbol::Symbols;
+
+    fn fmt(source: &str) -> String
+    {
+        format(source, FormatOptions::default()).unwrap()
+    }
+
+    fn fmt_width(source: &str, width: usize) -> String
+    {
+        let options = FormatOptions{width, ..FormatOptions::default()};
+        format(source, options).unwrap()
+    }
+
+    #[test]
+    fn spacing()
+    {
+        assert_eq!(fmt("f( x ,y ,)"), "f(x, y)\n");
+        assert_eq!(fmt("|x,~y|x+y*-z"), "|x, ~y| x + y * -z\n");
+        assert_eq!(fmt("f(x):=x^2;a:=( b )"), "f(x) := x ^ 2;\na := (b)\n");
+        assert_eq!(fmt("f()(x)"), "f()(x)\n");
+        assert_eq!(fmt(";;a;;b;"), "a;\nb;\n");
+        assert_eq!(fmt("f(x_:0,y__)/;x<y"), "f(x_ : 0, y__) /; x < y\n");
+        assert_eq!(fmt("{ a ,[b] ,}[[ 1 ]]"), "{a, [b]}[[1]]\n");
+        assert_eq!(fmt("let x=1,y=2,in x+y"), "let x = 1, y = 2 in x + y\n");
+        assert_eq!(fmt("π×r²≤√ x·2−1"), "π × r² ≤ √x · 2 − 1\n");
+        assert_eq!(fmt("let rec ~f=g,in f where g=|x|x"),
+                   "let rec ~f = g in f where g = |x| x\n");
+        assert_eq!(fmt("(with x=1 in x)"), "(with x = 1 in x)\n");
+        assert_eq!(fmt("Sin(x)/.x->Pi//.f(x_):>x"),
+                   "Sin(x) /. x -> Pi //. f(x_) :> x\n");
+        assert_eq!(fmt("<|a->1,\"b\"  ->  2|>;<| |>;{ }"),
+                   "<|a -> 1, \"b\" -> 2|>;\n<||>;\n{}\n");
+        assert_eq!(fmt("infixl 6\"⊕\"=DirectSum;a⊕b*c"),
+                   "infixl 6 \"⊕\" = DirectSum;\na ⊕ b * c\n");
+        assert_eq!(fmt(""), "");
+    }
+
+    #[test]
+    fn line_width()
+    {
+        assert_eq!(
+            fmt_width("f(alpha, beta, gamma)", 21),
+            "f(alpha, beta, gamma)\n",
+        );
+        assert_eq!(
+            fmt_width("f(alpha, beta, gamma)", 20),
+            "f(\n    alpha,\n    beta,\n    gamma,\n)\n",
+        );
+        assert_eq!(
+            fmt_width("f(g(alpha, beta), gamma)", 20),
+            "f(\n    g(alpha, beta),\n    gamma,\n)\n",
+        );
+        assert_eq!(
+            fmt_width("m[[alpha, beta, gamma]]", 20),
+            "m[[\n    alpha,\n    beta,\n    gamma,\n]]\n",
+        );
+        assert_eq!(
+            fmt_width("let alpha = 1, beta = 2 in alpha", 20),
+            "let\n    alpha = 1,\n    beta = 2\nin alpha\n",
+        );
+        assert_eq!(
+            fmt_width("alpha where alpha = 1, beta = 2", 20),
+            "alpha\n    where\n        alpha = 1,\n        beta = 2\n",
+        );
+        assert_eq!(
+            fmt_width("alpha + beta * gamma - delta", 20),
+            "alpha\n    + beta * gamma\n    - delta\n",
+        );
+    }
+
+    #[test]
+    fn blank_lines()
+    {
+        assert_eq!(
+            fmt("List(\n\n  a,\n\n\n  b,\n  c\n\n)"),
+            "List(\n    a,\n\n    b,\n    c,\n)\n",
+        );
+        assert_eq!(
+            fmt_width("List(\n\n  alpha,\n\n\n  beta,\n  gamma\n\n)", 10),
+            "List(\n    alpha,\n\n    beta,\n    gamma,\n)\n",
+        );
+        assert_eq!(fmt("a;\n\n\nb;\nc"), "a;\n\nb;\nc\n");
+    }
+
+    #[test]
+    fn prose()
+    {
+        assert_eq!(
+            fmt_width("Meaning(\n\"Raises \", Markup(\"x\"),\n\
+                       \"to \", Markup(\"y\"), \".\")", 30),
+            "Meaning(\n    \"Raises \", Markup(\"x\"),\n    \
+             \"to \", Markup(\"y\"), \".\",\n)\n",
+        );
+        assert_eq!(
+            fmt_width("Meaning(\"Raises \", Markup(\"x\"))", 30),
+            "Meaning(\n    \"Raises \", Markup(\"x\"),\n)\n",
+        );
+        assert_eq!(
+            fmt("Meaning(\"Raises \",\n  Markup(\"x\"))"),
+            "Meaning(\"Raises \", Markup(\"x\"))\n",
+        );
+    }
+
+    #[test]
+    fn comments()
+    {
+        assert_eq!(
+            fmt("# Header.\n\n## Doc.\nf(x) := x; # Trailing.\n# Footer."),
+            "# Header.\n\n## Doc.\nf(x) := x; # Trailing.\n# Footer.\n",
+        );
+        assert_eq!(
+            fmt("f(a, # About a.\n  b)"),
+            "f(\n    a, # About a.\n    b,\n)\n",
+        );
+        assert_eq!(
+            fmt("f(a (* inline *), b, (* dropped comma *))"),
+            "f(a (* inline *), b (* dropped comma *))\n",
+        );
+        assert_eq!(
+            fmt("f(\n  # Before b.\n  b)"),
+            "f(\n    # Before b.\n    b,\n)\n",
+        );
+        assert_eq!(fmt("(* only *)\n# comments"), "(* only *)\n# comments\n");
+    }
+
+    #[test]
+    fn errors()
+    {
+        let error = format("f(x", FormatOptions::default()).unwrap_err();
+        assert_eq!(error.message, "expected `)` or `,`, found end of input");
+    }
+
+    #[test]
+    fn preserves_meaning()
+    {
+        let sources = [
+            "a-1; - -1; a - -b; (a + b) + c; a+(b+c)",
+            "f(x, y) := |~z| x*y/z^-2 ^ 3",
+            "## Doc.\nList(\"a\\n\", 1, g(h(i(j(k(l(m(n, o, p, q, r, s, t, u, \
+             v, w, x, y, z, aa, bb, cc, dd, ee, ff, gg, hh, ii))))))))",
+            "a == b; a != (b <= c); a(* c *)-1",
+            "f(x_:-1, n_Integer) /; n > 0 /; x_ : 2",
+            "[[1, 2], {3}][[1,-1]]; <|\"k\" -> [a], b -> <||>|>",
+            "e /. {f(x_) :> x /; x > 0, a -> b -> c} //. r",
+            "let x = 1, (* c *) y = x, # d\n in (x where x = y)",
+            "let rec f = |n| f(n - 1) in with ~x = f(1), in x",
+            "√(x² + y²) ≠ π · r⁻¹ − √√f(x)²",
+            "infixr 8 \"⊗\" = T; infix 4 \"≅\" = I; -a⊗b⊗c ≅ (d ≅ e)",
+        ];
+        for source in sources {
+            let formatted = fmt(source);
+            assert_eq!(fmt(&formatted), formatted, "{:?}", source);
+
+            let symbols = Symbols::new();
+            let scope = Scope::new(None, []);
+            let parse = |source| {
+                let mut lex = Lexer::new(source);
+                let statements = parse_program(&symbols, &scope, &mut lex);
+                format!("{:?}", statements.unwrap())
+            };
+            assert_eq!(parse(&formatted), parse(source), "{:?}", source);
+            assert!(formatted.lines().all(|line| line.len() <= 80));
+        }
+    }
+}
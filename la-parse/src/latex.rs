@@ -0,0 +1,1671 @@
+use crate::Error;
+use crate::Notation;
+use crate::Notations;
+use crate::Operand;
+use crate::Result;
+use crate::Scope;
+use crate::Span;
+use crate::is_symbol;
+use crate::make_operand;
+use crate::negate;
+use crate::power;
+use crate::reciprocal;
+use crate::root;
+use crate::root_degree;
+use crate::square_root;
+use crate::without_sign;
+
+use la_term::Term;
+use la_term::View;
+use la_term::lambda::Parameter;
+use la_term::lambda::Strictness;
+use la_term::symbol::Symbol;
+use la_term::symbol::Symbols;
+use logos::Logos;
+
+////////////////////////////////////////////////////////////////////////////////
+// Lexing
+
+/// Token of LaTeX math.
+#[derive(Clone, Copy, Debug, Eq, Logos, PartialEq)]
+enum LatexToken<'a>
+{
+    /// Emitted when there is a lexical error.
+    ///
+    /// Whitespace, comments and spacing commands such as `\,`
+    /// are skipped, as they do not affect the meaning of a formula.
+    #[error]
+    #[regex(r"[\t\n\r ]+", logos::skip)]
+    #[regex(r"%[^\n]*", logos::skip)]
+    #[regex(r"\\[,:;! ]", logos::skip)]
+    #[token(r"\quad", logos::skip)]
+    #[token(r"\qquad", logos::skip)]
+    #[token(r"\displaystyle", logos::skip)]
+    Error,
+
+    /// Command such as `\frac`, without the backslash.
+    #[regex(r"\\[a-zA-Z]+", |lex| &lex.slice()[1 ..])]
+    Command(&'a str),
+
+    #[token("{")]
+    LeftBrace,
+
+    #[token("}")]
+    RightBrace,
+
+    #[token("(")]
+    LeftParenthesis,
+
+    #[token(")")]
+    RightParenthesis,
+
+    #[token("[")]
+    LeftBracket,
+
+    #[token("]")]
+    RightBracket,
+
+    #[token("|")]
+    Pipe,
+
+    #[token("^")]
+    Caret,
+
+    #[token("_")]
+    Underscore,
+
+    #[token("+")]
+    Plus,
+
+    #[token("-")]
+    Minus,
+
+    #[token("*")]
+    Asterisk,
+
+    #[token("/")]
+    Slash,
+
+    #[token("=")]
+    Equal,
+
+    #[token("<")]
+    Less,
+
+    #[token(">")]
+    Greater,
+
+    /// Digit; as in LaTeX, `x^12` means `x^{1}2`.
+    #[regex("[0-9]", |lex| lex.slice().as_bytes()[0] - b'0')]
+    Digit(u8),
+
+    /// Letter; as in LaTeX, each letter is a variable of its own.
+    #[regex("[a-zA-Z]", |lex| lex.slice().chars().next())]
+    Letter(char),
+}
+
+/// Commands for Greek letters, and the letters they stand for.
+///
+/// Variant forms such as `\varepsilon` stand for the same letters
+/// as the plain forms, so that they mean the same variables.
+const GREEK: &[(&str, &str)] = &[
+    ("alpha", "α"), ("beta", "β"), ("gamma", "γ"), ("delta", "δ"),
+    ("epsilon", "ε"), ("varepsilon", "ε"), ("zeta", "ζ"), ("eta", "η"),
+    ("theta", "θ"), ("vartheta", "θ"), ("iota", "ι"), ("kappa", "κ"),
+    ("lambda", "λ"), ("mu", "μ"), ("nu", "ν"), ("xi", "ξ"), ("rho", "ρ"),
+    ("sigma", "σ"), ("tau", "τ"), ("upsilon", "υ"), ("phi", "φ"),
+    ("varphi", "φ"), ("chi", "χ"), ("psi", "ψ"), ("omega", "ω"),
+    ("Gamma", "Γ"), ("Delta", "Δ"), ("Theta", "Θ"), ("Lambda", "Λ"),
+    ("Xi", "Ξ"), ("Pi", "Π"), ("Sigma", "Σ"), ("Upsilon", "Υ"),
+    ("Phi", "Φ"), ("Psi", "Ψ"), ("Omega", "Ω"),
+];
+
+/// Commands for functions, and the symbols they stand for.
+const FUNCTIONS: &[(&str, &str)] = &[
+    ("sin", "Sin"), ("cos", "Cos"), ("tan", "Tan"),
+    ("cot", "Cot"), ("sec", "Sec"), ("csc", "Csc"),
+    ("arcsin", "ArcSin"), ("arccos", "ArcCos"), ("arctan", "ArcTan"),
+    ("sinh", "Sinh"), ("cosh", "Cosh"), ("tanh", "Tanh"),
+    ("ln", "Ln"), ("log", "Log"),
+];
+
+fn greek(command: &str) -> Option<&'static str>
+{
+    GREEK.iter().find(|(c, _)| *c == command).map(|(_, letter)| *letter)
+}
+
+fn function(command: &str) -> Option<&'static str>
+{
+    FUNCTIONS.iter().find(|(c, _)| *c == command).map(|(_, head)| *head)
+}
+
+/// The head of the relation a token stands for, if any.
+fn relation_head(token: LatexToken) -> Option<&'static str>
+{
+    match token {
+        LatexToken::Equal => Some("Equal"),
+        LatexToken::Less => Some("Less"),
+        LatexToken::Greater => Some("Greater"),
+        LatexToken::Command("le" | "leq") => Some("LessEqual"),
+        LatexToken::Command("ge" | "geq") => Some("GreaterEqual"),
+        LatexToken::Command("ne" | "neq") => Some("Unequal"),
+        _ => None,
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Parsing
+
+/// Descriptions of the tokens that may start a factor.
+const FACTOR: &[&str] = &["`(`", "`[`", "`{`", "command", "digit", "letter"];
+
+/// Parse a formula written in LaTeX math.
+///
+/// The formula becomes the term that the equivalent source code
+/// would have been parsed into by [`parse_term`], using the same symbols.
+/// Letters and Greek letters are variables if they are in the scope,
+/// and symbols otherwise, except that `e` stands for `E`.
+/// Factors may be juxtaposed, as in `2xy` or `2(x + 1)`,
+/// so `f(x)` is a product rather than an application.
+///
+/// | Syntax                     | Term                                 |
+/// |----------------------------|--------------------------------------|
+/// | `a \cdot b`, `a \times b`  | `a * b`                              |
+/// | `\frac{a}{b}`, `a \div b`  | `a / b`                              |
+/// | `x^{n}`, `e^{x}`           | `x ^ n`, `E ^ x`                     |
+/// | `x_{1}`                    | `Subscript(x, 1)`                    |
+/// | `\sqrt{x}`, `\sqrt[n]{x}`  | `x ^ (1 / 2)`, `x ^ (1 / n)`         |
+/// | `\left( a \right)`         | `a`                                  |
+/// | `\left\| a \right\|`       | `Abs(a)`                             |
+/// | `\le`, `\ge`, `\ne`        | `<=`, `>=`, `!=`                     |
+/// | `\pi`, `\infty`, `\alpha`  | `Pi`, `Infinity`, `α`                |
+/// | `\sin x`, `\sin^2 x`       | `Sin(x)`, `Sin(x) ^ 2`               |
+/// | `\log_b x`, `\exp x`       | `Log(b, x)`, `E ^ x`                 |
+/// | `\int f \,dx`              | `Antiderivative(\|x\| f)(x)`         |
+/// | `\int_a^b f \,dx`          | `Integrate(\|x\| f, a, b)`           |
+/// | `\sum_{k=a}^{b} f`         | `Sum(\|k\| f, a, b)`                 |
+/// | `\prod_{k=a}^{b} f`        | `Product(\|k\| f, a, b)`             |
+///
+/// The argument of a function without parentheses extends over
+/// juxtaposed factors up to the next function, so `\sin 2x \cos x`
+/// means `Sin(2 * x) * Cos(x)`. Likewise, the summand of a sum
+/// extends over the product that follows it.
+/// The integrand of an integral extends up to its differential,
+/// which is `d` or `\mathrm{d}` followed by the variable.
+///
+/// [`parse_term`]: `crate::parse_term`
+pub fn parse_latex(symbols: &Symbols, scope: &Scope, source: &str)
+    -> Result<Term>
+{
+    let tokens = LatexToken::lexer(source).spanned().collect();
+    let mut parser =
+        LatexParser{symbols, source, tokens, position: 0, integrals: 0};
+    let term = parser.relation(scope)?;
+    match parser.peek() {
+        None => Ok(term),
+        Some(_) => Err(parser.unexpected(&["end of input"])),
+    }
+}
+
+/// State of the parser for LaTeX math.
+///
+/// The tokens are lexed up front,
+/// so that the parser can go back to parse integrands again.
+struct LatexParser<'a, 's>
+{
+    symbols: &'s Symbols,
+    source: &'a str,
+    tokens: Vec<(LatexToken<'a>, Span)>,
+    position: usize,
+
+    /// How many integrals the parser is in the integrand of.
+    /// Inside an integrand, a differential is not a product.
+    integrals: usize,
+}
+
+impl<'a, 's> LatexParser<'a, 's>
+{
+    /// Parse an equation, an inequality, or any other formula.
+    fn relation(&mut self, scope: &Scope) -> Result<Term>
+    {
+        let lhs = self.sum(scope)?;
+        let head = match self.peek().and_then(relation_head) {
+            Some(head) => head,
+            None => return Ok(lhs),
+        };
+        self.position += 1;
+        let rhs = self.sum(scope)?;
+        if self.peek().and_then(relation_head).is_some() {
+            let span = self.peek_span();
+            let found = &self.source[span.clone()];
+            let message =
+                format!("`{}` cannot be chained; use parentheses", found);
+            return Err(Error::new(span, Some(found), message));
+        }
+        Ok(self.apply(head, vec![lhs, rhs]))
+    }
+
+    fn sum(&mut self, scope: &Scope) -> Result<Term>
+    {
+        let mut terms = vec![self.product(scope)?];
+        loop {
+            let operand = match self.peek() {
+                Some(LatexToken::Plus) => Operand::AsIs,
+                Some(LatexToken::Minus) => Operand::Negated,
+                _ => break,
+            };
+            self.position += 1;
+            let term = self.product(scope)?;
+            terms.push(make_operand(self.symbols, operand, term));
+        }
+        Ok(self.flat("Add", terms))
+    }
+
+    /// Parse a product, whose factors may be juxtaposed.
+    ///
+    /// The first factor may be negated, as in `-2x`,
+    /// in which case the negation applies to that factor only.
+    fn product(&mut self, scope: &Scope) -> Result<Term>
+    {
+        let first = match self.peek() {
+            Some(LatexToken::Minus) => {
+                self.position += 1;
+                let factor = self.factor(scope)?;
+                negate(self.symbols, factor)
+            },
+            Some(LatexToken::Plus) => {
+                self.position += 1;
+                self.factor(scope)?
+            },
+            _ => self.factor(scope)?,
+        };
+
+        let mut factors = vec![first];
+        loop {
+            let operand = match self.peek() {
+                Some(LatexToken::Asterisk
+                        | LatexToken::Command("cdot" | "times")) => {
+                    self.position += 1;
+                    Operand::AsIs
+                },
+                Some(LatexToken::Slash | LatexToken::Command("div")) => {
+                    self.position += 1;
+                    Operand::Reciprocal
+                },
+                _ if self.starts_factor() => Operand::AsIs,
+                _ => break,
+            };
+            let factor = self.factor(scope)?;
+            factors.push(make_operand(self.symbols, operand, factor));
+        }
+        Ok(self.flat("Multiply", factors))
+    }
+
+    /// Whether the next token starts a factor juxtaposed to the previous one.
+    fn starts_factor(&self) -> bool
+    {
+        match self.peek() {
+            Some(LatexToken::Digit(..) | LatexToken::LeftParenthesis
+                    | LatexToken::LeftBracket | LatexToken::LeftBrace) =>
+                true,
+            Some(LatexToken::Letter(..)) =>
+                self.differential().is_none(),
+            Some(LatexToken::Command(command)) =>
+                matches!(
+                    command,
+                    "frac" | "dfrac" | "tfrac" | "sqrt" | "left"
+                        | "pi" | "infty" | "exp" | "int" | "sum" | "prod"
+                ) || greek(command).is_some() || function(command).is_some(),
+            _ =>
+                false,
+        }
+    }
+
+    /// Whether the next token is a function or a big operator,
+    /// which ends the argument of a function without parentheses.
+    fn at_function(&self) -> bool
+    {
+        match self.peek() {
+            Some(LatexToken::Command(command)) =>
+                matches!(command, "exp" | "int" | "sum" | "prod")
+                    || function(command).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Parse a factor with subscripts and superscripts, as in `x_1^2`.
+    fn factor(&mut self, scope: &Scope) -> Result<Term>
+    {
+        let mut term = self.primary(scope)?;
+        loop {
+            match self.peek() {
+                Some(LatexToken::Caret) => {
+                    self.position += 1;
+                    let exponent = self.argument(scope)?;
+                    term = power(self.symbols, term, exponent);
+                },
+                Some(LatexToken::Underscore) => {
+                    self.position += 1;
+                    let subscript = self.argument(scope)?;
+                    term = self.apply("Subscript", vec![term, subscript]);
+                },
+                _ => return Ok(term),
+            }
+        }
+    }
+
+    /// Parse an argument of a command or a script,
+    /// which is a group in braces or a single token, as in `\frac12`.
+    fn argument(&mut self, scope: &Scope) -> Result<Term>
+    {
+        match self.peek() {
+            Some(LatexToken::Digit(digit)) => {
+                self.position += 1;
+                Ok(Term::integer_i32(digit.into()))
+            },
+            _ => self.primary(scope),
+        }
+    }
+
+    fn primary(&mut self, scope: &Scope) -> Result<Term>
+    {
+        let span = self.peek_span();
+        match self.peek() {
+
+            Some(LatexToken::Digit(..)) =>
+                self.integer(),
+
+            Some(LatexToken::Letter(letter)) => {
+                self.position += 1;
+                Ok(self.variable(scope, &letter.to_string()))
+            },
+
+            Some(LatexToken::LeftParenthesis) =>
+                self.group(scope, LatexToken::RightParenthesis, "`)`"),
+
+            Some(LatexToken::LeftBracket) =>
+                self.group(scope, LatexToken::RightBracket, "`]`"),
+
+            Some(LatexToken::LeftBrace) =>
+                self.group(scope, LatexToken::RightBrace, "`}`"),
+
+            Some(LatexToken::Command(command)) => {
+                self.position += 1;
+                self.command(scope, command, span)
+            },
+
+            _ => Err(self.unexpected(FACTOR)),
+
+        }
+    }
+
+    /// Parse a formula between the next token and the given one.
+    fn group(
+        &mut self,
+        scope: &Scope,
+        close: LatexToken,
+        expected: &'static str,
+    ) -> Result<Term>
+    {
+        self.position += 1;
+        let term = self.relation(scope)?;
+        self.expect(close, expected)?;
+        Ok(term)
+    }
+
+    /// Parse an integer, whose digits may be separated by whitespace.
+    fn integer(&mut self) -> Result<Term>
+    {
+        let start = self.peek_span().start;
+        let mut value = Some(0i32);
+        while let Some(LatexToken::Digit(digit)) = self.peek() {
+            value = value
+                .and_then(|value| value.checked_mul(10))
+                .and_then(|value| value.checked_add(digit.into()));
+            self.position += 1;
+        }
+        match value {
+            Some(value) => Ok(Term::integer_i32(value)),
+            None => {
+                let span = start .. self.tokens[self.position - 1].1.end;
+                let found = &self.source[span.clone()];
+                let message = "integer is too large".to_owned();
+                Err(Error::new(span, Some(found), message))
+            },
+        }
+    }
+
+    /// Parse the rest of a command whose name was just parsed.
+    fn command(&mut self, scope: &Scope, command: &str, span: Span)
+        -> Result<Term>
+    {
+        match command {
+
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.argument(scope)?;
+                let denominator = self.argument(scope)?;
+                let operand = Operand::Reciprocal;
+                let reciprocal =
+                    make_operand(self.symbols, operand, denominator);
+                Ok(self.apply("Multiply", vec![numerator, reciprocal]))
+            },
+
+            "sqrt" => {
+                let degree = if self.eat(LatexToken::LeftBracket) {
+                    let degree = self.relation(scope)?;
+                    self.expect(LatexToken::RightBracket, "`]`")?;
+                    Some(degree)
+                } else {
+                    None
+                };
+                let radicand = self.argument(scope)?;
+                match degree {
+                    Some(degree) => Ok(root(self.symbols, radicand, degree)),
+                    None => Ok(square_root(self.symbols, radicand)),
+                }
+            },
+
+            "left" => self.delimited(scope),
+
+            "pi" => Ok(self.symbol("Pi")),
+
+            "infty" => Ok(self.symbol("Infinity")),
+
+            "exp" => {
+                let exponent = self.function_argument(scope)?;
+                Ok(power(self.symbols, self.symbol("E"), exponent))
+            },
+
+            "int" => self.integral(scope, span),
+
+            "sum" => self.big_operator(scope, "Sum", span),
+
+            "prod" => self.big_operator(scope, "Product", span),
+
+            _ => {
+                if let Some(letter) = greek(command) {
+                    return Ok(self.variable(scope, letter));
+                }
+                if let Some(head) = function(command) {
+                    return self.function(scope, head);
+                }
+                let found = &self.source[span.clone()];
+                let message = format!("unknown command `{}`", found);
+                Err(Error::new(span, Some(found), message))
+            },
+
+        }
+    }
+
+    /// Parse the rest of `\left( a \right)`, `\left[ a \right]`
+    /// or `\left| a \right|`, the last of which is an absolute value.
+    fn delimited(&mut self, scope: &Scope) -> Result<Term>
+    {
+        let (close, expected, head) = match self.peek() {
+            Some(LatexToken::LeftParenthesis) =>
+                (LatexToken::RightParenthesis, "`)`", None),
+            Some(LatexToken::LeftBracket) =>
+                (LatexToken::RightBracket, "`]`", None),
+            Some(LatexToken::Pipe) =>
+                (LatexToken::Pipe, "`|`", Some("Abs")),
+            _ => return Err(self.unexpected(&["`(`", "`[`", "`|`"])),
+        };
+        self.position += 1;
+        let term = self.relation(scope)?;
+        self.expect(LatexToken::Command("right"), "`\\right`")?;
+        self.expect(close, expected)?;
+        match head {
+            Some(head) => Ok(self.apply(head, vec![term])),
+            None => Ok(term),
+        }
+    }
+
+    /// Parse the rest of a function, as in `\sin^2 x` or `\log_2(x)`.
+    fn function(&mut self, scope: &Scope, head: &str) -> Result<Term>
+    {
+        let mut base = None;
+        let mut exponent = None;
+        loop {
+            match self.peek() {
+                Some(LatexToken::Caret) if exponent.is_none() => {
+                    self.position += 1;
+                    exponent = Some(self.argument(scope)?);
+                },
+                Some(LatexToken::Underscore)
+                    if base.is_none() && head == "Log" =>
+                {
+                    self.position += 1;
+                    base = Some(self.argument(scope)?);
+                },
+                _ => break,
+            }
+        }
+        let mut arguments: Vec<_> = base.into_iter().collect();
+        arguments.push(self.function_argument(scope)?);
+        let term = self.apply(head, arguments);
+        match exponent {
+            Some(exponent) => Ok(power(self.symbols, term, exponent)),
+            None => Ok(term),
+        }
+    }
+
+    /// Parse the argument of a function.
+    ///
+    /// Without parentheses, the argument extends over
+    /// juxtaposed factors up to the next function.
+    fn function_argument(&mut self, scope: &Scope) -> Result<Term>
+    {
+        if let Some(
+            LatexToken::LeftParenthesis | LatexToken::Command("left")
+        ) = self.peek() {
+            return self.primary(scope);
+        }
+        let mut factors = vec![self.factor(scope)?];
+        while self.starts_factor() && !self.at_function() {
+            factors.push(self.factor(scope)?);
+        }
+        Ok(self.flat("Multiply", factors))
+    }
+
+    /// Parse the rest of an integral, as in `\int_a^b f \,dx`.
+    fn integral(&mut self, scope: &Scope, span: Span) -> Result<Term>
+    {
+        let (lower, upper) = self.limits(scope)?;
+        self.integrals += 1;
+        let integrand = self.integrand(scope);
+        self.integrals -= 1;
+        let (name, integrand) = integrand?;
+
+        let variable = self.symbols.get(name.as_bytes());
+        let function = lambda(variable, integrand);
+        match (lower, upper) {
+            (Some(lower), Some(upper)) =>
+                Ok(self.apply("Integrate", vec![function, lower, upper])),
+            (None, None) => {
+                let antiderivative =
+                    self.apply("Antiderivative", vec![function]);
+                let variable = self.variable(scope, &name);
+                Ok(Term::application(antiderivative, [variable]))
+            },
+            _ => {
+                let found = &self.source[span.clone()];
+                let message =
+                    "an integral must have both limits or neither".to_owned();
+                Err(Error::new(span, Some(found), message))
+            },
+        }
+    }
+
+    /// Parse the limits of an integral, as in `_a^b` or `^b_a`.
+    fn limits(&mut self, scope: &Scope)
+        -> Result<(Option<Term>, Option<Term>)>
+    {
+        let mut lower = None;
+        let mut upper = None;
+        loop {
+            match self.peek() {
+                Some(LatexToken::Underscore) if lower.is_none() => {
+                    self.position += 1;
+                    lower = Some(self.argument(scope)?);
+                },
+                Some(LatexToken::Caret) if upper.is_none() => {
+                    self.position += 1;
+                    upper = Some(self.argument(scope)?);
+                },
+                _ => return Ok((lower, upper)),
+            }
+        }
+    }
+
+    /// Parse an integrand and its differential,
+    /// returning the name of the variable of integration.
+    ///
+    /// The variable is bound in the integrand,
+    /// but it is not known until the differential is reached,
+    /// so the integrand is parsed once to find the differential,
+    /// and once more with the variable in scope.
+    fn integrand(&mut self, scope: &Scope) -> Result<(String, Term)>
+    {
+        let start = self.position;
+        let is_empty = self.differential().is_some();
+        if !is_empty {
+            self.sum(scope)?;
+        }
+        let d = self.differential().ok_or_else(|| {
+            self.unexpected(&["differential"])
+        })?;
+        self.position += d;
+        let name = self.variable_name()?;
+        let end = self.position;
+
+        let integrand = if is_empty {
+            Term::integer_i32(1)
+        } else {
+            self.position = start;
+            let variable = self.symbols.get(name.as_bytes());
+            let scope = Scope::new(Some(scope), [variable]);
+            let integrand = self.sum(&scope)?;
+            self.position = end;
+            integrand
+        };
+        Ok((name, integrand))
+    }
+
+    /// If the next tokens are a differential in an integrand,
+    /// as in `dx` or `\mathrm{d}\theta`,
+    /// the number of tokens before the variable.
+    fn differential(&self) -> Option<usize>
+    {
+        if self.integrals == 0 {
+            return None;
+        }
+        let d = match (self.peek(), self.peek_at(1)) {
+            (Some(LatexToken::Letter('d')), _) => 1,
+            (Some(LatexToken::Command("mathrm")),
+             Some(LatexToken::LeftBrace)) => {
+                let d = (self.peek_at(2), self.peek_at(3));
+                match d {
+                    (Some(LatexToken::Letter('d')),
+                     Some(LatexToken::RightBrace)) => 4,
+                    _ => return None,
+                }
+            },
+            _ => return None,
+        };
+        match self.peek_at(d) {
+            Some(LatexToken::Letter(..)) => Some(d),
+            Some(LatexToken::Command(command))
+                if greek(command).is_some() => Some(d),
+            _ => None,
+        }
+    }
+
+    /// Parse the rest of a sum or a product, as in `\sum_{k=1}^{n} k^2`.
+    fn big_operator(&mut self, scope: &Scope, head: &str, span: Span)
+        -> Result<Term>
+    {
+        let mut index = None;
+        let mut upper = None;
+        loop {
+            match self.peek() {
+                Some(LatexToken::Underscore) if index.is_none() => {
+                    self.position += 1;
+                    index = Some(self.index(scope)?);
+                },
+                Some(LatexToken::Caret) if upper.is_none() => {
+                    self.position += 1;
+                    upper = Some(self.argument(scope)?);
+                },
+                _ => break,
+            }
+        }
+        let ((variable, lower), upper) = match (index, upper) {
+            (Some(index), Some(upper)) => (index, upper),
+            _ => {
+                let found = &self.source[span.clone()];
+                let message = format!(
+                    "`{0}` must have limits, as in `{0}_{{k=1}}^{{n}}`",
+                    found,
+                );
+                return Err(Error::new(span, Some(found), message));
+            },
+        };
+        let body = {
+            let scope = Scope::new(Some(scope), [variable.clone()]);
+            self.product(&scope)?
+        };
+        let function = lambda(variable, body);
+        Ok(self.apply(head, vec![function, lower, upper]))
+    }
+
+    /// Parse `{k=1}` in the limits of a sum or a product.
+    fn index(&mut self, scope: &Scope) -> Result<(Symbol, Term)>
+    {
+        self.expect(LatexToken::LeftBrace, "`{`")?;
+        let name = self.variable_name()?;
+        self.expect(LatexToken::Equal, "`=`")?;
+        let lower = self.sum(scope)?;
+        self.expect(LatexToken::RightBrace, "`}`")?;
+        Ok((self.symbols.get(name.as_bytes()), lower))
+    }
+
+    /// Parse a letter or a Greek letter that is to be bound.
+    fn variable_name(&mut self) -> Result<String>
+    {
+        let name = match self.peek() {
+            Some(LatexToken::Letter(letter)) => letter.to_string(),
+            Some(LatexToken::Command(command)) if greek(command).is_some() =>
+                greek(command).unwrap().to_owned(),
+            _ => return Err(self.unexpected(&["letter"])),
+        };
+        self.position += 1;
+        Ok(name)
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    // Building blocks
+
+    fn peek(&self) -> Option<LatexToken<'a>>
+    {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<LatexToken<'a>>
+    {
+        self.tokens.get(self.position + offset).map(|(token, _)| *token)
+    }
+
+    fn peek_span(&self) -> Span
+    {
+        match self.tokens.get(self.position) {
+            Some((_, span)) => span.clone(),
+            None => self.source.len() .. self.source.len(),
+        }
+    }
+
+    /// Skip the next token if it is the given one.
+    fn eat(&mut self, token: LatexToken) -> bool
+    {
+        let matched = self.peek() == Some(token);
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+
+    /// Skip the next token if it is the given one, and fail otherwise.
+    fn expect(&mut self, token: LatexToken, expected: &'static str)
+        -> Result<()>
+    {
+        match self.eat(token) {
+            true => Ok(()),
+            false => Err(self.unexpected(&[expected])),
+        }
+    }
+
+    /// Error for the next token not being one of those expected.
+    fn unexpected(&self, expected: &[&'static str]) -> Error
+    {
+        let span = self.peek_span();
+        let found = self.peek().map(|_| &self.source[span.clone()]);
+        Error::unexpected(span, found, expected)
+    }
+
+    /// The variable in scope with the given name, or else the symbol.
+    fn variable(&self, scope: &Scope, name: &str) -> Term
+    {
+        let symbol = self.symbols.get(name.as_bytes());
+        match scope.get(&symbol) {
+            Some(de_bruijn) => Term::variable(de_bruijn),
+            None if name == "e" => self.symbol("E"),
+            None => Term::symbol(symbol),
+        }
+    }
+
+    fn symbol(&self, name: &str) -> Term
+    {
+        self.symbols.get(name.as_bytes()).term()
+    }
+
+    fn apply(&self, head: &str, arguments: Vec<Term>) -> Term
+    {
+        Term::application(self.symbol(head), arguments)
+    }
+
+    /// Apply a flat operator, unless there is only one operand.
+    fn flat(&self, head: &str, mut operands: Vec<Term>) -> Term
+    {
+        match operands.len() {
+            1 => operands.pop().unwrap(),
+            _ => self.apply(head, operands),
+        }
+    }
+}
+
+/// The function `|x| body`.
+fn lambda(variable: Symbol, body: Term) -> Term
+{
+    let parameter = Parameter{strictness: Strictness::Strict, name: variable};
+    Term::lambda(vec![parameter].into(), body)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Rendering
+
+/// Precedence levels of rendered math, from loosest to tightest.
+pub(crate) mod precedence
+{
+    use crate::Associativity;
+
+    pub const LAMBDA: u32 = 0;
+    pub const RELATION: u32 = 10;
+    pub const ADD: u32 = 20;
+    pub const NEGATE: u32 = 25;
+    pub const MULTIPLY: u32 = 30;
+
+    /// Function without parentheses, as in `\sin x`,
+    /// and big operators, which extend as far to the right as they can.
+    pub const FUNCTION: u32 = 35;
+
+    pub const FRACTION: u32 = 40;
+    pub const POWER: u32 = 50;
+    pub const APPLICATION: u32 = 55;
+    pub const ATOM: u32 = 60;
+
+    /// Precedence of an operator declared at the given level,
+    /// among the built-in operators, which are at levels 4, 6, 7 and 8.
+    pub fn notation(level: u32) -> u32
+    {
+        match level {
+            0 ..= 4 => RELATION + level - 4,
+            5 => (RELATION + ADD) / 2,
+            6 => ADD,
+            7 => MULTIPLY,
+            8 => POWER,
+            _ => (POWER + APPLICATION) / 2,
+        }
+    }
+
+    /// Precedences of the operands of an operator with a precedence.
+    pub fn operands(precedence: u32, associativity: Associativity)
+        -> (u32, u32)
+    {
+        match associativity {
+            Associativity::Left => (precedence, precedence + 1),
+            Associativity::Right => (precedence + 1, precedence),
+            Associativity::None => (precedence + 1, precedence + 1),
+        }
+    }
+}
+
+/// Render a term as LaTeX math.
+///
+/// Operators are written with as few parentheses as their precedence
+/// allows, and many heads are written in their usual notation:
+///
+/// | Term                         | LaTeX                              |
+/// |------------------------------|------------------------------------|
+/// | `a + -1 * b`                 | `a - b`                            |
+/// | `a * b ^ -1`, `x ^ -2`       | `\frac{a}{b}`, `\frac{1}{x^{2}}`   |
+/// | `x ^ n`, `E ^ x`             | `x^{n}`, `e^{x}`                   |
+/// | `x ^ (1 / 2)`, `x ^ (1 / n)` | `\sqrt{x}`, `\sqrt[n]{x}`          |
+/// | `Sin(x)`, `Sin(x + 1)`       | `\sin x`, `\sin\left(x + 1\right)` |
+/// | `Sin(x) ^ 2`, `Log(b, x)`    | `\sin^{2} x`, `\log_{b} x`         |
+/// | `Derivative(f)`              | `f'`                               |
+/// | `Derivative(\|x\| b)`        | `\frac{d}{dx} b`                   |
+/// | `Integrate(\|x\| f, a, b)`   | `\int_{a}^{b} f \,dx`              |
+/// | `Sum(\|k\| f, a, b)`         | `\sum_{k=a}^{b} f`                 |
+/// | `f(a)`                       | `f\left(a\right)`                  |
+/// | `Foo(a)`                     | `\operatorname{Foo}\left(a\right)` |
+/// | `\|x\| b`, `\|x, y\| b`      | `x \mapsto b`, `(x, y) \mapsto b`  |
+/// | `Pi`, `E`, `Infinity`, `α`   | `\pi`, `e`, `\infty`, `\alpha`     |
+///
+/// Declared operators are written as described at [`Notations`].
+///
+/// Formulas without lambdas, derivatives, declared operators or
+/// applications of functions without notation are parsed back
+/// into the same term by [`parse_latex`].
+pub fn render_latex(term: &Term, notations: &Notations) -> String
+{
+    LatexRenderer{notations, names: Vec::new()}.term(term).text
+}
+
+/// Rendered LaTeX, with the precedence of its outermost operator.
+struct Latex
+{
+    text: String,
+    precedence: u32,
+}
+
+impl Latex
+{
+    fn new(text: String, precedence: u32) -> Self
+    {
+        Self{text, precedence}
+    }
+
+    /// The text, in parentheses if it binds looser than the given level.
+    fn at(self, precedence: u32) -> String
+    {
+        if self.precedence < precedence {
+            format!(r"\left({}\right)", self.text)
+        } else {
+            self.text
+        }
+    }
+}
+
+/// State of the renderer for LaTeX math.
+struct LatexRenderer<'n>
+{
+    notations: &'n Notations,
+
+    /// Rendered names of the variables in scope,
+    /// the last of which has De Bruijn index zero.
+    names: Vec<String>,
+}
+
+impl<'n> LatexRenderer<'n>
+{
+    fn term(&mut self, term: &Term) -> Latex
+    {
+        use precedence::*;
+        match term.view() {
+
+            View::Application(function, arguments) =>
+                self.application(function, arguments),
+
+            View::Integer(value) if value < 0 =>
+                Latex::new(value.to_string(), NEGATE),
+
+            View::Integer(value) =>
+                Latex::new(value.to_string(), ATOM),
+
+            View::Lambda(parameters, body) => {
+                let (names, body) = self.lambda(parameters, body, LAMBDA);
+                let names = match names.as_slice() {
+                    [name] => name.clone(),
+                    _ => format!("({})", names.join(", ")),
+                };
+                Latex::new(format!(r"{} \mapsto {}", names, body), LAMBDA)
+            },
+
+            View::String(bytes) => {
+                let text = escape(&String::from_utf8_lossy(bytes));
+                Latex::new(format!(r"\text{{{}}}", text), ATOM)
+            },
+
+            View::Symbol(symbol) =>
+                Latex::new(symbol_latex(symbol.name()), ATOM),
+
+            View::Variable(de_bruijn) => {
+                let index = de_bruijn.0 as usize;
+                let text = match self.names.len().checked_sub(index + 1) {
+                    Some(i) => self.names[i].clone(),
+                    None => format!(r"\#{}", index),
+                };
+                Latex::new(text, ATOM)
+            },
+
+        }
+    }
+
+    /// Render the body of a lambda with its parameters in scope,
+    /// returning the names of the parameters and the body.
+    fn lambda(&mut self, parameters: &[Parameter], body: &Term, level: u32)
+        -> (Vec<String>, String)
+    {
+        let names: Vec<String> =
+            parameters.iter()
+            .map(|parameter| symbol_latex(parameter.name.name()))
+            .collect();
+        let outer = self.names.len();
+        self.names.extend(names.iter().rev().cloned());
+        let body = self.term(body).at(level);
+        self.names.truncate(outer);
+        (names, body)
+    }
+
+    fn application(&mut self, function: &Term, arguments: &[Term]) -> Latex
+    {
+        use precedence::*;
+
+        let head = match function.view() {
+            View::Symbol(symbol) => symbol.name(),
+            _ => {
+                if let Some(latex) = self.antiderivative(function, arguments) {
+                    return latex;
+                }
+                return self.generic(function, arguments);
+            },
+        };
+
+        if let (Some(notation), [lhs, rhs]) =
+            (self.notations.of_head(head), arguments)
+        {
+            return self.infix(notation, lhs, rhs);
+        }
+
+        match (head, arguments) {
+
+            (b"Add", [first, rest @ ..]) if !rest.is_empty() =>
+                self.add(first, rest),
+
+            (b"Multiply", [_, _, ..]) =>
+                self.multiply(arguments),
+
+            (b"Power", [base, exponent]) =>
+                self.power(base, exponent),
+
+            (b"Derivative", [function]) =>
+                self.derivative(function),
+
+            (b"Abs", [argument]) => {
+                let argument = self.term(argument).text;
+                Latex::new(format!(r"\left|{}\right|", argument), ATOM)
+            },
+
+            (b"Subscript", [base, subscript]) => {
+                let base = self.term(base).at(ATOM);
+                let subscript = self.term(subscript).text;
+                Latex::new(format!("{}_{{{}}}", base, subscript), ATOM)
+            },
+
+            (b"Log", [base, argument]) => {
+                let base = self.term(base).text;
+                self.function(&format!(r"\log_{{{}}}", base), "", argument)
+            },
+
+            (b"List", _) => {
+                let elements = self.arguments(arguments);
+                Latex::new(format!(r"\left\{{{}\right\}}", elements), ATOM)
+            },
+
+            (b"Integrate", [integrand, lower, upper]) =>
+                self.integral(integrand, Some((lower, upper)))
+                    .unwrap_or_else(|| self.generic(function, arguments)),
+
+            (b"Sum", [summand, lower, upper]) =>
+                self.big_operator(r"\sum", summand, lower, upper)
+                    .unwrap_or_else(|| self.generic(function, arguments)),
+
+            (b"Product", [factor, lower, upper]) =>
+                self.big_operator(r"\prod", factor, lower, upper)
+                    .unwrap_or_else(|| self.generic(function, arguments)),
+
+            _ => {
+                if let Some(relation) = relation_command(head) {
+                    if let [lhs, rhs] = arguments {
+                        let lhs = self.term(lhs).at(ADD);
+                        let rhs = self.term(rhs).at(ADD);
+                        let text = format!("{} {} {}", lhs, relation, rhs);
+                        return Latex::new(text, RELATION);
+                    }
+                }
+                match (function_command(head), arguments) {
+                    (Some(command), [argument]) =>
+                        self.function(&format!(r"\{}", command), "", argument),
+                    _ => self.generic(function, arguments),
+                }
+            },
+
+        }
+    }
+
+    /// Render an application of the head of a declared operator.
+    fn infix(&mut self, notation: &Notation, lhs: &Term, rhs: &Term)
+        -> Latex
+    {
+        let precedence = precedence::notation(notation.level);
+        let (left, right) =
+            precedence::operands(precedence, notation.associativity);
+        let lhs = self.term(lhs).at(left);
+        let rhs = self.term(rhs).at(right);
+        let text = format!("{} {} {}", lhs, notation.operator, rhs);
+        Latex::new(text, precedence)
+    }
+
+    /// Render an application without notation, as in `f\left(x\right)`.
+    fn generic(&mut self, function: &Term, arguments: &[Term]) -> Latex
+    {
+        use precedence::*;
+        let function = match function.view() {
+            View::Symbol(symbol) => operator_latex(symbol.name()),
+            _ => self.term(function).at(APPLICATION),
+        };
+        let arguments = self.arguments(arguments);
+        let text = format!(r"{}\left({}\right)", function, arguments);
+        Latex::new(text, APPLICATION)
+    }
+
+    fn arguments(&mut self, arguments: &[Term]) -> String
+    {
+        let arguments: Vec<String> =
+            arguments.iter()
+            .map(|argument| self.term(argument).text)
+            .collect();
+        arguments.join(", ")
+    }
+
+    /// Render a function such as `\sin`, with an optional exponent.
+    ///
+    /// Parentheses around the argument are left out
+    /// if it is a power or tighter, as in `\sin x^{2}`.
+    fn function(&mut self, command: &str, exponent: &str, argument: &Term)
+        -> Latex
+    {
+        use precedence::*;
+        let argument = self.term(argument);
+        if argument.precedence >= POWER {
+            let text = format!("{}{} {}", command, exponent, argument.text);
+            Latex::new(text, FUNCTION)
+        } else {
+            let text = format!(
+                r"{}{}\left({}\right)",
+                command, exponent, argument.text,
+            );
+            Latex::new(text, APPLICATION)
+        }
+    }
+
+    fn add(&mut self, first: &Term, rest: &[Term]) -> Latex
+    {
+        use precedence::*;
+        let mut text = self.term(first).at(ADD);
+        for term in rest {
+            match self.negative(term) {
+                Some(term) => {
+                    text.push_str(" - ");
+                    text.push_str(&term.at(MULTIPLY));
+                },
+                None => {
+                    text.push_str(" + ");
+                    text.push_str(&self.term(term).at(MULTIPLY));
+                },
+            }
+        }
+        Latex::new(text, ADD)
+    }
+
+    /// Render the negation of a term that is negative,
+    /// such as `-2` or `-1 * x`, without its sign.
+    fn negative(&mut self, term: &Term) -> Option<Latex>
+    {
+        use precedence::*;
+        match term.view() {
+            View::Integer(value) if value < 0 => {
+                let value = value.checked_neg()?;
+                Some(Latex::new(value.to_string(), ATOM))
+            },
+            View::Application(function, arguments)
+                if is_symbol(function, b"Multiply") =>
+            {
+                let factors = without_sign(arguments)?;
+                Some(self.product(&factors))
+            },
+            _ => None,
+        }
+    }
+
+    fn multiply(&mut self, factors: &[Term]) -> Latex
+    {
+        use precedence::*;
+        match without_sign(factors) {
+            Some(factors) => {
+                let product = self.product(&factors).at(MULTIPLY);
+                Latex::new(format!("-{}", product), NEGATE)
+            },
+            None => self.product(factors),
+        }
+    }
+
+    /// Render a product, with the factors that have negative exponents
+    /// in the denominator of a fraction.
+    fn product(&mut self, factors: &[Term]) -> Latex
+    {
+        use precedence::*;
+        let mut numerator = Vec::new();
+        let mut denominator = Vec::new();
+        for factor in factors {
+            match reciprocal(factor) {
+                Some(factor) => denominator.push(factor),
+                None => numerator.push(factor.clone()),
+            }
+        }
+        if denominator.is_empty() {
+            return self.juxtapose(&numerator);
+        }
+        let numerator = match numerator.is_empty() {
+            true => "1".to_owned(),
+            false => self.juxtapose(&numerator).text,
+        };
+        let denominator = self.juxtapose(&denominator).text;
+        let text = format!(r"\frac{{{}}}{{{}}}", numerator, denominator);
+        Latex::new(text, FRACTION)
+    }
+
+    /// Render factors next to each other, as in `2 x y`.
+    ///
+    /// A `\cdot` separates factors where juxtaposing them
+    /// would change the meaning, as in `x \cdot 2` or `\sin x \cdot y`.
+    fn juxtapose(&mut self, factors: &[Term]) -> Latex
+    {
+        use precedence::*;
+        if let [factor] = factors {
+            return self.term(factor);
+        }
+        let mut text = String::new();
+        let mut open = false;
+        for factor in factors {
+            let factor = self.term(factor);
+            let function = factor.precedence == FUNCTION;
+            let factor = factor.at(FUNCTION);
+            if !text.is_empty() {
+                let digit = factor.starts_with(|c: char| c.is_ascii_digit());
+                text.push_str(if open || digit { r" \cdot " } else { " " });
+            }
+            text.push_str(&factor);
+            open = function;
+        }
+        Latex::new(text, MULTIPLY)
+    }
+
+    fn power(&mut self, base: &Term, exponent: &Term) -> Latex
+    {
+        use precedence::*;
+
+        if let Some(degree) = root_degree(exponent) {
+            let radicand = self.term(base).text;
+            let text = match degree.view() {
+                View::Integer(2) => format!(r"\sqrt{{{}}}", radicand),
+                _ => {
+                    let degree = self.term(degree).text;
+                    format!(r"\sqrt[{}]{{{}}}", degree, radicand)
+                },
+            };
+            return Latex::new(text, ATOM);
+        }
+
+        if let View::Integer(value) = exponent.view() {
+            if value < 0 {
+                if let Some(value) = value.checked_neg() {
+                    let denominator = match value {
+                        1 => self.term(base).text,
+                        _ => self.power(base, &Term::integer_i32(value)).text,
+                    };
+                    let text = format!(r"\frac{{1}}{{{}}}", denominator);
+                    return Latex::new(text, FRACTION);
+                }
+            }
+            if value > 0 {
+                if let Some(latex) = self.function_power(base, exponent) {
+                    return latex;
+                }
+            }
+        }
+
+        let base = self.term(base).at(APPLICATION);
+        let exponent = self.term(exponent).text;
+        Latex::new(format!("{}^{{{}}}", base, exponent), POWER)
+    }
+
+    /// Render a power of a function as in `\sin^{2} x`,
+    /// if the function is written without parentheses.
+    fn function_power(&mut self, base: &Term, exponent: &Term)
+        -> Option<Latex>
+    {
+        let (function, arguments) = match base.view() {
+            View::Application(function, arguments) => (function, arguments),
+            _ => return None,
+        };
+        let command = match (function.view(), arguments) {
+            (View::Symbol(symbol), [_]) => function_command(symbol.name())?,
+            _ => return None,
+        };
+        let exponent = format!("^{{{}}}", self.term(exponent).text);
+        let latex = self.function(&format!(r"\{}", command), &exponent,
+                                  &arguments[0]);
+        match latex.precedence {
+            precedence::FUNCTION => Some(latex),
+            _ => None,
+        }
+    }
+
+    /// Render a derivative as in `f'` or `\frac{d}{dx} b`.
+    fn derivative(&mut self, function: &Term) -> Latex
+    {
+        use precedence::*;
+        if let View::Lambda(parameters, body) = function.view() {
+            if let [_] = &parameters[..] {
+                let (names, body) = self.lambda(parameters, body, MULTIPLY);
+                let text = format!(r"\frac{{d}}{{d{}}} {}", names[0], body);
+                return Latex::new(text, FUNCTION);
+            }
+        }
+        let function = self.term(function).at(APPLICATION);
+        Latex::new(format!("{}'", function), APPLICATION)
+    }
+
+    /// Render `Antiderivative(|x| f)(x)` as in `\int f \,dx`.
+    fn antiderivative(&mut self, function: &Term, arguments: &[Term])
+        -> Option<Latex>
+    {
+        let integrand = match function.view() {
+            View::Application(head, [integrand])
+                if is_symbol(head, b"Antiderivative") => integrand,
+            _ => return None,
+        };
+        match (integrand.view(), arguments) {
+            (View::Lambda(parameters, _), [argument])
+                if parameters.len() == 1
+                    && is_symbol(argument, parameters[0].name.name()) =>
+                self.integral(integrand, None),
+            _ => None,
+        }
+    }
+
+    /// Render an integral of a function of one variable,
+    /// with limits if it is a definite integral.
+    fn integral(&mut self, integrand: &Term, limits: Option<(&Term, &Term)>)
+        -> Option<Latex>
+    {
+        use precedence::*;
+        let (parameters, body) = match integrand.view() {
+            View::Lambda(parameters, body) if parameters.len() == 1 =>
+                (parameters, body),
+            _ => return None,
+        };
+        let limits = match limits {
+            Some((lower, upper)) => {
+                let lower = self.term(lower).text;
+                let upper = self.term(upper).text;
+                format!("_{{{}}}^{{{}}}", lower, upper)
+            },
+            None => String::new(),
+        };
+        let (names, body) = self.lambda(parameters, body, ADD);
+        let text = format!(r"\int{} {} \,d{}", limits, body, names[0]);
+        Some(Latex::new(text, FUNCTION))
+    }
+
+    /// Render a sum or a product as in `\sum_{k=a}^{b} f`.
+    fn big_operator(
+        &mut self,
+        command: &str,
+        function: &Term,
+        lower: &Term,
+        upper: &Term,
+    ) -> Option<Latex>
+    {
+        use precedence::*;
+        let (parameters, body) = match function.view() {
+            View::Lambda(parameters, body) if parameters.len() == 1 =>
+                (parameters, body),
+            _ => return None,
+        };
+        let lower = self.term(lower).text;
+        let upper = self.term(upper).text;
+        let (names, body) = self.lambda(parameters, body, MULTIPLY);
+        let text = format!(
+            "{}_{{{}={}}}^{{{}}} {}",
+            command, names[0], lower, upper, body,
+        );
+        Some(Latex::new(text, FUNCTION))
+    }
+}
+
+fn relation_command(head: &[u8]) -> Option<&'static str>
+{
+    match head {
+        b"Equal" => Some("="),
+        b"Unequal" => Some(r"\ne"),
+        b"Less" => Some("<"),
+        b"LessEqual" => Some(r"\le"),
+        b"Greater" => Some(">"),
+        b"GreaterEqual" => Some(r"\ge"),
+        _ => None,
+    }
+}
+
+/// The name of the function a head stands for, as in `sin`.
+pub(crate) fn function_command(head: &[u8]) -> Option<&'static str>
+{
+    FUNCTIONS.iter()
+        .find(|(_, h)| h.as_bytes() == head)
+        .map(|(command, _)| *command)
+}
+
+/// Render the name of a symbol or variable.
+///
+/// Letters are written as they are, and longer names upright.
+fn symbol_latex(name: &[u8]) -> String
+{
+    let name = String::from_utf8_lossy(name);
+    match &*name {
+        "Pi" => return r"\pi".to_owned(),
+        "E" => return "e".to_owned(),
+        "Infinity" => return r"\infty".to_owned(),
+        _ => (),
+    }
+    if let Some((command, _)) = GREEK.iter().find(|(_, l)| *l == name) {
+        return format!(r"\{}", command);
+    }
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if c.is_ascii_alphabetic() => name.into_owned(),
+        _ => format!(r"\mathrm{{{}}}", escape(&name)),
+    }
+}
+
+/// Render the name of a function, which is upright if it is long.
+fn operator_latex(name: &[u8]) -> String
+{
+    let latex = symbol_latex(name);
+    match latex.strip_prefix(r"\mathrm") {
+        Some(rest) => format!(r"\operatorname{}", rest),
+        None => latex,
+    }
+}
+
+/// Escape the characters that are special in LaTeX text.
+fn escape(text: &str) -> String
+{
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '#' | '$' | '%' | '&' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            },
+            '\\' => escaped.push_str(r"\textbackslash{}"),
+            '^' => escaped.push_str(r"\textasciicircum{}"),
+            '~' => escaped.push_str(r"\textasciitilde{}"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::Lexer;
+    use crate::parse_term;
+    use crate::testing::*;
+
+    #[test]
+    fn arithmetic()
+    {
+        let cases = [
+            (r"\frac{a}{b}", "a / b"),
+            (r"\dfrac12", "1 / 2"),
+            (r"x^{n+1}", "x ^ (n + 1)"),
+            (r"x^12", "x ^ 1 * 2"),
+            (r"e^{x}", "E ^ x"),
+            (r"2xy", "2 * x * y"),
+            (r"1 2 % comment", "12"),
+            (r"-2x + 3", "-2 * x + 3"),
+            (r"a - b c", "a - b * c"),
+            (r"-x^2", "-x ^ 2"),
+            (r"a \cdot b \times c / d \div f", "a * b * c / d / f"),
+            (r"2(x + 1)[y]", "2 * (x + 1) * y"),
+            (r"\sqrt{x}", "x ^ (1 / 2)"),
+            (r"\sqrt[3]{x+1}", "(x + 1) ^ (1 / 3)"),
+            (r"\left( a + b \right)^{2}", "(a + b) ^ 2"),
+            (r"\left| x \right|", "Abs(x)"),
+            (r"x_1^2 + x_{n}", "Subscript(x, 1) ^ 2 + Subscript(x, n)"),
+            (r"\alpha \theta \varepsilon", "α * θ * ε"),
+            (r"2\pi r \ne \infty", "2 * Pi * r != Infinity"),
+            (r"x \le 1", "x <= 1"),
+            (r"\displaystyle a\,=\;b", "a == b"),
+        ];
+        for (source, expected) in cases {
+            assert_imports(parse_latex, source, expected);
+        }
+    }
+
+    #[test]
+    fn functions()
+    {
+        let cases = [
+            (r"\sin x", "Sin(x)"),
+            (r"\sin 2x \cos x", "Sin(2 * x) * Cos(x)"),
+            (r"\sin \cos x", "Sin(Cos(x))"),
+            (r"\sin^2 x + \cos^{2} x", "Sin(x) ^ 2 + Cos(x) ^ 2"),
+            (r"\sin(x)^2", "Sin(x) ^ 2"),
+            (r"\sin\left(x + \pi\right)", "Sin(x + Pi)"),
+            (r"\tan x^2 y", "Tan(x ^ 2 * y)"),
+            (r"\log_2 8", "Log(2, 8)"),
+            (r"\ln x + \exp x", "Ln(x) + E ^ x"),
+            (r"\arctan\frac{y}{x}", "ArcTan(y / x)"),
+        ];
+        for (source, expected) in cases {
+            assert_imports(parse_latex, source, expected);
+        }
+    }
+
+    #[test]
+    fn big_operators()
+    {
+        let cases = [
+            (r"\int x^2 \, dx", "Antiderivative(|x| x ^ 2)(x)"),
+            (r"\int dx", "Antiderivative(|x| 1)(x)"),
+            (r"\int_0^\infty e^{-t} \,\mathrm{d}t",
+             "Integrate(|t| E ^ -t, 0, Infinity)"),
+            (r"\int^{1}_{0} \int_{0}^{x} x y \, dy \, dx",
+             "Integrate(|x| Integrate(|y| x * y, 0, x), 0, 1)"),
+            (r"\int_a^b \sin \theta d\theta + 1",
+             "Integrate(|θ| Sin(θ), a, b) + 1"),
+            (r"\sum_{k=1}^{n} k^2 + 1", "Sum(|k| k ^ 2, 1, n) + 1"),
+            (r"\prod^n_{i=1} \frac{i}{2} d", "Product(|i| (i / 2) * d, 1, n)"),
+            (r"\sum_{k=0}^{\infty} \frac{x^k}{k}",
+             "Sum(|k| x ^ k / k, 0, Infinity)"),
+        ];
+        for (source, expected) in cases {
+            assert_imports(parse_latex, source, expected);
+        }
+
+        // Variables in scope are variables in the formula too.
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, [symbols.get(b"y")]);
+        let term = parse_latex(&symbols, &scope, r"\int x y \,dx").unwrap();
+        let expected = {
+            let mut lex = Lexer::new("Antiderivative(|x| x * y)(x)");
+            parse_term(&symbols, &scope, &mut lex).unwrap()
+        };
+        assert_eq!(format!("{:?}", term), format!("{:?}", expected));
+    }
+
+    #[test]
+    fn errors()
+    {
+        let cases = [
+            (r"\frac{a}",
+             "expected `(`, `[`, `{`, command, digit or letter, \
+              found end of input"),
+            (r"a)", "expected end of input, found `)`"),
+            (r"(a", "expected `)`, found end of input"),
+            (r"\left(a\right]", "expected `)`, found `]`"),
+            (r"\foo x", r"unknown command `\foo`"),
+            (r"a < b \le c", r"`\le` cannot be chained; use parentheses"),
+            (r"\int x", "expected differential, found end of input"),
+            (r"\int_0 x dx", "an integral must have both limits or neither"),
+            (r"\sum k", r"`\sum` must have limits, as in `\sum_{k=1}^{n}`"),
+            (r"\sum_{1=k}^n k", "expected letter, found `1`"),
+            ("2147483648", "integer is too large"),
+            ("$x$",
+             "expected `(`, `[`, `{`, command, digit or letter, \
+              found `$`"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(message(parse_latex, source), expected, "{}", source);
+        }
+    }
+
+    fn render(source: &str) -> String
+    {
+        let term = native(&Symbols::new(), source);
+        render_latex(&term, &Notations::new())
+    }
+
+    #[test]
+    fn rendering()
+    {
+        let cases = [
+            ("a + b - c", r"a + b - c"),
+            ("a - 2 * b + -3", r"a - 2 b - 3"),
+            ("-x", r"-x"),
+            ("-2 * x * y", r"-2 x y"),
+            ("x * 2 * Pi", r"x \cdot 2 \pi"),
+            ("(a + b) * -c", r"\left(a + b\right) \left(-c\right)"),
+            ("a - (b + c)", r"a - \left(b + c\right)"),
+            ("a / b", r"\frac{a}{b}"),
+            ("x ^ -1", r"\frac{1}{x}"),
+            ("2 * y / x ^ 2 / z", r"\frac{2 y}{x^{2} z}"),
+            ("-a / b", r"\frac{-a}{b}"),
+            ("(a + b) ^ 2", r"\left(a + b\right)^{2}"),
+            ("x ^ y ^ z", r"x^{y^{z}}"),
+            ("(x ^ y) ^ z", r"\left(x^{y}\right)^{z}"),
+            ("E ^ -t", r"e^{-t}"),
+            ("x ^ (1 / 2) + (x + 1) ^ (1 / n)",
+             r"\sqrt{x} + \sqrt[n]{x + 1}"),
+            ("Sin(x) * Cos(x + 1)", r"\sin x \cdot \cos\left(x + 1\right)"),
+            ("Sin(x) ^ 2 + Sin(2 * x)", r"\sin^{2} x + \sin\left(2 x\right)"),
+            ("Log(2, x) + Ln(x ^ 2)", r"\log_{2} x + \ln x^{2}"),
+            ("Abs(x) <= Subscript(x, 1) ^ 2", r"\left|x\right| \le x_{1}^{2}"),
+            ("(a == b) != c", r"\left(a = b\right) \ne c"),
+            ("Derivative(f)", r"f'"),
+            ("Derivative(Derivative(f))(x)", r"f''\left(x\right)"),
+            ("Derivative(|x| x ^ 2)", r"\frac{d}{dx} x^{2}"),
+            ("Integrate(|t| E ^ -t, 0, Infinity)",
+             r"\int_{0}^{\infty} e^{-t} \,dt"),
+            ("Antiderivative(|θ| Sin(θ))(θ)", r"\int \sin \theta \,d\theta"),
+            ("Sum(|k| k ^ 2, 1, n) + 1", r"\sum_{k=1}^{n} k^{2} + 1"),
+            ("|x| x + 1", r"x \mapsto x + 1"),
+            ("|x, y| f(y, x)", r"(x, y) \mapsto f\left(y, x\right)"),
+            ("Foo(α, {1})",
+             r"\operatorname{Foo}\left(\alpha, \left\{1\right\}\right)"),
+            ("\"a_b\" + Alpha", r"\text{a\_b} + \mathrm{Alpha}"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(render(source), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn rendering_notations()
+    {
+        let source = "infixl 6 \"⊕\" = DirectSum; \
+                      infixr 8 \"⊗\" = Tensor; \
+                      (a ⊕ b) ⊕ (c ⊕ d) * (x ⊗ y) ⊗ z ⊗ w";
+        let (term, notations) = program(&Symbols::new(), source);
+        assert_eq!(
+            render_latex(&term, &notations),
+            r"a ⊕ b ⊕ \left(c ⊕ d\right) \left(x ⊗ y\right) ⊗ z ⊗ w",
+        );
+    }
+
+    #[test]
+    fn rendering_round_trip()
+    {
+        let sources = [
+            r"a - 2 b \cdot 3 + \frac{x}{y^{2}}",
+            r"-2x^2 + x \cdot 2",
+            r"\sqrt[3]{x + 1} = e^{-x}",
+            r"\sin^2 x + \cos 2x \tan x \le \log_2 8",
+            r"\int_0^\infty e^{-t} \,dt + \int x^2 \,dx",
+            r"\sum_{k=1}^{n} \frac{x^k}{k} \ne \prod_{i=1}^{n} (i + 1)",
+            r"\left| x_1 \right|^2 - \alpha \pi",
+        ];
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, []);
+        for source in sources {
+            let term = parse_latex(&symbols, &scope, source).unwrap();
+            let latex = render_latex(&term, &Notations::new());
+            let again = parse_latex(&symbols, &scope, &latex).unwrap();
+            assert_eq!(format!("{:?}", again), format!("{:?}", term),
+                       "{} rendered as {}", source, latex);
+        }
+    }
+}
@@ -1,10 +1,13 @@
 pub use logos::Logos;
 
+use crate::Span;
+
 use logos::Lexer;
 use std::borrow::Cow;
+use std::str::Chars;
 
 /// Token generated during lexing.
-#[derive(Debug, Logos)]
+#[derive(Clone, Debug, Logos)]
 pub enum Token<'a>
 {
     /// Emitted when there is a lexical error.
@@ -12,14 +15,62 @@ pub enum Token<'a>
     #[regex(r"[\t\n\v\f\r ]+", logos::skip)]
     Error,
 
+    /// Comment, which is ignored by the parser.
+    ///
+    /// Line comments start with `#` and extend to the end of the line.
+    /// Block comments are enclosed in `(*` and `*)`, and may be nested.
+    /// `(**)` is an empty block comment rather than a documentation comment.
+    #[regex(r"#([^#\n][^\n]*)?")]
+    #[token("(*", lex_block_comment)]
+    #[token("(**)")]
+    Comment,
+
+    /// Documentation comment.
+    ///
+    /// Line documentation comments start with `##`.
+    /// Block documentation comments are enclosed in `(**` and `*)`.
+    /// The parser attaches them to the definition that follows them.
+    /// The token records the text of the comment without the delimiters.
+    #[regex(r"##[^\n]*", lex_line_doc_comment)]
+    #[token("(**", lex_block_doc_comment)]
+    DocComment(Cow<'a, str>),
+
     /// `,`.
     #[token(",")]
     Comma,
 
+    /// `;`.
+    #[token(";")]
+    Semicolon,
+
+    /// `:`.
+    #[token(":")]
+    Colon,
+
+    /// `:=`.
+    #[token(":=")]
+    ColonEqual,
+
+    /// `:>`.
+    #[token(":>")]
+    ColonGreater,
+
+    /// `=`.
+    #[token("=")]
+    Equal,
+
     /// `|`.
     #[token("|")]
     Pipe,
 
+    /// `<|`.
+    #[token("<|")]
+    LessPipe,
+
+    /// `|>`.
+    #[token("|>")]
+    PipeGreater,
+
     /// `~`.
     #[token("~")]
     Tilde,
@@ -32,33 +83,372 @@ pub enum Token<'a>
     #[token(")")]
     RightParenthesis,
 
+    /// `[`.
+    #[token("[")]
+    LeftBracket,
+
+    /// `[[`.
+    ///
+    /// Where this does not directly follow a token that ends an operand,
+    /// as in `[[1, 2], [3, 4]]`, the token is split into two `[`;
+    /// see [`Lexer`]. There is no `]]` token, as `]]` is always two `]`.
+    ///
+    /// [`Lexer`]: `crate::Lexer`
+    #[token("[[")]
+    LeftDoubleBracket,
+
+    /// `]`.
+    #[token("]")]
+    RightBracket,
+
+    /// `{`.
+    #[token("{")]
+    LeftBrace,
+
+    /// `}`.
+    #[token("}")]
+    RightBrace,
+
+    /// `+`.
+    #[token("+")]
+    Plus,
+
+    /// `-`, or `−`.
+    #[token("-")]
+    #[token("−")]
+    Minus,
+
+    /// `->`.
+    #[token("->")]
+    MinusGreater,
+
+    /// `*`, or `×` or `·`.
+    #[token("*")]
+    #[token("×")]
+    #[token("·")]
+    Asterisk,
+
+    /// `/`.
+    #[token("/")]
+    Slash,
+
+    /// `/;`.
+    #[token("/;")]
+    SlashSemicolon,
+
+    /// `/.`.
+    #[token("/.")]
+    SlashDot,
+
+    /// `//.`.
+    #[token("//.")]
+    SlashSlashDot,
+
+    /// `^`.
+    #[token("^")]
+    Caret,
+
+    /// `√`, which takes the square root of the operand that follows it.
+    #[token("√")]
+    SquareRoot,
+
+    /// `π`, which stands for `Pi`.
+    #[token("π")]
+    Pi,
+
+    /// `==`.
+    #[token("==")]
+    EqualEqual,
+
+    /// `!=`, or `≠`.
+    #[token("!=")]
+    #[token("≠")]
+    ExclamationEqual,
+
+    /// `<`.
+    #[token("<")]
+    Less,
+
+    /// `<=`, or `≤`.
+    #[token("<=")]
+    #[token("≤")]
+    LessEqual,
+
+    /// `>`.
+    #[token(">")]
+    Greater,
+
+    /// `>=`, or `≥`.
+    #[token(">=")]
+    #[token("≥")]
+    GreaterEqual,
+
+    /// `in`.
+    #[token("in")]
+    In,
+
+    /// `infix`.
+    #[token("infix")]
+    Infix,
+
+    /// `infixl`.
+    #[token("infixl")]
+    Infixl,
+
+    /// `infixr`.
+    #[token("infixr")]
+    Infixr,
+
+    /// `let`.
+    #[token("let")]
+    Let,
+
+    /// `rec`.
+    #[token("rec")]
+    Rec,
+
+    /// `where`.
+    #[token("where")]
+    Where,
+
+    /// `with`.
+    #[token("with")]
+    With,
+
     /// Integer token.
+    ///
+    /// The integer is never negative. In `-1`, the `-` is a separate token,
+    /// which the parser folds into the integer.
+    /// As `-2147483648` is a valid integer,
+    /// the token may be one greater than the greatest integer.
     #[regex(r"[0-9]+", lex_integer)]
-    Integer(i32),
+    Integer(u32),
+
+    /// Superscript integer, as in `x²` or `x⁻¹`,
+    /// which raises the operand before it to that power.
+    #[regex(r"⁻?[⁰¹²³⁴⁵⁶⁷⁸⁹]+", lex_superscript)]
+    Superscript(i32),
 
     /// String token.
-    #[regex(r#""[^"]+""#, lex_string)]
+    ///
+    /// The escape sequences `\"`, `\\`, `\n`, `\t`
+    /// and `\u{...}` (with one to six hexadecimal digits) are recognized.
+    #[regex(r#""([^"\\]|\\.)*""#, lex_string)]
     String(Cow<'a, [u8]>),
 
     /// Identifier token.
     ///
+    /// Identifiers consist of letters and digits,
+    /// and do not start with a digit.
+    /// Letters and digits from any script are permitted,
+    /// so Greek letters may be used as in `α` or `θ1`.
+    /// Underscores are not, as they make up blanks,
+    /// and neither are superscript digits, as they make up powers.
+    /// The keywords `in`, `infix`, `infixl`, `infixr`, `let`, `rec`,
+    /// `where` and `with`, and `π`, are not identifiers.
+    ///
     /// Whether this is interpreted as a symbol or as a variable
     /// depends on the scope given to the parser; see [`Scope`].
     ///
     /// [`Scope`]: `crate::Scope`
-    #[regex(r"[A-Za-z]+", lex_identifier)]
+    #[regex(r"\p{L}[\p{L}\p{Nd}]*", lex_identifier)]
     Identifier(Cow<'a, [u8]>),
+
+    /// User-defined operator, as in `a ⊕ b`.
+    ///
+    /// Operators consist of Unicode symbols outside of ASCII,
+    /// other than those that make up built-in operators,
+    /// so `⊕⊗` is a single operator but `⊕×` is not.
+    /// Operators are declared in the program; see [`Notations`].
+    ///
+    /// [`Notations`]: `crate::Notations`
+    #[regex(r"[\p{Sm}\p{So}--[\x00-\x7F×−√≤≥≠⁻]]+", |lex| lex.slice())]
+    Operator(&'a str),
+
+    /// Blank token, which stands for terms in a pattern.
+    ///
+    /// A blank is one to three underscores, optionally preceded
+    /// by the name of a pattern variable and followed by a head,
+    /// as in `_`, `x_`, `n_Integer`, `xs__` and `xs___`.
+    #[regex(r"(\p{L}[\p{L}\p{Nd}]*)?_+(\p{L}[\p{L}\p{Nd}]*)?", lex_blank)]
+    Blank(Blank<'a>),
+}
+
+/// Contents of a blank token.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Blank<'a>
+{
+    /// Name of the pattern variable, as in `x_`.
+    pub name: Option<&'a [u8]>,
+
+    /// How many terms the blank stands for.
+    pub kind: BlankKind,
+
+    /// Head that the terms must have, as in `_Integer`.
+    pub head: Option<&'a [u8]>,
+}
+
+/// How many terms a blank stands for.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlankKind
+{
+    /// `_` stands for exactly one term.
+    One,
+
+    /// `__` stands for a sequence of one or more terms.
+    Sequence,
+
+    /// `___` stands for a sequence of zero or more terms.
+    NullSequence,
 }
 
-fn lex_integer<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Option<i32>
+pub(crate) fn lex_block_comment<'a, T>(lex: &mut Lexer<'a, T>) -> bool
+    where T: Logos<'a, Source=str>
 {
-    i32::from_str_radix(lex.slice(), 10).ok()
+    lex_block_comment_contents(lex).is_some()
 }
 
-fn lex_string<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Cow<'a, [u8]>
+fn lex_line_doc_comment<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Cow<'a, str>
+{
+    let text = &lex.slice()["##".len() ..];
+    text.strip_prefix(' ').unwrap_or(text).into()
+}
+
+fn lex_block_doc_comment<'a>(lex: &mut Lexer<'a, Token<'a>>)
+    -> Option<Cow<'a, str>>
+{
+    let text = lex_block_comment_contents(lex)?;
+    Some(text.trim().into())
+}
+
+/// Skip past the end of a block comment whose opening `(*` was lexed.
+///
+/// Returns the text between the delimiters,
+/// or [`None`] if the comment is not terminated.
+fn lex_block_comment_contents<'a, T>(lex: &mut Lexer<'a, T>)
+    -> Option<&'a str>
+    where T: Logos<'a, Source=str>
+{
+    let remainder = lex.remainder();
+    let mut depth = 1usize;
+    let mut i = 0;
+    while i < remainder.len() {
+        let rest = &remainder.as_bytes()[i ..];
+        if rest.starts_with(b"(*") {
+            depth += 1;
+            i += 2;
+        } else if rest.starts_with(b"*)") {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                lex.bump(i);
+                return Some(&remainder[.. i - 2]);
+            }
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+fn lex_integer<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Option<u32>
+{
+    lex.slice().parse().ok()
+        .filter(|&value: &u32| value <= i32::MIN.unsigned_abs())
+}
+
+fn lex_superscript<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Option<i32>
+{
+    let digits: Option<String> =
+        lex.slice().chars()
+        .map(|char| match char {
+            '⁻' => Some('-'),
+            _ => "⁰¹²³⁴⁵⁶⁷⁸⁹".chars().position(|digit| digit == char)
+                    .and_then(|digit| char::from_digit(digit as u32, 10)),
+        })
+        .collect();
+    digits?.parse().ok()
+}
+
+fn lex_string<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Option<Cow<'a, [u8]>>
 {
     let input = lex.slice();
-    input[1 .. input.len() - 1].as_bytes().into()
+    let input = &input[1 .. input.len() - 1];
+
+    // Most strings contain no escape sequences,
+    // in which case we can borrow from the input.
+    if !input.contains('\\') {
+        return Some(input.as_bytes().into());
+    }
+
+    unescape(input).ok().map(Cow::Owned)
+}
+
+/// Find the first invalid escape sequence in a string literal.
+///
+/// Returns its span within the literal, which includes the quotes.
+pub(crate) fn invalid_escape(literal: &str) -> Option<Span>
+{
+    let input = literal.strip_prefix('"')?.strip_suffix('"')?;
+    let escape = unescape(input).err()?;
+    Some(escape.start + 1 .. escape.end + 1)
+}
+
+/// Replace the escape sequences in the contents of a string literal.
+///
+/// On failure, returns the span of the first invalid escape sequence.
+fn unescape(input: &str) -> Result<Vec<u8>, Span>
+{
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(char) = chars.next() {
+        let unescaped = match char {
+            '\\' => {
+                let start = input.len() - chars.as_str().len() - 1;
+                lex_escape(&mut chars).ok_or_else(|| {
+                    let rest = chars.as_str();
+                    let end = input.len() - rest.len();
+                    // Include the braces of a malformed `\u{...}`.
+                    let braces =
+                        rest.strip_prefix('{')
+                        .and_then(|braced| braced.find('}'))
+                        .filter(|_| input[.. end].ends_with('u'))
+                        .map_or(0, |close| close + 2);
+                    start .. end + braces
+                })?
+            },
+            _ => char,
+        };
+        let mut buffer = [0; 4];
+        bytes.extend_from_slice(unescaped.encode_utf8(&mut buffer).as_bytes());
+    }
+    Ok(bytes)
+}
+
+/// Lex the part of an escape sequence after the `\`.
+fn lex_escape(chars: &mut Chars) -> Option<char>
+{
+    match chars.next()? {
+        '"' => Some('"'),
+        '\\' => Some('\\'),
+        'n' => Some('\n'),
+        't' => Some('\t'),
+        'u' => lex_unicode_escape(chars),
+        _ => None,
+    }
+}
+
+/// Lex the `{...}` part of a `\u{...}` escape sequence.
+fn lex_unicode_escape(chars: &mut Chars) -> Option<char>
+{
+    let rest = chars.as_str().strip_prefix('{')?;
+    let digits = &rest[.. rest.find('}')?];
+    if digits.is_empty() || digits.len() > 6 {
+        return None;
+    }
+    let code_point = u32::from_str_radix(digits, 16).ok()?;
+    *chars = rest[digits.len() + 1 ..].chars();
+    char::from_u32(code_point)
 }
 
 fn lex_identifier<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Cow<'a, [u8]>
@@ -66,15 +456,192 @@ fn lex_identifier<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Cow<'a, [u8]>
     lex.slice().as_bytes().into()
 }
 
+fn lex_blank<'a>(lex: &mut Lexer<'a, Token<'a>>) -> Option<Blank<'a>>
+{
+    let slice = lex.slice();
+    let start = slice.find('_')?;
+    let end = slice.rfind('_')? + 1;
+    let kind = match end - start {
+        1 => BlankKind::One,
+        2 => BlankKind::Sequence,
+        3 => BlankKind::NullSequence,
+        _ => return None,
+    };
+    let part = |part: &'a str| {
+        if part.is_empty() { None } else { Some(part.as_bytes()) }
+    };
+    Some(Blank{name: part(&slice[.. start]), kind, head: part(&slice[end ..])})
+}
+
 #[cfg(test)]
 mod tests
 {
     use super::*;
 
+    #[test]
+    fn statements()
+    {
+        let mut lex = Token::lexer("f:=1;");
+        let mut next = || format!("{:?}", lex.next());
+        assert_eq!(next(), "Some(Identifier([102]))");
+        assert_eq!(next(), "Some(ColonEqual)");
+        assert_eq!(next(), "Some(Integer(1))");
+        assert_eq!(next(), "Some(Semicolon)");
+        assert_eq!(next(), "None");
+    }
+
+    #[test]
+    fn operators()
+    {
+        let mut lex = Token::lexer("+-*/^ == != < <= > >=<");
+        let mut next = || format!("{:?}", lex.next());
+        assert_eq!(next(), "Some(Plus)");
+        assert_eq!(next(), "Some(Minus)");
+        assert_eq!(next(), "Some(Asterisk)");
+        assert_eq!(next(), "Some(Slash)");
+        assert_eq!(next(), "Some(Caret)");
+        assert_eq!(next(), "Some(EqualEqual)");
+        assert_eq!(next(), "Some(ExclamationEqual)");
+        assert_eq!(next(), "Some(Less)");
+        assert_eq!(next(), "Some(LessEqual)");
+        assert_eq!(next(), "Some(Greater)");
+        assert_eq!(next(), "Some(GreaterEqual)");
+        assert_eq!(next(), "Some(Less)");
+        assert_eq!(next(), "None");
+    }
+
+    #[test]
+    fn comments()
+    {
+        let mut lex = Token::lexer(
+            "a # line\n\
+             ## doc 1\n\
+             ##doc 2\n\
+             b (* block (* nested *) ) *) * c\n\
+             (** doc 3 *) (**) #\n\
+             (* unterminated",
+        );
+        let mut next = || format!("{:?}", lex.next());
+        assert_eq!(next(), "Some(Identifier([97]))");
+        assert_eq!(next(), "Some(Comment)");
+        assert_eq!(next(), "Some(DocComment(\"doc 1\"))");
+        assert_eq!(next(), "Some(DocComment(\"doc 2\"))");
+        assert_eq!(next(), "Some(Identifier([98]))");
+        assert_eq!(next(), "Some(Comment)");
+        assert_eq!(next(), "Some(Asterisk)");
+        assert_eq!(next(), "Some(Identifier([99]))");
+        assert_eq!(next(), "Some(DocComment(\"doc 3\"))");
+        assert_eq!(next(), "Some(Comment)");
+        assert_eq!(next(), "Some(Comment)");
+        assert_eq!(next(), "Some(Error)");
+    }
+
+    #[test]
+    fn integers()
+    {
+        let mut lex = Token::lexer("0 -1 42 2147483648 2147483649");
+        let mut next = || format!("{:?}", lex.next());
+        assert_eq!(next(), "Some(Integer(0))");
+        assert_eq!(next(), "Some(Minus)");
+        assert_eq!(next(), "Some(Integer(1))");
+        assert_eq!(next(), "Some(Integer(42))");
+        assert_eq!(next(), "Some(Integer(2147483648))");
+        assert_eq!(next(), "Some(Error)");
+        assert_eq!(next(), "None");
+    }
+
+    #[test]
+    fn strings()
+    {
+        let string = |source| match Token::lexer(source).next() {
+            Some(Token::String(bytes)) =>
+                Some(String::from_utf8(bytes.into_owned()).unwrap()),
+            _ => None,
+        };
+        assert_eq!(string(r#""""#).as_deref(), Some(""));
+        assert_eq!(string(r#""abc""#).as_deref(), Some("abc"));
+        assert_eq!(string(r#""a\"b""#).as_deref(), Some("a\"b"));
+        assert_eq!(string(r#""a\\b""#).as_deref(), Some("a\\b"));
+        assert_eq!(string(r#""a\nb\t""#).as_deref(), Some("a\nb\t"));
+        assert_eq!(string(r#""\u{3C0}\u{1F600}""#).as_deref(),
+                   Some("\u{3C0}\u{1F600}"));
+        assert_eq!(string("\"a\nb\"").as_deref(), Some("a\nb"));
+        assert_eq!(string(r#""\q""#), None);
+        assert_eq!(string(r#""\u{}""#), None);
+        assert_eq!(string(r#""\u{1234567}""#), None);
+        assert_eq!(string(r#""\u{D800}""#), None);
+        assert_eq!(string(r#""\u3C0""#), None);
+        assert_eq!(string(r#""abc"#), None);
+    }
+
+    #[test]
+    fn identifiers()
+    {
+        let identifier = |source| match Token::lexer(source).next() {
+            Some(Token::Identifier(bytes)) =>
+                Some(String::from_utf8(bytes.into_owned()).unwrap()),
+            _ => None,
+        };
+        assert_eq!(identifier("abc").as_deref(), Some("abc"));
+        assert_eq!(identifier("x2").as_deref(), Some("x2"));
+        assert_eq!(identifier("snake_case"), None);
+        assert_eq!(identifier("_x"), None);
+        assert_eq!(identifier("αβγ").as_deref(), Some("αβγ"));
+        assert_eq!(identifier("Größe").as_deref(), Some("Größe"));
+        assert_eq!(identifier("θ1").as_deref(), Some("θ1"));
+        assert_eq!(identifier("x²").as_deref(), Some("x"));
+        assert_eq!(identifier("πr").as_deref(), Some("πr"));
+        assert_eq!(identifier("π"), None);
+        assert_eq!(identifier("x$0").as_deref(), Some("x"));
+        assert_eq!(identifier("2x"), None);
+        assert_eq!(identifier("let"), None);
+        assert_eq!(identifier("lettuce").as_deref(), Some("lettuce"));
+        assert_eq!(identifier("within").as_deref(), Some("within"));
+    }
+
+    #[test]
+    fn blanks()
+    {
+        let blank = |source| match Token::lexer(source).next() {
+            Some(Token::Blank(Blank{name, kind, head})) => {
+                let string = |bytes: Option<&[u8]>| {
+                    bytes.map(|b| String::from_utf8(b.to_vec()).unwrap())
+                };
+                Some((string(name), kind, string(head)))
+            },
+            _ => None,
+        };
+        let some = |name: Option<&str>, kind, head: Option<&str>| {
+            Some((name.map(str::to_owned), kind, head.map(str::to_owned)))
+        };
+        assert_eq!(blank("_"), some(None, BlankKind::One, None));
+        assert_eq!(blank("x_"), some(Some("x"), BlankKind::One, None));
+        assert_eq!(blank("_Integer"),
+                   some(None, BlankKind::One, Some("Integer")));
+        assert_eq!(blank("n2_Integer"),
+                   some(Some("n2"), BlankKind::One, Some("Integer")));
+        assert_eq!(blank("xs__"),
+                   some(Some("xs"), BlankKind::Sequence, None));
+        assert_eq!(blank("xs___f"),
+                   some(Some("xs"), BlankKind::NullSequence, Some("f")));
+        assert_eq!(blank("α_β"), some(Some("α"), BlankKind::One, Some("β")));
+
+        let mut lex = Token::lexer("x____ x_. _2");
+        let mut next = || format!("{:?}", lex.next());
+        assert_eq!(next(), "Some(Error)");
+        assert_eq!(next(), "Some(Blank(Blank { name: Some([120]), \
+                            kind: One, head: None }))");
+        assert_eq!(next(), "Some(Error)");
+        assert_eq!(next(), "Some(Blank(Blank { name: None, \
+                            kind: One, head: None }))");
+        assert_eq!(next(), "Some(Integer(2))");
+        assert_eq!(next(), "None");
+    }
+
     #[test]
     fn fine()
     {
-        let mut lex = Token::lexer(r#",|~()123"Abc"Abc"#);
+        let mut lex = Token::lexer(r#",|~()123"Abc"Abc: /;"#);
         let mut next = || format!("{:?}", lex.next());
         assert_eq!(next(), "Some(Comma)");
         assert_eq!(next(), "Some(Pipe)");
@@ -84,6 +651,110 @@ mod tests
         assert_eq!(next(), "Some(Integer(123))");
         assert_eq!(next(), "Some(String([65, 98, 99]))");
         assert_eq!(next(), "Some(Identifier([65, 98, 99]))");
+        assert_eq!(next(), "Some(Colon)");
+        assert_eq!(next(), "Some(SlashSemicolon)");
+        assert_eq!(next(), "None");
+    }
+
+    #[test]
+    fn brackets()
+    {
+        let mut lex = Token::lexer("{[[]]}<|a->b|>[ [");
+        let mut next = || format!("{:?}", lex.next());
+        assert_eq!(next(), "Some(LeftBrace)");
+        assert_eq!(next(), "Some(LeftDoubleBracket)");
+        assert_eq!(next(), "Some(RightBracket)");
+        assert_eq!(next(), "Some(RightBracket)");
+        assert_eq!(next(), "Some(RightBrace)");
+        assert_eq!(next(), "Some(LessPipe)");
+        assert_eq!(next(), "Some(Identifier([97]))");
+        assert_eq!(next(), "Some(MinusGreater)");
+        assert_eq!(next(), "Some(Identifier([98]))");
+        assert_eq!(next(), "Some(PipeGreater)");
+        assert_eq!(next(), "Some(LeftBracket)");
+        assert_eq!(next(), "Some(LeftBracket)");
+        assert_eq!(next(), "None");
+    }
+
+    #[test]
+    fn rules()
+    {
+        let mut lex = Token::lexer("x_:>1 /. a->b //. c:=/;/");
+        let mut next = || format!("{:?}", lex.next());
+        assert!(next().starts_with("Some(Blank("));
+        assert_eq!(next(), "Some(ColonGreater)");
+        assert_eq!(next(), "Some(Integer(1))");
+        assert_eq!(next(), "Some(SlashDot)");
+        assert_eq!(next(), "Some(Identifier([97]))");
+        assert_eq!(next(), "Some(MinusGreater)");
+        assert_eq!(next(), "Some(Identifier([98]))");
+        assert_eq!(next(), "Some(SlashSlashDot)");
+        assert_eq!(next(), "Some(Identifier([99]))");
+        assert_eq!(next(), "Some(ColonEqual)");
+        assert_eq!(next(), "Some(SlashSemicolon)");
+        assert_eq!(next(), "Some(Slash)");
+        assert_eq!(next(), "None");
+    }
+
+    #[test]
+    fn unicode()
+    {
+        let mut lex = Token::lexer("π×x·y−√z ≤ ≥ ≠ x²⁰ x⁻¹ ⁻ ²¹⁴⁷⁴⁸³⁶⁴⁸");
+        let mut next = || format!("{:?}", lex.next());
+        assert_eq!(next(), "Some(Pi)");
+        assert_eq!(next(), "Some(Asterisk)");
+        assert_eq!(next(), "Some(Identifier([120]))");
+        assert_eq!(next(), "Some(Asterisk)");
+        assert_eq!(next(), "Some(Identifier([121]))");
+        assert_eq!(next(), "Some(Minus)");
+        assert_eq!(next(), "Some(SquareRoot)");
+        assert_eq!(next(), "Some(Identifier([122]))");
+        assert_eq!(next(), "Some(LessEqual)");
+        assert_eq!(next(), "Some(GreaterEqual)");
+        assert_eq!(next(), "Some(ExclamationEqual)");
+        assert_eq!(next(), "Some(Identifier([120]))");
+        assert_eq!(next(), "Some(Superscript(20))");
+        assert_eq!(next(), "Some(Identifier([120]))");
+        assert_eq!(next(), "Some(Superscript(-1))");
+        assert_eq!(next(), "Some(Error)");
+        assert_eq!(next(), "Some(Error)");
+        assert_eq!(next(), "None");
+    }
+
+    #[test]
+    fn keywords()
+    {
+        let mut lex = Token::lexer("let rec x=1 in with y == 2 in z where");
+        let mut next = || format!("{:?}", lex.next());
+        assert_eq!(next(), "Some(Let)");
+        assert_eq!(next(), "Some(Rec)");
+        assert_eq!(next(), "Some(Identifier([120]))");
+        assert_eq!(next(), "Some(Equal)");
+        assert_eq!(next(), "Some(Integer(1))");
+        assert_eq!(next(), "Some(In)");
+        assert_eq!(next(), "Some(With)");
+        assert_eq!(next(), "Some(Identifier([121]))");
+        assert_eq!(next(), "Some(EqualEqual)");
+        assert_eq!(next(), "Some(Integer(2))");
+        assert_eq!(next(), "Some(In)");
+        assert_eq!(next(), "Some(Identifier([122]))");
+        assert_eq!(next(), "Some(Where)");
+        assert_eq!(next(), "None");
+    }
+
+    #[test]
+    fn user_operators()
+    {
+        let mut lex = Token::lexer("infixl a⊕b ⊕⊗ ⊕× ∘");
+        let mut next = || format!("{:?}", lex.next());
+        assert_eq!(next(), "Some(Infixl)");
+        assert_eq!(next(), "Some(Identifier([97]))");
+        assert_eq!(next(), "Some(Operator(\"⊕\"))");
+        assert_eq!(next(), "Some(Identifier([98]))");
+        assert_eq!(next(), "Some(Operator(\"⊕⊗\"))");
+        assert_eq!(next(), "Some(Operator(\"⊕\"))");
+        assert_eq!(next(), "Some(Asterisk)");
+        assert_eq!(next(), "Some(Operator(\"∘\"))");
         assert_eq!(next(), "None");
     }
 }
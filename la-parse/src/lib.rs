@@ -3,12 +3,38 @@
 #![feature(decl_macro)]
 #![warn(missing_docs)]
 
+pub use self::cst::*;
 pub use self::error::*;
+pub use self::format::*;
+pub use self::latex::*;
 pub use self::lex::*;
+pub use self::mathematica::*;
+pub use self::mathml::*;
+pub use self::operator::*;
 pub use self::parse::*;
+pub use self::partial::*;
+pub use self::pattern::*;
+pub use self::print::*;
 pub use self::scope::*;
+pub use self::sympy::*;
 
+pub(crate) use self::binding::*;
+
+mod binding;
+mod cst;
 mod error;
+mod format;
+mod latex;
 mod lex;
+mod mathematica;
+mod mathml;
+mod operator;
 mod parse;
+mod partial;
+mod pattern;
+mod print;
 mod scope;
+mod sympy;
+
+#[cfg(test)]
+mod testing;
@@ -0,0 +1,1715 @@
+use crate::Error;
+use crate::Result;
+use crate::Scope;
+use crate::Span;
+use crate::Notations;
+use crate::MAX_DERIVATIVE_ORDER;
+use crate::is_symbol;
+use crate::lex_block_comment;
+use crate::square_root;
+
+use la_term::Term;
+use la_term::View;
+use la_term::lambda::Parameter;
+use la_term::lambda::Strictness;
+use la_term::symbol::Symbol;
+use la_term::symbol::Symbols;
+use la_term::variable::DeBruijn;
+use logos::Logos;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+////////////////////////////////////////////////////////////////////////////////
+// Names
+
+/// Mapping between the names of Mathematica symbols and of symbols here.
+///
+/// Names that are not in the mapping are the same in both,
+/// as most of them are, such as `Power`, `Sin`, `Pi` and `List`.
+/// The default mapping has `Plus` for `Add` and `Times` for `Multiply`;
+/// more pairs can be inserted for the names of other packages.
+#[derive(Clone, Debug)]
+pub struct MathematicaNames
+{
+    to_native: HashMap<String, String>,
+    to_mathematica: HashMap<String, String>,
+}
+
+impl MathematicaNames
+{
+    /// The mapping in which every name is the same in both.
+    pub fn identity() -> Self
+    {
+        Self{to_native: HashMap::new(), to_mathematica: HashMap::new()}
+    }
+
+    /// Map a Mathematica name to a name here, and the other way around.
+    ///
+    /// Any previous pairs with either name are removed.
+    pub fn insert(&mut self, mathematica: &str, native: &str)
+    {
+        if let Some(old) = self.to_native.remove(mathematica) {
+            self.to_mathematica.remove(&old);
+        }
+        if let Some(old) = self.to_mathematica.remove(native) {
+            self.to_native.remove(&old);
+        }
+        self.to_native.insert(mathematica.to_owned(), native.to_owned());
+        self.to_mathematica.insert(native.to_owned(), mathematica.to_owned());
+    }
+
+    /// The name here of a Mathematica symbol.
+    pub fn to_native<'a>(&'a self, mathematica: &'a str) -> &'a str
+    {
+        self.to_native.get(mathematica).map_or(mathematica, String::as_str)
+    }
+
+    /// The Mathematica name of a symbol here.
+    pub fn to_mathematica<'a>(&'a self, native: &'a str) -> &'a str
+    {
+        self.to_mathematica.get(native).map_or(native, String::as_str)
+    }
+}
+
+impl Default for MathematicaNames
+{
+    fn default() -> Self
+    {
+        let mut names = Self::identity();
+        names.insert("Plus", "Add");
+        names.insert("Times", "Multiply");
+        names
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Expressions
+
+/// Expression in Mathematica syntax, before its symbols are mapped.
+///
+/// Both the reader and the writers go through expressions,
+/// so that the notations with bound variables, such as `Function`,
+/// are translated in one place.
+#[derive(Clone, Debug)]
+struct Expression
+{
+    kind: ExpressionKind,
+    span: Span,
+}
+
+#[derive(Clone, Debug)]
+enum ExpressionKind
+{
+    Apply(Box<Expression>, Vec<Expression>),
+    Integer(i32),
+    Slot(u32),
+    String(String),
+    Symbol(String),
+}
+
+impl Expression
+{
+    /// Create an expression that does not come from source code.
+    fn new(kind: ExpressionKind) -> Self
+    {
+        Self{kind, span: 0 .. 0}
+    }
+
+    fn symbol(name: &str) -> Self
+    {
+        Self::new(ExpressionKind::Symbol(name.to_owned()))
+    }
+
+    fn apply(head: Self, arguments: Vec<Self>) -> Self
+    {
+        Self::new(ExpressionKind::Apply(Box::new(head), arguments))
+    }
+
+    fn apply_symbol(head: &str, arguments: Vec<Self>) -> Self
+    {
+        Self::apply(Self::symbol(head), arguments)
+    }
+
+    fn is_symbol(&self, name: &str) -> bool
+    {
+        matches!(&self.kind, ExpressionKind::Symbol(s) if s == name)
+    }
+
+    /// The head and the arguments, if this is an application.
+    fn application(&self) -> Option<(&Expression, &[Expression])>
+    {
+        match &self.kind {
+            ExpressionKind::Apply(head, arguments) => Some((head, arguments)),
+            _ => None,
+        }
+    }
+
+    /// The number of times `Derivative[1]` is applied to a function.
+    fn derivatives(&self) -> Option<(u32, &Expression)>
+    {
+        let (head, arguments) = self.application()?;
+        let (derivative, order) = head.application()?;
+        match (order, arguments) {
+            ([order], [function]) if derivative.is_symbol("Derivative") =>
+                match order.kind {
+                    ExpressionKind::Integer(order) if order >= 0 =>
+                        Some((order as u32, function)),
+                    _ => None,
+                },
+            _ => None,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Lexing
+
+/// Token of Mathematica source code.
+#[derive(Clone, Copy, Debug, Eq, Logos, PartialEq)]
+enum MathematicaToken<'a>
+{
+    /// Emitted when there is a lexical error.
+    #[error]
+    #[regex(r"[\t\n\r ]+", logos::skip)]
+    Error,
+
+    /// Comment, as in `(* ... *)`, which may be nested.
+    #[token("(*", lex_block_comment)]
+    Comment,
+
+    /// Symbol, which may be qualified by its context, as in `System`Plus`.
+    #[regex(r"`?[$a-zA-Z][$a-zA-Z0-9]*(`[$a-zA-Z][$a-zA-Z0-9]*)*")]
+    Symbol(&'a str),
+
+    #[regex("[0-9]+")]
+    Integer(&'a str),
+
+    /// Real number, which is not supported.
+    #[regex(r"[0-9]+\.[0-9]*|\.[0-9]+")]
+    Real(&'a str),
+
+    #[regex(r#""([^"\\]|\\.)*""#)]
+    String(&'a str),
+
+    /// Slot of a pure function, as in `#` or `#2`.
+    #[regex("#[0-9]*")]
+    Slot(&'a str),
+
+    #[token("[")]
+    LeftBracket,
+
+    #[token("]")]
+    RightBracket,
+
+    #[token("{")]
+    LeftBrace,
+
+    #[token("}")]
+    RightBrace,
+
+    #[token("(")]
+    LeftParenthesis,
+
+    #[token(")")]
+    RightParenthesis,
+
+    #[token(",")]
+    Comma,
+
+    #[token("+")]
+    Plus,
+
+    #[token("-")]
+    Minus,
+
+    #[token("*")]
+    Asterisk,
+
+    #[token("/")]
+    Slash,
+
+    #[token("^")]
+    Caret,
+
+    #[token("&")]
+    Ampersand,
+
+    #[token("'")]
+    Apostrophe,
+
+    #[token("~")]
+    Tilde,
+
+    #[token("->")]
+    MinusGreater,
+
+    #[token("==")]
+    EqualEqual,
+
+    #[token("!=")]
+    ExclamationEqual,
+
+    #[token("<")]
+    Less,
+
+    #[token("<=")]
+    LessEqual,
+
+    #[token(">")]
+    Greater,
+
+    #[token(">=")]
+    GreaterEqual,
+}
+
+/// The head of the relation a token stands for, if any.
+fn relation_head(token: MathematicaToken) -> Option<&'static str>
+{
+    match token {
+        MathematicaToken::EqualEqual => Some("Equal"),
+        MathematicaToken::ExclamationEqual => Some("Unequal"),
+        MathematicaToken::Less => Some("Less"),
+        MathematicaToken::LessEqual => Some("LessEqual"),
+        MathematicaToken::Greater => Some("Greater"),
+        MathematicaToken::GreaterEqual => Some("GreaterEqual"),
+        _ => None,
+    }
+}
+
+/// The operator that stands for the head of a relation, if any.
+fn relation_operator(head: &str) -> Option<&'static str>
+{
+    match head {
+        "Equal" => Some("=="),
+        "Unequal" => Some("!="),
+        "Less" => Some("<"),
+        "LessEqual" => Some("<="),
+        "Greater" => Some(">"),
+        "GreaterEqual" => Some(">="),
+        _ => None,
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Parsing
+
+/// Descriptions of the tokens that may start an operand.
+const OPERAND: &[&str] =
+    &["`#`", "`(`", "`-`", "`{`", "integer", "string", "symbol"];
+
+/// Parse Mathematica source code in FullForm or InputForm.
+///
+/// FullForm, as in `Plus[a, Times[-1, b]]`, is read in full.
+/// Of InputForm, the operators `+`, `-`, `*`, `/`, `^`, `==`, `!=`,
+/// `<`, `<=`, `>`, `>=`, `->`, `&`, `'` and `~`, as in `a~f~b`, are read,
+/// as are lists in braces, so `a - b*c` means the same as it would in
+/// Mathematica, namely `Plus[a, Times[-1, b, c]]`.
+/// Juxtaposition is not read as multiplication, and reals are not read.
+/// `Slot[n]` is read as `#n`, and derivatives of order above 1000,
+/// which would be very large terms, are not read.
+///
+/// Symbols are renamed with the given names, and are variables
+/// if they are in the scope. Besides names, these are translated:
+///
+/// | Mathematica                 | Term                              |
+/// |-----------------------------|-----------------------------------|
+/// | `Function[{x, y}, b]`       | `\|x, y\| b`                      |
+/// | `Function[#1 + #2]`, `# &`  | `\|#1, #2\| #1 + #2`, `\|#1\| #1` |
+/// | `Derivative[2][f]`, `f''`   | `Derivative(Derivative(f))`       |
+/// | `Integrate[f, {x, a, b}]`   | `Integrate(\|x\| f, a, b)`        |
+/// | `Integrate[f, x]`           | `Antiderivative(\|x\| f)(x)`      |
+/// | `Sum[f, {k, a, b}]`         | `Sum(\|k\| f, a, b)`              |
+/// | `Product[f, {k, a, b}]`     | `Product(\|k\| f, a, b)`          |
+/// | `Log[x]`, `Log[b, x]`       | `Ln(x)`, `Log(b, x)`              |
+/// | `Sqrt[x]`                   | `x ^ (1 / 2)`                     |
+pub fn parse_mathematica(
+    symbols: &Symbols,
+    scope: &Scope,
+    names: &MathematicaNames,
+    source: &str,
+) -> Result<Term>
+{
+    let tokens = MathematicaToken::lexer(source).spanned()
+        .filter(|(token, _)| *token != MathematicaToken::Comment)
+        .collect();
+    let mut parser = MathematicaParser{source, tokens, position: 0};
+    let expression = parser.function()?;
+    if parser.peek().is_some() {
+        return Err(parser.unexpected(&["end of input"]));
+    }
+    Lowering{symbols, names, source}.term(scope, &expression)
+}
+
+/// State of the parser for Mathematica source code.
+struct MathematicaParser<'a>
+{
+    source: &'a str,
+    tokens: Vec<(MathematicaToken<'a>, Span)>,
+    position: usize,
+}
+
+impl<'a> MathematicaParser<'a>
+{
+    /// Parse an expression, which may be a pure function, as in `# + 1 &`.
+    ///
+    /// As in Mathematica, a pure function may be applied in place,
+    /// as in `f[#] &[x]`.
+    fn function(&mut self) -> Result<Expression>
+    {
+        let start = self.peek_span().start;
+        let mut body = self.rule()?;
+        while self.eat(MathematicaToken::Ampersand) {
+            body = self.apply(start, "Function", vec![body]);
+            while self.eat(MathematicaToken::LeftBracket) {
+                body = self.call(start, body)?;
+            }
+        }
+        Ok(body)
+    }
+
+    fn rule(&mut self) -> Result<Expression>
+    {
+        let start = self.peek_span().start;
+        let lhs = self.relation()?;
+        if !self.eat(MathematicaToken::MinusGreater) {
+            return Ok(lhs);
+        }
+        let rhs = self.rule()?;
+        Ok(self.apply(start, "Rule", vec![lhs, rhs]))
+    }
+
+    fn relation(&mut self) -> Result<Expression>
+    {
+        let start = self.peek_span().start;
+        let lhs = self.sum()?;
+        let head = match self.peek().and_then(relation_head) {
+            Some(head) => head,
+            None => return Ok(lhs),
+        };
+        self.position += 1;
+        let rhs = self.sum()?;
+        if self.peek().and_then(relation_head).is_some() {
+            let span = self.peek_span();
+            let found = &self.source[span.clone()];
+            let message =
+                format!("`{}` cannot be chained; use parentheses", found);
+            return Err(Error::new(span, Some(found), message));
+        }
+        Ok(self.apply(start, head, vec![lhs, rhs]))
+    }
+
+    fn sum(&mut self) -> Result<Expression>
+    {
+        let start = self.peek_span().start;
+        let negated = self.eat(MathematicaToken::Minus);
+        let mut terms = vec![self.product(negated)?];
+        loop {
+            let negated = match self.peek() {
+                Some(MathematicaToken::Plus) => false,
+                Some(MathematicaToken::Minus) => true,
+                _ => break,
+            };
+            self.position += 1;
+            terms.push(self.product(negated)?);
+        }
+        Ok(self.flat(start, "Plus", terms))
+    }
+
+    /// Parse a product, negating it if it follows a `-`.
+    ///
+    /// As in Mathematica, the negation is a factor of the product,
+    /// so `-a*b` means `Times[-1, a, b]` and `-2*a` means `Times[-2, a]`.
+    fn product(&mut self, negated: bool) -> Result<Expression>
+    {
+        let start = self.peek_span().start;
+        let mut factors = Vec::new();
+        let first = self.power()?;
+        match (negated, &first.kind) {
+            (false, _) =>
+                factors.push(first),
+            (true, ExpressionKind::Integer(value)) => {
+                let kind = ExpressionKind::Integer(-value);
+                factors.push(Expression{kind, span: first.span});
+            },
+            (true, _) => {
+                factors.push(Expression::new(ExpressionKind::Integer(-1)));
+                factors.push(first);
+            },
+        }
+        loop {
+            match self.peek() {
+                Some(MathematicaToken::Asterisk) => {
+                    self.position += 1;
+                    factors.push(self.power()?);
+                },
+                Some(MathematicaToken::Slash) => {
+                    self.position += 1;
+                    let start = self.peek_span().start;
+                    let factor = self.power()?;
+                    let minus_one = ExpressionKind::Integer(-1);
+                    let arguments = vec![factor, Expression::new(minus_one)];
+                    factors.push(self.apply(start, "Power", arguments));
+                },
+                _ => break,
+            }
+        }
+        Ok(self.flat(start, "Times", factors))
+    }
+
+    /// Parse a power, whose exponent may be negated, as in `x^-1`.
+    fn power(&mut self) -> Result<Expression>
+    {
+        let start = self.peek_span().start;
+        let base = self.infix()?;
+        if !self.eat(MathematicaToken::Caret) {
+            return Ok(base);
+        }
+        let exponent = match self.eat(MathematicaToken::Minus) {
+            true => self.negated_power()?,
+            false => self.power()?,
+        };
+        Ok(self.apply(start, "Power", vec![base, exponent]))
+    }
+
+    /// Parse infix applications, as in `a~f~b~g~c`,
+    /// which means `g[f[a, b], c]`.
+    fn infix(&mut self) -> Result<Expression>
+    {
+        let start = self.peek_span().start;
+        let mut lhs = self.postfix()?;
+        while self.eat(MathematicaToken::Tilde) {
+            let head = self.postfix()?;
+            self.expect(MathematicaToken::Tilde, "`~`")?;
+            let rhs = self.postfix()?;
+            let span = start .. self.previous_end();
+            let kind = ExpressionKind::Apply(Box::new(head), vec![lhs, rhs]);
+            lhs = Expression{kind, span};
+        }
+        Ok(lhs)
+    }
+
+    /// Parse a power that follows a `-`, as in an exponent.
+    fn negated_power(&mut self) -> Result<Expression>
+    {
+        let start = self.peek_span().start;
+        let power = self.power()?;
+        match power.kind {
+            ExpressionKind::Integer(value) => {
+                let kind = ExpressionKind::Integer(-value);
+                Ok(Expression{kind, span: power.span})
+            },
+            _ => {
+                let minus_one = Expression::new(ExpressionKind::Integer(-1));
+                Ok(self.apply(start, "Times", vec![minus_one, power]))
+            },
+        }
+    }
+
+    /// Parse an operand with arguments and primes, as in `f[x]` or `f'`.
+    fn postfix(&mut self) -> Result<Expression>
+    {
+        let start = self.peek_span().start;
+        let mut expression = self.primary()?;
+        loop {
+            if self.eat(MathematicaToken::LeftBracket) {
+                expression = self.call(start, expression)?;
+            } else if self.eat(MathematicaToken::Apostrophe) {
+                let one = Expression::new(ExpressionKind::Integer(1));
+                let derivative = Expression::apply_symbol("Derivative",
+                                                          vec![one]);
+                let span = start .. self.previous_end();
+                let kind = ExpressionKind::Apply(Box::new(derivative),
+                                                 vec![expression]);
+                expression = Expression{kind, span};
+            } else {
+                return Ok(expression);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expression>
+    {
+        let span = self.peek_span();
+        let kind = match self.peek() {
+
+            Some(MathematicaToken::Integer(digits)) =>
+                match digits.parse() {
+                    Ok(value) => ExpressionKind::Integer(value),
+                    Err(_) => {
+                        let message = "integer is too large".to_owned();
+                        return Err(Error::new(span, Some(digits), message));
+                    },
+                },
+
+            Some(MathematicaToken::Real(digits)) => {
+                let message = "real numbers are not supported".to_owned();
+                return Err(Error::new(span, Some(digits), message));
+            },
+
+            Some(MathematicaToken::String(literal)) =>
+                ExpressionKind::String(unescape(literal)),
+
+            Some(MathematicaToken::Symbol(name)) => {
+                let name = name.rsplit('`').next().unwrap();
+                ExpressionKind::Symbol(name.to_owned())
+            },
+
+            Some(MathematicaToken::Slot(slot)) =>
+                match slot[1 ..].parse() {
+                    Ok(0) => {
+                        let message = "`#0` is not supported".to_owned();
+                        return Err(Error::new(span, Some(slot), message));
+                    },
+                    Ok(index) => ExpressionKind::Slot(index),
+                    Err(_) if slot.len() == 1 => ExpressionKind::Slot(1),
+                    Err(_) => {
+                        let message = "slot is too large".to_owned();
+                        return Err(Error::new(span, Some(slot), message));
+                    },
+                },
+
+            Some(MathematicaToken::LeftParenthesis) => {
+                self.position += 1;
+                let expression = self.function()?;
+                self.expect(MathematicaToken::RightParenthesis, "`)`")?;
+                return Ok(expression);
+            },
+
+            Some(MathematicaToken::LeftBrace) => {
+                self.position += 1;
+                let elements =
+                    self.arguments(MathematicaToken::RightBrace, "`}`")?;
+                return Ok(self.apply(span.start, "List", elements));
+            },
+
+            _ => return Err(self.unexpected(OPERAND)),
+
+        };
+        self.position += 1;
+        Ok(Expression{kind, span})
+    }
+
+    /// Parse the arguments of a function, whose `[` was parsed.
+    fn call(&mut self, start: usize, function: Expression)
+        -> Result<Expression>
+    {
+        let arguments =
+            self.arguments(MathematicaToken::RightBracket, "`]`")?;
+        let span = start .. self.previous_end();
+
+        // `Slot[2]` is the FullForm of `#2`.
+        if function.is_symbol("Slot") {
+            if let [Expression{kind: ExpressionKind::Integer(index), ..}] =
+                arguments[..]
+            {
+                if index > 0 {
+                    let kind = ExpressionKind::Slot(index as u32);
+                    return Ok(Expression{kind, span});
+                }
+            }
+        }
+
+        let kind = ExpressionKind::Apply(Box::new(function), arguments);
+        Ok(Expression{kind, span})
+    }
+
+    /// Parse arguments separated by commas, up to the given token.
+    fn arguments(&mut self, close: MathematicaToken, expected: &'static str)
+        -> Result<Vec<Expression>>
+    {
+        let mut arguments = Vec::new();
+        if self.eat(close) {
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.function()?);
+            if self.eat(close) {
+                return Ok(arguments);
+            }
+            if !self.eat(MathematicaToken::Comma) {
+                return Err(self.unexpected(&["`,`", expected]));
+            }
+        }
+    }
+
+    ////////////////////////////////////////////////////////////////////////////
+    // Building blocks
+
+    fn peek(&self) -> Option<MathematicaToken<'a>>
+    {
+        self.tokens.get(self.position).map(|(token, _)| *token)
+    }
+
+    /// The span of the next token, or the empty span at the end.
+    fn peek_span(&self) -> Span
+    {
+        match self.tokens.get(self.position) {
+            Some((_, span)) => span.clone(),
+            None => self.source.len() .. self.source.len(),
+        }
+    }
+
+    /// The end of the previous token.
+    fn previous_end(&self) -> usize
+    {
+        self.tokens[self.position - 1].1.end
+    }
+
+    fn eat(&mut self, token: MathematicaToken) -> bool
+    {
+        let found = self.peek() == Some(token);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, token: MathematicaToken, expected: &'static str)
+        -> Result<()>
+    {
+        match self.eat(token) {
+            true => Ok(()),
+            false => Err(self.unexpected(&[expected])),
+        }
+    }
+
+    fn unexpected(&self, expected: &[&'static str]) -> Error
+    {
+        let span = self.peek_span();
+        let found = self.peek().map(|_| &self.source[span.clone()]);
+        Error::unexpected(span, found, expected)
+    }
+
+    /// Apply a head to arguments, spanning from the given start.
+    fn apply(&self, start: usize, head: &str, arguments: Vec<Expression>)
+        -> Expression
+    {
+        let span = start .. self.previous_end();
+        let kind = ExpressionKind::Apply(
+            Box::new(Expression::symbol(head)),
+            arguments,
+        );
+        Expression{kind, span}
+    }
+
+    /// Apply a flat operator, unless there is only one operand.
+    fn flat(&self, start: usize, head: &str, mut operands: Vec<Expression>)
+        -> Expression
+    {
+        match operands.len() {
+            1 => operands.pop().unwrap(),
+            _ => self.apply(start, head, operands),
+        }
+    }
+}
+
+/// The contents of a string literal, with escape sequences replaced.
+fn unescape(literal: &str) -> String
+{
+    let mut contents = String::new();
+    let mut chars = literal[1 .. literal.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            contents.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => contents.push('\n'),
+            Some('t') => contents.push('\t'),
+            Some('r') => contents.push('\r'),
+            Some(c) => contents.push(c),
+            None => (),
+        }
+    }
+    contents
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Lowering
+
+/// Translation of expressions into terms.
+struct Lowering<'a, 's>
+{
+    symbols: &'s Symbols,
+    names: &'s MathematicaNames,
+    source: &'a str,
+}
+
+impl<'a, 's> Lowering<'a, 's>
+{
+    fn term(&self, scope: &Scope, expression: &Expression) -> Result<Term>
+    {
+        match &expression.kind {
+
+            ExpressionKind::Integer(value) =>
+                Ok(Term::integer_i32(*value)),
+
+            ExpressionKind::String(string) =>
+                Ok(Term::string(string.bytes())),
+
+            ExpressionKind::Symbol(name) => {
+                let symbol = self.symbol(name);
+                match scope.get(&symbol) {
+                    Some(de_bruijn) => Ok(Term::variable(de_bruijn)),
+                    None => Ok(Term::symbol(symbol)),
+                }
+            },
+
+            ExpressionKind::Slot(index) => {
+                let slot = self.symbols.get(slot_name(*index).as_bytes());
+                match scope.get(&slot) {
+                    Some(de_bruijn) => Ok(Term::variable(de_bruijn)),
+                    None => Err(self.error(
+                        expression,
+                        "slots must be inside a pure function",
+                    )),
+                }
+            },
+
+            ExpressionKind::Apply(head, arguments) =>
+                self.application(scope, expression, head, arguments),
+
+        }
+    }
+
+    fn application(
+        &self,
+        scope: &Scope,
+        expression: &Expression,
+        head: &Expression,
+        arguments: &[Expression],
+    ) -> Result<Term>
+    {
+        if let Some((order, function)) = expression.derivatives() {
+            if order > MAX_DERIVATIVE_ORDER {
+                let message = format!(
+                    "derivatives of order above {} are not supported",
+                    MAX_DERIVATIVE_ORDER,
+                );
+                return Err(self.error(expression, &message));
+            }
+            let mut term = self.term(scope, function)?;
+            for _ in 0 .. order {
+                term = self.apply("Derivative", vec![term]);
+            }
+            return Ok(term);
+        }
+
+        let name = match &head.kind {
+            ExpressionKind::Symbol(name) => name.as_str(),
+            _ => "",
+        };
+        match (name, arguments) {
+
+            ("Function", [body]) => {
+                let slots = (1 ..= max_slot(body)).map(slot_name);
+                let parameters: Vec<Symbol> =
+                    slots.map(|slot| self.symbols.get(slot.as_bytes()))
+                    .collect();
+                self.lambda(scope, parameters, body)
+            },
+
+            ("Function", [parameters, body]) => {
+                let parameters = match parameters.application() {
+                    Some((list, elements)) if list.is_symbol("List") =>
+                        elements,
+                    _ => std::slice::from_ref(parameters),
+                };
+                let parameters = self.variables(parameters)?;
+                self.lambda(scope, parameters, body)
+            },
+
+            ("Function", _) =>
+                Err(self.error(
+                    expression,
+                    "`Function` must have one or two arguments",
+                )),
+
+            ("Integrate" | "Sum" | "Product", [body, iterator]) => {
+                if let ExpressionKind::Symbol(..) = iterator.kind {
+                    if name == "Integrate" {
+                        let variable =
+                            self.variables(std::slice::from_ref(iterator))?;
+                        let function =
+                            self.lambda(scope, variable, body)?;
+                        let antiderivative =
+                            self.apply("Antiderivative", vec![function]);
+                        let variable = self.term(scope, iterator)?;
+                        return Ok(Term::application(antiderivative,
+                                                    [variable]));
+                    }
+                }
+                let (variable, lower, upper) = match iterator.application() {
+                    Some((list, [variable, lower, upper]))
+                        if list.is_symbol("List") =>
+                        (variable, lower, upper),
+                    _ => return Err(self.error(
+                        iterator,
+                        "expected an iterator, as in `{k, 1, n}`",
+                    )),
+                };
+                let variable = self.variables(std::slice::from_ref(variable))?;
+                let function = self.lambda(scope, variable, body)?;
+                let lower = self.term(scope, lower)?;
+                let upper = self.term(scope, upper)?;
+                let head = self.names.to_native(name);
+                Ok(self.apply(head, vec![function, lower, upper]))
+            },
+
+            ("Log", [argument]) => {
+                let argument = self.term(scope, argument)?;
+                Ok(self.apply("Ln", vec![argument]))
+            },
+
+            ("Sqrt", [argument]) => {
+                let argument = self.term(scope, argument)?;
+                Ok(square_root(self.symbols, argument))
+            },
+
+            _ => {
+                let head = self.term(scope, head)?;
+                let arguments = arguments.iter()
+                    .map(|argument| self.term(scope, argument))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Term::application(head, arguments))
+            },
+
+        }
+    }
+
+    /// The symbols of the parameters of a function.
+    fn variables(&self, parameters: &[Expression]) -> Result<Vec<Symbol>>
+    {
+        parameters.iter()
+            .map(|parameter| match &parameter.kind {
+                ExpressionKind::Symbol(name) =>
+                    Ok(self.symbols.get(name.as_bytes())),
+                _ => Err(self.error(parameter, "expected a symbol")),
+            })
+            .collect()
+    }
+
+    fn lambda(&self, scope: &Scope, parameters: Vec<Symbol>, body: &Expression)
+        -> Result<Term>
+    {
+        let scope = Scope::new(Some(scope), parameters.iter().cloned());
+        let body = self.term(&scope, body)?;
+        let parameters: Vec<Parameter> =
+            parameters.into_iter()
+            .map(|name| Parameter{strictness: Strictness::Strict, name})
+            .collect();
+        Ok(Term::lambda(parameters.into(), body))
+    }
+
+    fn symbol(&self, name: &str) -> Symbol
+    {
+        self.symbols.get(self.names.to_native(name).as_bytes())
+    }
+
+    fn apply(&self, head: &str, arguments: Vec<Term>) -> Term
+    {
+        Term::application(self.symbols.get(head.as_bytes()).term(), arguments)
+    }
+
+    fn error(&self, expression: &Expression, message: &str) -> Error
+    {
+        let span = expression.span.clone();
+        let found = &self.source[span.clone()];
+        Error::new(span, Some(found), message.to_owned())
+    }
+}
+
+/// The name of the parameter that a slot stands for, as in `#1`.
+fn slot_name(index: u32) -> String
+{
+    format!("#{}", index)
+}
+
+/// The highest slot in the body of a pure function,
+/// not counting the slots of pure functions inside it.
+fn max_slot(body: &Expression) -> u32
+{
+    match &body.kind {
+        ExpressionKind::Slot(index) => *index,
+        ExpressionKind::Apply(head, arguments) => {
+            if head.is_symbol("Function") {
+                return 0;
+            }
+            arguments.iter().map(max_slot).fold(max_slot(head), u32::max)
+        },
+        _ => 0,
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Writing
+
+/// Render a term as Mathematica FullForm, as in `Plus[a, Times[-1, b]]`.
+///
+/// Symbols are renamed with the given names, and the notations in the
+/// table of [`parse_mathematica`] are translated the other way around,
+/// except that roots are written as powers.
+/// Lambdas read from pure functions with slots are written with slots,
+/// as in `Function[Plus[Slot[1], Slot[2]]]`.
+pub fn render_full_form(term: &Term, names: &MathematicaNames) -> String
+{
+    let expression = Lifting{names, variables: Vec::new()}.expression(term);
+    let mut out = String::new();
+    write_full_form(&expression, &mut out);
+    out
+}
+
+/// Render a term as Mathematica InputForm, as in `a - b`.
+///
+/// This is like [`render_full_form`], but with operators
+/// and with braces for lists, as understood by [`parse_mathematica`].
+/// Declared operators are written as described at [`Notations`],
+/// but with the head in place of the operator, as in `a~DirectSum~b`,
+/// as Mathematica reads `⊕` as an operator of its own.
+pub fn render_input_form(
+    term: &Term,
+    names: &MathematicaNames,
+    notations: &Notations,
+) -> String
+{
+    let expression = Lifting{names, variables: Vec::new()}.expression(term);
+    InputForm{names, notations}.expression(&expression).text
+}
+
+/// Translation of terms into expressions.
+struct Lifting<'s>
+{
+    names: &'s MathematicaNames,
+
+    /// Names of the variables in scope,
+    /// the last of which has De Bruijn index zero.
+    variables: Vec<String>,
+}
+
+impl<'s> Lifting<'s>
+{
+    fn expression(&mut self, term: &Term) -> Expression
+    {
+        let kind = match term.view() {
+
+            View::Application(function, arguments) =>
+                return self.application(function, arguments),
+
+            View::Integer(value) =>
+                ExpressionKind::Integer(value),
+
+            View::Lambda(parameters, body) if is_pure(parameters, body) => {
+                let outer = self.variables.len();
+                self.variables.extend(
+                    (1 ..= parameters.len() as u32).rev().map(slot_name),
+                );
+                let body = self.expression(body);
+                self.variables.truncate(outer);
+                return Expression::apply_symbol("Function", vec![body]);
+            },
+
+            View::Lambda(parameters, body) => {
+                let (parameters, body) = self.lambda(parameters, body);
+                let parameters = parameters.iter()
+                    .map(|name| Expression::symbol(name))
+                    .collect();
+                let parameters = Expression::apply_symbol("List", parameters);
+                return Expression::apply_symbol("Function",
+                                                vec![parameters, body]);
+            },
+
+            View::String(bytes) =>
+                ExpressionKind::String(
+                    String::from_utf8_lossy(bytes).into_owned(),
+                ),
+
+            View::Symbol(symbol) => {
+                let name = String::from_utf8_lossy(symbol.name());
+                ExpressionKind::Symbol(
+                    self.names.to_mathematica(&name).to_owned(),
+                )
+            },
+
+            View::Variable(de_bruijn) => {
+                let index = de_bruijn.0 as usize;
+                match self.variables.len().checked_sub(index + 1) {
+                    Some(i) => {
+                        let name = &self.variables[i];
+                        match name.strip_prefix('#') {
+                            Some(slot) =>
+                                ExpressionKind::Slot(slot.parse().unwrap()),
+                            None =>
+                                ExpressionKind::Symbol(name.clone()),
+                        }
+                    },
+                    None => ExpressionKind::Slot(de_bruijn.0 + 1),
+                }
+            },
+
+        };
+        Expression::new(kind)
+    }
+
+    /// Lift the body of a lambda with its parameters in scope,
+    /// returning the names of the parameters and the body.
+    ///
+    /// Parameters named after slots, as in `#1`, are renamed to `slot$1`,
+    /// as they are not symbols in Mathematica.
+    fn lambda(&mut self, parameters: &[Parameter], body: &Term)
+        -> (Vec<String>, Expression)
+    {
+        let names: Vec<String> =
+            parameters.iter()
+            .map(|parameter| {
+                let name = String::from_utf8_lossy(parameter.name.name());
+                match name.strip_prefix('#') {
+                    Some(slot) => format!("slot${}", slot),
+                    None => name.into_owned(),
+                }
+            })
+            .collect();
+        let outer = self.variables.len();
+        self.variables.extend(names.iter().rev().cloned());
+        let body = self.expression(body);
+        self.variables.truncate(outer);
+        (names, body)
+    }
+
+    fn application(&mut self, function: &Term, arguments: &[Term])
+        -> Expression
+    {
+        // Antiderivative(|x| f)(x) is Integrate[f, x].
+        if let (View::Application(head, [integrand]), [argument]) =
+            (function.view(), arguments)
+        {
+            if let View::Lambda(parameters, body) = integrand.view() {
+                if is_symbol(head, b"Antiderivative")
+                    && parameters.len() == 1
+                    && is_symbol(argument, parameters[0].name.name())
+                {
+                    let (names, body) = self.lambda(parameters, body);
+                    let variable = Expression::symbol(&names[0]);
+                    return Expression::apply_symbol("Integrate",
+                                                    vec![body, variable]);
+                }
+            }
+        }
+
+        let head = match function.view() {
+            View::Symbol(symbol) => symbol.name(),
+            _ => b"",
+        };
+        match (head, arguments) {
+
+            (b"Derivative", [function]) => {
+                let mut order = 1;
+                let mut function = function;
+                while let View::Application(head, [inner]) = function.view() {
+                    if !is_symbol(head, b"Derivative") {
+                        break;
+                    }
+                    order += 1;
+                    function = inner;
+                }
+                let order = Expression::new(ExpressionKind::Integer(order));
+                let derivative =
+                    Expression::apply_symbol("Derivative", vec![order]);
+                Expression::apply(derivative, vec![self.expression(function)])
+            },
+
+            (b"Ln", [argument]) => {
+                let argument = self.expression(argument);
+                Expression::apply_symbol("Log", vec![argument])
+            },
+
+            (b"Integrate" | b"Sum" | b"Product", [function, lower, upper]) =>
+                match function.view() {
+                    View::Lambda(parameters, body) if parameters.len() == 1 => {
+                        let (names, body) = self.lambda(parameters, body);
+                        let iterator = Expression::apply_symbol("List", vec![
+                            Expression::symbol(&names[0]),
+                            self.expression(lower),
+                            self.expression(upper),
+                        ]);
+                        let head = String::from_utf8_lossy(head);
+                        let head = self.names.to_mathematica(&head).to_owned();
+                        Expression::apply_symbol(&head, vec![body, iterator])
+                    },
+                    _ => self.generic(function, arguments),
+                },
+
+            _ => self.generic(function, arguments),
+
+        }
+    }
+
+    fn generic(&mut self, function: &Term, arguments: &[Term]) -> Expression
+    {
+        let head = self.expression(function);
+        let arguments = arguments.iter()
+            .map(|argument| self.expression(argument))
+            .collect();
+        Expression::apply(head, arguments)
+    }
+}
+
+/// Whether a lambda can be written as a pure function with slots,
+/// as in `Function[Plus[Slot[1], Slot[2]]]`.
+///
+/// This is so if its parameters are named `#1` to `#n`, as when read,
+/// if its last parameter is used, so that it has the same arity,
+/// and if none of its parameters are used inside a nested lambda
+/// with slots, where the slots would refer to the nested lambda.
+fn is_pure(parameters: &[Parameter], body: &Term) -> bool
+{
+    let named_after_slots =
+        parameters.iter().enumerate()
+        .all(|(i, parameter)| {
+            parameter.name.name() == slot_name(i as u32 + 1).as_bytes()
+        });
+    !parameters.is_empty()
+        && named_after_slots
+        && slot_uses(body, 0, parameters.len() as u32, false) == Some(true)
+}
+
+/// Whether the last of the `count` parameters
+/// bound `depth` variables outside of a term is used in it.
+///
+/// Returns [`None`] if any of the parameters is used inside a lambda
+/// with parameters named after slots, or if `nested` and any is used.
+fn slot_uses(term: &Term, depth: u32, count: u32, nested: bool)
+    -> Option<bool>
+{
+    if term.header().de_bruijn_cache.is_empty() {
+        return Some(false);
+    }
+
+    match term.view() {
+
+        View::Variable(DeBruijn(index)) =>
+            if index < depth || index - depth >= count {
+                Some(false)
+            } else if nested {
+                None
+            } else {
+                Some(index - depth == count - 1)
+            },
+
+        View::Application(function, arguments) => {
+            let mut used = slot_uses(function, depth, count, nested)?;
+            for argument in arguments {
+                used |= slot_uses(argument, depth, count, nested)?;
+            }
+            Some(used)
+        },
+
+        View::Lambda(parameters, body) => {
+            let depth = depth + parameters.len() as u32;
+            let slots = parameters.iter()
+                .any(|parameter| parameter.name.name().starts_with(b"#"));
+            let nested = nested || slots;
+            slot_uses(body, depth, count, nested)
+        },
+
+        View::Integer(..) => Some(false),
+        View::String(..) => Some(false),
+        View::Symbol(..) => Some(false),
+
+    }
+}
+
+fn write_full_form(expression: &Expression, out: &mut String)
+{
+    match &expression.kind {
+        ExpressionKind::Apply(head, arguments) => {
+            write_full_form(head, out);
+            out.push('[');
+            for (i, argument) in arguments.iter().enumerate() {
+                if i != 0 {
+                    out.push_str(", ");
+                }
+                write_full_form(argument, out);
+            }
+            out.push(']');
+        },
+        ExpressionKind::Integer(value) => write!(out, "{}", value).unwrap(),
+        ExpressionKind::Slot(index) => write!(out, "Slot[{}]", index).unwrap(),
+        ExpressionKind::String(string) => out.push_str(&quote(string)),
+        ExpressionKind::Symbol(name) => out.push_str(name),
+    }
+}
+
+/// A string literal with the given contents.
+fn quote(string: &str) -> String
+{
+    let mut literal = "\"".to_owned();
+    for c in string.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            '\r' => literal.push_str("\\r"),
+            _ => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Precedence levels of InputForm, from loosest to tightest.
+const RULE: u32 = 10;
+const RELATION: u32 = 20;
+const PLUS: u32 = 30;
+const TIMES: u32 = 40;
+const NEGATE: u32 = 45;
+const POWER: u32 = 50;
+const TILDE: u32 = 55;
+const POSTFIX: u32 = 60;
+const ATOM: u32 = 70;
+
+/// Rendered InputForm, with the precedence of its outermost operator.
+struct Text
+{
+    text: String,
+    precedence: u32,
+}
+
+impl Text
+{
+    fn new(text: String, precedence: u32) -> Self
+    {
+        Self{text, precedence}
+    }
+
+    /// The text, in parentheses if it binds looser than the given level.
+    fn at(self, precedence: u32) -> String
+    {
+        if self.precedence < precedence {
+            format!("({})", self.text)
+        } else {
+            self.text
+        }
+    }
+}
+
+/// Writer of InputForm.
+///
+/// Operators are written only where [`parse_mathematica`]
+/// reads them back into the same expression;
+/// for instance, `Times[Power[b, -1], a]` is written as `b^(-1)*a`
+/// rather than `a/b`, which would change the order of the factors.
+struct InputForm<'s>
+{
+    names: &'s MathematicaNames,
+    notations: &'s Notations,
+}
+
+impl<'s> InputForm<'s>
+{
+    fn expression(&self, expression: &Expression) -> Text
+    {
+        match &expression.kind {
+            ExpressionKind::Apply(head, arguments) =>
+                self.application(expression, head, arguments),
+            ExpressionKind::Integer(value) if *value < 0 =>
+                Text::new(value.to_string(), NEGATE),
+            ExpressionKind::Integer(value) =>
+                Text::new(value.to_string(), ATOM),
+            ExpressionKind::Slot(index) =>
+                Text::new(format!("#{}", index), ATOM),
+            ExpressionKind::String(string) =>
+                Text::new(quote(string), ATOM),
+            ExpressionKind::Symbol(name) =>
+                Text::new(name.clone(), ATOM),
+        }
+    }
+
+    fn application(
+        &self,
+        expression: &Expression,
+        head: &Expression,
+        arguments: &[Expression],
+    ) -> Text
+    {
+        if let Some((order, function)) = expression.derivatives() {
+            let primes = "'".repeat(order as usize);
+            let function = self.expression(function).at(POSTFIX);
+            return Text::new(format!("{}{}", function, primes), POSTFIX);
+        }
+
+        let name = match &head.kind {
+            ExpressionKind::Symbol(name) => name.as_str(),
+            _ => "",
+        };
+        match (name, arguments) {
+
+            ("Plus", [first, rest @ ..]) if !rest.is_empty() => {
+                let mut text = self.expression(first).at(PLUS);
+                for term in rest {
+                    match self.negative(term) {
+                        Some(term) => {
+                            text.push_str(" - ");
+                            text.push_str(&term.at(TIMES));
+                        },
+                        None => {
+                            text.push_str(" + ");
+                            text.push_str(&self.expression(term).at(TIMES));
+                        },
+                    }
+                }
+                Text::new(text, PLUS)
+            },
+
+            ("Times", [_, _, ..]) =>
+                match self.negative(expression) {
+                    Some(product) => {
+                        let product = product.at(TIMES);
+                        Text::new(format!("-{}", product), NEGATE)
+                    },
+                    None => self.product(arguments),
+                },
+
+            ("Power", [base, exponent]) => {
+                let base = self.expression(base).at(POSTFIX);
+                let exponent = self.expression(exponent).at(POWER);
+                Text::new(format!("{}^{}", base, exponent), POWER)
+            },
+
+            ("Rule", [lhs, rhs]) => {
+                let lhs = self.expression(lhs).at(RELATION);
+                let rhs = self.expression(rhs).at(RULE);
+                Text::new(format!("{} -> {}", lhs, rhs), RULE)
+            },
+
+            ("List", _) => {
+                let elements = self.arguments(arguments);
+                Text::new(format!("{{{}}}", elements), ATOM)
+            },
+
+            _ => {
+                if let (Some(operator), [lhs, rhs]) =
+                    (relation_operator(name), arguments)
+                {
+                    let lhs = self.expression(lhs).at(PLUS);
+                    let rhs = self.expression(rhs).at(PLUS);
+                    let text = format!("{} {} {}", lhs, operator, rhs);
+                    return Text::new(text, RELATION);
+                }
+                if let [lhs, rhs] = arguments {
+                    let native = self.names.to_native(name).as_bytes();
+                    if self.notations.of_head(native).is_some() {
+                        let lhs = self.expression(lhs).at(TILDE);
+                        let rhs = self.expression(rhs).at(POSTFIX);
+                        let text = format!("{}~{}~{}", lhs, name, rhs);
+                        return Text::new(text, TILDE);
+                    }
+                }
+                let head = self.expression(head).at(POSTFIX);
+                let arguments = self.arguments(arguments);
+                Text::new(format!("{}[{}]", head, arguments), POSTFIX)
+            },
+
+        }
+    }
+
+    fn arguments(&self, arguments: &[Expression]) -> String
+    {
+        let arguments: Vec<String> =
+            arguments.iter()
+            .map(|argument| self.expression(argument).text)
+            .collect();
+        arguments.join(", ")
+    }
+
+    /// Render the negation of an expression that is negative,
+    /// such as `-2` or `Times[-1, x]`, without its sign.
+    fn negative(&self, expression: &Expression) -> Option<Text>
+    {
+        match &expression.kind {
+            ExpressionKind::Integer(value) if *value < 0 => {
+                let value = value.checked_neg()?;
+                Some(Text::new(value.to_string(), ATOM))
+            },
+            ExpressionKind::Apply(head, factors)
+                if head.is_symbol("Times") =>
+            {
+                let (first, rest) = factors.split_first()?;
+                match first.kind {
+                    ExpressionKind::Integer(-1) if !rest.is_empty() =>
+                        Some(self.product(rest)),
+                    ExpressionKind::Integer(value) if value < 0 => {
+                        let value = value.checked_neg()?;
+                        let first = ExpressionKind::Integer(value);
+                        let mut factors = vec![Expression::new(first)];
+                        factors.extend(rest.iter().cloned());
+                        Some(self.product(&factors))
+                    },
+                    _ => None,
+                }
+            },
+            _ => None,
+        }
+    }
+
+    /// Render factors with `*`, or with `/` for reciprocals after the first.
+    fn product(&self, factors: &[Expression]) -> Text
+    {
+        if let [factor] = factors {
+            return Text::new(self.expression(factor).at(POWER), POWER);
+        }
+        let mut text = String::new();
+        for (i, factor) in factors.iter().enumerate() {
+            match reciprocal(factor).filter(|_| i != 0) {
+                Some(factor) => {
+                    text.push('/');
+                    text.push_str(&self.expression(factor).at(POWER));
+                },
+                None => {
+                    if i != 0 {
+                        text.push('*');
+                    }
+                    text.push_str(&self.expression(factor).at(POWER));
+                },
+            }
+        }
+        Text::new(text, TIMES)
+    }
+}
+
+/// The base of `Power[b, -1]`.
+fn reciprocal(expression: &Expression) -> Option<&Expression>
+{
+    match expression.application()? {
+        (head, [base, exponent]) if head.is_symbol("Power") =>
+            match exponent.kind {
+                ExpressionKind::Integer(-1) => Some(base),
+                _ => None,
+            },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing::*;
+    use std::rc::Rc;
+
+    /// Parse Mathematica with the default names.
+    fn mathematica(symbols: &Symbols, scope: &Scope, source: &str)
+        -> Result<Term>
+    {
+        let names = MathematicaNames::default();
+        parse_mathematica(symbols, scope, &names, source)
+    }
+
+    fn plain_input_form(term: &Term, names: &MathematicaNames) -> String
+    {
+        render_input_form(term, names, &Notations::new())
+    }
+
+    #[test]
+    fn reading()
+    {
+        let cases = [
+            ("Plus[a, Times[-1, b]]", "a + -1 * b"),
+            ("System`Sin[x] (* comment *)", "Sin(x)"),
+            ("a - b*c", "a + Multiply(-1, b, c)"),
+            ("-2*x + 1", "-2 * x + 1"),
+            ("a/b/c", "a * b ^ -1 * c ^ -1"),
+            ("x^-1 - x^2^3", "x ^ -1 + -1 * x ^ (2 ^ 3)"),
+            ("{1, \"a\\\"b\"} -> {}", "{1, \"a\\\"b\"} -> {}"),
+            ("Sqrt[x] == Power[x, 1/2]", "x ^ (1 / 2) == x ^ (1 / 2)"),
+            ("Log[x] < Log[2, x]", "Ln(x) < Log(2, x)"),
+            ("Function[{x, y}, f[y, x]]", "|x, y| f(y, x)"),
+            ("Function[x, x^2][3]", "(|x| x ^ 2)(3)"),
+            ("f'[x] + Derivative[2][g]",
+             "Derivative(f)(x) + Derivative(Derivative(g))"),
+            ("Derivative[0][f][x]", "f(x)"),
+            ("Integrate[x^2, {x, 0, 1}]", "Integrate(|x| x ^ 2, 0, 1)"),
+            ("Integrate[x*y, x]", "Antiderivative(|x| x * y)(x)"),
+            ("Sum[k, {k, 1, n}] * Product[k, {k, 1, n}]",
+             "Sum(|k| k, 1, n) * Product(|k| k, 1, n)"),
+        ];
+        for (source, expected) in cases {
+            assert_imports(mathematica, source, expected);
+        }
+    }
+
+    #[test]
+    fn slots()
+    {
+        let cases = [
+            ("f[#2, #] &", "Function[f[Slot[2], Slot[1]]]"),
+            ("Function[#^2 + (#&)[1]]",
+             "Function[Plus[Power[Slot[1], 2], Function[Slot[1]][1]]]"),
+            ("(1 &)[]", "Function[List[], 1][]"),
+            ("f[#] &[x] & [y]",
+             "Function[List[], Function[f[Slot[1]]][x]][y]"),
+            ("Function[Slot[1]]", "Function[Slot[1]]"),
+            ("Function[Plus[Slot[1], Slot[2]]]",
+             "Function[Plus[Slot[1], Slot[2]]]"),
+            ("Slot[1] + Slot[2] &", "Function[Plus[Slot[1], Slot[2]]]"),
+        ];
+        let symbols = Symbols::new();
+        let names = MathematicaNames::default();
+        for (source, expected) in cases {
+            let term = import(mathematica, &symbols, source).unwrap();
+            assert_eq!(render_full_form(&term, &names), expected);
+            for render in [render_full_form, plain_input_form] {
+                let text = render(&term, &names);
+                let again = import(mathematica, &symbols, &text).unwrap();
+                assert_eq!(format!("{:?}", again), format!("{:?}", term),
+                           "{} rendered as {}", source, text);
+            }
+        }
+
+        // Parameters named after slots are renamed
+        // where the slots would mean something else.
+        let slots = |count: u32| -> Rc<[Parameter]> {
+            (1 ..= count)
+            .map(|index| Parameter{
+                strictness: Strictness::Strict,
+                name: symbols.get(slot_name(index).as_bytes()),
+            })
+            .collect()
+        };
+        let variable = |index| Term::variable(DeBruijn(index));
+        let add = symbols.get(b"Add").term();
+        let cases = [
+            (Term::lambda(slots(2), variable(1)),
+             "Function[Slot[2]]"),
+            (Term::lambda(slots(2), variable(0)),
+             "Function[List[slot$1, slot$2], slot$1]"),
+            (Term::lambda(slots(1), Term::lambda(slots(1), Term::application(
+                add, [variable(0), variable(1)],
+             ))),
+             "Function[List[slot$1], Function[Plus[Slot[1], slot$1]]]"),
+        ];
+        for (term, expected) in cases {
+            assert_eq!(render_full_form(&term, &names), expected);
+        }
+    }
+
+    #[test]
+    fn writing()
+    {
+        let cases = [
+            ("a + Multiply(-2, b)", "Plus[a, Times[-2, b]]", "a - 2*b"),
+            ("a - 2 * b", "Plus[a, Times[-1, Times[2, b]]]", "a - (2*b)"),
+            ("Multiply(-1, x, y) + x ^ -1",
+             "Plus[Times[-1, x, y], Power[x, -1]]",
+             "-x*y + x^(-1)"),
+            ("a * b ^ -1 * c", "Times[a, Power[b, -1], c]", "a/b*c"),
+            ("(a + b) ^ (c * d)", "Power[Plus[a, b], Times[c, d]]",
+             "(a + b)^(c*d)"),
+            ("{Ln(x), \"a\\n\"} -> x == 1",
+             "Rule[List[Log[x], \"a\\n\"], Equal[x, 1]]",
+             "{Log[x], \"a\\n\"} -> x == 1"),
+            ("Derivative(Derivative(f))(x)", "Derivative[2][f][x]",
+             "f''[x]"),
+            ("(|x, y| x)(1, 2)", "Function[List[x, y], x][1, 2]",
+             "Function[{x, y}, x][1, 2]"),
+            ("Integrate(|t| Sin(t), 0, Pi)",
+             "Integrate[Sin[t], List[t, 0, Pi]]",
+             "Integrate[Sin[t], {t, 0, Pi}]"),
+            ("Antiderivative(|x| E ^ x)(x)", "Integrate[Power[E, x], x]",
+             "Integrate[E^x, x]"),
+        ];
+        let symbols = Symbols::new();
+        let names = MathematicaNames::default();
+        for (source, full_form, input_form) in cases {
+            let term = native(&symbols, source);
+            assert_eq!(render_full_form(&term, &names), full_form);
+            assert_eq!(plain_input_form(&term, &names), input_form);
+        }
+    }
+
+    #[test]
+    fn round_trip()
+    {
+        let sources = [
+            "a - 2 * b / c ^ 2",
+            "-(a * b) + -a * b - (a - b)",
+            "x ^ (1 / 3) <= Abs(Log(2, -y))",
+            "(a == b) != (c -> d -> e)",
+            "Derivative(Sin)(x) + Derivative(|x| x ^ 2)",
+            "Antiderivative(|x| x * y)(x)",
+            "Sum(|k| k ^ 2, 1, n) * Product(|k| k, 1, n)",
+            "(|x, y| f(y, x))(1, \"a b\")",
+            "{1, -2, Infinity}[[1]]",
+        ];
+        let symbols = Symbols::new();
+        let names = MathematicaNames::default();
+        for source in sources {
+            let term = native(&symbols, source);
+            for render in [render_full_form, plain_input_form] {
+                let text = render(&term, &names);
+                let again = import(mathematica, &symbols, &text).unwrap();
+                assert_eq!(format!("{:?}", again), format!("{:?}", term),
+                           "{} rendered as {}", source, text);
+            }
+        }
+    }
+
+    #[test]
+    fn notations()
+    {
+        let source = "infixl 6 \"⊕\" = DirectSum; \
+                      infixr 8 \"⊗\" = Tensor; \
+                      (a ⊕ b) ⊕ (c ⊕ d) * (x ⊗ y) ⊗ z";
+        let symbols = Symbols::new();
+        let (term, notations) = program(&symbols, source);
+        let names = MathematicaNames::default();
+        let text = render_input_form(&term, &names, &notations);
+        assert_eq!(text,
+                   "a~DirectSum~b~DirectSum~\
+                    (c~DirectSum~d*x~Tensor~y~Tensor~z)");
+        let again = import(mathematica, &symbols, &text).unwrap();
+        assert_eq!(format!("{:?}", again), format!("{:?}", term));
+    }
+
+    #[test]
+    fn names()
+    {
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, []);
+        let mut names = MathematicaNames::default();
+        names.insert("BesselJ", "Bessel");
+        names.insert("Plus", "Sum");
+        assert_eq!(names.to_native("Plus"), "Sum");
+        assert_eq!(names.to_mathematica("Add"), "Add");
+        let term =
+            parse_mathematica(&symbols, &scope, &names, "BesselJ[0, x] + 1");
+        let term = term.unwrap();
+        assert_eq!(format!("{:?}", term),
+                   format!("{:?}", native(&symbols, "Sum(Bessel(0, x), 1)")));
+        assert_eq!(render_full_form(&term, &names), "Plus[BesselJ[0, x], 1]");
+    }
+
+    #[test]
+    fn errors()
+    {
+        let cases = [
+            ("",
+             "expected `#`, `(`, `-`, `{`, integer, string or symbol, \
+              found end of input"),
+            ("f[x", "expected `,` or `]`, found end of input"),
+            ("1.5", "real numbers are not supported"),
+            ("99999999999", "integer is too large"),
+            ("a < b < c", "`<` cannot be chained; use parentheses"),
+            ("#1 + 1", "slots must be inside a pure function"),
+            ("Function[]", "`Function` must have one or two arguments"),
+            ("Function[{1}, 1]", "expected a symbol"),
+            ("Derivative[2000000000][f]",
+             "derivatives of order above 1000 are not supported"),
+            ("Sum[k, k]", "expected an iterator, as in `{k, 1, n}`"),
+            ("a b", "expected end of input, found `b`"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(message(mathematica, source), expected, "{}", source);
+        }
+    }
+}
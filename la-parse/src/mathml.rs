@@ -0,0 +1,1665 @@
+use crate::Error;
+use crate::Notation;
+use crate::Notations;
+use crate::Operand;
+use crate::Result;
+use crate::Scope;
+use crate::Span;
+use crate::function_command;
+use crate::is_symbol;
+use crate::make_operand;
+use crate::negate;
+use crate::precedence;
+use crate::reciprocal;
+use crate::root;
+use crate::root_degree;
+use crate::square_root;
+use crate::without_sign;
+
+use la_term::Term;
+use la_term::View;
+use la_term::lambda::Parameter;
+use la_term::lambda::Strictness;
+use la_term::symbol::Symbol;
+use la_term::symbol::Symbols;
+
+/// Namespace of MathML elements.
+const NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+
+/// Heads and the Content MathML operators they stand for.
+const OPERATORS: &[(&str, &str)] = &[
+    ("Add", "plus"), ("Multiply", "times"), ("Power", "power"),
+    ("Sin", "sin"), ("Cos", "cos"), ("Tan", "tan"),
+    ("Cot", "cot"), ("Sec", "sec"), ("Csc", "csc"),
+    ("ArcSin", "arcsin"), ("ArcCos", "arccos"), ("ArcTan", "arctan"),
+    ("Sinh", "sinh"), ("Cosh", "cosh"), ("Tanh", "tanh"),
+    ("Ln", "ln"), ("Log", "log"), ("Exp", "exp"), ("Abs", "abs"),
+    ("Derivative", "diff"), ("Integrate", "int"),
+    ("Sum", "sum"), ("Product", "product"), ("List", "list"),
+    ("Equal", "eq"), ("Unequal", "neq"), ("Less", "lt"),
+    ("LessEqual", "leq"), ("Greater", "gt"), ("GreaterEqual", "geq"),
+];
+
+/// Symbols and the Content MathML constants they stand for.
+const CONSTANTS: &[(&str, &str)] = &[
+    ("Pi", "pi"), ("E", "exponentiale"), ("Infinity", "infinity"),
+];
+
+fn operator(head: &[u8]) -> Option<&'static str>
+{
+    OPERATORS.iter()
+        .find(|(h, _)| h.as_bytes() == head)
+        .map(|(_, element)| *element)
+}
+
+fn operator_head(element: &str) -> Option<&'static str>
+{
+    OPERATORS.iter()
+        .find(|(_, e)| *e == element)
+        .map(|(head, _)| *head)
+}
+
+fn constant(name: &[u8]) -> Option<&'static str>
+{
+    CONSTANTS.iter()
+        .find(|(n, _)| n.as_bytes() == name)
+        .map(|(_, element)| *element)
+}
+
+fn constant_name(element: &str) -> Option<&'static str>
+{
+    CONSTANTS.iter()
+        .find(|(_, e)| *e == element)
+        .map(|(name, _)| *name)
+}
+
+/// Escape the characters that are special in XML.
+fn escape(text: &str) -> String
+{
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Presentation MathML
+
+/// Render a term as Presentation MathML, for display in browsers.
+///
+/// The notation is the same as that of [`render_latex`],
+/// so `a + -1 * b` is written as `a − b`, `Sin(x)` as `sin x`,
+/// `x ^ -1` as a fraction and `Derivative(f)` as `f′`.
+/// Declared operators are written as described at [`Notations`].
+/// The result is a `math` element without surrounding whitespace.
+///
+/// [`render_latex`]: `crate::render_latex`
+pub fn render_presentation_mathml(term: &Term, notations: &Notations)
+    -> String
+{
+    let mut renderer = PresentationRenderer{notations, names: Vec::new()};
+    let markup = renderer.term(term).markup;
+    format!(r#"<math xmlns="{}">{}</math>"#, NAMESPACE, markup)
+}
+
+/// Rendered MathML element, with the precedence of its outermost operator.
+struct Markup
+{
+    markup: String,
+    precedence: u32,
+}
+
+impl Markup
+{
+    fn new(markup: String, precedence: u32) -> Self
+    {
+        Self{markup, precedence}
+    }
+
+    /// The element, in parentheses if it binds looser than the given level.
+    fn at(self, precedence: u32) -> String
+    {
+        if self.precedence < precedence {
+            mrow(&[mo("("), self.markup, mo(")")])
+        } else {
+            self.markup
+        }
+    }
+}
+
+fn mrow(children: &[String]) -> String
+{
+    format!("<mrow>{}</mrow>", children.concat())
+}
+
+fn mo(operator: &str) -> String
+{
+    format!("<mo>{}</mo>", operator)
+}
+
+/// Invisible operator between a function and its argument.
+const APPLY_FUNCTION: &str = "&#x2061;";
+
+/// Invisible operator between juxtaposed factors.
+const INVISIBLE_TIMES: &str = "&#x2062;";
+
+/// State of the renderer for Presentation MathML.
+struct PresentationRenderer<'n>
+{
+    notations: &'n Notations,
+
+    /// Rendered names of the variables in scope,
+    /// the last of which has De Bruijn index zero.
+    names: Vec<String>,
+}
+
+impl<'n> PresentationRenderer<'n>
+{
+    fn term(&mut self, term: &Term) -> Markup
+    {
+        use precedence::*;
+        match term.view() {
+
+            View::Application(function, arguments) =>
+                self.application(function, arguments),
+
+            View::Integer(value) if value < 0 => {
+                let number = format!("<mn>{}</mn>", value.unsigned_abs());
+                Markup::new(mrow(&[mo("−"), number]), NEGATE)
+            },
+
+            View::Integer(value) =>
+                Markup::new(format!("<mn>{}</mn>", value), ATOM),
+
+            View::Lambda(parameters, body) => {
+                let (names, body) = self.lambda(parameters, body, LAMBDA);
+                let names = match names.as_slice() {
+                    [name] => name.clone(),
+                    _ => mrow(&[mo("("), names.join(&mo(",")), mo(")")]),
+                };
+                Markup::new(mrow(&[names, mo("↦"), body]), LAMBDA)
+            },
+
+            View::String(bytes) => {
+                let text = escape(&String::from_utf8_lossy(bytes));
+                Markup::new(format!("<ms>{}</ms>", text), ATOM)
+            },
+
+            View::Symbol(symbol) =>
+                Markup::new(identifier(symbol.name()), ATOM),
+
+            View::Variable(de_bruijn) => {
+                let index = de_bruijn.0 as usize;
+                let markup = match self.names.len().checked_sub(index + 1) {
+                    Some(i) => self.names[i].clone(),
+                    None => format!("<mi>#{}</mi>", index),
+                };
+                Markup::new(markup, ATOM)
+            },
+
+        }
+    }
+
+    /// Render the body of a lambda with its parameters in scope,
+    /// returning the names of the parameters and the body.
+    fn lambda(&mut self, parameters: &[Parameter], body: &Term, level: u32)
+        -> (Vec<String>, String)
+    {
+        let names: Vec<String> =
+            parameters.iter()
+            .map(|parameter| identifier(parameter.name.name()))
+            .collect();
+        let outer = self.names.len();
+        self.names.extend(names.iter().rev().cloned());
+        let body = self.term(body).at(level);
+        self.names.truncate(outer);
+        (names, body)
+    }
+
+    fn application(&mut self, function: &Term, arguments: &[Term]) -> Markup
+    {
+        use precedence::*;
+
+        let head = match function.view() {
+            View::Symbol(symbol) => symbol.name(),
+            _ => return self.generic(function, arguments),
+        };
+
+        if let (Some(notation), [lhs, rhs]) =
+            (self.notations.of_head(head), arguments)
+        {
+            return self.infix(notation, lhs, rhs);
+        }
+
+        match (head, arguments) {
+
+            (b"Add", [first, rest @ ..]) if !rest.is_empty() =>
+                self.add(first, rest),
+
+            (b"Multiply", [_, _, ..]) =>
+                self.multiply(arguments),
+
+            (b"Power", [base, exponent]) =>
+                self.power(base, exponent),
+
+            (b"Derivative", [function]) =>
+                self.derivative(function),
+
+            (b"Abs", [argument]) => {
+                let argument = self.term(argument).markup;
+                Markup::new(mrow(&[mo("|"), argument, mo("|")]), ATOM)
+            },
+
+            _ => {
+                if let Some(relation) = relation_operator(head) {
+                    if let [lhs, rhs] = arguments {
+                        let lhs = self.term(lhs).at(ADD);
+                        let rhs = self.term(rhs).at(ADD);
+                        let markup = mrow(&[lhs, mo(relation), rhs]);
+                        return Markup::new(markup, RELATION);
+                    }
+                }
+                match (function_command(head), arguments) {
+                    (Some(name), [argument]) => self.function(name, argument),
+                    _ => self.generic(function, arguments),
+                }
+            },
+
+        }
+    }
+
+    /// Render an application of the head of a declared operator.
+    fn infix(&mut self, notation: &Notation, lhs: &Term, rhs: &Term)
+        -> Markup
+    {
+        let precedence = precedence::notation(notation.level);
+        let (left, right) =
+            precedence::operands(precedence, notation.associativity);
+        let lhs = self.term(lhs).at(left);
+        let rhs = self.term(rhs).at(right);
+        let markup = mrow(&[lhs, mo(&escape(&notation.operator)), rhs]);
+        Markup::new(markup, precedence)
+    }
+
+    /// Render an application without notation, as in `f(a, b)`.
+    fn generic(&mut self, function: &Term, arguments: &[Term]) -> Markup
+    {
+        use precedence::*;
+        let function = self.term(function).at(APPLICATION);
+        let mut parenthesized = vec![mo("(")];
+        for (i, argument) in arguments.iter().enumerate() {
+            if i != 0 {
+                parenthesized.push(mo(","));
+            }
+            parenthesized.push(self.term(argument).markup);
+        }
+        parenthesized.push(mo(")"));
+        let arguments = mrow(&parenthesized);
+        let markup = mrow(&[function, mo(APPLY_FUNCTION), arguments]);
+        Markup::new(markup, APPLICATION)
+    }
+
+    /// Render a function such as `sin`, leaving out the parentheses
+    /// around the argument if it is a power or tighter.
+    fn function(&mut self, name: &str, argument: &Term) -> Markup
+    {
+        use precedence::*;
+        let name = format!("<mi>{}</mi>", name);
+        let argument = self.term(argument);
+        if argument.precedence >= POWER {
+            let markup = mrow(&[name, mo(APPLY_FUNCTION), argument.markup]);
+            Markup::new(markup, FUNCTION)
+        } else {
+            let argument = argument.at(ATOM);
+            let markup = mrow(&[name, mo(APPLY_FUNCTION), argument]);
+            Markup::new(markup, APPLICATION)
+        }
+    }
+
+    fn add(&mut self, first: &Term, rest: &[Term]) -> Markup
+    {
+        use precedence::*;
+        let mut children = vec![self.term(first).at(ADD)];
+        for term in rest {
+            match self.negative(term) {
+                Some(term) => {
+                    children.push(mo("−"));
+                    children.push(term.at(MULTIPLY));
+                },
+                None => {
+                    children.push(mo("+"));
+                    children.push(self.term(term).at(MULTIPLY));
+                },
+            }
+        }
+        Markup::new(mrow(&children), ADD)
+    }
+
+    /// Render the negation of a term that is negative,
+    /// such as `-2` or `-1 * x`, without its sign.
+    fn negative(&mut self, term: &Term) -> Option<Markup>
+    {
+        use precedence::*;
+        match term.view() {
+            View::Integer(value) if value < 0 => {
+                let number = format!("<mn>{}</mn>", value.unsigned_abs());
+                Some(Markup::new(number, ATOM))
+            },
+            View::Application(function, arguments)
+                if is_symbol(function, b"Multiply") =>
+            {
+                let factors = without_sign(arguments)?;
+                Some(self.product(&factors))
+            },
+            _ => None,
+        }
+    }
+
+    fn multiply(&mut self, factors: &[Term]) -> Markup
+    {
+        use precedence::*;
+        match without_sign(factors) {
+            Some(factors) => {
+                let product = self.product(&factors).at(MULTIPLY);
+                Markup::new(mrow(&[mo("−"), product]), NEGATE)
+            },
+            None => self.product(factors),
+        }
+    }
+
+    /// Render a product, with the factors that have negative exponents
+    /// in the denominator of a fraction.
+    fn product(&mut self, factors: &[Term]) -> Markup
+    {
+        use precedence::*;
+        let mut numerator = Vec::new();
+        let mut denominator = Vec::new();
+        for factor in factors {
+            match reciprocal(factor) {
+                Some(factor) => denominator.push(factor),
+                None => numerator.push(factor.clone()),
+            }
+        }
+        if denominator.is_empty() {
+            return self.juxtapose(&numerator);
+        }
+        let numerator = match numerator.is_empty() {
+            true => "<mn>1</mn>".to_owned(),
+            false => self.juxtapose(&numerator).markup,
+        };
+        let denominator = self.juxtapose(&denominator).markup;
+        let markup = format!("<mfrac>{}{}</mfrac>", numerator, denominator);
+        Markup::new(markup, FRACTION)
+    }
+
+    /// Render factors next to each other, separated by invisible times,
+    /// or by a dot where that would be ambiguous, as in `x · 2`.
+    fn juxtapose(&mut self, factors: &[Term]) -> Markup
+    {
+        use precedence::*;
+        if let [factor] = factors {
+            return self.term(factor);
+        }
+        let mut children = Vec::new();
+        let mut open = false;
+        for factor in factors {
+            let factor = self.term(factor);
+            let function = factor.precedence == FUNCTION;
+            let factor = factor.at(FUNCTION);
+            if !children.is_empty() {
+                let digit = factor.starts_with("<mn>");
+                let separator =
+                    if open || digit { "⋅" } else { INVISIBLE_TIMES };
+                children.push(mo(separator));
+            }
+            children.push(factor);
+            open = function;
+        }
+        Markup::new(mrow(&children), MULTIPLY)
+    }
+
+    fn power(&mut self, base: &Term, exponent: &Term) -> Markup
+    {
+        use precedence::*;
+
+        if let Some(degree) = root_degree(exponent) {
+            let radicand = self.term(base).markup;
+            let markup = match degree.view() {
+                View::Integer(2) => format!("<msqrt>{}</msqrt>", radicand),
+                _ => {
+                    let degree = self.term(degree).markup;
+                    format!("<mroot>{}{}</mroot>", radicand, degree)
+                },
+            };
+            return Markup::new(markup, ATOM);
+        }
+
+        if let View::Integer(value) = exponent.view() {
+            if let Some(value) = value.checked_neg().filter(|&v| v > 0) {
+                let denominator = match value {
+                    1 => self.term(base).markup,
+                    _ => self.power(base, &Term::integer_i32(value)).markup,
+                };
+                let markup =
+                    format!("<mfrac><mn>1</mn>{}</mfrac>", denominator);
+                return Markup::new(markup, FRACTION);
+            }
+        }
+
+        let base = self.term(base).at(APPLICATION);
+        let exponent = self.term(exponent).markup;
+        Markup::new(format!("<msup>{}{}</msup>", base, exponent), POWER)
+    }
+
+    /// Render a derivative as in `f′` or `d/dx b`.
+    fn derivative(&mut self, function: &Term) -> Markup
+    {
+        use precedence::*;
+
+        if let View::Lambda(parameters, body) = function.view() {
+            if let [_] = &parameters[..] {
+                let (names, body) = self.lambda(parameters, body, MULTIPLY);
+                let d = "<mi>d</mi>".to_owned();
+                let operator = format!(
+                    "<mfrac>{}{}</mfrac>",
+                    d, mrow(&[d.clone(), names[0].clone()]),
+                );
+                return Markup::new(mrow(&[operator, body]), FUNCTION);
+            }
+        }
+
+        // Repeated derivatives are written with repeated primes.
+        let mut primes = "′".to_owned();
+        let mut function = function;
+        while let View::Application(head, [inner]) = function.view() {
+            if !is_symbol(head, b"Derivative") {
+                break;
+            }
+            if let View::Lambda(..) = inner.view() {
+                break;
+            }
+            primes.push('′');
+            function = inner;
+        }
+        let function = self.term(function).at(APPLICATION);
+        let markup = format!("<msup>{}{}</msup>", function, mo(&primes));
+        Markup::new(markup, APPLICATION)
+    }
+}
+
+fn relation_operator(head: &[u8]) -> Option<&'static str>
+{
+    match head {
+        b"Equal" => Some("="),
+        b"Unequal" => Some("≠"),
+        b"Less" => Some("&lt;"),
+        b"LessEqual" => Some("≤"),
+        b"Greater" => Some("&gt;"),
+        b"GreaterEqual" => Some("≥"),
+        _ => None,
+    }
+}
+
+/// Render the name of a symbol or variable as an identifier.
+fn identifier(name: &[u8]) -> String
+{
+    let name = match name {
+        b"Pi" => "π".into(),
+        b"E" => "e".into(),
+        b"Infinity" => "∞".into(),
+        _ => String::from_utf8_lossy(name),
+    };
+    format!("<mi>{}</mi>", escape(&name))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Content MathML
+
+/// Render a term as Content MathML, for semantic interchange.
+///
+/// Applications of heads with a Content MathML operator,
+/// such as `Add` and `Sin`, use that operator, as in `<apply><plus/>…`,
+/// and `Pi`, `E` and `Infinity` are written as `<pi/>`, `<exponentiale/>`
+/// and `<infinity/>`. Other symbols and variables are written as `<ci>`,
+/// lambdas as `<lambda>` with a `<bvar>` for each parameter,
+/// and `Integrate`, `Sum`, `Product` and `Antiderivative`
+/// with bound variables and limits.
+/// The result is parsed back into the same term
+/// by [`parse_content_mathml`].
+pub fn render_content_mathml(term: &Term) -> String
+{
+    let mut out = String::new();
+    ContentRenderer{names: Vec::new()}.term(term, &mut out);
+    format!(r#"<math xmlns="{}">{}</math>"#, NAMESPACE, out)
+}
+
+/// State of the renderer for Content MathML.
+struct ContentRenderer
+{
+    /// Escaped names of the variables in scope,
+    /// the last of which has De Bruijn index zero.
+    names: Vec<String>,
+}
+
+impl ContentRenderer
+{
+    fn term(&mut self, term: &Term, out: &mut String)
+    {
+        match term.view() {
+
+            View::Application(function, arguments) =>
+                self.application(function, arguments, out),
+
+            View::Integer(value) =>
+                out.push_str(&format!(r#"<cn type="integer">{}</cn>"#, value)),
+
+            View::Lambda(parameters, body) => {
+                out.push_str("<lambda>");
+                self.bound(parameters, body, out);
+                out.push_str("</lambda>");
+            },
+
+            View::String(bytes) => {
+                let text = escape(&String::from_utf8_lossy(bytes));
+                out.push_str(&format!("<cs>{}</cs>", text));
+            },
+
+            View::Symbol(symbol) =>
+                match constant(symbol.name()) {
+                    Some(element) => out.push_str(&format!("<{}/>", element)),
+                    None => out.push_str(&ci(symbol.name())),
+                },
+
+            View::Variable(de_bruijn) => {
+                let index = de_bruijn.0 as usize;
+                match self.names.len().checked_sub(index + 1) {
+                    Some(i) => out.push_str(&self.names[i]),
+                    None => out.push_str(&format!("<ci>#{}</ci>", index)),
+                }
+            },
+
+        }
+    }
+
+    /// Write the bound variables of a lambda, followed by its body,
+    /// with the given qualifiers in between.
+    fn bound_with(
+        &mut self,
+        parameters: &[Parameter],
+        qualifiers: &str,
+        body: &Term,
+        out: &mut String,
+    )
+    {
+        let names: Vec<String> =
+            parameters.iter()
+            .map(|parameter| ci(parameter.name.name()))
+            .collect();
+        for name in &names {
+            out.push_str(&format!("<bvar>{}</bvar>", name));
+        }
+        out.push_str(qualifiers);
+        let outer = self.names.len();
+        self.names.extend(names.into_iter().rev());
+        self.term(body, out);
+        self.names.truncate(outer);
+    }
+
+    fn bound(&mut self, parameters: &[Parameter], body: &Term, out: &mut String)
+    {
+        self.bound_with(parameters, "", body, out);
+    }
+
+    fn application(
+        &mut self,
+        function: &Term,
+        arguments: &[Term],
+        out: &mut String,
+    )
+    {
+        out.push_str("<apply>");
+        self.operator_and_arguments(function, arguments, out);
+        out.push_str("</apply>");
+    }
+
+    fn operator_and_arguments(
+        &mut self,
+        function: &Term,
+        arguments: &[Term],
+        out: &mut String,
+    )
+    {
+        // Antiderivative(|x| f)(x) is the indefinite integral of f.
+        if let (View::Application(head, [integrand]), [argument]) =
+            (function.view(), arguments)
+        {
+            if let View::Lambda(parameters, body) = integrand.view() {
+                if is_symbol(head, b"Antiderivative")
+                    && parameters.len() == 1
+                    && is_symbol(argument, parameters[0].name.name())
+                {
+                    out.push_str("<int/>");
+                    self.bound(parameters, body, out);
+                    return;
+                }
+            }
+        }
+
+        let symbol = match function.view() {
+            View::Symbol(symbol) => symbol,
+            _ => {
+                self.term(function, out);
+                self.arguments(arguments, out);
+                return;
+            },
+        };
+        let element = match operator(symbol.name()) {
+            Some(element) => element,
+            None => {
+                out.push_str(&ci(symbol.name()));
+                self.arguments(arguments, out);
+                return;
+            },
+        };
+
+        match (element, arguments) {
+
+            ("log", [base, argument]) => {
+                out.push_str("<log/><logbase>");
+                self.term(base, out);
+                out.push_str("</logbase>");
+                self.term(argument, out);
+            },
+
+            ("int" | "sum" | "product", [function, lower, upper]) =>
+                match function.view() {
+                    View::Lambda(parameters, body) if parameters.len() == 1 => {
+                        let mut limits = "<lowlimit>".to_owned();
+                        self.term(lower, &mut limits);
+                        limits.push_str("</lowlimit><uplimit>");
+                        self.term(upper, &mut limits);
+                        limits.push_str("</uplimit>");
+                        out.push_str(&format!("<{}/>", element));
+                        self.bound_with(parameters, &limits, body, out);
+                    },
+                    _ => {
+                        out.push_str(&format!("<{}/>", element));
+                        self.arguments(arguments, out);
+                    },
+                },
+
+            _ => {
+                out.push_str(&format!("<{}/>", element));
+                self.arguments(arguments, out);
+            },
+
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[Term], out: &mut String)
+    {
+        for argument in arguments {
+            self.term(argument, out);
+        }
+    }
+}
+
+fn ci(name: &[u8]) -> String
+{
+    format!("<ci>{}</ci>", escape(&String::from_utf8_lossy(name)))
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Reading XML
+
+/// Element of an XML document.
+struct Element<'a>
+{
+    /// Name of the element, without namespace prefix.
+    name: &'a str,
+
+    /// Location of the start tag.
+    span: Span,
+
+    attributes: Vec<(&'a str, String)>,
+    children: Vec<Content<'a>>,
+}
+
+/// Child of an XML element.
+enum Content<'a>
+{
+    Element(Element<'a>),
+    Text(String, Span),
+}
+
+impl<'a> Element<'a>
+{
+    fn attribute(&self, name: &str) -> Option<&str>
+    {
+        self.attributes.iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The child elements, ignoring whitespace between them.
+    fn elements(&self) -> Result<Vec<&Element<'a>>>
+    {
+        let mut elements = Vec::new();
+        for child in &self.children {
+            match child {
+                Content::Element(element) => elements.push(element),
+                Content::Text(text, _) if text.trim().is_empty() => (),
+                Content::Text(text, span) => {
+                    let message = format!(
+                        "unexpected text in `<{}>`",
+                        self.name,
+                    );
+                    return Err(Error::new(span.clone(), Some(text), message));
+                },
+            }
+        }
+        Ok(elements)
+    }
+
+    /// The text content, which must not contain elements.
+    fn text(&self) -> Result<String>
+    {
+        let mut text = String::new();
+        for child in &self.children {
+            match child {
+                Content::Text(chunk, _) => text.push_str(chunk),
+                Content::Element(element) => {
+                    let message = format!(
+                        "`<{}>` must contain only text",
+                        self.name,
+                    );
+                    return Err(Error::new(
+                        element.span.clone(),
+                        Some(element.name),
+                        message,
+                    ));
+                },
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Parser for the subset of XML that MathML documents use.
+///
+/// Comments, processing instructions, document type declarations
+/// and CDATA sections are supported, as are the predefined entities
+/// and character references. Other entities are not supported.
+struct XmlReader<'a>
+{
+    source: &'a str,
+    position: usize,
+}
+
+impl<'a> XmlReader<'a>
+{
+    /// Parse a document, which contains a single root element.
+    fn document(&mut self) -> Result<Element<'a>>
+    {
+        self.skip_misc()?;
+        if !self.rest().starts_with('<') {
+            return Err(self.unexpected(&["`<`"]));
+        }
+        let root = self.element()?;
+        self.skip_misc()?;
+        match self.rest().is_empty() {
+            true => Ok(root),
+            false => Err(self.unexpected(&["end of input"])),
+        }
+    }
+
+    /// Skip whitespace, comments, processing instructions
+    /// and document type declarations.
+    fn skip_misc(&mut self) -> Result<()>
+    {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+            if trimmed.starts_with("<!--") {
+                self.skip_past("-->", "comment")?;
+            } else if trimmed.starts_with("<?") {
+                self.skip_past("?>", "processing instruction")?;
+            } else if trimmed.starts_with("<!DOCTYPE") {
+                self.skip_past(">", "document type declaration")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_past(&mut self, terminator: &'static str, what: &str)
+        -> Result<()>
+    {
+        match self.rest().find(terminator) {
+            Some(offset) => {
+                self.position += offset + terminator.len();
+                Ok(())
+            },
+            None => {
+                let span = self.position .. self.source.len();
+                Err(Error::unterminated(span, what, terminator))
+            },
+        }
+    }
+
+    /// Parse an element, starting at its `<`.
+    fn element(&mut self) -> Result<Element<'a>>
+    {
+        let start = self.position;
+        self.position += 1;
+        let name = self.name()?;
+
+        let mut attributes = Vec::new();
+        let empty = loop {
+            self.skip_whitespace();
+            if self.eat("/>") {
+                break true;
+            }
+            if self.eat(">") {
+                break false;
+            }
+            let attribute = self.name()?;
+            self.skip_whitespace();
+            if !self.eat("=") {
+                return Err(self.unexpected(&["`=`"]));
+            }
+            self.skip_whitespace();
+            let value = self.attribute_value()?;
+            attributes.push((attribute, value));
+        };
+        let span = start .. self.position;
+
+        let mut children = Vec::new();
+        if !empty {
+            self.contents(&mut children)?;
+            let close = self.position;
+            self.position += 2;
+            let close_name = self.name()?;
+            self.skip_whitespace();
+            if close_name != name || !self.eat(">") {
+                let found = &self.source[close .. self.position];
+                let message = format!("expected `</{}>`", name);
+                return Err(Error::new(close .. self.position,
+                                      Some(found), message));
+            }
+        }
+
+        let name = local_name(name);
+        Ok(Element{name, span, attributes, children})
+    }
+
+    /// Parse the contents of an element, up to its end tag.
+    fn contents(&mut self, children: &mut Vec<Content<'a>>) -> Result<()>
+    {
+        let mut text = String::new();
+        let mut text_start = self.position;
+        loop {
+            let rest = self.rest();
+            if rest.is_empty() {
+                return Err(self.unexpected(&["end tag"]));
+            } else if rest.starts_with("</") {
+                break;
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->", "comment")?;
+            } else if rest.starts_with("<?") {
+                self.skip_past("?>", "processing instruction")?;
+            } else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = cdata.find("]]>").ok_or_else(|| {
+                    let span = self.position .. self.source.len();
+                    Error::unterminated(span, "CDATA section", "]]>")
+                })?;
+                text.push_str(&cdata[.. end]);
+                self.position += "<![CDATA[".len() + end + "]]>".len();
+            } else if rest.starts_with('<') {
+                if !text.is_empty() {
+                    let span = text_start .. self.position;
+                    children.push(Content::Text(text, span));
+                    text = String::new();
+                }
+                children.push(Content::Element(self.element()?));
+                text_start = self.position;
+            } else if rest.starts_with('&') {
+                text.push(self.reference()?);
+            } else {
+                let c = rest.chars().next().unwrap();
+                text.push(c);
+                self.position += c.len_utf8();
+            }
+        }
+        if !text.is_empty() {
+            let span = text_start .. self.position;
+            children.push(Content::Text(text, span));
+        }
+        Ok(())
+    }
+
+    /// Parse a name, which may have a namespace prefix.
+    fn name(&mut self) -> Result<&'a str>
+    {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| {
+                !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'))
+            })
+            .unwrap_or(rest.len());
+        if length == 0 {
+            return Err(self.unexpected(&["name"]));
+        }
+        self.position += length;
+        Ok(&rest[.. length])
+    }
+
+    fn attribute_value(&mut self) -> Result<String>
+    {
+        let quote = match self.rest().chars().next() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => return Err(self.unexpected(&["`\"`", "`'`"])),
+        };
+        let start = self.position;
+        self.position += 1;
+        let mut value = String::new();
+        loop {
+            match self.rest().chars().next() {
+                None => {
+                    let span = start .. self.source.len();
+                    let terminator = if quote == '"' { "\"" } else { "'" };
+                    let what = "attribute value";
+                    return Err(Error::unterminated(span, what, terminator));
+                },
+                Some(c) if c == quote => {
+                    self.position += 1;
+                    return Ok(value);
+                },
+                Some('&') => value.push(self.reference()?),
+                Some(c) => {
+                    value.push(c);
+                    self.position += c.len_utf8();
+                },
+            }
+        }
+    }
+
+    /// Parse an entity or character reference, as in `&lt;` or `&#x3C0;`.
+    fn reference(&mut self) -> Result<char>
+    {
+        let start = self.position;
+        let end = match self.rest().find(';') {
+            Some(end) => start + end + 1,
+            None => return Err(self.unexpected(&["reference"])),
+        };
+        let reference = &self.source[start + 1 .. end - 1];
+        let c = match reference {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => {
+                let code = match reference.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => reference.strip_prefix('#')
+                        .and_then(|decimal| decimal.parse().ok()),
+                };
+                code.and_then(char::from_u32)
+            },
+        };
+        match c {
+            Some(c) => {
+                self.position = end;
+                Ok(c)
+            },
+            None => {
+                let found = &self.source[start .. end];
+                let message = format!("unknown reference `{}`", found);
+                Err(Error::new(start .. end, Some(found), message))
+            },
+        }
+    }
+
+    fn skip_whitespace(&mut self)
+    {
+        let rest = self.rest();
+        self.position += rest.len() - rest.trim_start().len();
+    }
+
+    fn eat(&mut self, token: &str) -> bool
+    {
+        let found = self.rest().starts_with(token);
+        if found {
+            self.position += token.len();
+        }
+        found
+    }
+
+    fn rest(&self) -> &'a str
+    {
+        &self.source[self.position ..]
+    }
+
+    fn unexpected(&self, expected: &[&'static str]) -> Error
+    {
+        let found = self.rest().chars().next();
+        let end = self.position + found.map_or(0, char::len_utf8);
+        let found = found.map(|_| &self.source[self.position .. end]);
+        Error::unexpected(self.position .. end, found, expected)
+    }
+}
+
+/// The name without its namespace prefix.
+fn local_name(name: &str) -> &str
+{
+    match name.rsplit_once(':') {
+        Some((_, local)) => local,
+        None => name,
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Importing Content MathML
+
+/// Parse a term written in Content MathML.
+///
+/// This accepts what [`render_content_mathml`] renders,
+/// as well as common forms that it does not render:
+///
+/// | Content MathML                                | Term                |
+/// |-----------------------------------------------|---------------------|
+/// | `<apply><minus/>a b</apply>`                  | `a - b`             |
+/// | `<apply><divide/>a b</apply>`                 | `a / b`             |
+/// | `<apply><root/><degree>n</degree>x</apply>`   | `x ^ (1 / n)`       |
+/// | `<apply><diff/><bvar>x</bvar>f</apply>`       | `Derivative(\|x\| f)(x)` |
+/// | `<cn type="rational">1<sep/>2</cn>`           | `1 / 2`             |
+/// | `<csymbol cd="arith1">plus</csymbol>`         | `Add`               |
+/// | `<semantics>` with annotations                | the first child     |
+///
+/// The root element may be `<math>` or the term itself,
+/// and elements may have a namespace prefix, as in `<m:apply>`.
+/// Identifiers are variables if they are in the scope,
+/// and symbols otherwise. Only integers and rationals are supported.
+pub fn parse_content_mathml(symbols: &Symbols, scope: &Scope, source: &str)
+    -> Result<Term>
+{
+    let root = XmlReader{source, position: 0}.document()?;
+    ContentParser{symbols, source}.term(scope, &root)
+}
+
+/// State of the parser for Content MathML.
+struct ContentParser<'a, 's>
+{
+    symbols: &'s Symbols,
+    source: &'a str,
+}
+
+/// Qualifiers of an application, which are not arguments.
+const QUALIFIERS: &[&str] = &["lowlimit", "uplimit", "logbase", "degree"];
+
+impl<'a, 's> ContentParser<'a, 's>
+{
+    fn term(&self, scope: &Scope, element: &Element) -> Result<Term>
+    {
+        match element.name {
+
+            "math" =>
+                match element.elements()?.as_slice() {
+                    [term] => self.term(scope, term),
+                    _ => Err(self.error(element,
+                                        "`<math>` must contain one element")),
+                },
+
+            "semantics" =>
+                match element.elements()?.first() {
+                    Some(term) => self.term(scope, term),
+                    None => Err(self.error(element,
+                                           "`<semantics>` must not be empty")),
+                },
+
+            "cn" =>
+                self.number(element),
+
+            "ci" => {
+                let name = element.text()?;
+                Ok(self.variable(scope, name.trim()))
+            },
+
+            "cs" =>
+                Ok(Term::string(element.text()?.bytes())),
+
+            "csymbol" => {
+                let name = element.text()?;
+                let name = name.trim();
+                // Symbols from content dictionaries, as in
+                // `<csymbol cd="arith1">plus</csymbol>`, are known by
+                // the names of the elements that stand for them.
+                let known = operator_head(name).or_else(|| constant_name(name));
+                match (known, element.attribute("cd")) {
+                    (Some(head), Some(_)) => Ok(self.symbol(head)),
+                    _ => Ok(self.symbol(name)),
+                }
+            },
+
+            "lambda" => {
+                let (variables, rest) = self.bound(element)?;
+                let body = match rest.as_slice() {
+                    [body] => {
+                        let scope = Scope::new(Some(scope), variables.clone());
+                        self.term(&scope, body)?
+                    },
+                    _ => return Err(self.error(
+                        element,
+                        "`<lambda>` must have one body",
+                    )),
+                };
+                Ok(lambda(variables, body))
+            },
+
+            "apply" =>
+                self.apply(scope, element),
+
+            name => {
+                if let Some(name) = constant_name(name) {
+                    return Ok(self.symbol(name));
+                }
+                if let Some(head) = operator_head(name) {
+                    return Ok(self.symbol(head));
+                }
+                let message = format!("unsupported element `<{}>`", name);
+                Err(self.error(element, &message))
+            },
+
+        }
+    }
+
+    /// Parse a number, which must be an integer or a rational.
+    fn number(&self, element: &Element) -> Result<Term>
+    {
+        let kind = element.attribute("type").unwrap_or("integer");
+        let parts: Vec<&str> = element.children.iter()
+            .filter_map(|child| match child {
+                Content::Text(text, _) => Some(text.trim()),
+                Content::Element(..) => None,
+            })
+            .collect();
+        let separators: Vec<&Element> = element.children.iter()
+            .filter_map(|child| match child {
+                Content::Element(element) => Some(element),
+                Content::Text(..) => None,
+            })
+            .collect();
+        let integer = |text: &str| {
+            text.parse::<i32>().map(Term::integer_i32).map_err(|_| {
+                let message = format!("`{}` is not a supported number", text);
+                self.error(element, &message)
+            })
+        };
+        match (kind, parts.as_slice(), separators.as_slice()) {
+            ("integer" | "real", [text], []) =>
+                integer(text),
+            ("rational", [numerator, denominator], [separator])
+                if separator.name == "sep" =>
+            {
+                let numerator = integer(numerator)?;
+                let denominator = integer(denominator)?;
+                let operand = Operand::Reciprocal;
+                let reciprocal =
+                    make_operand(self.symbols, operand, denominator);
+                Ok(self.apply_head("Multiply", vec![numerator, reciprocal]))
+            },
+            _ => {
+                let message =
+                    "only integers and rationals are supported".to_owned();
+                Err(self.error(element, &message))
+            },
+        }
+    }
+
+    /// Parse the bound variables of an element, as in `<bvar><ci>x</ci>`,
+    /// returning them and the other child elements.
+    fn bound<'e>(&self, element: &'e Element<'a>)
+        -> Result<(Vec<Symbol>, Vec<&'e Element<'a>>)>
+    {
+        let mut variables = Vec::new();
+        let mut rest = Vec::new();
+        for child in element.elements()? {
+            if child.name != "bvar" {
+                rest.push(child);
+                continue;
+            }
+            match child.elements()?.as_slice() {
+                [ci] if ci.name == "ci" => {
+                    let name = ci.text()?;
+                    variables.push(self.symbols.get(name.trim().as_bytes()));
+                },
+                _ => return Err(self.error(
+                    child,
+                    "`<bvar>` must contain one `<ci>`",
+                )),
+            }
+        }
+        Ok((variables, rest))
+    }
+
+    fn apply(&self, scope: &Scope, element: &Element) -> Result<Term>
+    {
+        let (variables, children) = self.bound(element)?;
+        let (operator, children) = match children.split_first() {
+            Some(split) => split,
+            None => return Err(self.error(element,
+                                          "`<apply>` must not be empty")),
+        };
+
+        let mut qualifiers = Vec::new();
+        let mut operands = Vec::new();
+        for child in children {
+            match QUALIFIERS.contains(&child.name) {
+                true => qualifiers.push(*child),
+                false => operands.push(*child),
+            }
+        }
+        let qualifier = |name: &str| -> Result<Option<&Element>> {
+            let mut found = qualifiers.iter().filter(|q| q.name == name);
+            match (found.next(), found.next()) {
+                (None, _) => Ok(None),
+                (Some(qualifier), None) =>
+                    match qualifier.elements()?.as_slice() {
+                        [term] => Ok(Some(*term)),
+                        _ => {
+                            let message = format!(
+                                "`<{}>` must contain one element",
+                                name,
+                            );
+                            Err(self.error(qualifier, &message))
+                        },
+                    },
+                (Some(_), Some(duplicate)) => {
+                    let message = format!("`<{}>` is given twice", name);
+                    Err(self.error(duplicate, &message))
+                },
+            }
+        };
+
+        let name = match operator.name {
+            "csymbol" if operator.attribute("cd").is_some() => {
+                let name = operator.text()?;
+                name.trim().to_owned()
+            },
+            name => name.to_owned(),
+        };
+        let allowed: &[&str] = match name.as_str() {
+            "int" | "sum" | "product" => &["lowlimit", "uplimit"],
+            "log" => &["logbase"],
+            "root" => &["degree"],
+            _ => &[],
+        };
+        let binds = matches!(name.as_str(), "int" | "sum" | "product" | "diff");
+        if let Some(qualifier) =
+            qualifiers.iter().find(|q| !allowed.contains(&q.name))
+        {
+            let message = format!("`<{}>` is not allowed here", qualifier.name);
+            return Err(self.error(qualifier, &message));
+        }
+        if !binds && !variables.is_empty() {
+            return Err(self.error(element, "`<bvar>` is not allowed here"));
+        }
+
+        // Operands of bound operators are parsed with the variable in scope.
+        if binds {
+            let variable = match variables.as_slice() {
+                [variable] => variable.clone(),
+                [] if name == "diff" => {
+                    let operands = self.operands(scope, &operands)?;
+                    return Ok(self.apply_head("Derivative", operands));
+                },
+                _ => {
+                    let message =
+                        format!("`<{}/>` must have one `<bvar>`", name);
+                    return Err(self.error(element, &message));
+                },
+            };
+            let body = match operands.as_slice() {
+                [body] => {
+                    let scope = Scope::new(Some(scope), [variable.clone()]);
+                    self.term(&scope, body)?
+                },
+                _ => {
+                    let message =
+                        format!("`<{}/>` must have one operand", name);
+                    return Err(self.error(element, &message));
+                },
+            };
+            let function = lambda(vec![variable.clone()], body);
+            let lower = qualifier("lowlimit")?;
+            let upper = qualifier("uplimit")?;
+            let head = operator_head(&name).unwrap();
+            return match (name.as_str(), lower, upper) {
+                (_, Some(lower), Some(upper)) => {
+                    let lower = self.term(scope, lower)?;
+                    let upper = self.term(scope, upper)?;
+                    Ok(self.apply_head(head, vec![function, lower, upper]))
+                },
+                ("int" | "diff", None, None) => {
+                    let head = match name.as_str() {
+                        "int" => "Antiderivative",
+                        _ => "Derivative",
+                    };
+                    let operator = self.apply_head(head, vec![function]);
+                    let variable = self.variable(scope, variable.name());
+                    Ok(Term::application(operator, [variable]))
+                },
+                _ => {
+                    let message = format!(
+                        "`<{}/>` must have both limits{}",
+                        name,
+                        if name == "int" { " or neither" } else { "" },
+                    );
+                    Err(self.error(element, &message))
+                },
+            };
+        }
+
+        let mut operands = self.operands(scope, &operands)?;
+        match (name.as_str(), operands.len()) {
+            ("minus", 1) =>
+                Ok(negate(self.symbols, operands.pop().unwrap())),
+            ("minus", 2) => {
+                let rhs = operands.pop().unwrap();
+                let rhs = make_operand(self.symbols, Operand::Negated, rhs);
+                operands.push(rhs);
+                Ok(self.apply_head("Add", operands))
+            },
+            ("divide", 2) => {
+                let rhs = operands.pop().unwrap();
+                let rhs = make_operand(self.symbols, Operand::Reciprocal, rhs);
+                operands.push(rhs);
+                Ok(self.apply_head("Multiply", operands))
+            },
+            ("root", 1) => {
+                let radicand = operands.pop().unwrap();
+                match qualifier("degree")? {
+                    Some(degree) => {
+                        let degree = self.term(scope, degree)?;
+                        Ok(root(self.symbols, radicand, degree))
+                    },
+                    None => Ok(square_root(self.symbols, radicand)),
+                }
+            },
+            ("log", _) => {
+                if let Some(base) = qualifier("logbase")? {
+                    operands.insert(0, self.term(scope, base)?);
+                }
+                Ok(self.apply_head("Log", operands))
+            },
+            ("minus" | "divide" | "root", _) => {
+                let message = format!(
+                    "`<{}/>` has the wrong number of operands",
+                    name,
+                );
+                Err(self.error(element, &message))
+            },
+            _ => {
+                let function = match operator_head(&name) {
+                    Some(head) => self.symbol(head),
+                    None => self.term(scope, operator)?,
+                };
+                Ok(Term::application(function, operands))
+            },
+        }
+    }
+
+    fn operands(&self, scope: &Scope, operands: &[&Element])
+        -> Result<Vec<Term>>
+    {
+        operands.iter().map(|operand| self.term(scope, operand)).collect()
+    }
+
+    /// The variable in scope with the given name, or else the symbol.
+    fn variable(&self, scope: &Scope, name: impl AsRef<[u8]>) -> Term
+    {
+        let symbol = self.symbols.get(name.as_ref());
+        match scope.get(&symbol) {
+            Some(de_bruijn) => Term::variable(de_bruijn),
+            None => Term::symbol(symbol),
+        }
+    }
+
+    fn symbol(&self, name: &str) -> Term
+    {
+        self.symbols.get(name.as_bytes()).term()
+    }
+
+    fn apply_head(&self, head: &str, arguments: Vec<Term>) -> Term
+    {
+        Term::application(self.symbol(head), arguments)
+    }
+
+    fn error(&self, element: &Element, message: &str) -> Error
+    {
+        let span = element.span.clone();
+        let found = &self.source[span.clone()];
+        Error::new(span, Some(found), message.to_owned())
+    }
+}
+
+/// The function `|x, ...| body`.
+fn lambda(variables: Vec<Symbol>, body: Term) -> Term
+{
+    let parameters: Vec<Parameter> =
+        variables.into_iter()
+        .map(|name| Parameter{strictness: Strictness::Strict, name})
+        .collect();
+    Term::lambda(parameters.into(), body)
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing::*;
+
+    /// Render a term, leaving out the `math` element.
+    fn render(render: fn(&Term) -> String, source: &str) -> String
+    {
+        let symbols = Symbols::new();
+        let markup = render(&native(&symbols, source));
+        let prefix = format!(r#"<math xmlns="{}">"#, NAMESPACE);
+        markup.strip_prefix(&prefix).unwrap()
+            .strip_suffix("</math>").unwrap()
+            .to_owned()
+    }
+
+    #[test]
+    fn presentation()
+    {
+        let cases = [
+            ("a - 2 * b",
+             "<mrow><mi>a</mi><mo>−</mo>\
+              <mrow><mn>2</mn><mo>&#x2062;</mo><mi>b</mi></mrow></mrow>"),
+            ("x * 2",
+             "<mrow><mi>x</mi><mo>⋅</mo><mn>2</mn></mrow>"),
+            ("-x ^ 2",
+             "<mrow><mo>−</mo><msup><mi>x</mi><mn>2</mn></msup></mrow>"),
+            ("Pi * r ^ -1",
+             "<mfrac><mi>π</mi><mi>r</mi></mfrac>"),
+            ("(a + b) ^ (1 / 2)",
+             "<msqrt><mrow><mi>a</mi><mo>+</mo><mi>b</mi></mrow></msqrt>"),
+            ("E ^ (x + 1)",
+             "<msup><mi>e</mi>\
+              <mrow><mi>x</mi><mo>+</mo><mn>1</mn></mrow></msup>"),
+            ("Sin(x) * Cos(x + 1)",
+             "<mrow><mrow><mi>sin</mi><mo>&#x2061;</mo><mi>x</mi></mrow>\
+              <mo>⋅</mo><mrow><mi>cos</mi><mo>&#x2061;</mo>\
+              <mrow><mo>(</mo><mrow><mi>x</mi><mo>+</mo><mn>1</mn></mrow>\
+              <mo>)</mo></mrow></mrow></mrow>"),
+            ("Tan(x) <= Ln(x)",
+             "<mrow><mrow><mi>tan</mi><mo>&#x2061;</mo><mi>x</mi></mrow>\
+              <mo>≤</mo>\
+              <mrow><mi>ln</mi><mo>&#x2061;</mo><mi>x</mi></mrow></mrow>"),
+            ("Derivative(Derivative(f))(x)",
+             "<mrow><msup><mi>f</mi><mo>′′</mo></msup><mo>&#x2061;</mo>\
+              <mrow><mo>(</mo><mi>x</mi><mo>)</mo></mrow></mrow>"),
+            ("Derivative(|t| t ^ 2)",
+             "<mrow><mfrac><mi>d</mi><mrow><mi>d</mi><mi>t</mi></mrow>\
+              </mfrac><msup><mi>t</mi><mn>2</mn></msup></mrow>"),
+            ("|x, y| x < y",
+             "<mrow><mrow><mo>(</mo><mi>x</mi><mo>,</mo><mi>y</mi>\
+              <mo>)</mo></mrow><mo>↦</mo>\
+              <mrow><mi>x</mi><mo>&lt;</mo><mi>y</mi></mrow></mrow>"),
+            ("\"a<b\"", "<ms>a&lt;b</ms>"),
+        ];
+        for (source, expected) in cases {
+            let markup = render(
+                |term| render_presentation_mathml(term, &Notations::new()),
+                source,
+            );
+            assert_eq!(markup, expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn presentation_notations()
+    {
+        let source = "infixl 6 \"⊕\" = DirectSum; a ⊕ (b ⊕ c) * d";
+        let (term, notations) = program(&Symbols::new(), source);
+        assert_eq!(
+            render_presentation_mathml(&term, &notations),
+            format!(
+                "<math xmlns=\"{}\"><mrow><mi>a</mi><mo>⊕</mo>\
+                 <mrow><mrow><mo>(</mo><mrow><mi>b</mi><mo>⊕</mo>\
+                 <mi>c</mi></mrow><mo>)</mo></mrow><mo>&#x2062;</mo>\
+                 <mi>d</mi></mrow></mrow></math>",
+                NAMESPACE,
+            ),
+        );
+    }
+
+    #[test]
+    fn content()
+    {
+        let cases = [
+            ("a + 2 * Pi",
+             r#"<apply><plus/><ci>a</ci><apply><times/>"#.to_owned()
+                 + r#"<cn type="integer">2</cn><pi/></apply></apply>"#),
+            ("Sin(E ^ x)",
+             "<apply><sin/><apply><power/><exponentiale/><ci>x</ci>\
+              </apply></apply>".to_owned()),
+            ("Derivative(|x| Ln(x))",
+             "<apply><diff/><lambda><bvar><ci>x</ci></bvar>\
+              <apply><ln/><ci>x</ci></apply></lambda></apply>".to_owned()),
+            ("Integrate(|t| t, 0, Infinity)",
+             "<apply><int/><bvar><ci>t</ci></bvar>\
+              <lowlimit><cn type=\"integer\">0</cn></lowlimit>\
+              <uplimit><infinity/></uplimit><ci>t</ci></apply>".to_owned()),
+            ("f(\"&\")",
+             "<apply><ci>f</ci><cs>&amp;</cs></apply>".to_owned()),
+        ];
+        for (source, expected) in cases {
+            let markup = render(render_content_mathml, source);
+            assert_eq!(markup, expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn content_round_trip()
+    {
+        let sources = [
+            "a - 2 * b / c ^ 2",
+            "Sin(x) ^ 2 + Cos(x) ^ 2 == 1",
+            "Tan(Pi / 4) != Ln(E)",
+            "x ^ (1 / 3) <= Abs(Log(2, y))",
+            "Derivative(Sin)(x) + Derivative(|x| x ^ 2)",
+            "Antiderivative(|x| x * y)(x)",
+            "Sum(|k| k ^ 2, 1, n) * Product(|k| k, 1, n)",
+            "(|x, y| f(y, x))(1, \"a b\")",
+            "{1, 2, Infinity}",
+        ];
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, []);
+        for source in sources {
+            let term = native(&symbols, source);
+            let markup = render_content_mathml(&term);
+            let again = parse_content_mathml(&symbols, &scope, &markup);
+            assert_eq!(format!("{:?}", again.unwrap()), format!("{:?}", term),
+                       "{} rendered as {}", source, markup);
+        }
+    }
+
+    #[test]
+    fn content_import()
+    {
+        let cases = [
+            ("<?xml version=\"1.0\"?>\n<!-- comment -->\n\
+              <m:math xmlns:m=\"http://www.w3.org/1998/Math/MathML\">\n\
+              <m:apply> <m:minus/> <m:ci> a </m:ci> <m:cn>2</m:cn> \
+              </m:apply>\n</m:math>\n",
+             "a - 2"),
+            ("<apply><minus/><ci>x</ci></apply>", "-x"),
+            ("<apply><divide/><cn>1</cn><ci>x</ci></apply>", "1 / x"),
+            ("<apply><root/><degree><cn>3</cn></degree><ci>x</ci></apply>",
+             "x ^ (1 / 3)"),
+            ("<apply><root/><ci>x</ci></apply>", "x ^ (1 / 2)"),
+            ("<apply><diff/><bvar><ci>x</ci></bvar>\
+              <apply><sin/><ci>x</ci></apply></apply>",
+             "Derivative(|x| Sin(x))(x)"),
+            ("<apply><log/><logbase><cn>2</cn></logbase><ci>x</ci></apply>",
+             "Log(2, x)"),
+            ("<cn type=\"rational\">1<sep/>2</cn>", "1 / 2"),
+            ("<apply><csymbol cd=\"arith1\">plus</csymbol>\
+              <csymbol cd=\"nums1\">pi</csymbol><csymbol>Foo</csymbol>\
+              </apply>",
+             "Pi + Foo"),
+            ("<semantics><apply><cos/><ci>&#x3B8;</ci></apply>\
+              <annotation encoding=\"TeX\">\\cos\\theta</annotation>\
+              </semantics>",
+             "Cos(θ)"),
+            ("<cs><![CDATA[a<b]]> &amp; c</cs>", "\"a<b & c\""),
+            ("<apply><ci>f</ci><tan/></apply>", "f(Tan)"),
+        ];
+        for (source, expected) in cases {
+            assert_imports(parse_content_mathml, source, expected);
+        }
+    }
+
+    #[test]
+    fn content_errors()
+    {
+        let cases = [
+            ("", "expected `<`, found end of input"),
+            ("<ci>x</cn>", "expected `</ci>`"),
+            ("<ci>x", "expected end tag, found end of input"),
+            ("<ci a=1>x</ci>", "expected `\"` or `'`, found `1`"),
+            ("<ci>&nbsp;</ci>", "unknown reference `&nbsp;`"),
+            ("<ci>x</ci><ci>y</ci>", "expected end of input, found `<`"),
+            ("<mi>x</mi>", "unsupported element `<mi>`"),
+            ("<cn>2.5</cn>", "`2.5` is not a supported number"),
+            ("<cn type=\"complex\">1</cn>",
+             "only integers and rationals are supported"),
+            ("<apply><plus/>1<ci>x</ci></apply>",
+             "unexpected text in `<apply>`"),
+            ("<apply><plus/><degree><cn>2</cn></degree>\
+              </apply>",
+             "`<degree>` is not allowed here"),
+            ("<apply><sum/><bvar><ci>k</ci></bvar>\
+              <ci>k</ci></apply>",
+             "`<sum/>` must have both limits"),
+            ("<apply><minus/></apply>",
+             "`<minus/>` has the wrong number of operands"),
+            ("<apply/>", "`<apply>` must not be empty"),
+        ];
+        for (source, expected) in cases {
+            let message = message(parse_content_mathml, source);
+            assert_eq!(message, expected, "{}", source);
+        }
+    }
+}
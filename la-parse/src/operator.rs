@@ -0,0 +1,364 @@
+use crate::Token;
+
+use logos::Logos;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// How an infix operator groups with operators of the same precedence.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Associativity
+{
+    /// `a ∘ b ∘ c` means `(a ∘ b) ∘ c`.
+    Left,
+
+    /// `a ∘ b ∘ c` means `a ∘ (b ∘ c)`.
+    Right,
+
+    /// `a ∘ b ∘ c` is a syntax error.
+    None,
+}
+
+/// How the right operand of an infix operator is passed to its head.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Operand
+{
+    /// The operand is passed as written.
+    AsIs,
+
+    /// The operand `x` is passed as `Multiply(-1, x)`.
+    Negated,
+
+    /// The operand `x` is passed as `Power(x, -1)`.
+    Reciprocal,
+}
+
+/// Information about an infix operator.
+#[derive(Clone, Debug)]
+pub struct Infix
+{
+    /// Name of the symbol the operator is syntax for.
+    pub head: Cow<'static, str>,
+
+    /// Operators with higher precedence bind more tightly.
+    pub precedence: u32,
+
+    /// How the operator groups with operators of the same precedence.
+    pub associativity: Associativity,
+
+    /// Whether a chain of left-associative operators with the same head
+    /// becomes a single application rather than nested applications.
+    /// For example, `a + b - c` becomes `Add(a, b, Multiply(-1, c))`.
+    pub flat: bool,
+
+    /// How the right operand is passed.
+    /// The left operand is always passed as written.
+    pub operand: Operand,
+}
+
+/// The head of a rule with the given arrow token, if it is one.
+///
+/// Rules are not infix operators: they bind more loosely than
+/// conditions, which bind more loosely than any infix operator.
+/// See [`parse_term`](`crate::parse_term`).
+pub(crate) fn rule_head(token: &Token) -> Option<&'static str>
+{
+    match token {
+        Token::MinusGreater => Some("Rule"),
+        Token::ColonGreater => Some("RuleDelayed"),
+        _ => None,
+    }
+}
+
+/// The head of a replacement with the given operator token, if it is one.
+///
+/// Like rules, replacements are not infix operators;
+/// they bind even more loosely than rules.
+pub(crate) fn replace_head(token: &Token) -> Option<&'static str>
+{
+    match token {
+        Token::SlashDot => Some("ReplaceAll"),
+        Token::SlashSlashDot => Some("ReplaceRepeated"),
+        _ => None,
+    }
+}
+
+/// The keyword of a notation declaration, if the token is one,
+/// with the associativity it declares.
+pub(crate) fn notation_associativity(token: &Token) -> Option<Associativity>
+{
+    match token {
+        Token::Infixl => Some(Associativity::Left),
+        Token::Infixr => Some(Associativity::Right),
+        Token::Infix => Some(Associativity::None),
+        _ => None,
+    }
+}
+
+/// Precedence of comparison operators.
+///
+/// Precedences are ten times the levels of [`Notation`],
+/// so that declared operators can be placed among the built-in ones.
+pub const PRECEDENCE_COMPARISON: u32 = 40;
+
+/// Precedence of `+` and binary `-`.
+pub const PRECEDENCE_ADDITIVE: u32 = 60;
+
+/// Precedence of `*` and `/`.
+pub const PRECEDENCE_MULTIPLICATIVE: u32 = 70;
+
+/// Precedence of unary `-`.
+///
+/// This is not an infix operator, but it is parsed in the same way.
+/// Its operand is parsed with this precedence,
+/// so `-a * b` means `(-a) * b`, and `-a ^ b` means `-(a ^ b)`.
+pub const PRECEDENCE_NEGATE: u32 = 75;
+
+/// Precedence of `^`.
+pub const PRECEDENCE_POWER: u32 = 80;
+
+impl Infix
+{
+    const fn new(
+        head: &'static str,
+        precedence: u32,
+        associativity: Associativity,
+        flat: bool,
+        operand: Operand,
+    ) -> Self
+    {
+        let head = Cow::Borrowed(head);
+        Self{head, precedence, associativity, flat, operand}
+    }
+
+    /// Find the infix operator a token stands for, if any.
+    pub fn of_token(token: &Token) -> Option<Self>
+    {
+        let comparison = |head| Self::new(
+            head, PRECEDENCE_COMPARISON,
+            Associativity::None, false, Operand::AsIs,
+        );
+
+        let additive = |operand| Self::new(
+            "Add", PRECEDENCE_ADDITIVE,
+            Associativity::Left, true, operand,
+        );
+
+        let multiplicative = |operand| Self::new(
+            "Multiply", PRECEDENCE_MULTIPLICATIVE,
+            Associativity::Left, true, operand,
+        );
+
+        let power = Self::new(
+            "Power", PRECEDENCE_POWER,
+            Associativity::Right, false, Operand::AsIs,
+        );
+
+        match token {
+            Token::EqualEqual => Some(comparison("Equal")),
+            Token::ExclamationEqual => Some(comparison("Unequal")),
+            Token::Less => Some(comparison("Less")),
+            Token::LessEqual => Some(comparison("LessEqual")),
+            Token::Greater => Some(comparison("Greater")),
+            Token::GreaterEqual => Some(comparison("GreaterEqual")),
+            Token::Plus => Some(additive(Operand::AsIs)),
+            Token::Minus => Some(additive(Operand::Negated)),
+            Token::Asterisk => Some(multiplicative(Operand::AsIs)),
+            Token::Slash => Some(multiplicative(Operand::Reciprocal)),
+            Token::Caret => Some(power),
+            _ => None,
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Notations
+
+/// Declaration of an infix operator, as in `infixl 6 "⊕" = DirectSum`.
+///
+/// The declared operator binds like a built-in operator at the same level:
+/// comparisons are at level 4, `+` and `-` at level 6,
+/// `*` and `/` at level 7, and `^` at level 8.
+/// Declared operators are never flat, so with the above declaration,
+/// `a ⊕ b ⊕ c` means `DirectSum(DirectSum(a, b), c)`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Notation
+{
+    /// The operator, as in `⊕`; see [`Token::Operator`].
+    pub operator: String,
+
+    /// Level from 0 to 9; operators with higher levels bind more tightly.
+    pub level: u32,
+
+    /// How the operator groups with operators of the same level.
+    pub associativity: Associativity,
+
+    /// Name of the symbol the operator is syntax for.
+    pub head: String,
+}
+
+impl Notation
+{
+    /// The infix operator the notation declares.
+    pub fn infix(&self) -> Infix
+    {
+        Infix{
+            head: Cow::Owned(self.head.clone()),
+            precedence: self.level * 10,
+            associativity: self.associativity,
+            flat: false,
+            operand: Operand::AsIs,
+        }
+    }
+}
+
+/// Table of declared infix operators.
+///
+/// The parser declares operators in the table as it reads declarations,
+/// and consults it for the operators that follow;
+/// see [`Lexer::notations`](`crate::Lexer::notations`).
+/// The formatter reads the same declarations, so it lays out
+/// declared operators exactly as the parser groups them,
+/// and so do the renderers, such as [`render_term`](`crate::render_term`).
+/// The renderers write an application of the head of a declared operator
+/// to two arguments with that operator, as in `a ⊕ b` for `DirectSum(a, b)`
+/// after `infixl 6 "⊕" = DirectSum`, grouped by its precedence.
+/// Where several operators are declared for a head,
+/// the one declared last is used.
+#[derive(Clone, Debug, Default)]
+pub struct Notations
+{
+    operators: HashMap<String, Notation>,
+
+    /// The operator most recently declared for each head.
+    heads: HashMap<String, String>,
+}
+
+impl Notations
+{
+    /// Create a table with no declared operators.
+    pub fn new() -> Self
+    {
+        Self::default()
+    }
+
+    /// Declare an operator, replacing any earlier declaration of it.
+    pub fn declare(&mut self, notation: Notation)
+    {
+        self.heads.insert(notation.head.clone(), notation.operator.clone());
+        self.operators.insert(notation.operator.clone(), notation);
+    }
+
+    /// The declaration of an operator, if it is declared.
+    pub fn get(&self, operator: &str) -> Option<&Notation>
+    {
+        self.operators.get(operator)
+    }
+
+    /// The declaration of the operator most recently declared
+    /// for a head, if any is still declared for it.
+    pub fn of_head(&self, head: &[u8]) -> Option<&Notation>
+    {
+        let head = std::str::from_utf8(head).ok()?;
+        self.heads.get(head)
+            .and_then(|operator| self.get(operator))
+            .filter(|notation| notation.head == head)
+    }
+
+    /// The declared operators, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item=&Notation>
+    {
+        self.operators.values()
+    }
+
+    /// Find the infix operator a token stands for, if any.
+    ///
+    /// This is like [`Infix::of_token`],
+    /// but also finds the declared operators.
+    pub fn infix(&self, token: &Token) -> Option<Infix>
+    {
+        match token {
+            Token::Operator(operator) =>
+                self.get(operator).map(Notation::infix),
+            _ => Infix::of_token(token),
+        }
+    }
+}
+
+/// Check the level of a notation declaration.
+///
+/// On failure, returns the error message.
+pub(crate) fn notation_level(level: u32) -> Result<u32, String>
+{
+    match level {
+        0 ..= 9 => Ok(level),
+        _ => Err("precedence level must be from 0 to 9".into()),
+    }
+}
+
+/// Check the operator of a notation declaration,
+/// which is the contents of a string literal.
+///
+/// On failure, returns the error message.
+pub(crate) fn notation_operator(operator: &[u8]) -> Result<String, String>
+{
+    let text = String::from_utf8_lossy(operator);
+    let mut tokens = Token::lexer(&text).spanned();
+    match (tokens.next(), tokens.next()) {
+        (Some((Token::Operator(..), span)), None) if span.start == 0
+            && span.end == text.len() => Ok(text.into_owned()),
+        _ => Err(format!(
+            "`{}` cannot be declared; operators consist of symbols \
+             such as `⊕`, other than those of built-in operators",
+            text,
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn notations()
+    {
+        let mut notations = Notations::new();
+        notations.declare(Notation{
+            operator: "⊕".into(),
+            level: 6,
+            associativity: Associativity::Left,
+            head: "DirectSum".into(),
+        });
+
+        assert_eq!(notations.of_head(b"DirectSum").unwrap().operator, "⊕");
+        assert!(notations.of_head(b"Add").is_none());
+        notations.declare(Notation{
+            operator: "⊕".into(),
+            level: 6,
+            associativity: Associativity::Left,
+            head: "Sum".into(),
+        });
+        assert!(notations.of_head(b"DirectSum").is_none());
+        notations.declare(Notation{
+            operator: "⊕".into(),
+            level: 6,
+            associativity: Associativity::Left,
+            head: "DirectSum".into(),
+        });
+
+        let infix = notations.infix(&Token::Operator("⊕")).unwrap();
+        assert_eq!(infix.head, "DirectSum");
+        assert_eq!(infix.precedence, PRECEDENCE_ADDITIVE);
+        assert!(!infix.flat);
+        assert!(notations.infix(&Token::Operator("⊗")).is_none());
+        assert_eq!(notations.infix(&Token::Caret).unwrap().head, "Power");
+
+        assert_eq!(notation_level(9), Ok(9));
+        assert!(notation_level(10).is_err());
+        assert_eq!(notation_operator("⊕⊗".as_bytes()), Ok("⊕⊗".into()));
+        assert!(notation_operator(b"+").is_err());
+        assert!(notation_operator(" ⊕".as_bytes()).is_err());
+        assert!(notation_operator("⊕×".as_bytes()).is_err());
+        assert!(notation_operator(b"").is_err());
+    }
+}
@@ -1,126 +1,663 @@
+use crate::Error;
+use crate::Notations;
+use crate::Operand;
 use crate::Result;
 use crate::Scope;
+use crate::Span;
 use crate::Token;
+use crate::TokenKind;
+use crate::Infix;
+use crate::Notation;
+use crate::invalid_escape;
+use crate::parse_statement_cst;
+use crate::parse_term_cst;
 
 use la_term::Term;
+use la_term::View;
 use la_term::lambda::Parameter;
 use la_term::lambda::Strictness;
+use la_term::symbol::Symbol;
 use la_term::symbol::Symbols;
-use std::borrow::Cow;
-use std::iter::Peekable;
+use logos::Logos;
+use logos::SpannedIter;
+use std::collections::VecDeque;
+use std::mem::take;
+
+////////////////////////////////////////////////////////////////////////////////
+// Token streams
 
 /// Stream of tokens generated from text.
-pub type Lexer<'a> = Peekable<logos::Lexer<'a, Token<'a>>>;
+///
+/// Each token is paired with its location in the text,
+/// so that errors can point at the offending token.
+///
+/// `[[` only opens a part extraction as in `m[[1]]`
+/// when it follows a token that ends an operand;
+/// elsewhere, as in `[[1, 2]]`, it is split into two `[`.
+///
+/// Comments are not part of the stream.
+/// Documentation comments are remembered until the next token is consumed,
+/// so that the parser can attach them to definitions; see [`take_doc`].
+///
+/// The stream carries the table of declared operators,
+/// so that the parser can tell which operator tokens are infix operators;
+/// see [`notations`].
+///
+/// [`notations`]: `Lexer::notations`
+/// [`take_doc`]: `Lexer::take_doc`
+pub struct Lexer<'a>
+{
+    source: &'a str,
+    tokens: SpannedIter<'a, Token<'a>>,
+
+    /// Tokens that were read from `tokens` but not yet consumed.
+    peeked: VecDeque<(Token<'a>, Span)>,
+
+    /// Documentation comments directly preceding the next token.
+    doc: Option<String>,
+
+    /// Whether the last token read from `tokens` ends an operand.
+    ends_operand: bool,
+
+    /// Whitespace and comments directly preceding the next token,
+    /// or [`None`] if they need not be recorded.
+    trivia: Option<Vec<(TokenKind, Span)>>,
+
+    /// End of the last token read from `tokens`,
+    /// used for finding the whitespace that the lexer skips.
+    lexed_end: usize,
+
+    previous_end: usize,
+
+    notations: Notations,
+}
+
+impl<'a> Lexer<'a>
+{
+    /// Create a token stream for the given text.
+    pub fn new(source: &'a str) -> Self
+    {
+        let tokens = Token::lexer(source).spanned();
+        Self{
+            source,
+            tokens,
+            peeked: VecDeque::new(),
+            doc: None,
+            ends_operand: false,
+            trivia: None,
+            lexed_end: 0,
+            previous_end: 0,
+            notations: Notations::new(),
+        }
+    }
+
+    /// Create a token stream for the given text,
+    /// in which the given operators are already declared.
+    pub fn with_notations(source: &'a str, notations: Notations) -> Self
+    {
+        Self{notations, ..Self::new(source)}
+    }
+
+    /// Create a token stream that also records
+    /// the whitespace and comments between tokens.
+    ///
+    /// Use [`take_trivia`] to retrieve them.
+    ///
+    /// [`take_trivia`]: `Lexer::take_trivia`
+    pub(crate) fn with_trivia(source: &'a str) -> Self
+    {
+        Self{trivia: Some(Vec::new()), ..Self::new(source)}
+    }
+
+    /// Take the whitespace and comments that were skipped
+    /// since the last call to this method.
+    ///
+    /// This includes those directly preceding the next token,
+    /// but only once the next token was peeked.
+    /// Nothing is recorded unless the stream was created
+    /// with [`with_trivia`].
+    ///
+    /// [`with_trivia`]: `Lexer::with_trivia`
+    pub(crate) fn take_trivia(&mut self) -> Vec<(TokenKind, Span)>
+    {
+        self.trivia.as_mut().map(take).unwrap_or_default()
+    }
+
+    /// The text the tokens are generated from.
+    pub fn source(&self) -> &'a str
+    {
+        self.source
+    }
+
+    /// The declared operators.
+    ///
+    /// These include those given to [`with_notations`],
+    /// and those declared by the statements parsed so far.
+    ///
+    /// [`with_notations`]: `Lexer::with_notations`
+    pub fn notations(&self) -> &Notations
+    {
+        &self.notations
+    }
+
+    /// Mutable access to the declared operators.
+    pub fn notations_mut(&mut self) -> &mut Notations
+    {
+        &mut self.notations
+    }
+
+    /// Make sure the next token, if any, is in `peeked`.
+    fn fill(&mut self)
+    {
+        if !self.peeked.is_empty() {
+            return;
+        }
+
+        let (token, span) = loop {
+            let next = self.tokens.next();
+            let start = next.as_ref().map_or(self.source.len(), |n| n.1.start);
+            self.push_trivia(TokenKind::Whitespace, self.lexed_end .. start);
+            match next {
+                Some((Token::Comment, span)) =>
+                    self.push_trivia(TokenKind::Comment, span),
+                Some((Token::DocComment(text), span)) => {
+                    self.push_doc(&text);
+                    self.push_trivia(TokenKind::DocComment, span);
+                },
+                Some(next) =>
+                    break next,
+                None =>
+                    return,
+            }
+        };
+        self.lexed_end = span.end;
+
+        let ends_operand = self.ends_operand;
+        self.ends_operand = matches!(
+            token,
+            Token::Identifier(..) | Token::Integer(..) | Token::String(..)
+                | Token::Blank(..) | Token::Pi | Token::Superscript(..)
+                | Token::RightParenthesis
+                | Token::RightBracket | Token::RightBrace
+                | Token::PipeGreater
+        );
+
+        match token {
+            Token::LeftDoubleBracket if !ends_operand => {
+                let first = span.start .. span.start + 1;
+                let second = span.start + 1 .. span.end;
+                self.peeked.push_back((Token::LeftBracket, first));
+                self.peeked.push_back((Token::LeftBracket, second));
+            },
+            _ => self.peeked.push_back((token, span)),
+        }
+    }
+
+    /// Record whitespace or a comment, if trivia are being recorded.
+    fn push_trivia(&mut self, kind: TokenKind, span: Span)
+    {
+        if let Some(trivia) = &mut self.trivia {
+            if !span.is_empty() {
+                self.lexed_end = span.end;
+                trivia.push((kind, span));
+            }
+        }
+    }
+
+    /// Append the text of a documentation comment to `doc`.
+    fn push_doc(&mut self, text: &str)
+    {
+        if text.is_empty() {
+            return;
+        }
+        match &mut self.doc {
+            Some(doc) => {
+                doc.push('\n');
+                doc.push_str(text);
+            },
+            None => self.doc = Some(text.to_owned()),
+        }
+    }
+
+    /// Take the documentation comments directly preceding the next token.
+    ///
+    /// Consecutive documentation comments are joined by newlines.
+    /// Returns [`None`] if there are none,
+    /// or if they were already taken.
+    pub fn take_doc(&mut self) -> Option<String>
+    {
+        self.fill();
+        self.doc.take()
+    }
+
+    /// Look at the next token without consuming it.
+    pub fn peek(&mut self) -> Option<&Token<'a>>
+    {
+        self.fill();
+        self.peeked.front().map(|(token, _)| token)
+    }
+
+    /// The infix operator the next token stands for, if any,
+    /// taking declared operators into account.
+    pub fn peek_infix(&mut self) -> Option<Infix>
+    {
+        self.fill();
+        let (token, _) = self.peeked.front()?;
+        self.notations.infix(token)
+    }
+
+    /// Location of the next token.
+    ///
+    /// At the end of the input, this is the empty span after the text.
+    pub fn peek_span(&mut self) -> Span
+    {
+        self.fill();
+        let end = self.source.len();
+        self.peeked.front().map_or(end .. end, |(_, span)| span.clone())
+    }
+
+    /// Location just after the most recently consumed token.
+    ///
+    /// Before any token is consumed, this is the start of the text.
+    pub fn previous_end(&self) -> usize
+    {
+        self.previous_end
+    }
+
+    /// Consume the next token if it matches the predicate.
+    pub fn next_if<F>(&mut self, pred: F) -> Option<(Token<'a>, Span)>
+        where F: FnOnce(&Token<'a>) -> bool
+    {
+        match self.peek() {
+            Some(token) if pred(token) => self.next(),
+            _ => None,
+        }
+    }
+
+    /// Create an error about the next token not being one of those expected.
+    ///
+    /// The token is consumed.
+    pub fn unexpected(&mut self, expected: &[&'static str]) -> Error
+    {
+        let span = self.peek_span();
+        match self.next() {
+            Some((_, span)) => self.unexpected_at(span, expected),
+            None => Error::unexpected(span, None, expected),
+        }
+    }
+
+    /// Create an error about the token at the given location
+    /// not being one of those expected.
+    ///
+    /// If the location is the start of a string or block comment
+    /// that is not terminated, the error is about that instead.
+    /// Such an error is at the end of the input; see [`Error::found`].
+    /// Likewise, if the token is an operator that was not declared,
+    /// or a string with an invalid escape sequence,
+    /// the error is about that.
+    pub fn unexpected_at(&self, span: Span, expected: &[&'static str])
+        -> Error
+    {
+        let rest = &self.source[span.start ..];
+        let end = self.source.len();
+        if rest.starts_with("(*") {
+            return Error::unterminated(span.start .. end, "comment", "`*)`");
+        }
+        if rest.starts_with('"') && !is_terminated_string(rest) {
+            return Error::unterminated(span.start .. end, "string", "`\"`");
+        }
+
+        let found = &self.source[span.clone()];
+        if let Some(escape) = invalid_escape(found) {
+            let span = span.start + escape.start .. span.start + escape.end;
+            let escape = &self.source[span.clone()];
+            let message = format!("invalid escape sequence `{}`", escape);
+            return Error::new(span, Some(escape), message);
+        }
+        if let Some(Token::Operator(operator)) = Token::lexer(found).next() {
+            if self.notations.get(operator).is_none() {
+                let message =
+                    format!("operator `{}` is not declared", operator);
+                return Error::new(span, Some(found), message);
+            }
+        }
+        Error::unexpected(span, Some(found), expected)
+    }
+
+    /// Create an error with a custom message about the given location.
+    pub fn error_at(&self, span: Span, message: String) -> Error
+    {
+        let found = &self.source[span.clone()];
+        Error::new(span, Some(found), message)
+    }
+}
+
+/// Whether the string literal at the start of the text has a closing quote.
+fn is_terminated_string(text: &str) -> bool
+{
+    let mut bytes = text.bytes().skip(1);
+    while let Some(byte) = bytes.next() {
+        match byte {
+            b'\\' => { bytes.next(); },
+            b'"' => return true,
+            _ => (),
+        }
+    }
+    false
+}
+
+impl<'a> Iterator for Lexer<'a>
+{
+    type Item = (Token<'a>, Span);
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        self.fill();
+        let next = self.peeked.pop_front();
+        self.doc = None;
+        if let Some((_, span)) = &next {
+            self.previous_end = span.end;
+        }
+        next
+    }
+}
 
 ////////////////////////////////////////////////////////////////////////////////
-// Terms
+// Programs
 
-/// Parse a term from a token stream.
-pub fn parse_term(symbols: &Symbols, scope: &Scope, lex: &mut Lexer)
-    -> Result<Term>
+/// Statement in a program.
+#[derive(Debug)]
+pub enum Statement
 {
-    parse_term_2(symbols, scope, lex)
+    /// `name := definition`.
+    ///
+    /// The function definition `name(x, y) := body`
+    /// is parsed as `name := |x, y| body`.
+    /// Documentation comments preceding the statement are in `doc`.
+    #[allow(missing_docs)]
+    Definition{name: Symbol, definition: Term, doc: Option<String>},
+
+    /// A term on its own, to be simplified.
+    Term(Term),
+
+    /// `infixl 6 "⊕" = DirectSum`, or likewise with `infixr` or `infix`,
+    /// which declares an operator for the statements that follow.
+    Notation(Notation),
 }
 
-fn parse_term_2(symbols: &Symbols, scope: &Scope, lex: &mut Lexer)
-    -> Result<Term>
+/// Parse a sequence of statements separated by `;`.
+///
+/// Empty statements are permitted, so the program
+/// may begin or end with `;`, and may be empty altogether.
+/// The whole token stream is consumed.
+pub fn parse_program(symbols: &Symbols, scope: &Scope, lex: &mut Lexer)
+    -> Result<Vec<Statement>>
 {
-    let mut term = parse_term_1(symbols, scope, lex)?;
-    while let Some(arguments) = parse_argument_list(symbols, scope, lex)? {
-        term = Term::application(term, arguments);
+    let mut statements = Vec::new();
+    loop {
+        while parse_optional_matches!(lex, Token::Semicolon) {}
+        if lex.peek().is_none() {
+            break;
+        }
+        let statement = parse_statement(symbols, scope, lex)?;
+        statements.push(statement);
+        if lex.peek().is_none() {
+            break;
+        }
+        parse_exact_matches!(lex, Token::Semicolon, "`;`")?;
     }
-    Ok(term)
+    Ok(statements)
 }
 
-fn parse_term_1(symbols: &Symbols, scope: &Scope, lex: &mut Lexer)
-    -> Result<Term>
+/// Parse a single statement.
+///
+/// Documentation comments preceding the statement
+/// are attached to it if it is a definition.
+/// A notation declaration declares its operator in the token stream,
+/// so that the statements that follow may use it.
+pub fn parse_statement(symbols: &Symbols, scope: &Scope, lex: &mut Lexer)
+    -> Result<Statement>
 {
-    match lex.next() {
-
-        Some(Token::Pipe) => {
-            let parameters = parse_comma_matches!(
-                lex,
-                Token::Pipe,
-                |lex| parse_parameter(symbols, lex),
-            )?;
-            let body = {
-                let parameters = parameters.iter().map(|p| p.name.clone());
-                let scope = Scope::new(Some(scope), parameters);
-                parse_term(symbols, &scope, lex)?
-            };
-            Ok(Term::lambda(parameters.into(), body))
-        },
+    let doc = lex.take_doc();
+    let cst = parse_statement_cst(lex);
+    cst.to_statement(symbols, scope, &cst.root, doc)
+}
 
-        Some(Token::LeftParenthesis) => {
-            let term = parse_term(symbols, scope, lex)?;
-            parse_exact_matches!(lex, Token::RightParenthesis)?;
-            Ok(term)
-        },
+/// Reinterpret the left-hand side of `:=`.
+///
+/// Returns the name being defined,
+/// and the parameters if a function is being defined.
+/// On failure, returns the error message.
+pub(crate) fn definition_head(term: &Term)
+    -> std::result::Result<(Symbol, Option<Vec<Parameter>>), String>
+{
+    let invalid = || {
+        "left-hand side of `:=` must be a symbol, \
+         or a symbol applied to parameters".to_owned()
+    };
+
+    match term.view() {
 
-        Some(Token::Integer(value)) =>
-            Ok(Term::integer_i32(value)),
+        View::Symbol(name) =>
+            Ok((name.clone(), None)),
 
-        Some(Token::String(value)) =>
-            Ok(Term::string(value.iter().copied())),
+        View::Application(function, arguments) => {
+            let name = function.as_symbol().ok_or_else(invalid)?;
 
-        Some(Token::Identifier(ref name)) => {
-            let name = symbols.get(name);
-            match scope.get(&name) {
-                Some(de_bruijn) => Ok(Term::variable(de_bruijn)),
-                None => Ok(Term::symbol(name)),
+            let mut parameters = Vec::with_capacity(arguments.len());
+            for argument in arguments {
+                let name = argument.as_symbol().ok_or_else(invalid)?;
+                if parameters.iter().any(|p: &Parameter| &p.name == name) {
+                    return Err(format!(
+                        "parameter `{}` is bound more than once",
+                        String::from_utf8_lossy(name.name()),
+                    ));
+                }
+                let strictness = Strictness::Strict;
+                parameters.push(Parameter{strictness, name: name.clone()});
             }
+
+            Ok((name.clone(), Some(parameters)))
         },
 
-        _ => todo!(),
+        _ => Err(invalid()),
 
     }
 }
 
 ////////////////////////////////////////////////////////////////////////////////
-// Components
+// Terms
+
+/// Descriptions of the tokens that may start a term.
+pub(crate) const TERM: &[&str] = &[
+    "`(`", "`-`", "`<|`", "`[`", "`let`", "`with`", "`{`", "`|`",
+    "`π`", "`√`", "blank", "identifier", "integer", "string",
+];
 
-fn parse_identifier<'a>(lex: &mut Lexer<'a>) -> Result<Cow<'a, [u8]>>
+/// Parse a term from a token stream.
+///
+/// The term may be a pattern with conditions, as in `x_ /; x > 0`,
+/// a rule, as in `x -> Pi` or `f(x_) :> x ^ 2`,
+/// or a replacement, as in `Sin(x) /. x -> Pi` or `e //. rules`.
+/// Conditions bind more loosely than any infix operator,
+/// rules bind more loosely than conditions,
+/// and replacements bind more loosely than rules.
+/// Rules group to the right, and replacements to the left.
+///
+/// The term may also bind local variables, which become lambda parameters:
+///
+/// | Syntax                     | Term                                      |
+/// |----------------------------|-------------------------------------------|
+/// | `let x = a, y = b in c`    | `(\|x\| (\|y\| c)(b))(a)`                 |
+/// | `let rec x = a, y = b in c`| `LetRec(\|x, y\| List(a, b), \|x, y\| c)` |
+/// | `with x = a, y = b in c`   | `(\|x, y\| c)(a, b)`                      |
+/// | `c where x = a, y = b`     | `(\|x, y\| c)(a, b)`                      |
+///
+/// In `let`, each value is in the scope of the binders before it.
+/// In `let rec`, the values are in the scope of all binders,
+/// and `LetRec(values, body)` stands for the body applied to
+/// the fixed point of the values. In `with` and `where`,
+/// the values are in the enclosing scope. A binder may occur only once,
+/// and may be non-strict, as in `let ~x = a in c`.
+///
+/// `let` and `with` extend as far as possible, like lambdas,
+/// whereas `where` follows the term it applies to.
+/// `where` binds more loosely than anything else,
+/// and its bindings are separated by commas,
+/// so it must be in parentheses in an argument list.
+///
+/// Some mathematical notation may be used in place of ASCII,
+/// so that formulas can be pasted from elsewhere:
+///
+/// | Syntax           | Means              |
+/// |------------------|--------------------|
+/// | `π`              | `Pi`               |
+/// | `a × b`, `a · b` | `a * b`            |
+/// | `a − b`, `−a`    | `a - b`, `-a`      |
+/// | `a ≤ b`, `a ≥ b` | `a <= b`, `a >= b` |
+/// | `a ≠ b`          | `a != b`           |
+/// | `x²`, `x⁻¹`      | `x ^ 2`, `x ^ -1`  |
+/// | `√x`             | `x ^ (1 / 2)`      |
+///
+/// Superscripts bind as tightly as argument lists, so `f(x)²` means
+/// `f(x) ^ 2`, and `√` applies to the operand that follows it
+/// including its argument lists and superscripts, so `√x²` means
+/// `√(x²)`.
+pub fn parse_term(symbols: &Symbols, scope: &Scope, lex: &mut Lexer)
+    -> Result<Term>
 {
-    let token = lex.next();
-    match token {
-        Some(Token::Identifier(name)) => Ok(name),
-        _ => todo!(),
+    let cst = parse_term_cst(lex);
+    if let Some(error) = cst.errors.first() {
+        return Err(error.clone());
     }
+    cst.to_term(symbols, scope, &cst.root)
 }
 
-fn parse_argument_list(symbols: &Symbols, scope: &Scope, lex: &mut Lexer)
-    -> Result<Option<Vec<Term>>>
+/// Assert that the token stream has no tokens left.
+pub fn parse_end(lex: &mut Lexer) -> Result<()>
 {
-    if parse_optional_matches!(lex, Token::LeftParenthesis) {
-        let arguments = parse_comma_matches!(
-            lex,
-            Token::RightParenthesis,
-            |lex| parse_term(symbols, scope, lex),
-        )?;
-        Ok(Some(arguments))
-    } else {
-        Ok(None)
+    match lex.peek() {
+        None => Ok(()),
+        Some(_) => Err(lex.unexpected(&["end of input"])),
     }
 }
 
-fn parse_parameter(symbols: &Symbols, lex: &mut Lexer) -> Result<Parameter>
+pub(crate) fn make_operand(symbols: &Symbols, operand: Operand, term: Term)
+    -> Term
 {
-    let strictness = parse_strictness(lex);
-    let name = parse_identifier(lex)?;
-    let name = symbols.get(&name);
-    Ok(Parameter{strictness, name})
+    match operand {
+        Operand::AsIs => term,
+        Operand::Negated => negate(symbols, term),
+        Operand::Reciprocal =>
+            power(symbols, term, Term::integer_i32(-1)),
+    }
 }
 
-fn parse_strictness(lex: &mut Lexer) -> Strictness
+/// Negate a term, writing negated integers as negative integers.
+pub(crate) fn negate(symbols: &Symbols, term: Term) -> Term
 {
-    let tilde = parse_optional_matches!(lex, Token::Tilde);
-    if tilde {
-        Strictness::NonStrict
-    } else {
-        Strictness::Strict
+    if let View::Integer(value) = term.view() {
+        if let Some(negated) = value.checked_neg() {
+            return Term::integer_i32(negated);
+        }
+    }
+    let multiply = symbols.get(b"Multiply").term();
+    Term::application(multiply, [Term::integer_i32(-1), term])
+}
+
+/// Raise a term to a power.
+pub(crate) fn power(symbols: &Symbols, base: Term, exponent: Term) -> Term
+{
+    let power = symbols.get(b"Power").term();
+    Term::application(power, [base, exponent])
+}
+
+/// Take the square root of a term, writing it as `x ^ (1 / 2)` would be.
+pub(crate) fn square_root(symbols: &Symbols, term: Term) -> Term
+{
+    root(symbols, term, Term::integer_i32(2))
+}
+
+/// Take a root of a term, writing it as `x ^ (1 / n)` would be.
+pub(crate) fn root(symbols: &Symbols, term: Term, degree: Term) -> Term
+{
+    let reciprocal = make_operand(symbols, Operand::Reciprocal, degree);
+    let multiply = symbols.get(b"Multiply").term();
+    let exponent =
+        Term::application(multiply, [Term::integer_i32(1), reciprocal]);
+    power(symbols, term, exponent)
+}
+
+/// Whether a term is the symbol with the given name.
+pub(crate) fn is_symbol(term: &Term, name: &[u8]) -> bool
+{
+    matches!(term.view(), View::Symbol(symbol) if symbol.name() == name)
+}
+
+/// The factors of a product with a negative coefficient,
+/// with the coefficient negated or left out if it is `-1`.
+pub(crate) fn without_sign(factors: &[Term]) -> Option<Vec<Term>>
+{
+    let (first, rest) = factors.split_first()?;
+    match first.view() {
+        View::Integer(-1) if !rest.is_empty() =>
+            Some(rest.to_vec()),
+        View::Integer(value) if value < 0 => {
+            let first = Term::integer_i32(value.checked_neg()?);
+            Some(std::iter::once(first).chain(rest.iter().cloned()).collect())
+        },
+        _ => None,
     }
 }
 
+/// The reciprocal of a power with a negative integer exponent.
+pub(crate) fn reciprocal(term: &Term) -> Option<Term>
+{
+    let (function, arguments) = match term.view() {
+        View::Application(function, arguments) => (function, arguments),
+        _ => return None,
+    };
+    match arguments {
+        [base, exponent] if is_symbol(function, b"Power") =>
+            match exponent.view() {
+                View::Integer(-1) => Some(base.clone()),
+                View::Integer(value) if value < 0 => {
+                    let exponent = Term::integer_i32(value.checked_neg()?);
+                    Some(Term::application(
+                        function.clone(),
+                        [base.clone(), exponent],
+                    ))
+                },
+                _ => None,
+            },
+        _ => None,
+    }
+}
+
+/// The degree of a root, if the exponent is `1 / n` or `n ^ -1`.
+pub(crate) fn root_degree(exponent: &Term) -> Option<&Term>
+{
+    let exponent = match exponent.view() {
+        View::Application(function, [one, exponent])
+            if is_symbol(function, b"Multiply") && one.eq_integer_i32(1) =>
+            exponent,
+        _ => exponent,
+    };
+    match exponent.view() {
+        View::Application(function, [degree, minus_one])
+            if is_symbol(function, b"Power")
+                && minus_one.eq_integer_i32(-1) =>
+            Some(degree),
+        _ => None,
+    }
+}
+
+/// The highest order of derivative that the importers read.
+///
+/// A derivative of order n is read as n nested applications of `Derivative`,
+/// so higher orders would take very long to build and to drop.
+pub(crate) const MAX_DERIVATIVE_ORDER: u32 = 1000;
+
 ////////////////////////////////////////////////////////////////////////////////
 // Combinators
 
@@ -133,45 +670,17 @@ fn parse_optional<F>(lex: &mut Lexer, pred: F) -> bool
 }
 
 /// Read the next token and assert that it matches the predicate.
-fn parse_exact<F>(lex: &mut Lexer, pred: F) -> Result<()>
+///
+/// The description of the expected token is used in the error message.
+fn parse_exact<F>(lex: &mut Lexer, pred: F, expected: &'static str)
+    -> Result<()>
     where F: FnOnce(&Token) -> bool
 {
-    let token = lex.next();
-    match token {
-        Some(ref token) if pred(token) => Ok(()),
-        _ => todo!(),
-    }
-}
-
-/// Parse a comma-separated list terminated by the given terminator.
-/// A trailing comma is permitted at the end of the list.
-fn parse_comma<F, G, T>(
-    lex: &mut Lexer,
-    mut is_terminator: F,
-    mut parse_element: G,
-) -> Result<Vec<T>>
-    where F: FnMut(&Token) -> bool
-        , G: FnMut(&mut Lexer) -> Result<T>
-{
-    let mut elements = Vec::new();
-    if parse_optional(lex, &mut is_terminator) {
-        return Ok(elements);
-    }
-    loop {
-        let element = parse_element(lex)?;
-        elements.push(element);
-        if parse_optional_matches!(lex, Token::Comma) {
-            if parse_optional(lex, &mut is_terminator) {
-                break;
-            }
-            continue;
-        }
-        if parse_optional(lex, is_terminator) {
-            break;
-        }
-        todo!();
+    if parse_optional(lex, pred) {
+        Ok(())
+    } else {
+        Err(lex.unexpected(&[expected]))
     }
-    Ok(elements)
 }
 
 macro parse_optional_matches($lex:expr, $token:pat $(,)?)
@@ -179,12 +688,618 @@ macro parse_optional_matches($lex:expr, $token:pat $(,)?)
     parse_optional($lex, |token| matches!(token, $token))
 }
 
-macro parse_exact_matches($lex:expr, $token:pat $(,)?)
+macro parse_exact_matches($lex:expr, $token:pat, $expected:expr $(,)?)
 {
-    parse_exact($lex, |token| matches!(token, $token))
+    parse_exact($lex, |token| matches!(token, $token), $expected)
 }
 
-macro parse_comma_matches($lex:expr, $token:pat, $parse_element:expr $(,)?)
+#[cfg(test)]
+mod tests
 {
-    parse_comma($lex, |token| matches!(token, $token), $parse_element)
+    use super::*;
+
+    use std::fmt::Write;
+
+    fn parse(source: &str) -> Result<Term>
+    {
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, []);
+        let mut lex = Lexer::new(source);
+        let term = parse_term(&symbols, &scope, &mut lex)?;
+        parse_end(&mut lex)?;
+        Ok(term)
+    }
+
+    fn parse_error(source: &str) -> (Span, String)
+    {
+        let error = parse(source).unwrap_err();
+        (error.span, error.message)
+    }
+
+    /// Write a term in prefix syntax, with variables as `#i`.
+    fn show(term: &Term) -> String
+    {
+        fn go(term: &Term, out: &mut String)
+        {
+            match term.view() {
+                View::Application(function, arguments) => {
+                    if let View::Lambda(..) = function.view() {
+                        out.push('(');
+                        go(function, out);
+                        out.push(')');
+                    } else {
+                        go(function, out);
+                    }
+                    out.push('(');
+                    for (i, argument) in arguments.iter().enumerate() {
+                        if i != 0 {
+                            out.push_str(", ");
+                        }
+                        go(argument, out);
+                    }
+                    out.push(')');
+                },
+                View::Integer(value) => write!(out, "{}", value).unwrap(),
+                View::Lambda(parameters, body) => {
+                    out.push('|');
+                    for (i, parameter) in parameters.iter().enumerate() {
+                        if i != 0 {
+                            out.push_str(", ");
+                        }
+                        if let Strictness::NonStrict = parameter.strictness {
+                            out.push('~');
+                        }
+                        let name = parameter.name.name();
+                        out.push_str(&String::from_utf8_lossy(name));
+                    }
+                    out.push_str("| ");
+                    go(body, out);
+                },
+                View::String(bytes) =>
+                    write!(out, "{:?}", String::from_utf8_lossy(bytes))
+                        .unwrap(),
+                View::Symbol(symbol) =>
+                    out.push_str(&String::from_utf8_lossy(symbol.name())),
+                View::Variable(de_bruijn) =>
+                    write!(out, "#{}", de_bruijn.0).unwrap(),
+            }
+        }
+        let mut out = String::new();
+        go(term, &mut out);
+        out
+    }
+
+    fn parse_show(source: &str) -> String
+    {
+        show(&parse(source).unwrap())
+    }
+
+    #[test]
+    fn fine()
+    {
+        assert_eq!(
+            parse_show("Sin(|x, ~y| Add(x, y, 1, \"z\"),)"),
+            "Sin(|x, ~y| Add(#0, #1, 1, \"z\"))",
+        );
+    }
+
+    #[test]
+    fn operators()
+    {
+        let cases = [
+            ("a + b + c", "Add(a, b, c)"),
+            ("a - b + 1", "Add(a, Multiply(-1, b), 1)"),
+            ("a - 1", "Add(a, -1)"),
+            ("a * b / c", "Multiply(a, b, Power(c, -1))"),
+            ("a + b * c", "Add(a, Multiply(b, c))"),
+            ("(a + b) * c", "Multiply(Add(a, b), c)"),
+            ("(a + b) + c", "Add(Add(a, b), c)"),
+            ("a ^ b ^ c", "Power(a, Power(b, c))"),
+            ("-a ^ 2", "Multiply(-1, Power(a, 2))"),
+            ("-a * b", "Multiply(Multiply(-1, a), b)"),
+            ("a ^ -1", "Power(a, -1)"),
+            ("-1", "-1"),
+            ("--a", "Multiply(-1, Multiply(-1, a))"),
+            ("2 * x + 1 == y", "Equal(Add(Multiply(2, x), 1), y)"),
+            ("a != b", "Unequal(a, b)"),
+            ("a < b", "Less(a, b)"),
+            ("a <= b", "LessEqual(a, b)"),
+            ("a > b", "Greater(a, b)"),
+            ("a >= b", "GreaterEqual(a, b)"),
+            ("f(x) ^ 2", "Power(f(x), 2)"),
+            ("|x| x + 1", "|x| Add(#0, 1)"),
+            ("f(|x| x * 2, 3)", "f(|x| Multiply(#0, 2), 3)"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_show(source), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn nested_scopes()
+    {
+        // The variables of inner scopes are numbered
+        // before those of outer scopes.
+        let cases = [
+            ("|x, y| |z| f(x, y, z)", "|x, y| |z| f(#1, #2, #0)"),
+            ("|x| |y, z| f(x, y, z)", "|x| |y, z| f(#2, #0, #1)"),
+            ("|x, y| |z, w| |v| x", "|x, y| |z, w| |v| #3"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_show(source), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn unicode()
+    {
+        let half = "Multiply(1, Power(2, -1))";
+        let cases = [
+            ("π", "Pi"),
+            ("a × b · c", "Multiply(a, b, c)"),
+            ("a − b", "Add(a, Multiply(-1, b))"),
+            ("−1", "-1"),
+            ("a−1", "Add(a, -1)"),
+            ("a ≤ b", "LessEqual(a, b)"),
+            ("a ≥ b", "GreaterEqual(a, b)"),
+            ("a ≠ b", "Unequal(a, b)"),
+            ("x² + y⁻¹", "Add(Power(x, 2), Power(y, -1))"),
+            ("x²-1", "Add(Power(x, 2), -1)"),
+            ("f(x)²³", "Power(f(x), 23)"),
+            ("x²³", "Power(x, 23)"),
+            ("x²^3", "Power(Power(x, 2), 3)"),
+            ("-x²", "Multiply(-1, Power(x, 2))"),
+            ("√x", &format!("Power(x, {})", half)),
+            ("√x²", &format!("Power(Power(x, 2), {})", half)),
+            ("√(x + 1)", &format!("Power(Add(x, 1), {})", half)),
+            ("√f(x) * 2", &format!("Multiply(Power(f(x), {}), 2)", half)),
+            ("√√x", &format!("Power(Power(x, {0}), {0})", half)),
+            ("π²", "Power(Pi, 2)"),
+            ("α + β1 - θ", "Add(α, β1, Multiply(-1, θ))"),
+            ("|θ| θ", "|θ| #0"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_show(source), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn patterns()
+    {
+        let cases = [
+            ("_", "Blank()"),
+            ("_Integer", "Blank(Integer)"),
+            ("x_", "Pattern(x, Blank())"),
+            ("n_Integer", "Pattern(n, Blank(Integer))"),
+            ("snake_case", "Pattern(snake, Blank(case))"),
+            ("xs__", "Pattern(xs, BlankSequence())"),
+            ("xs___", "Pattern(xs, BlankNullSequence())"),
+            ("x_-1", "Add(Pattern(x, Blank()), -1)"),
+            ("|x| f(x_, x)", "|x| f(Pattern(x, Blank()), #0)"),
+            ("x_ : -1", "Optional(Pattern(x, Blank()), -1)"),
+            ("f(x_, y_:g(1) + 1)",
+             "f(Pattern(x, Blank()), \
+                Add(Optional(Pattern(y, Blank()), g(1)), 1))"),
+            ("x_ /; x > 0",
+             "Condition(Pattern(x, Blank()), |x| Greater(#0, 0))"),
+            ("f(x_, y_, x_) /; x > y",
+             "Condition(\
+                f(Pattern(x, Blank()), Pattern(y, Blank()), \
+                  Pattern(x, Blank())), \
+                |x, y| Greater(#0, #1))"),
+            ("|y| f(x_ /; x > y, z_) /; z == x",
+             "|y| Condition(\
+                f(\
+                  Condition(Pattern(x, Blank()), |x| Greater(#0, #1)), \
+                  Pattern(z, Blank())), \
+                |x, z| Equal(#1, #0))"),
+            ("a /; b /; c", "Condition(Condition(a, || b), || c)"),
+            ("x_ /; (|x| x)(x)",
+             "Condition(Pattern(x, Blank()), |x| (|x| #0)(#0))"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_show(source), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn lists()
+    {
+        let cases = [
+            ("{}", "List()"),
+            ("{a, b, c,}", "List(a, b, c)"),
+            ("[a, b, c]", "List(a, b, c)"),
+            ("[[1, 2], {3}]", "List(List(1, 2), List(3))"),
+            ("f([[1]])", "f(List(List(1)))"),
+            ("<||>", "Association()"),
+            ("<|\"k\" -> v, x + 1 -> |x| x|>",
+             "Association(Rule(\"k\", v), Rule(Add(x, 1), |x| #0))"),
+            ("m[[1]]", "Part(m, 1)"),
+            ("m[[i, j]]", "Part(m, i, j)"),
+            ("m [[1]] [[2]]", "Part(Part(m, 1), 2)"),
+            ("f(x)[[1]](y)", "Part(f(x), 1)(y)"),
+            ("{a}[[-1]]", "Part(List(a), -1)"),
+            ("m[[l[[1]]]]", "Part(m, Part(l, 1))"),
+            ("m[[ [1, 2] ]]", "Part(m, List(1, 2))"),
+            ("-m[[1]]^2", "Multiply(-1, Power(Part(m, 1), 2))"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_show(source), expected, "{}", source);
+        }
+
+        assert_eq!(
+            parse_error("m[[1]"),
+            (5 .. 5, "expected `]`, found end of input".into()),
+        );
+        assert_eq!(
+            parse_error("{a b}"),
+            (3 .. 4, "expected `,` or `}`, found `b`".into()),
+        );
+        assert_eq!(
+            parse_error("m[[1 2]]"),
+            (5 .. 6, "expected `,` or `]`, found `2`".into()),
+        );
+
+        // A part extraction needs an index.
+        assert_eq!(
+            parse_error("m[[ ]]"),
+            (4 .. 5, "expected `(`, `-`, `<|`, `[`, `let`, `with`, \
+                      `{`, `|`, `π`, `√`, blank, identifier, \
+                      integer or string, found `]`".into()),
+        );
+
+        // Association entries must be rules.
+        assert_eq!(
+            parse_show("<|a :> 1, (b -> 2)|>"),
+            "Association(RuleDelayed(a, 1), Rule(b, 2))",
+        );
+        assert_eq!(
+            parse_error("<|a -> 1, b|>"),
+            (10 .. 11, "association entry must be a rule, \
+                        as in `key -> value`".into()),
+        );
+        assert_eq!(
+            parse_error("<|a == b|>"),
+            (2 .. 8, "association entry must be a rule, \
+                      as in `key -> value`".into()),
+        );
+    }
+
+    #[test]
+    fn rules()
+    {
+        let cases = [
+            ("x -> Pi", "Rule(x, Pi)"),
+            ("x :> Random()", "RuleDelayed(x, Random())"),
+            ("Sin(x) /. x -> Pi", "ReplaceAll(Sin(x), Rule(x, Pi))"),
+            ("e //. {a -> b, b -> c}",
+             "ReplaceRepeated(e, List(Rule(a, b), Rule(b, c)))"),
+            ("a -> b -> c", "Rule(a, Rule(b, c))"),
+            ("e /. r /. s //. t",
+             "ReplaceRepeated(ReplaceAll(ReplaceAll(e, r), s), t)"),
+            ("a + b -> c < d", "Rule(Add(a, b), Less(c, d))"),
+            ("f(x_) -> x ^ 2",
+             "Rule(f(Pattern(x, Blank())), |x| Power(#0, 2))"),
+            ("f(x_, y_) :> g(y, x)",
+             "RuleDelayed(\
+                f(Pattern(x, Blank()), Pattern(y, Blank())), \
+                |x, y| g(#1, #0))"),
+            ("x_ /; x > 0 -> x",
+             "Rule(\
+                Condition(Pattern(x, Blank()), |x| Greater(#0, 0)), \
+                |x| #0)"),
+            ("|y| f(x_) -> x + y",
+             "|y| Rule(f(Pattern(x, Blank())), |x| Add(#0, #1))"),
+            ("x_ -> y_ -> x + y",
+             "Rule(Pattern(x, Blank()), \
+                |x| Rule(Pattern(y, Blank()), |y| Add(#1, #0)))"),
+            ("<|\"k\" -> 1, k :> 2|>",
+             "Association(Rule(\"k\", 1), RuleDelayed(k, 2))"),
+            ("f(x -> 1, (a /. b))",
+             "f(Rule(x, 1), ReplaceAll(a, b))"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_show(source), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn bindings()
+    {
+        let cases = [
+            ("let x = 1 in x", "(|x| #0)(1)"),
+            ("let x = 1, y = x + 1, in f(x, y)",
+             "(|x| (|y| f(#1, #0))(Add(#0, 1)))(1)"),
+            ("let in a", "a"),
+            ("let x = 1 in let x = x in x", "(|x| (|x| #0)(#0))(1)"),
+            ("|y| let x = y in x + y", "|y| (|x| Add(#0, #1))(#0)"),
+            ("let ~x = a in x", "(|~x| #0)(a)"),
+            ("with x = 1, y = x in f(x, y)", "(|x, y| f(#0, #1))(1, x)"),
+            ("f(x, y) where x = 1, y = x",
+             "(|x, y| f(#0, #1))(1, x)"),
+            ("(x where x = 1) + x", "Add((|x| #0)(1), x)"),
+            ("|x| x + y where y = x", "|x| (|y| Add(#1, #0))(#0)"),
+            ("x -> y /. a where y = 1",
+             "(|y| ReplaceAll(Rule(x, #0), a))(1)"),
+            ("let x = 1 in x where x = 2", "(|x| (|x| #0)(2))(1)"),
+            ("(x where x = 1) where x = 2", "(|x| (|x| #0)(1))(2)"),
+            ("(a where b = 1, c = 2)[[1]]", "Part((|b, c| a)(1, 2), 1)"),
+            ("let rec f = |n| g(n), g = |n| f(n) in f(x)",
+             "LetRec(|f, g| List(|n| #2(#0), |n| #1(#0)), |f, g| #0(x))"),
+            ("let rec x = x in y", "LetRec(|x| List(#0), |x| y)"),
+            ("f(x_) -> y where y = 1",
+             "(|y| Rule(f(Pattern(x, Blank())), |x| #1))(1)"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_show(source), expected, "{}", source);
+        }
+
+        assert_eq!(
+            parse_error("let x = 1, x = 2 in x"),
+            (11 .. 12, "variable `x` is bound more than once".into()),
+        );
+        assert_eq!(
+            parse_error("with ~x = 1, x = 2 in x"),
+            (13 .. 14, "variable `x` is bound more than once".into()),
+        );
+        assert_eq!(
+            parse_error("a where x = 1, ~x = 2"),
+            (15 .. 17, "variable `x` is bound more than once".into()),
+        );
+        assert_eq!(
+            parse_error("let rec f = g, f = 1 in f"),
+            (15 .. 16, "variable `f` is bound more than once".into()),
+        );
+        assert_eq!(
+            parse_error("let x = 1 x"),
+            (10 .. 11, "expected `,` or `in`, found `x`".into()),
+        );
+        assert_eq!(
+            parse_error("let x in x"),
+            (6 .. 8, "expected `=`, found `in`".into()),
+        );
+        assert_eq!(
+            parse_error("f(a where x = 1, 2)"),
+            (17 .. 18, "expected identifier, found `2`".into()),
+        );
+    }
+
+    fn parse_program_show(source: &str) -> Result<Vec<String>>
+    {
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, []);
+        let mut lex = Lexer::new(source);
+        let statements = parse_program(&symbols, &scope, &mut lex)?;
+        let statements = statements.iter().map(|statement| match statement {
+            Statement::Definition{name, definition, doc} => format!(
+                "{}{} := {}",
+                doc.as_ref().map_or(String::new(), |d| format!("{:?} ", d)),
+                String::from_utf8_lossy(name.name()),
+                show(definition),
+            ),
+            Statement::Term(term) => show(term),
+            Statement::Notation(notation) => format!("{:?}", notation),
+        });
+        Ok(statements.collect())
+    }
+
+    #[test]
+    fn programs()
+    {
+        assert_eq!(parse_program_show("").unwrap(), Vec::<String>::new());
+        assert_eq!(parse_program_show(" ; ;").unwrap(), Vec::<String>::new());
+        assert_eq!(
+            parse_program_show("a := 1; f(x, y) := x + y + a; f(a, 2);")
+                .unwrap(),
+            [
+                "a := 1",
+                "f := |x, y| Add(#0, #1, a)",
+                "f(a, 2)",
+            ],
+        );
+    }
+
+    #[test]
+    fn comments()
+    {
+        assert_eq!(
+            parse_program_show(
+                "# Not documentation.\n\
+                 ## The answer.\n\
+                 ## Or so they say.\n\
+                 a := 42;\n\
+                 (** Not attached *) 1 + (** Also not *) 2;\n\
+                 (** Successor. *) (* comment *) f(x) := x + 1;\n\
+                 b (** Not attached either *) := 1",
+            ).unwrap(),
+            [
+                "\"The answer.\\nOr so they say.\" a := 42",
+                "Add(1, 2)",
+                "\"Successor.\" f := |x| Add(#0, 1)",
+                "b := 1",
+            ],
+        );
+    }
+
+    #[test]
+    fn program_errors()
+    {
+        let error = |source| {
+            let error = parse_program_show(source).unwrap_err();
+            (error.span, error.message)
+        };
+        assert_eq!(
+            error("a b"),
+            (2 .. 3, "expected `;`, found `b`".into()),
+        );
+        assert_eq!(
+            error("1 := 2"),
+            (0 .. 1, "left-hand side of `:=` must be a symbol, \
+                      or a symbol applied to parameters".into()),
+        );
+        assert_eq!(
+            error("f(x, 1) := 2"),
+            (0 .. 7, "left-hand side of `:=` must be a symbol, \
+                      or a symbol applied to parameters".into()),
+        );
+        assert_eq!(
+            error("f(x, x) := x"),
+            (0 .. 7, "parameter `x` is bound more than once".into()),
+        );
+    }
+
+    #[test]
+    fn notations()
+    {
+        assert_eq!(
+            parse_program_show(
+                "infixl 6 \"⊕\" = DirectSum; a ⊕ b ⊕ c * d + e;\n\
+                 infixr 8 \"⊗\" = Tensor; -a ⊗ b ⊗ c ^ d;\n\
+                 infix 4 \"≅\" = Isomorphic; a ⊕ b ≅ c",
+            ).unwrap(),
+            [
+                "Notation { operator: \"⊕\", level: 6, \
+                 associativity: Left, head: \"DirectSum\" }",
+                "Add(DirectSum(DirectSum(a, b), Multiply(c, d)), e)",
+                "Notation { operator: \"⊗\", level: 8, \
+                 associativity: Right, head: \"Tensor\" }",
+                "Multiply(-1, Tensor(a, Tensor(b, Power(c, d))))",
+                "Notation { operator: \"≅\", level: 4, \
+                 associativity: None, head: \"Isomorphic\" }",
+                "Isomorphic(DirectSum(a, b), c)",
+            ],
+        );
+
+        let error = |source| {
+            let error = parse_program_show(source).unwrap_err();
+            (error.span, error.message)
+        };
+        assert_eq!(
+            error("a ⊕ b; infixl 6 \"⊕\" = DirectSum"),
+            (2 .. 5, "operator `⊕` is not declared".into()),
+        );
+        assert_eq!(
+            error("infix 4 \"≅\" = Isomorphic; a ≅ b ≅ c"),
+            (36 .. 39, "`≅` cannot be chained; use parentheses".into()),
+        );
+        assert_eq!(
+            error("infixl 10 \"⊕\" = DirectSum"),
+            (7 .. 9, "precedence level must be from 0 to 9".into()),
+        );
+        assert_eq!(
+            error("infixl 6 \"+\" = Add"),
+            (9 .. 12, "`+` cannot be declared; operators consist of \
+                       symbols such as `⊕`, other than those of \
+                       built-in operators".into()),
+        );
+        assert_eq!(
+            error("infixl 6 \"⊕\" DirectSum"),
+            (15 .. 24, "expected `=`, found `DirectSum`".into()),
+        );
+    }
+
+    #[test]
+    fn negative_integers()
+    {
+        let cases = [
+            ("-1", "-1"),
+            ("- 1", "-1"),
+            ("-2147483648", "-2147483648"),
+            ("a-1", "Add(a, -1)"),
+            ("a -1", "Add(a, -1)"),
+            ("f(x)-1", "Add(f(x), -1)"),
+            ("1-1", "Add(1, -1)"),
+            ("a*-1", "Multiply(a, -1)"),
+            ("a^-1", "Power(a, -1)"),
+            ("f(-1)", "f(-1)"),
+            ("|x| -1", "|x| -1"),
+            ("--1", "1"),
+            ("a-2147483648", "Add(a, -2147483648)"),
+            ("-2 ^ 2", "Multiply(-1, Power(2, 2))"),
+            ("- 2 ^ 2", "Multiply(-1, Power(2, 2))"),
+            ("-(2 ^ 2)", "Multiply(-1, Power(2, 2))"),
+            ("-2²", "Multiply(-1, Power(2, 2))"),
+            ("(-2) ^ 2", "Power(-2, 2)"),
+            ("x_ : -1", "Optional(Pattern(x, Blank()), -1)"),
+            ("√-1", "Power(-1, Multiply(1, Power(2, -1)))"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(parse_show(source), expected, "{}", source);
+        }
+        assert_eq!(
+            parse_error("2147483648"),
+            (0 .. 10, "integer is too large".into()),
+        );
+        assert_eq!(
+            parse_error("-(2147483648)"),
+            (2 .. 12, "integer is too large".into()),
+        );
+    }
+
+    #[test]
+    fn errors()
+    {
+        assert_eq!(
+            parse_error(""),
+            (0 .. 0, "expected `(`, `-`, `<|`, `[`, `let`, `with`, \
+                      `{`, `|`, `π`, `√`, blank, identifier, \
+                      integer or string, found end of input".into()),
+        );
+        assert_eq!(
+            parse_error("Sin(x"),
+            (5 .. 5, "expected `)` or `,`, found end of input".into()),
+        );
+        assert_eq!(
+            parse_error("Sin(x y)"),
+            (6 .. 7, "expected `)` or `,`, found `y`".into()),
+        );
+        assert_eq!(
+            parse_error("|1| x"),
+            (1 .. 2, "expected identifier, found `1`".into()),
+        );
+        assert_eq!(
+            parse_error("(x"),
+            (2 .. 2, "expected `)`, found end of input".into()),
+        );
+        assert_eq!(
+            parse_error("x)"),
+            (1 .. 2, "expected end of input, found `)`".into()),
+        );
+        assert_eq!(
+            parse_error("Sin($)"),
+            (4 .. 5, "expected `(`, `-`, `<|`, `[`, `let`, `with`, \
+                      `{`, `|`, `π`, `√`, blank, identifier, \
+                      integer or string, found `$`".into()),
+        );
+        assert_eq!(
+            parse_error("a + "),
+            (4 .. 4, "expected `(`, `-`, `<|`, `[`, `let`, `with`, \
+                      `{`, `|`, `π`, `√`, blank, identifier, \
+                      integer or string, found end of input".into()),
+        );
+        assert_eq!(
+            parse_error("a < b < c"),
+            (6 .. 7, "`<` cannot be chained; use parentheses".into()),
+        );
+        assert_eq!(
+            parse_error("f(\"a\\\"b"),
+            (2 .. 7, "unterminated string".into()),
+        );
+        assert_eq!(
+            parse_error("f(\"a\\q\")"),
+            (4 .. 6, "invalid escape sequence `\\q`".into()),
+        );
+        assert_eq!(
+            parse_error("\"\\u{110000}\""),
+            (1 .. 11, "invalid escape sequence `\\u{110000}`".into()),
+        );
+        assert_eq!(
+            parse_error("\"a\\u12\""),
+            (2 .. 4, "invalid escape sequence `\\u`".into()),
+        );
+        assert_eq!(
+            parse_error("a (* b (* c *)"),
+            (2 .. 14, "unterminated comment".into()),
+        );
+    }
 }
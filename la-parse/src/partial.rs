@@ -0,0 +1,130 @@
+use crate::Error;
+use crate::Lexer;
+use crate::Notations;
+use crate::Scope;
+use crate::Statement;
+use crate::TERM;
+use crate::parse_program;
+
+use la_term::symbol::Symbols;
+use std::collections::BTreeSet;
+
+/// Result of parsing input that may not have been entered completely.
+#[derive(Debug)]
+pub enum Partial<T>
+{
+    /// The input was parsed successfully.
+    Complete(T),
+
+    /// The input ended prematurely,
+    /// for example inside parentheses or a string.
+    /// It may become valid once more input is appended.
+    Incomplete
+    {
+        /// Descriptions of the tokens that would continue the input.
+        expected: BTreeSet<&'static str>,
+    },
+
+    /// The input is wrong, regardless of what would be appended.
+    Invalid(Error),
+}
+
+/// Parse a program that may not have been entered completely.
+///
+/// This is like [`parse_program`], but it distinguishes
+/// input that is incomplete from input that is wrong.
+/// A front end can use this to keep reading lines until the input
+/// is complete, and an editor can use this to avoid reporting errors
+/// about input that the user is still typing.
+///
+/// Documentation comments at the end of the input
+/// are taken to precede a definition that is yet to be entered,
+/// so such input is incomplete.
+/// The given operators are declared before the program is parsed.
+pub fn parse_partial(
+    symbols: &Symbols,
+    scope: &Scope,
+    notations: &Notations,
+    source: &str,
+) -> Partial<Vec<Statement>>
+{
+    let mut lex = Lexer::with_notations(source, notations.clone());
+    match parse_program(symbols, scope, &mut lex) {
+        Ok(_) if lex.take_doc().is_some() => {
+            let expected = TERM.iter().copied().collect();
+            Partial::Incomplete{expected}
+        },
+        Ok(statements) =>
+            Partial::Complete(statements),
+        Err(error) if error.is_incomplete() =>
+            Partial::Incomplete{expected: error.expected},
+        Err(error) =>
+            Partial::Invalid(error),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn parse(source: &str) -> Partial<usize>
+    {
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, []);
+        let notations = Notations::new();
+        match parse_partial(&symbols, &scope, &notations, source) {
+            Partial::Complete(statements) =>
+                Partial::Complete(statements.len()),
+            Partial::Incomplete{expected} =>
+                Partial::Incomplete{expected},
+            Partial::Invalid(error) =>
+                Partial::Invalid(error),
+        }
+    }
+
+    fn expected(source: &str) -> Vec<&'static str>
+    {
+        match parse(source) {
+            Partial::Incomplete{expected} => expected.into_iter().collect(),
+            other => panic!("{:?} is not incomplete: {:?}", source, other),
+        }
+    }
+
+    #[test]
+    fn complete()
+    {
+        assert!(matches!(parse(""), Partial::Complete(0)));
+        assert!(matches!(parse("a; b"), Partial::Complete(2)));
+        assert!(matches!(parse("f(x) := x;\n"), Partial::Complete(1)));
+        assert!(matches!(parse("a (* comment *)"), Partial::Complete(1)));
+        assert!(matches!(parse("a; (**)"), Partial::Complete(1)));
+        assert!(matches!(parse("## Doc.\na := 1"), Partial::Complete(1)));
+    }
+
+    #[test]
+    fn incomplete()
+    {
+        assert_eq!(expected("Sin(x"), ["`)`", "`,`"]);
+        assert_eq!(expected("(a + b"), ["`)`"]);
+        assert_eq!(expected("\"abc"), ["`\"`"]);
+        assert_eq!(expected("a (* b"), ["`*)`"]);
+        assert_eq!(expected("|x|"), TERM);
+        assert_eq!(expected("|x, y"), ["`,`", "`|`"]);
+        assert_eq!(expected("f(x) :="), TERM);
+        assert_eq!(expected("a *"), TERM);
+        assert_eq!(expected("## Doc."), TERM);
+    }
+
+    #[test]
+    fn invalid()
+    {
+        let error = match parse("Sin(x))") {
+            Partial::Invalid(error) => error,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(error.span, 6 .. 7);
+        assert!(matches!(parse("a < b < "), Partial::Invalid(..)));
+        assert!(matches!(parse("f(1) :="), Partial::Invalid(..)));
+    }
+}
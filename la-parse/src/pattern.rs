@@ -0,0 +1,407 @@
+use crate::Blank;
+use crate::BlankKind;
+use crate::Result;
+use crate::Scope;
+use crate::is_symbol;
+
+use la_term::Term;
+use la_term::View;
+use la_term::lambda::Parameter;
+use la_term::lambda::Strictness;
+use la_term::symbol::Symbol;
+use la_term::symbol::Symbols;
+
+/// Create the pattern term for a blank token.
+pub(crate) fn blank_pattern(symbols: &Symbols, blank: &Blank) -> Term
+{
+    let kind: &[u8] = match blank.kind {
+        BlankKind::One => b"Blank",
+        BlankKind::Sequence => b"BlankSequence",
+        BlankKind::NullSequence => b"BlankNullSequence",
+    };
+    let head = blank.head.map(|head| symbols.get(head).term());
+    let term = Term::application(symbols.get(kind).term(), head);
+    match blank.name {
+        None => term,
+        Some(name) => {
+            let pattern = symbols.get(b"Pattern").term();
+            Term::application(pattern, [symbols.get(name).term(), term])
+        },
+    }
+}
+
+/// Create the pattern term for `pattern : default`.
+pub(crate) fn optional_pattern(symbols: &Symbols, pattern: Term, default: Term)
+    -> Term
+{
+    let optional = symbols.get(b"Optional").term();
+    Term::application(optional, [pattern, default])
+}
+
+/// Create the pattern term for `pattern /; test`.
+///
+/// The test is parsed by the given function,
+/// in a scope in which the pattern variables are bound.
+pub(crate) fn condition_pattern<F>(
+    symbols: &Symbols,
+    scope: &Scope,
+    pattern: Term,
+    parse_test: F,
+) -> Result<Term>
+    where F: FnOnce(&Scope) -> Result<Term>
+{
+    let variables = pattern_variables(symbols, &pattern);
+    let test = pattern_lambda(scope, variables, parse_test)?;
+    let condition = symbols.get(b"Condition").term();
+    Ok(Term::application(condition, [pattern, test]))
+}
+
+/// Create the term for `lhs -> rhs` or `lhs :> rhs`,
+/// where the head is `Rule` or `RuleDelayed`.
+///
+/// If the left-hand side has pattern variables, the right-hand side
+/// is parsed by the given function in a scope in which they are bound,
+/// and becomes a lambda like the test of a condition.
+/// Otherwise the right-hand side is kept as is.
+pub(crate) fn rule_pattern<F>(
+    symbols: &Symbols,
+    scope: &Scope,
+    head: &str,
+    lhs: Term,
+    parse_rhs: F,
+) -> Result<Term>
+    where F: FnOnce(&Scope) -> Result<Term>
+{
+    let variables = pattern_variables(symbols, &lhs);
+    let rhs = if variables.is_empty() {
+        parse_rhs(scope)?
+    } else {
+        pattern_lambda(scope, variables, parse_rhs)?
+    };
+    let head = symbols.get(head.as_bytes()).term();
+    Ok(Term::application(head, [lhs, rhs]))
+}
+
+/// Parse a term in which the given pattern variables are bound,
+/// and make it the body of a lambda with a parameter for each of them.
+fn pattern_lambda<F>(scope: &Scope, variables: Vec<Symbol>, parse_body: F)
+    -> Result<Term>
+    where F: FnOnce(&Scope) -> Result<Term>
+{
+    let body = {
+        let scope = Scope::new(Some(scope), variables.iter().cloned());
+        parse_body(&scope)?
+    };
+    let parameters: Vec<_> =
+        variables.into_iter()
+        .map(|name| Parameter{strictness: Strictness::Strict, name})
+        .collect();
+    Ok(Term::lambda(parameters.into(), body))
+}
+
+/// The names of the pattern variables in a pattern,
+/// in order of first occurrence.
+///
+/// Patterns are ordinary terms with particular heads,
+/// which are interpreted by the pattern matcher:
+///
+/// | Syntax   | Term                               |
+/// |----------|------------------------------------|
+/// | `_`      | `Blank()`                          |
+/// | `_h`     | `Blank(h)`                         |
+/// | `__`     | `BlankSequence()`                  |
+/// | `___`    | `BlankNullSequence()`              |
+/// | `x_h`    | `Pattern(x, Blank(h))`             |
+/// | `x_ : d` | `Optional(Pattern(x, Blank()), d)` |
+/// | `p /; c` | `Condition(p, \|x, y\| c)`         |
+/// | `p -> r` | `Rule(p, \|x, y\| r)`              |
+/// | `p :> r` | `RuleDelayed(p, \|x, y\| r)`       |
+///
+/// The pattern variables are bound in the test of a condition
+/// like the parameters of a lambda are bound in its body:
+/// the test is a lambda with a parameter for each pattern variable
+/// of the pattern it constrains, in the order returned by this function.
+/// The same goes for the right-hand side of a rule,
+/// except that it is not wrapped in a lambda
+/// if the left-hand side has no pattern variables,
+/// so that `x -> Pi` is simply `Rule(x, Pi)`.
+/// A name that occurs more than once names a single variable,
+/// which must match the same term at each occurrence.
+/// Patterns in tests and in lambdas
+/// are not part of the pattern, so their variables are not included.
+pub fn pattern_variables(symbols: &Symbols, pattern: &Term) -> Vec<Symbol>
+{
+    let heads = PatternHeads::Symbols{
+        pattern: symbols.get(b"Pattern"),
+        condition: symbols.get(b"Condition"),
+    };
+    let mut variables = Vec::new();
+    heads.collect(pattern, &mut variables);
+    variables
+}
+
+/// Like [`pattern_variables`], but recognizes the heads by their names,
+/// for when no symbol table is at hand, as when rendering terms.
+pub(crate) fn pattern_variable_names(pattern: &Term) -> Vec<Symbol>
+{
+    let mut variables = Vec::new();
+    PatternHeads::Names.collect(pattern, &mut variables);
+    variables
+}
+
+enum PatternHeads
+{
+    Symbols{pattern: Symbol, condition: Symbol},
+    Names,
+}
+
+impl PatternHeads
+{
+    fn is_pattern(&self, function: &Term) -> bool
+    {
+        match self {
+            Self::Symbols{pattern, ..} => function.eq_symbol(pattern),
+            Self::Names => is_symbol(function, b"Pattern"),
+        }
+    }
+
+    fn is_condition(&self, function: &Term) -> bool
+    {
+        match self {
+            Self::Symbols{condition, ..} => function.eq_symbol(condition),
+            Self::Names => is_symbol(function, b"Condition"),
+        }
+    }
+
+    fn collect(&self, term: &Term, variables: &mut Vec<Symbol>)
+    {
+        let (function, arguments) = match term.view() {
+            View::Application(function, arguments) => (function, arguments),
+            _ => return,
+        };
+        match arguments {
+            [name, pattern] if self.is_pattern(function) => {
+                if let Some(name) = name.as_symbol() {
+                    if !variables.contains(name) {
+                        variables.push(name.clone());
+                    }
+                }
+                self.collect(pattern, variables);
+            },
+            [pattern, _test] if self.is_condition(function) =>
+                self.collect(pattern, variables),
+            _ => {
+                self.collect(function, variables);
+                for argument in arguments {
+                    self.collect(argument, variables);
+                }
+            },
+        }
+    }
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Compiled patterns
+
+/// Pattern in the form consumed by a pattern matcher.
+///
+/// Compiling a pattern term resolves the names of its pattern variables
+/// to indices into the list returned by [`pattern_variables`],
+/// so that a matcher can record matches in a slice.
+#[allow(missing_docs)]
+#[derive(Debug)]
+pub enum Pattern
+{
+    /// `Blank()` or `Blank(h)`: any term, with head `h` if given.
+    Blank{head: Option<Symbol>},
+
+    /// `BlankSequence(...)` or `BlankNullSequence(...)`:
+    /// a sequence of arguments, each with head `h` if given.
+    /// Only `BlankNullSequence` matches the empty sequence.
+    Sequence{head: Option<Symbol>, empty: bool},
+
+    /// `Pattern(x, p)`: what `p` matches, bound to variable `index`.
+    Variable{index: usize, pattern: Box<Pattern>},
+
+    /// `Optional(p, d)`: what `p` matches; as an argument it may be
+    /// omitted, in which case it matches `d`.
+    Optional{pattern: Box<Pattern>, default: Term},
+
+    /// `Condition(p, test)`: what `p` matches, provided that
+    /// applying `test` to the given variables yields `True`.
+    Condition{pattern: Box<Pattern>, variables: Vec<usize>, test: Term},
+
+    /// Application whose function and arguments match.
+    Application{function: Box<Pattern>, arguments: Vec<Pattern>},
+
+    /// Any other term, which matches only itself.
+    Literal(Term),
+}
+
+impl Pattern
+{
+    /// Compile a pattern term, see [`pattern_variables`].
+    ///
+    /// Returns the pattern along with the names of its variables.
+    pub fn compile(symbols: &Symbols, term: &Term) -> (Self, Vec<Symbol>)
+    {
+        let variables = pattern_variables(symbols, term);
+        let compiler = PatternCompiler{
+            blank: symbols.get(b"Blank"),
+            blank_sequence: symbols.get(b"BlankSequence"),
+            blank_null_sequence: symbols.get(b"BlankNullSequence"),
+            condition: symbols.get(b"Condition"),
+            optional: symbols.get(b"Optional"),
+            pattern: symbols.get(b"Pattern"),
+            variables: &variables,
+        };
+        let pattern = compiler.compile(term);
+        (pattern, variables)
+    }
+
+    /// Append the variables in this pattern to a list,
+    /// in order of first occurrence.
+    fn variables(&self, variables: &mut Vec<usize>)
+    {
+        match self {
+            Self::Blank{..} | Self::Sequence{..} | Self::Literal(_) => (),
+            Self::Variable{index, pattern} => {
+                if !variables.contains(index) {
+                    variables.push(*index);
+                }
+                pattern.variables(variables);
+            },
+            Self::Optional{pattern, ..} | Self::Condition{pattern, ..} =>
+                pattern.variables(variables),
+            Self::Application{function, arguments} => {
+                function.variables(variables);
+                for argument in arguments {
+                    argument.variables(variables);
+                }
+            },
+        }
+    }
+}
+
+struct PatternCompiler<'a>
+{
+    blank: Symbol,
+    blank_sequence: Symbol,
+    blank_null_sequence: Symbol,
+    condition: Symbol,
+    optional: Symbol,
+    pattern: Symbol,
+    variables: &'a [Symbol],
+}
+
+impl<'a> PatternCompiler<'a>
+{
+    fn compile(&self, term: &Term) -> Pattern
+    {
+        let (function, arguments) = match term.view() {
+            View::Application(function, arguments) => (function, arguments),
+            _ => return Pattern::Literal(term.clone()),
+        };
+
+        let head = match arguments {
+            [] => Some(None),
+            [head] => head.as_symbol().map(|head| Some(head.clone())),
+            _ => None,
+        };
+        if let Some(head) = head {
+            if function.eq_symbol(&self.blank) {
+                return Pattern::Blank{head};
+            }
+            if function.eq_symbol(&self.blank_sequence) {
+                return Pattern::Sequence{head, empty: false};
+            }
+            if function.eq_symbol(&self.blank_null_sequence) {
+                return Pattern::Sequence{head, empty: true};
+            }
+        }
+
+        match arguments {
+            [name, pattern] if function.eq_symbol(&self.pattern) => {
+                let index = name.as_symbol()
+                    .and_then(|n| self.variables.iter().position(|v| v == n));
+                if let Some(index) = index {
+                    let pattern = Box::new(self.compile(pattern));
+                    return Pattern::Variable{index, pattern};
+                }
+            },
+            [pattern, default] if function.eq_symbol(&self.optional) => {
+                let pattern = Box::new(self.compile(pattern));
+                return Pattern::Optional{pattern, default: default.clone()};
+            },
+            [pattern, test] if function.eq_symbol(&self.condition) => {
+                let pattern = Box::new(self.compile(pattern));
+                let mut variables = Vec::new();
+                pattern.variables(&mut variables);
+                let test = test.clone();
+                return Pattern::Condition{pattern, variables, test};
+            },
+            _ => (),
+        }
+
+        let function = Box::new(self.compile(function));
+        let arguments = arguments.iter().map(|a| self.compile(a)).collect();
+        Pattern::Application{function, arguments}
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use crate::Lexer;
+    use crate::parse_end;
+    use crate::parse_term;
+
+    fn compile(source: &str) -> (Pattern, Vec<String>)
+    {
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, []);
+        let mut lex = Lexer::new(source);
+        let term = parse_term(&symbols, &scope, &mut lex).unwrap();
+        parse_end(&mut lex).unwrap();
+        let (pattern, variables) = Pattern::compile(&symbols, &term);
+        let variables = variables.iter()
+            .map(|v| String::from_utf8_lossy(v.name()).into_owned())
+            .collect();
+        (pattern, variables)
+    }
+
+    #[test]
+    fn compile_pattern()
+    {
+        let (pattern, variables) = compile("f(x_, ys___g, x_, _Integer)");
+        assert_eq!(variables, ["x", "ys"]);
+        let arguments = match pattern {
+            Pattern::Application{function, arguments} => {
+                assert!(matches!(*function, Pattern::Literal(_)));
+                arguments
+            },
+            _ => panic!("{:?}", pattern),
+        };
+        assert!(matches!(&arguments[..], [
+            Pattern::Variable{index: 0, pattern: x0},
+            Pattern::Variable{index: 1, pattern: ys},
+            Pattern::Variable{index: 0, pattern: x2},
+            Pattern::Blank{head: Some(_)},
+        ] if matches!(**x0, Pattern::Blank{head: None})
+          && matches!(**ys, Pattern::Sequence{head: Some(_), empty: true})
+          && matches!(**x2, Pattern::Blank{head: None})));
+
+        let (pattern, variables) = compile("g(a_, b_ : 0 /; b > a)");
+        assert_eq!(variables, ["a", "b"]);
+        let arguments = match pattern {
+            Pattern::Application{arguments, ..} => arguments,
+            _ => panic!("{:?}", pattern),
+        };
+        assert!(matches!(&arguments[1], Pattern::Condition{
+            pattern, variables, ..
+        } if variables == &[1]
+          && matches!(**pattern, Pattern::Optional{..})));
+    }
+}
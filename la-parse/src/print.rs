@@ -0,0 +1,576 @@
+use crate::Associativity;
+use crate::Notations;
+use crate::PRECEDENCE_ADDITIVE;
+use crate::PRECEDENCE_COMPARISON;
+use crate::PRECEDENCE_MULTIPLICATIVE;
+use crate::PRECEDENCE_NEGATE;
+use crate::PRECEDENCE_POWER;
+use crate::is_rule;
+use crate::is_symbol;
+use crate::pattern_variable_names;
+
+use la_term::Term;
+use la_term::View;
+use la_term::lambda::Parameter;
+use la_term::lambda::Strictness;
+use la_term::symbol::Symbol;
+use std::fmt::Write;
+
+/// Precedence of lambdas, whose bodies extend as far as they can.
+const LAMBDA: u32 = 0;
+
+/// Precedence of replacements, as in `e /. rules`.
+const REPLACE: u32 = 1;
+
+/// Precedence of rules, as in `x -> Pi`.
+const RULE: u32 = 2;
+
+/// Precedence of conditions, as in `x_ /; x > 0`.
+const CONDITION: u32 = 3;
+
+/// Offset of the precedences of infix operators,
+/// so that even operators declared at level zero
+/// bind more tightly than conditions and lambdas.
+const OPERATOR: u32 = 10;
+
+const COMPARISON: u32 = OPERATOR + PRECEDENCE_COMPARISON;
+const ADDITIVE: u32 = OPERATOR + PRECEDENCE_ADDITIVE;
+const MULTIPLICATIVE: u32 = OPERATOR + PRECEDENCE_MULTIPLICATIVE;
+const NEGATE: u32 = OPERATOR + PRECEDENCE_NEGATE;
+const POWER: u32 = OPERATOR + PRECEDENCE_POWER;
+
+/// Precedence of patterns with defaults, as in `x_ : 0`,
+/// whose defaults take any argument lists that follow them.
+const OPTIONAL: u32 = APPLICATION - 1;
+
+/// Precedence of applications, as in `f(x)`.
+/// This is above the precedence of any declared operator.
+const APPLICATION: u32 = OPERATOR + 100;
+
+/// Precedence of terms that never need parentheses.
+const ATOM: u32 = OPERATOR + 110;
+
+/// Render a term in the syntax of [`parse_term`](`crate::parse_term`).
+///
+/// Operators are written with as few parentheses as their precedence
+/// allows, and declared operators as described at [`Notations`].
+/// Lists, associations, parts, patterns, rules and replacements
+/// are written in their own syntax, as in `{a, b}`, `m[[1]]`
+/// or `e /. f(x_) -> x ^ 2`.
+/// The rendered term is parsed back into the same term,
+/// with the same declarations, as long as its symbols are identifiers
+/// and its lambdas and pattern variables do not shadow variables
+/// that are used inside them.
+pub fn render_term(term: &Term, notations: &Notations) -> String
+{
+    let mut renderer = TermRenderer{notations, names: Vec::new()};
+    renderer.term(term).text
+}
+
+/// Rendered term, with the precedence of its outermost operator.
+struct Text
+{
+    text: String,
+    precedence: u32,
+}
+
+impl Text
+{
+    fn new(text: String, precedence: u32) -> Self
+    {
+        Self{text, precedence}
+    }
+
+    /// The text, in parentheses if it binds looser than the given level.
+    fn at(self, precedence: u32) -> String
+    {
+        if self.precedence < precedence {
+            format!("({})", self.text)
+        } else {
+            self.text
+        }
+    }
+
+    /// Like [`at`](`Text::at`), but for the rightmost operand of an operator,
+    /// which can be a negation without parentheses, as in `x ^ -1`.
+    fn right(self, precedence: u32) -> String
+    {
+        match self.precedence {
+            NEGATE => self.text,
+            _ => self.at(precedence),
+        }
+    }
+}
+
+/// State of the renderer for terms.
+struct TermRenderer<'n>
+{
+    notations: &'n Notations,
+
+    /// Names of the variables in scope,
+    /// the last of which has De Bruijn index zero.
+    names: Vec<String>,
+}
+
+impl<'n> TermRenderer<'n>
+{
+    fn term(&mut self, term: &Term) -> Text
+    {
+        match term.view() {
+
+            View::Application(function, arguments) => match blank(term) {
+                Some(text) => Text::new(text, ATOM),
+                None => self.application(function, arguments),
+            },
+
+            View::Integer(value) if value < 0 =>
+                Text::new(value.to_string(), NEGATE),
+
+            View::Integer(value) =>
+                Text::new(value.to_string(), ATOM),
+
+            View::Lambda(parameters, body) =>
+                self.lambda(parameters, body),
+
+            View::String(bytes) =>
+                Text::new(quote(&String::from_utf8_lossy(bytes)), ATOM),
+
+            View::Symbol(symbol) => {
+                let name = String::from_utf8_lossy(symbol.name());
+                Text::new(name.into_owned(), ATOM)
+            },
+
+            View::Variable(de_bruijn) => {
+                let index = de_bruijn.0 as usize;
+                let text = match self.names.len().checked_sub(index + 1) {
+                    Some(i) => self.names[i].clone(),
+                    None => format!("#{}", index),
+                };
+                Text::new(text, ATOM)
+            },
+
+        }
+    }
+
+    fn lambda(&mut self, parameters: &[Parameter], body: &Term) -> Text
+    {
+        let names: Vec<String> =
+            parameters.iter()
+            .map(|parameter| {
+                String::from_utf8_lossy(parameter.name.name()).into_owned()
+            })
+            .collect();
+        let outer = self.names.len();
+        self.names.extend(names.iter().rev().cloned());
+        let body = self.term(body).text;
+        self.names.truncate(outer);
+
+        let mut text = "|".to_owned();
+        for (i, (parameter, name)) in parameters.iter().zip(names).enumerate() {
+            if i != 0 {
+                text.push_str(", ");
+            }
+            if let Strictness::NonStrict = parameter.strictness {
+                text.push('~');
+            }
+            text.push_str(&name);
+        }
+        write!(text, "| {}", body).unwrap();
+        Text::new(text, LAMBDA)
+    }
+
+    fn application(&mut self, function: &Term, arguments: &[Term]) -> Text
+    {
+        let head = match function.view() {
+            View::Symbol(symbol) => symbol.name(),
+            _ => return self.generic(function, arguments),
+        };
+
+        if let (Some(notation), [lhs, rhs]) =
+            (self.notations.of_head(head), arguments)
+        {
+            let precedence = OPERATOR + notation.level * 10;
+            let operator = notation.operator.clone();
+            return self.infix(lhs, &operator, rhs, precedence,
+                              notation.associativity);
+        }
+
+        match (head, arguments) {
+
+            (b"Add", [first, rest @ ..]) if !rest.is_empty() => {
+                let operand = ADDITIVE + 1;
+                let mut text = self.term(first).at(operand);
+                for term in rest {
+                    match self.negative(term) {
+                        Some(term) => {
+                            text.push_str(" - ");
+                            text.push_str(&term.right(operand));
+                        },
+                        None => {
+                            text.push_str(" + ");
+                            text.push_str(&self.term(term).right(operand));
+                        },
+                    }
+                }
+                Text::new(text, ADDITIVE)
+            },
+
+            (b"Multiply", [minus_one, operand])
+                if minus_one.eq_integer_i32(-1)
+                && !matches!(operand.view(), View::Integer(..)) =>
+            {
+                let operand = self.term(operand).at(NEGATE + 1);
+                Text::new(format!("-{}", operand), NEGATE)
+            },
+
+            (b"Multiply", [first, rest @ ..]) if !rest.is_empty() => {
+                let operand = MULTIPLICATIVE + 1;
+                let mut text = self.term(first).at(operand);
+                for factor in rest {
+                    match reciprocal(factor) {
+                        Some(factor) => {
+                            text.push_str(" / ");
+                            text.push_str(&self.term(factor).right(operand));
+                        },
+                        None => {
+                            text.push_str(" * ");
+                            text.push_str(&self.term(factor).right(operand));
+                        },
+                    }
+                }
+                Text::new(text, MULTIPLICATIVE)
+            },
+
+            (b"Power", [base, exponent]) =>
+                self.infix(base, "^", exponent, POWER, Associativity::Right),
+
+            (b"List", _) => {
+                let elements = self.arguments(arguments);
+                Text::new(format!("{{{}}}", elements), ATOM)
+            },
+
+            (b"Association", _) if arguments.iter().all(is_rule) => {
+                let entries = self.arguments(arguments);
+                Text::new(format!("<|{}|>", entries), ATOM)
+            },
+
+            (b"Part", [term, indices @ ..]) if !indices.is_empty() => {
+                let term = self.term(term).at(APPLICATION);
+                let indices = self.arguments(indices);
+                Text::new(format!("{}[[{}]]", term, indices), APPLICATION)
+            },
+
+            (b"Optional", [pattern, default]) => match blank(pattern) {
+                Some(pattern) => {
+                    let default = match default.view() {
+                        View::Integer(value) => value.to_string(),
+                        _ => self.term(default).at(APPLICATION),
+                    };
+                    Text::new(format!("{} : {}", pattern, default), OPTIONAL)
+                },
+                None => self.generic(function, arguments),
+            },
+
+            (b"Condition", [pattern, test]) => {
+                let variables = pattern_variable_names(pattern);
+                match self.bound(&variables, test, OPERATOR) {
+                    Some(test) => {
+                        let pattern = self.term(pattern).at(CONDITION);
+                        let text = format!("{} /; {}", pattern, test);
+                        Text::new(text, CONDITION)
+                    },
+                    None => self.generic(function, arguments),
+                }
+            },
+
+            (b"Rule" | b"RuleDelayed", [lhs, rhs]) => {
+                let operator = if head == b"Rule" { "->" } else { ":>" };
+                let variables = pattern_variable_names(lhs);
+                let rhs = if variables.is_empty() {
+                    Some(self.term(rhs).at(RULE))
+                } else {
+                    self.bound(&variables, rhs, RULE)
+                };
+                match rhs {
+                    Some(rhs) => {
+                        let lhs = self.term(lhs).at(CONDITION);
+                        let text = format!("{} {} {}", lhs, operator, rhs);
+                        Text::new(text, RULE)
+                    },
+                    None => self.generic(function, arguments),
+                }
+            },
+
+            (b"ReplaceAll" | b"ReplaceRepeated", [term, rules]) => {
+                let operator =
+                    if head == b"ReplaceAll" { "/." } else { "//." };
+                let term = self.term(term).at(REPLACE);
+                let rules = self.term(rules).at(RULE);
+                Text::new(format!("{} {} {}", term, operator, rules), REPLACE)
+            },
+
+            _ => match (comparison_operator(head), arguments) {
+                (Some(operator), [lhs, rhs]) =>
+                    self.infix(lhs, operator, rhs, COMPARISON,
+                               Associativity::None),
+                _ => self.generic(function, arguments),
+            },
+
+        }
+    }
+
+    /// Render a term in which the given pattern variables are bound.
+    ///
+    /// The parser makes such a term, such as the test of a condition,
+    /// into a lambda with a parameter for each variable;
+    /// this renders the body of that lambda at the given precedence.
+    /// Returns `None` if the term is not such a lambda.
+    fn bound(&mut self, variables: &[Symbol], term: &Term, precedence: u32)
+        -> Option<String>
+    {
+        let (parameters, body) = match term.view() {
+            View::Lambda(parameters, body) => (parameters, body),
+            _ => return None,
+        };
+        let is_variable = |(parameter, variable): (&Parameter, &Symbol)| {
+            parameter.name == *variable
+                && matches!(parameter.strictness, Strictness::Strict)
+        };
+        if parameters.len() != variables.len()
+            || !parameters.iter().zip(variables).all(is_variable)
+        {
+            return None;
+        }
+        let outer = self.names.len();
+        self.names.extend(
+            variables.iter().rev()
+            .map(|variable| {
+                String::from_utf8_lossy(variable.name()).into_owned()
+            }),
+        );
+        let body = self.term(body).at(precedence);
+        self.names.truncate(outer);
+        Some(body)
+    }
+
+    /// Render an application of an infix operator that is not flat.
+    fn infix(
+        &mut self,
+        lhs: &Term,
+        operator: &str,
+        rhs: &Term,
+        precedence: u32,
+        associativity: Associativity,
+    ) -> Text
+    {
+        let (left, right) = match associativity {
+            Associativity::Left => (precedence, precedence + 1),
+            Associativity::Right => (precedence + 1, precedence),
+            Associativity::None => (precedence + 1, precedence + 1),
+        };
+        let lhs = self.term(lhs).at(left);
+        let rhs = self.term(rhs).right(right);
+        Text::new(format!("{} {} {}", lhs, operator, rhs), precedence)
+    }
+
+    /// Render the negation of a term that is negative,
+    /// such as `-2` or `-1 * x`, without its sign.
+    fn negative(&mut self, term: &Term) -> Option<Text>
+    {
+        match term.view() {
+            View::Integer(value) if value < 0 =>
+                value.checked_neg()
+                .map(|value| Text::new(value.to_string(), ATOM)),
+            View::Application(head, [minus_one, operand])
+                if is_symbol(head, b"Multiply")
+                && minus_one.eq_integer_i32(-1)
+                && !matches!(operand.view(), View::Integer(..)) =>
+                Some(self.term(operand)),
+            _ => None,
+        }
+    }
+
+    /// Render an application without notation, as in `f(a, b)`.
+    fn generic(&mut self, function: &Term, arguments: &[Term]) -> Text
+    {
+        let function = self.term(function).at(APPLICATION);
+        let arguments = self.arguments(arguments);
+        Text::new(format!("{}({})", function, arguments), APPLICATION)
+    }
+
+    fn arguments(&mut self, arguments: &[Term]) -> String
+    {
+        let arguments: Vec<String> =
+            arguments.iter()
+            .map(|argument| self.term(argument).text)
+            .collect();
+        arguments.join(", ")
+    }
+}
+
+/// The blank token for a pattern, as in `_` or `xs__Integer`,
+/// if the pattern is a blank with an optional name and head.
+fn blank(term: &Term) -> Option<String>
+{
+    let (variable, blank) = match term.view() {
+        View::Application(function, [variable, blank])
+            if is_symbol(function, b"Pattern") =>
+            (Some(variable.as_symbol()?), blank),
+        _ => (None, term),
+    };
+    let (function, head) = match blank.view() {
+        View::Application(function, []) => (function, None),
+        View::Application(function, [head]) =>
+            (function, Some(head.as_symbol()?)),
+        _ => return None,
+    };
+    let underscores = match function.as_symbol()?.name() {
+        b"Blank" => "_",
+        b"BlankSequence" => "__",
+        b"BlankNullSequence" => "___",
+        _ => return None,
+    };
+    let name = |symbol: Option<&Symbol>| {
+        symbol.map_or(String::new(), |symbol| {
+            String::from_utf8_lossy(symbol.name()).into_owned()
+        })
+    };
+    Some(format!("{}{}{}", name(variable), underscores, name(head)))
+}
+
+/// The operator of a comparison with the given head, if it is one.
+fn comparison_operator(head: &[u8]) -> Option<&'static str>
+{
+    match head {
+        b"Equal" => Some("=="),
+        b"Unequal" => Some("!="),
+        b"Less" => Some("<"),
+        b"LessEqual" => Some("<="),
+        b"Greater" => Some(">"),
+        b"GreaterEqual" => Some(">="),
+        _ => None,
+    }
+}
+
+/// The base of `Power(b, -1)`.
+fn reciprocal(term: &Term) -> Option<&Term>
+{
+    match term.view() {
+        View::Application(head, [base, exponent])
+            if is_symbol(head, b"Power") && exponent.eq_integer_i32(-1) =>
+            Some(base),
+        _ => None,
+    }
+}
+
+/// A string literal with the given contents.
+fn quote(string: &str) -> String
+{
+    let mut literal = "\"".to_owned();
+    for c in string.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            _ if c.is_control() =>
+                write!(literal, "\\u{{{:x}}}", c as u32).unwrap(),
+            _ => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::Lexer;
+    use crate::Scope;
+    use crate::Statement;
+    use crate::parse_program;
+    use crate::testing::*;
+
+    use la_term::symbol::Symbols;
+
+    /// Parse a program, and render its last statement,
+    /// which is a term, with the notations it declares.
+    /// Check that the rendered term is parsed back into the same term.
+    fn round_trip(source: &str) -> String
+    {
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, []);
+        let (term, notations) = program(&symbols, source);
+        let text = render_term(&term, &notations);
+        let mut lex = Lexer::with_notations(&text, notations);
+        let again = parse_program(&symbols, &scope, &mut lex)
+            .unwrap_or_else(|error| panic!("{}: {:?}", text, error));
+        match again.as_slice() {
+            [Statement::Term(again)] =>
+                assert_eq!(format!("{:?}", again), format!("{:?}", term),
+                           "{} rendered as {}", source, text),
+            other => panic!("{}: {:?}", text, other),
+        }
+        text
+    }
+
+    #[test]
+    fn rendering()
+    {
+        let cases = [
+            ("a + b - c * d", "a + b - c * d"),
+            ("a - 2 + -b", "a - 2 - b"),
+            ("(a + b) + (c - d)", "(a + b) + (c - d)"),
+            ("a / b * c ^ -1", "a / b / c"),
+            ("-2 ^ 2 + (-2) ^ 2", "-2 ^ 2 + (-2) ^ 2"),
+            ("-a * b - -(a * b)", "-a * b - -(a * b)"),
+            ("x ^ -1 * 2 ^ 3 ^ 4 * (2 ^ 3) ^ 4",
+             "x ^ -1 * 2 ^ 3 ^ 4 * (2 ^ 3) ^ 4"),
+            ("(a < b) == (c != d)", "(a < b) == (c != d)"),
+            ("{1, \"a\\\"\\n\", {}}", "{1, \"a\\\"\\n\", {}}"),
+            ("(|x, ~y| f(x, y))(1, |z| z)", "(|x, ~y| f(x, y))(1, |z| z)"),
+            ("f(g)(x + 1)", "f(g)(x + 1)"),
+            ("x -> Pi", "x -> Pi"),
+            ("<|\"k\" -> v, k :> 2|>", "<|\"k\" -> v, k :> 2|>"),
+            ("m[[1]] + m[[1, 2]](x) ^ 2", "m[[1]] + m[[1, 2]](x) ^ 2"),
+            ("(Sin(x) /. x -> Pi) //. {a -> b, c -> d}",
+             "Sin(x) /. x -> Pi //. {a -> b, c -> d}"),
+            ("a /. (b /. c)", "a /. (b /. c)"),
+            ("f(x_, ys__, _Integer, zs___List) :> g(x, ys)",
+             "f(x_, ys__, _Integer, zs___List) :> g(x, ys)"),
+            ("x_ -> (y_ -> x + y)", "x_ -> y_ -> x + y"),
+            ("n_ /; n > 0 /; n < 9 -> -n", "n_ /; n > 0 /; n < 9 -> -n"),
+            ("(_ /; t) + (x_ : -1) + (y_ : g(1))",
+             "(_ /; t) + x_ : -1 + y_ : g(1)"),
+            ("(|x| x) -> (|y| y)", "(|x| x) -> (|y| y)"),
+            ("Rule(f(x_), 1) + Association(a) + Part(m)",
+             "Rule(f(x_), 1) + Association(a) + Part(m)"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(round_trip(source), expected);
+        }
+    }
+
+    #[test]
+    fn notations()
+    {
+        let cases = [
+            ("infixl 6 \"⊕\" = DirectSum; a ⊕ b ⊕ (c ⊕ d)",
+             "a ⊕ b ⊕ (c ⊕ d)"),
+            ("infixr 8 \"⊗\" = Tensor; (a ⊗ b) ⊗ c ⊗ d",
+             "(a ⊗ b) ⊗ c ⊗ d"),
+            ("infix 4 \"≈\" = Approx; (a ≈ b) ≈ c + d * e",
+             "(a ≈ b) ≈ c + d * e"),
+            ("infixl 7 \"⋆\" = Star; (a + b) ⋆ -c ⋆ d ^ 2",
+             "(a + b) ⋆ -c ⋆ d ^ 2"),
+            ("infixl 9 \"∘\" = Compose; (f ∘ g)(x) + Compose(f, g, h)",
+             "(f ∘ g)(x) + Compose(f, g, h)"),
+            ("infixr 0 \"⇒\" = Implies; (|x| x) ⇒ (a -> b) ⇒ c",
+             "(|x| x) ⇒ (a -> b) ⇒ c"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(round_trip(source), expected);
+        }
+    }
+}
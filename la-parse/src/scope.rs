@@ -45,15 +45,17 @@ impl<'a> Scope<'a>
         // So we write an iterative version manually (hence mut self).
 
         // The shift is added to the De Bruijn index.
-        // It is incremented when entering into a parent scope.
+        // When entering into a parent scope, it is incremented
+        // by the number of variables in the scope that is left,
+        // as those are numbered before the variables of the parent.
         let mut shift = 0;
 
         loop {
             if let Some(&variable) = self.variables.get(name) {
                 break Some(variable + shift);
             } else if let Some(parent) = self.parent {
-                self = parent;
                 shift += self.variables.len() as u32;
+                self = parent;
                 continue;
             } else {
                 break None;
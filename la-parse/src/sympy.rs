@@ -0,0 +1,1200 @@
+use crate::Error;
+use crate::MAX_DERIVATIVE_ORDER;
+use crate::Result;
+use crate::Scope;
+use crate::Span;
+use crate::is_symbol;
+
+use la_term::Term;
+use la_term::View;
+use la_term::lambda::Parameter;
+use la_term::lambda::Strictness;
+use la_term::symbol::Symbol;
+use la_term::symbol::Symbols;
+use logos::Logos;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+////////////////////////////////////////////////////////////////////////////////
+// Names
+
+/// Symbols and the SymPy names they stand for.
+///
+/// This covers the constants and builtins of the simplifier,
+/// and the functions and relations of the other notations.
+/// Heads that bind variables, such as `Derivative`, are translated
+/// separately, and so is `Log`, whose arguments are the other way around.
+const NAMES: &[(&str, &str)] = &[
+    ("Add", "Add"), ("Multiply", "Mul"), ("Power", "Pow"),
+    ("Sin", "sin"), ("Cos", "cos"), ("Tan", "tan"),
+    ("Cot", "cot"), ("Sec", "sec"), ("Csc", "csc"),
+    ("ArcSin", "asin"), ("ArcCos", "acos"), ("ArcTan", "atan"),
+    ("Sinh", "sinh"), ("Cosh", "cosh"), ("Tanh", "tanh"),
+    ("Ln", "log"), ("Exp", "exp"), ("Abs", "Abs"),
+    ("Equal", "Equality"), ("Unequal", "Unequality"),
+    ("Less", "StrictLessThan"), ("LessEqual", "LessThan"),
+    ("Greater", "StrictGreaterThan"), ("GreaterEqual", "GreaterThan"),
+    ("Pi", "pi"), ("E", "E"), ("Infinity", "oo"),
+];
+
+fn sympy_name(name: &[u8]) -> Option<&'static str>
+{
+    NAMES.iter()
+        .find(|(n, _)| n.as_bytes() == name)
+        .map(|(_, sympy)| *sympy)
+}
+
+fn native_name(sympy: &str) -> Option<&'static str>
+{
+    NAMES.iter()
+        .find(|(_, s)| *s == sympy)
+        .map(|(name, _)| *name)
+}
+
+/// Heads that bind variables, and the SymPy classes they stand for.
+const BINDERS: &[(&str, &str)] = &[
+    ("Integrate", "Integral"), ("Sum", "Sum"), ("Product", "Product"),
+];
+
+////////////////////////////////////////////////////////////////////////////////
+// Lexing
+
+/// Token of the output of SymPy's `srepr`, which is Python source code.
+#[derive(Clone, Copy, Debug, Eq, Logos, PartialEq)]
+enum SreprToken<'a>
+{
+    /// Emitted when there is a lexical error.
+    #[error]
+    #[regex(r"[\t\n\r ]+", logos::skip)]
+    Error,
+
+    #[regex("[A-Za-z_][A-Za-z0-9_]*")]
+    Identifier(&'a str),
+
+    #[regex("[0-9]+")]
+    Integer(&'a str),
+
+    /// Floating-point number, which is not supported.
+    #[regex(r"[0-9]+\.[0-9]*([eE][-+]?[0-9]+)?")]
+    Float(&'a str),
+
+    #[regex(r#"'([^'\\]|\\.)*'|"([^"\\]|\\.)*""#)]
+    String(&'a str),
+
+    #[token("(")]
+    LeftParenthesis,
+
+    #[token(")")]
+    RightParenthesis,
+
+    #[token("[")]
+    LeftBracket,
+
+    #[token("]")]
+    RightBracket,
+
+    #[token(",")]
+    Comma,
+
+    #[token("=")]
+    Equal,
+
+    #[token("-")]
+    Minus,
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Parsing
+
+/// Python expression, as found in the output of `srepr`.
+#[derive(Debug)]
+struct Python
+{
+    kind: PythonKind,
+    span: Span,
+}
+
+#[derive(Debug)]
+enum PythonKind
+{
+    Call(Box<Python>, Vec<Python>),
+    Integer(i32),
+    List(Vec<Python>),
+    Name(String),
+    String(String),
+}
+
+/// Parse the output of SymPy's `srepr`, as in `Add(Symbol('x'), Integer(1))`.
+///
+/// Names of SymPy classes and functions are translated to symbols here,
+/// such as `Mul` to `Multiply` and `sin` to `Sin`,
+/// and unknown ones are kept as they are.
+/// Symbols are variables if they are in the scope,
+/// and their assumptions, as in `Symbol('x', real=True)`, are ignored.
+/// Besides names, these are translated:
+///
+/// | SymPy                        | Term                                 |
+/// |------------------------------|--------------------------------------|
+/// | `Rational(1, 2)`             | `1 / 2`                              |
+/// | `Str('a')`                   | `"a"`                                |
+/// | `[a, b]`, `Tuple(a, b)`      | `{a, b}`                             |
+/// | `Function('f')(x)`           | `f(x)`                               |
+/// | `Lambda(Tuple(x, y), b)`     | `\|x, y\| b`                         |
+/// | `Derivative(f, Tuple(x, 2))` | `Derivative(Derivative(\|x\| f))(x)` |
+/// | `Integral(f, Tuple(x, a, b))`| `Integrate(\|x\| f, a, b)`           |
+/// | `Integral(f, Tuple(x))`      | `Antiderivative(\|x\| f)(x)`         |
+/// | `Sum(f, Tuple(k, a, b))`     | `Sum(\|k\| f, a, b)`                 |
+/// | `Product(f, Tuple(k, a, b))` | `Product(\|k\| f, a, b)`             |
+/// | `log(x, b)`                  | `Log(b, x)`                          |
+/// | `Subs(f, Tuple(x), Tuple(a))`| `(\|x\| f)(a)`                       |
+///
+/// With several variables, as in `Derivative(f, x, y)`,
+/// the first variable is innermost.
+/// A derivative or integral in which a point is substituted
+/// for its variable is applied to that point,
+/// so `Subs(Derivative(f, x), Tuple(x), Tuple(a))`
+/// means `Derivative(\|x\| f)(a)`.
+pub fn parse_srepr(symbols: &Symbols, scope: &Scope, source: &str)
+    -> Result<Term>
+{
+    let tokens = SreprToken::lexer(source).spanned().collect();
+    let mut parser = SreprParser{source, tokens, position: 0};
+    let python = parser.expression()?;
+    if parser.peek().is_some() {
+        return Err(parser.unexpected(&["end of input"]));
+    }
+    Lowering{symbols, source}.term(scope, &python)
+}
+
+/// State of the parser for the output of `srepr`.
+struct SreprParser<'a>
+{
+    source: &'a str,
+    tokens: Vec<(SreprToken<'a>, Span)>,
+    position: usize,
+}
+
+impl<'a> SreprParser<'a>
+{
+    /// Parse an expression, with any calls, as in `Function('f')(x)`.
+    fn expression(&mut self) -> Result<Python>
+    {
+        let start = self.peek_span().start;
+        let mut python = self.primary()?;
+        while self.eat(SreprToken::LeftParenthesis) {
+            let arguments = self.arguments(SreprToken::RightParenthesis,
+                                           "`)`")?;
+            let span = start .. self.tokens[self.position - 1].1.end;
+            let kind = PythonKind::Call(Box::new(python), arguments);
+            python = Python{kind, span};
+        }
+        Ok(python)
+    }
+
+    fn primary(&mut self) -> Result<Python>
+    {
+        let span = self.peek_span();
+        let kind = match self.peek() {
+
+            Some(SreprToken::Identifier(name)) =>
+                PythonKind::Name(name.to_owned()),
+
+            Some(SreprToken::Integer(digits)) =>
+                PythonKind::Integer(self.integer(span.clone(), digits, "")?),
+
+            Some(SreprToken::Minus) => {
+                self.position += 1;
+                let digits_span = self.peek_span();
+                let digits = match self.peek() {
+                    Some(SreprToken::Integer(digits)) => digits,
+                    _ => return Err(self.unexpected(&["integer"])),
+                };
+                let span = span.start .. digits_span.end;
+                PythonKind::Integer(self.integer(span, digits, "-")?)
+            },
+
+            Some(SreprToken::Float(digits)) => {
+                let message = "floats are not supported".to_owned();
+                return Err(Error::new(span, Some(digits), message));
+            },
+
+            Some(SreprToken::String(literal)) =>
+                PythonKind::String(unescape(literal)),
+
+            Some(SreprToken::LeftBracket) => {
+                self.position += 1;
+                let elements =
+                    self.arguments(SreprToken::RightBracket, "`]`")?;
+                let span = span.start .. self.tokens[self.position - 1].1.end;
+                return Ok(Python{kind: PythonKind::List(elements), span});
+            },
+
+            _ => return Err(self.unexpected(
+                &["`-`", "`[`", "identifier", "integer", "string"],
+            )),
+
+        };
+        self.position += 1;
+        Ok(Python{kind, span})
+    }
+
+    /// Parse an integer literal, with the given sign.
+    fn integer(&self, span: Span, digits: &str, sign: &str) -> Result<i32>
+    {
+        format!("{}{}", sign, digits).parse().map_err(|_| {
+            let found = &self.source[span.clone()];
+            Error::new(span, Some(found), "integer is too large".to_owned())
+        })
+    }
+
+    /// Parse arguments separated by commas, up to the given token.
+    ///
+    /// Keyword arguments, as in `real=True`, are skipped,
+    /// and so is a trailing comma.
+    fn arguments(&mut self, close: SreprToken, expected: &'static str)
+        -> Result<Vec<Python>>
+    {
+        let mut arguments = Vec::new();
+        loop {
+            if self.eat(close) {
+                return Ok(arguments);
+            }
+            let keyword = matches!(
+                self.tokens.get(self.position .. self.position + 2),
+                Some([(SreprToken::Identifier(_), _), (SreprToken::Equal, _)])
+            );
+            if keyword {
+                self.position += 2;
+                self.expression()?;
+            } else {
+                arguments.push(self.expression()?);
+            }
+            if self.eat(close) {
+                return Ok(arguments);
+            }
+            if !self.eat(SreprToken::Comma) {
+                return Err(self.unexpected(&["`,`", expected]));
+            }
+        }
+    }
+
+    fn peek(&self) -> Option<SreprToken<'a>>
+    {
+        self.tokens.get(self.position).map(|(token, _)| *token)
+    }
+
+    /// The span of the next token, or the empty span at the end.
+    fn peek_span(&self) -> Span
+    {
+        match self.tokens.get(self.position) {
+            Some((_, span)) => span.clone(),
+            None => self.source.len() .. self.source.len(),
+        }
+    }
+
+    fn eat(&mut self, token: SreprToken) -> bool
+    {
+        let found = self.peek() == Some(token);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn unexpected(&self, expected: &[&'static str]) -> Error
+    {
+        let span = self.peek_span();
+        let found = self.peek().map(|_| &self.source[span.clone()]);
+        Error::unexpected(span, found, expected)
+    }
+}
+
+/// The contents of a string literal, with escape sequences replaced.
+fn unescape(literal: &str) -> String
+{
+    let mut contents = String::new();
+    let mut chars = literal[1 .. literal.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            contents.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => contents.push('\n'),
+            Some('t') => contents.push('\t'),
+            Some('r') => contents.push('\r'),
+            Some(c) => contents.push(c),
+            None => (),
+        }
+    }
+    contents
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Lowering
+
+/// Translation of Python expressions into terms.
+struct Lowering<'a, 's>
+{
+    symbols: &'s Symbols,
+    source: &'a str,
+}
+
+impl<'a, 's> Lowering<'a, 's>
+{
+    fn term(&self, scope: &Scope, python: &Python) -> Result<Term>
+    {
+        match &python.kind {
+            PythonKind::Call(function, arguments) =>
+                self.call(scope, python, function, arguments),
+            PythonKind::Integer(value) =>
+                Ok(Term::integer_i32(*value)),
+            PythonKind::List(elements) =>
+                self.apply(scope, "List", elements),
+            PythonKind::Name(name) => {
+                let name = native_name(name).unwrap_or(name);
+                Ok(self.symbols.get(name.as_bytes()).term())
+            },
+            PythonKind::String(string) =>
+                Ok(Term::string(string.bytes())),
+        }
+    }
+
+    fn call(
+        &self,
+        scope: &Scope,
+        python: &Python,
+        function: &Python,
+        arguments: &[Python],
+    ) -> Result<Term>
+    {
+        let name = match &function.kind {
+            PythonKind::Name(name) => name.as_str(),
+            _ => "",
+        };
+        let kinds: Vec<&PythonKind> =
+            arguments.iter().map(|argument| &argument.kind).collect();
+        match (name, kinds.as_slice()) {
+
+            ("Symbol" | "Dummy", [PythonKind::String(name)]) => {
+                let symbol = self.symbols.get(name.as_bytes());
+                match scope.get(&symbol) {
+                    Some(de_bruijn) => Ok(Term::variable(de_bruijn)),
+                    None => Ok(symbol.term()),
+                }
+            },
+
+            ("Integer", [PythonKind::Integer(value)]) =>
+                Ok(Term::integer_i32(*value)),
+
+            ("Rational", [PythonKind::Integer(p), PythonKind::Integer(q)]) => {
+                if *q == 0 {
+                    return Err(self.error(python, "denominator is zero"));
+                }
+                let reciprocal = self.head("Power", vec![
+                    Term::integer_i32(*q),
+                    Term::integer_i32(-1),
+                ]);
+                Ok(self.head("Multiply", vec![Term::integer_i32(*p),
+                                              reciprocal]))
+            },
+
+            ("Str", [PythonKind::String(string)]) =>
+                Ok(Term::string(string.bytes())),
+
+            ("Function", [PythonKind::String(name)]) =>
+                Ok(self.symbols.get(name.as_bytes()).term()),
+
+            ("Tuple", _) =>
+                self.apply(scope, "List", arguments),
+
+            ("Lambda", [_, _]) => {
+                let parameters = match &arguments[0].kind {
+                    PythonKind::Call(tuple, parameters)
+                        if matches!(&tuple.kind,
+                                    PythonKind::Name(n) if n == "Tuple") =>
+                        parameters.as_slice(),
+                    _ => &arguments[.. 1],
+                };
+                let parameters = parameters.iter()
+                    .map(|parameter| self.variable(parameter))
+                    .collect::<Result<Vec<_>>>()?;
+                let scope = Scope::new(Some(scope), parameters.iter().cloned());
+                let body = self.term(&scope, &arguments[1])?;
+                Ok(lambda(parameters, body))
+            },
+
+            ("Derivative" | "Integral" | "Sum" | "Product", [_, _, ..]) =>
+                self.bound(scope, name, &arguments[0], &arguments[1 ..], None),
+
+            ("Subs", [_, _, _]) =>
+                self.subs(scope, &arguments[0], &arguments[1], &arguments[2]),
+
+            ("log", [_, _]) => {
+                let argument = self.term(scope, &arguments[0])?;
+                let base = self.term(scope, &arguments[1])?;
+                Ok(self.head("Log", vec![base, argument]))
+            },
+
+            ("Float", _) =>
+                Err(self.error(python, "floats are not supported")),
+
+            ("Symbol" | "Dummy" | "Integer" | "Rational" | "Str"
+                | "Function" | "Lambda" | "Subs"
+                | "Derivative" | "Integral" | "Sum" | "Product", _) =>
+                Err(self.error(
+                    python,
+                    &format!("unsupported arguments to `{}`", name),
+                )),
+
+            _ => {
+                let function = self.term(scope, function)?;
+                let arguments = arguments.iter()
+                    .map(|argument| self.term(scope, argument))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Term::application(function, arguments))
+            },
+
+        }
+    }
+
+    /// Lower `Subs(e, Tuple(x, y), Tuple(a, b))`,
+    /// which is `e` with `a` and `b` substituted for `x` and `y`,
+    /// as `(|x, y| e)(a, b)`.
+    ///
+    /// As an exception, a derivative or integral with respect to `x`
+    /// in which `a` is substituted for `x`,
+    /// as in `Subs(Derivative(f, x), Tuple(x), Tuple(a))`,
+    /// is lowered as `Derivative(|x| f)(a)`.
+    fn subs(
+        &self,
+        scope: &Scope,
+        expression: &Python,
+        variables: &Python,
+        points: &Python,
+    ) -> Result<Term>
+    {
+        let variables = tuple(variables);
+        let points = tuple(points);
+        if variables.len() != points.len() {
+            return Err(self.error(
+                expression,
+                "expected as many points as variables",
+            ));
+        }
+        let variables = variables.iter()
+            .map(|variable| self.variable(variable))
+            .collect::<Result<Vec<_>>>()?;
+
+        if let (PythonKind::Call(class, arguments), [variable], [point]) =
+            (&expression.kind, variables.as_slice(), points.as_slice())
+        {
+            if let (PythonKind::Name(class), [body, limits @ ..]) =
+                (&class.kind, arguments.as_slice())
+            {
+                let limit = limits.last().map(|limit| tuple(limit)[0]);
+                if (class == "Derivative" || class == "Integral")
+                    && limit.map(|limit| self.variable(limit).ok())
+                       == Some(Some(variable.clone()))
+                {
+                    return self.bound(scope, class, body, limits, Some(point));
+                }
+            }
+        }
+
+        let inner = Scope::new(Some(scope), variables.iter().cloned());
+        let body = self.term(&inner, expression)?;
+        let points = points.iter()
+            .map(|point| self.term(scope, point))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Term::application(lambda(variables, body), points))
+    }
+
+    /// Lower the expression of a class that binds variables,
+    /// such as `Integral`, under its limits, the last of which is outermost.
+    ///
+    /// A derivative or integral with respect to `x` is applied to `x`,
+    /// or to the given point instead, if any.
+    fn bound(
+        &self,
+        scope: &Scope,
+        class: &str,
+        body: &Python,
+        limits: &[Python],
+        point: Option<&Python>,
+    ) -> Result<Term>
+    {
+        let (limit, inner_limits) = match limits.split_last() {
+            Some(limits) => limits,
+            None => return self.term(scope, body),
+        };
+        let (variable, bounds) = match &limit.kind {
+            PythonKind::Call(tuple, elements)
+                if matches!(&tuple.kind, PythonKind::Name(n) if n == "Tuple")
+                && !elements.is_empty() =>
+                (&elements[0], &elements[1 ..]),
+            _ => (limit, &[][..]),
+        };
+        let symbol = self.variable(variable)?;
+        let inner = Scope::new(Some(scope), [symbol.clone()]);
+        let body = self.bound(&inner, class, body, inner_limits, None)?;
+        let function = lambda(vec![symbol], body);
+        let point = point.unwrap_or(variable);
+
+        match (class, bounds) {
+            ("Derivative", []) => {
+                let derivative = self.head("Derivative", vec![function]);
+                let point = self.term(scope, point)?;
+                Ok(Term::application(derivative, [point]))
+            },
+            ("Derivative", [count]) => {
+                let count = match integer(count) {
+                    Some(count) if count > 0 => count,
+                    _ => return Err(self.error(
+                        count, "expected a positive integer",
+                    )),
+                };
+                if count as u32 > MAX_DERIVATIVE_ORDER {
+                    let message = format!(
+                        "derivatives of order above {} are not supported",
+                        MAX_DERIVATIVE_ORDER,
+                    );
+                    return Err(self.error(limit, &message));
+                }
+                let mut derivative = function;
+                for _ in 0 .. count {
+                    derivative = self.head("Derivative", vec![derivative]);
+                }
+                let point = self.term(scope, point)?;
+                Ok(Term::application(derivative, [point]))
+            },
+            ("Integral", []) => {
+                let antiderivative =
+                    self.head("Antiderivative", vec![function]);
+                let point = self.term(scope, point)?;
+                Ok(Term::application(antiderivative, [point]))
+            },
+            (_, [lower, upper]) if class != "Derivative" => {
+                let lower = self.term(scope, lower)?;
+                let upper = self.term(scope, upper)?;
+                let head = BINDERS.iter()
+                    .find(|(_, c)| *c == class)
+                    .map_or(class, |(head, _)| *head);
+                Ok(self.head(head, vec![function, lower, upper]))
+            },
+            _ => Err(self.error(limit, "unsupported limits")),
+        }
+    }
+
+    /// The symbol of a variable, as in `Symbol('x')`.
+    fn variable(&self, python: &Python) -> Result<Symbol>
+    {
+        if let PythonKind::Call(function, arguments) = &python.kind {
+            if let (PythonKind::Name(class), [argument]) =
+                (&function.kind, arguments.as_slice())
+            {
+                if let ("Symbol" | "Dummy", PythonKind::String(name)) =
+                    (class.as_str(), &argument.kind)
+                {
+                    return Ok(self.symbols.get(name.as_bytes()));
+                }
+            }
+        }
+        Err(self.error(python, "expected a symbol, as in `Symbol('x')`"))
+    }
+
+    fn apply(&self, scope: &Scope, head: &str, arguments: &[Python])
+        -> Result<Term>
+    {
+        let arguments = arguments.iter()
+            .map(|argument| self.term(scope, argument))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(self.head(head, arguments))
+    }
+
+    fn head(&self, head: &str, arguments: Vec<Term>) -> Term
+    {
+        Term::application(self.symbols.get(head.as_bytes()).term(), arguments)
+    }
+
+    fn error(&self, python: &Python, message: &str) -> Error
+    {
+        let span = python.span.clone();
+        let found = &self.source[span.clone()];
+        Error::new(span, Some(found), message.to_owned())
+    }
+}
+
+/// The elements of a tuple, as in `Tuple(a, b)`,
+/// or the expression itself if it is not a tuple.
+fn tuple(python: &Python) -> Vec<&Python>
+{
+    match &python.kind {
+        PythonKind::Call(function, elements)
+            if matches!(&function.kind, PythonKind::Name(n) if n == "Tuple")
+            && !elements.is_empty() =>
+            elements.iter().collect(),
+        _ => vec![python],
+    }
+}
+
+/// The value of an integer, as in `2` or `Integer(2)`.
+fn integer(python: &Python) -> Option<i32>
+{
+    match &python.kind {
+        PythonKind::Integer(value) => Some(*value),
+        PythonKind::Call(function, arguments) =>
+            match (&function.kind, arguments.as_slice()) {
+                (PythonKind::Name(class), [argument])
+                    if class == "Integer" => integer(argument),
+                _ => None,
+            },
+        _ => None,
+    }
+}
+
+fn lambda(parameters: Vec<Symbol>, body: Term) -> Term
+{
+    let parameters: Vec<Parameter> =
+        parameters.into_iter()
+        .map(|name| Parameter{strictness: Strictness::Strict, name})
+        .collect();
+    Term::lambda(parameters.into(), body)
+}
+
+////////////////////////////////////////////////////////////////////////////////
+// Rendering
+
+/// Render a term as the output of SymPy's `srepr`,
+/// which SymPy reads back with `sympify`.
+///
+/// This is the other way around from [`parse_srepr`],
+/// except that symbols applied as functions, other than the known ones,
+/// are written as undefined functions, as in `Function('f')(Symbol('x'))`,
+/// and derivatives of functions other than lambdas are written
+/// as derivatives of their applications, as in `Derivative(sin(x), x)`.
+pub fn render_srepr(term: &Term) -> String
+{
+    let mut renderer = SreprRenderer{names: Vec::new(), out: String::new()};
+    renderer.term(term);
+    renderer.out
+}
+
+/// State of the renderer of `srepr`.
+struct SreprRenderer
+{
+    /// Names of the variables in scope,
+    /// the last of which has De Bruijn index zero.
+    names: Vec<String>,
+
+    out: String,
+}
+
+impl SreprRenderer
+{
+    fn term(&mut self, term: &Term)
+    {
+        match term.view() {
+
+            View::Application(function, arguments) =>
+                self.application(function, arguments),
+
+            View::Integer(value) =>
+                write!(self.out, "Integer({})", value).unwrap(),
+
+            View::Lambda(parameters, body) => {
+                self.out.push_str("Lambda(Tuple(");
+                for (i, parameter) in parameters.iter().enumerate() {
+                    if i != 0 {
+                        self.out.push_str(", ");
+                    }
+                    self.symbol(parameter.name.name());
+                }
+                self.out.push_str("), ");
+                self.bound(parameters, body);
+                self.out.push(')');
+            },
+
+            View::String(bytes) => {
+                self.out.push_str("Str(");
+                self.out.push_str(&quote(&String::from_utf8_lossy(bytes)));
+                self.out.push(')');
+            },
+
+            View::Symbol(symbol) =>
+                match sympy_name(symbol.name()) {
+                    Some(name) => self.out.push_str(name),
+                    None => self.symbol(symbol.name()),
+                },
+
+            View::Variable(de_bruijn) => {
+                let index = de_bruijn.0 as usize;
+                let name = self.names.len().checked_sub(index + 1)
+                    .map(|i| self.names[i].clone())
+                    .unwrap_or_else(|| format!("#{}", index));
+                self.symbol(name.as_bytes());
+            },
+
+        }
+    }
+
+    /// Write a symbol, as in `Symbol('x')`.
+    fn symbol(&mut self, name: &[u8])
+    {
+        let name = quote(&String::from_utf8_lossy(name));
+        write!(self.out, "Symbol({})", name).unwrap();
+    }
+
+    /// Write the body of a lambda with its parameters in scope.
+    fn bound(&mut self, parameters: &[Parameter], body: &Term)
+    {
+        let outer = self.names.len();
+        self.names.extend(
+            parameters.iter().rev()
+            .map(|p| String::from_utf8_lossy(p.name.name()).into_owned())
+        );
+        self.term(body);
+        self.names.truncate(outer);
+    }
+
+    fn application(&mut self, function: &Term, arguments: &[Term])
+    {
+        if self.derivative(function, arguments) {
+            return;
+        }
+
+        let head = match function.view() {
+            View::Symbol(symbol) => symbol.name(),
+            _ => b"",
+        };
+        match (head, arguments) {
+
+            (b"Multiply", [numerator, reciprocal]) => {
+                if let (View::Integer(p), Some(q)) =
+                    (numerator.view(), integer_reciprocal(reciprocal))
+                {
+                    write!(self.out, "Rational({}, {})", p, q).unwrap();
+                    return;
+                }
+            },
+
+            (b"Log", [base, argument]) => {
+                self.out.push_str("log(");
+                self.term(argument);
+                self.out.push_str(", ");
+                self.term(base);
+                self.out.push(')');
+                return;
+            },
+
+            (b"List", _) => {
+                self.out.push('[');
+                self.arguments(arguments);
+                self.out.push(']');
+                return;
+            },
+
+            (_, [function, lower, upper]) => {
+                let class = BINDERS.iter()
+                    .find(|(h, _)| h.as_bytes() == head)
+                    .map(|(_, class)| *class);
+                if let (Some(class), View::Lambda(parameters, body)) =
+                    (class, function.view())
+                {
+                    if let [parameter] = &parameters[..] {
+                        write!(self.out, "{}(", class).unwrap();
+                        self.bound(parameters, body);
+                        self.out.push_str(", Tuple(");
+                        self.symbol(parameter.name.name());
+                        self.out.push_str(", ");
+                        self.term(lower);
+                        self.out.push_str(", ");
+                        self.term(upper);
+                        self.out.push_str("))");
+                        return;
+                    }
+                }
+            },
+
+            _ => (),
+
+        }
+
+        self.callee(function);
+        self.out.push('(');
+        self.arguments(arguments);
+        self.out.push(')');
+    }
+
+    /// Write a function that is applied,
+    /// as an undefined function if it is an unknown symbol.
+    fn callee(&mut self, function: &Term)
+    {
+        match function.view() {
+            View::Symbol(symbol) if sympy_name(symbol.name()).is_none() => {
+                let name = quote(&String::from_utf8_lossy(symbol.name()));
+                write!(self.out, "Function({})", name).unwrap();
+            },
+            _ => self.term(function),
+        }
+    }
+
+    /// Write `Derivative(...)(a)` or `Antiderivative(...)(a)`
+    /// as `Derivative` or `Integral`, or an unapplied `Derivative(...)`.
+    ///
+    /// The function is written as a lambda that binds `x`, as in
+    /// `Derivative(f, Tuple(x, 1))`, where `f` is `g(x)` if the function
+    /// `g` is not a lambda. If `a` is not `x`, it is substituted for `x`,
+    /// as in `Subs(Derivative(f, Tuple(x, 1)), Tuple(x), Tuple(a))`,
+    /// and if the derivative is not applied, it is written as a lambda,
+    /// as in `Lambda(Tuple(x), Derivative(f, Tuple(x, 1)))`.
+    ///
+    /// Returns whether the application was of that form.
+    fn derivative(&mut self, function: &Term, arguments: &[Term])
+        -> bool
+    {
+        let (function, argument) = match (function.view(), arguments) {
+            (View::Application(..), [argument]) => (function, Some(argument)),
+            (View::Symbol(..), [_]) if is_symbol(function, b"Derivative") =>
+                (function, None),
+            _ => return false,
+        };
+        let applied = match argument {
+            Some(..) => function.clone(),
+            None => Term::application(function.clone(),
+                                      arguments.iter().cloned()),
+        };
+        let (mut inner, mut count) = (&applied, 0);
+        while let View::Application(head, [operand]) = inner.view() {
+            if !is_symbol(head, b"Derivative") {
+                break;
+            }
+            count += 1;
+            inner = operand;
+        }
+        let mut class = "Derivative";
+        if count == 0 {
+            match inner.view() {
+                View::Application(head, [operand])
+                    if is_symbol(head, b"Antiderivative")
+                    && argument.is_some() =>
+                {
+                    class = "Integral";
+                    inner = operand;
+                },
+                _ => return false,
+            }
+        }
+        let lambda = match inner.view() {
+            View::Lambda(parameters, body) if parameters.len() == 1 =>
+                Some((parameters, body)),
+            _ if class == "Integral" => return false,
+            _ => None,
+        };
+
+        // The variable `x`, which is named after `a`
+        // if that does not capture any variables of the function.
+        let name = match lambda {
+            Some((parameters, _)) =>
+                String::from_utf8_lossy(parameters[0].name.name())
+                .into_owned(),
+            None => {
+                let mut names = HashSet::new();
+                self.free_names(inner, 0, &mut names);
+                let preferred = argument.and_then(|a| self.name(a));
+                preferred.into_iter()
+                    .chain(std::iter::once("x".to_owned()))
+                    .chain((1 ..).map(|i| format!("x{}", i)))
+                    .find(|name| !names.contains(name.as_bytes()))
+                    .unwrap()
+            },
+        };
+        let at_variable =
+            argument.and_then(|a| self.name(a)).as_ref() == Some(&name);
+
+        match argument {
+            Some(..) if at_variable => (),
+            Some(..) => self.out.push_str("Subs("),
+            None => {
+                self.out.push_str("Lambda(Tuple(");
+                self.symbol(name.as_bytes());
+                self.out.push_str("), ");
+            },
+        }
+
+        write!(self.out, "{}(", class).unwrap();
+        match lambda {
+            Some((parameters, body)) =>
+                self.bound(parameters, body),
+            None => {
+                self.callee(inner);
+                self.out.push('(');
+                self.symbol(name.as_bytes());
+                self.out.push(')');
+            },
+        }
+        self.out.push_str(", Tuple(");
+        self.symbol(name.as_bytes());
+        if class == "Derivative" {
+            write!(self.out, ", Integer({})", count).unwrap();
+        }
+        self.out.push_str("))");
+
+        match argument {
+            Some(..) if at_variable => (),
+            Some(argument) => {
+                self.out.push_str(", Tuple(");
+                self.symbol(name.as_bytes());
+                self.out.push_str("), Tuple(");
+                self.term(argument);
+                self.out.push_str("))");
+            },
+            None => self.out.push(')'),
+        }
+        true
+    }
+
+    /// The name a symbol or variable is written with.
+    fn name(&self, term: &Term) -> Option<String>
+    {
+        match term.view() {
+            View::Symbol(symbol) =>
+                Some(String::from_utf8_lossy(symbol.name()).into_owned()),
+            View::Variable(de_bruijn) =>
+                self.names.len().checked_sub(de_bruijn.0 as usize + 1)
+                .map(|i| self.names[i].clone()),
+            _ => None,
+        }
+    }
+
+    /// Collect the names of the symbols and the free variables of a term,
+    /// which is `depth` variables inside the current scope.
+    fn free_names(&self, term: &Term, depth: u32, names: &mut HashSet<Vec<u8>>)
+    {
+        match term.view() {
+            View::Application(function, arguments) => {
+                self.free_names(function, depth, names);
+                for argument in arguments {
+                    self.free_names(argument, depth, names);
+                }
+            },
+            View::Lambda(parameters, body) => {
+                let depth = depth + parameters.len() as u32;
+                self.free_names(body, depth, names);
+            },
+            View::Symbol(symbol) => {
+                names.insert(symbol.name().to_vec());
+            },
+            View::Variable(de_bruijn) if de_bruijn.0 >= depth => {
+                let index = (de_bruijn.0 - depth) as usize;
+                if let Some(i) = self.names.len().checked_sub(index + 1) {
+                    names.insert(self.names[i].as_bytes().to_vec());
+                }
+            },
+            View::Variable(..) | View::Integer(..) | View::String(..) => (),
+        }
+    }
+
+    fn arguments(&mut self, arguments: &[Term])
+    {
+        for (i, argument) in arguments.iter().enumerate() {
+            if i != 0 {
+                self.out.push_str(", ");
+            }
+            self.term(argument);
+        }
+    }
+}
+
+/// The integer `q` of `Power(q, -1)`, if it is not zero.
+fn integer_reciprocal(term: &Term) -> Option<i32>
+{
+    match term.view() {
+        View::Application(head, [base, exponent])
+            if is_symbol(head, b"Power") && exponent.eq_integer_i32(-1) =>
+            match base.view() {
+                View::Integer(q) if q != 0 => Some(q),
+                _ => None,
+            },
+        _ => None,
+    }
+}
+
+/// A Python string literal with the given contents.
+fn quote(string: &str) -> String
+{
+    let mut literal = "'".to_owned();
+    for c in string.chars() {
+        match c {
+            '\'' => literal.push_str("\\'"),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\t' => literal.push_str("\\t"),
+            '\r' => literal.push_str("\\r"),
+            _ => literal.push(c),
+        }
+    }
+    literal.push('\'');
+    literal
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::testing::*;
+
+    #[test]
+    fn reading()
+    {
+        let cases = [
+            ("Add(Symbol('x'), Integer(1))", "x + 1"),
+            ("Mul(Integer(-1), Pow(Symbol('x', real=True), Rational(1, 2)))",
+             "-1 * x ^ (1 / 2)"),
+            ("Add(sin(pi), Mul(cos(E), tan(oo)))",
+             "Sin(Pi) + Cos(E) * Tan(Infinity)"),
+            ("StrictLessThan(log(Symbol('x')), log(Symbol('x'), Integer(2)))",
+             "Ln(x) < Log(2, x)"),
+            ("[Str(\"a'b\"), Tuple(Integer(1),)]", "{\"a'b\", {1}}"),
+            ("Function('f')(Symbol('x'), Dummy('y'))", "f(x, y)"),
+            ("Lambda(Tuple(Symbol('x'), Symbol('y')), Symbol('x'))",
+             "|x, y| x"),
+            ("Lambda(Symbol('x'), Pow(Symbol('x'), Integer(2)))",
+             "|x| x ^ 2"),
+            ("Derivative(sin(Symbol('x')), Tuple(Symbol('x'), Integer(2)))",
+             "Derivative(Derivative(|x| Sin(x)))(x)"),
+            ("Derivative(Mul(Symbol('x'), Symbol('y')), Symbol('x'), \
+              Symbol('y'))",
+             "Derivative(|y| Derivative(|x| x * y)(x))(y)"),
+            ("Integral(exp(Symbol('x')), Tuple(Symbol('x')))",
+             "Antiderivative(|x| Exp(x))(x)"),
+            ("Integral(Symbol('x'), Tuple(Symbol('x'), Integer(0), \
+              Integer(1)))",
+             "Integrate(|x| x, 0, 1)"),
+            ("Mul(Sum(Symbol('k'), Tuple(Symbol('k'), Integer(1), \
+              Symbol('n'))), Product(Symbol('k'), Tuple(Symbol('k'), \
+              Integer(1), Symbol('n'))))",
+             "Sum(|k| k, 1, n) * Product(|k| k, 1, n)"),
+            ("Equality(Abs(I), Integer(1))", "Abs(I) == 1"),
+            ("Subs(Add(Symbol('x'), Symbol('y')), Tuple(Symbol('x')), \
+              Tuple(Integer(1)))",
+             "(|x| x + y)(1)"),
+            ("Subs(Derivative(sin(Symbol('x')), Symbol('x')), \
+              Tuple(Symbol('x')), Tuple(Symbol('y')))",
+             "Derivative(|x| Sin(x))(y)"),
+            ("Subs(Derivative(Symbol('x'), Symbol('y')), \
+              Tuple(Symbol('x')), Tuple(Integer(1)))",
+             "(|x| Derivative(|y| x)(y))(1)"),
+        ];
+        for (source, expected) in cases {
+            assert_imports(parse_srepr, source, expected);
+        }
+    }
+
+    #[test]
+    fn writing()
+    {
+        let cases = [
+            ("a - 2", "Add(Symbol('a'), Integer(-2))"),
+            ("Sin(x) ^ 2 * (1 / 2)",
+             "Mul(Pow(sin(Symbol('x')), Integer(2)), Rational(1, 2))"),
+            ("{\"a'\", f(Pi, E)}",
+             "[Str('a\\''), Function('f')(pi, E)]"),
+            ("Log(2, x) != Ln(x)",
+             "Unequality(log(Symbol('x'), Integer(2)), log(Symbol('x')))"),
+            ("|x| Derivative(Derivative(|y| x * y))(x)",
+             "Lambda(Tuple(Symbol('x')), Subs(Derivative(Mul(Symbol('x'), \
+              Symbol('y')), Tuple(Symbol('y'), Integer(2))), \
+              Tuple(Symbol('y')), Tuple(Symbol('x'))))"),
+            ("Derivative(|x| x ^ 2)(y)",
+             "Subs(Derivative(Pow(Symbol('x'), Integer(2)), \
+              Tuple(Symbol('x'), Integer(1))), Tuple(Symbol('x')), \
+              Tuple(Symbol('y')))"),
+            ("Derivative(Sin)(t) + Derivative(g)(2)",
+             "Add(Derivative(sin(Symbol('t')), Tuple(Symbol('t'), \
+              Integer(1))), Subs(Derivative(Function('g')(Symbol('x')), \
+              Tuple(Symbol('x'), Integer(1))), Tuple(Symbol('x')), \
+              Tuple(Integer(2))))"),
+            ("Derivative(Sin)",
+             "Lambda(Tuple(Symbol('x')), Derivative(sin(Symbol('x')), \
+              Tuple(Symbol('x'), Integer(1))))"),
+            ("|x| Derivative(Derivative(|y| x * y))",
+             "Lambda(Tuple(Symbol('x')), Lambda(Tuple(Symbol('y')), \
+              Derivative(Mul(Symbol('x'), Symbol('y')), \
+              Tuple(Symbol('y'), Integer(2)))))"),
+            ("Derivative(x)(x)",
+             "Subs(Derivative(Function('x')(Symbol('x1')), \
+              Tuple(Symbol('x1'), Integer(1))), Tuple(Symbol('x1')), \
+              Tuple(Symbol('x')))"),
+            ("|x| Derivative(Derivative(|x| x * y))(x)",
+             "Lambda(Tuple(Symbol('x')), Derivative(Mul(Symbol('x'), \
+              Symbol('y')), Tuple(Symbol('x'), Integer(2))))"),
+            ("Antiderivative(|t| Cos(t))(t)",
+             "Integral(cos(Symbol('t')), Tuple(Symbol('t')))"),
+            ("Integrate(|t| t, 0, Infinity)",
+             "Integral(Symbol('t'), Tuple(Symbol('t'), Integer(0), oo))"),
+        ];
+        let symbols = Symbols::new();
+        for (source, expected) in cases {
+            let term = native(&symbols, source);
+            assert_eq!(render_srepr(&term), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn round_trip()
+    {
+        let sources = [
+            "Antiderivative(|x| Derivative(|x| Sin(x))(x) * Cos(x))(x)",
+            "Derivative(|x| x ^ 2)(y) + Antiderivative(|t| t)(1)",
+            "|x| Derivative(Derivative(|y| x * y))(x)",
+            "Derivative(Derivative(|x| x ^ 3))(2 * y) * Tan(E)",
+            "(|x| x + 1)(Derivative(|x| x)(x))",
+            "Add(Pi, Ln(x)) ^ Multiply(-1, Power(x, 1 / 3))",
+            "Derivative(|x| Derivative(|y| x ^ y)(y))(x) > 1",
+            "Sum(|k| k ^ 2, 1, n) <= Product(|k| k, 1, n)",
+            "(|x, y| f(y, x))(1, \"a\\nb\")",
+            "{1 == 2, Abs(-3) >= Exp(1)}",
+        ];
+        let symbols = Symbols::new();
+        for source in sources {
+            let term = native(&symbols, source);
+            let text = render_srepr(&term);
+            let again = import(parse_srepr, &symbols, &text).unwrap();
+            assert_eq!(format!("{:?}", again), format!("{:?}", term),
+                       "{} rendered as {}", source, text);
+        }
+    }
+
+    #[test]
+    fn errors()
+    {
+        let cases = [
+            ("",
+             "expected `-`, `[`, identifier, integer or string, \
+              found end of input"),
+            ("Add(Integer(1) Integer(2))",
+             "expected `)` or `,`, found `Integer`"),
+            ("Float('1.5', precision=53)", "floats are not supported"),
+            ("Float(1.5)", "floats are not supported"),
+            ("Integer(99999999999)", "integer is too large"),
+            ("Rational(1, 0)", "denominator is zero"),
+            ("Symbol(1)", "unsupported arguments to `Symbol`"),
+            ("Lambda(Tuple(Integer(1)), Integer(1))",
+             "expected a symbol, as in `Symbol('x')`"),
+            ("Derivative(Symbol('x'), \
+              Tuple(Symbol('x'), Integer(0)))",
+             "expected a positive integer"),
+            ("Derivative(Symbol('x'), \
+              Tuple(Symbol('x'), Integer(2000000000)))",
+             "derivatives of order above 1000 are not supported"),
+            ("Subs(Symbol('x'), \
+              Tuple(Symbol('x'), Symbol('y')), Integer(1))",
+             "expected as many points as variables"),
+            ("Sum(Symbol('k'), Tuple(Symbol('k')))", "unsupported limits"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(message(parse_srepr, source), expected, "{}", source);
+        }
+    }
+}
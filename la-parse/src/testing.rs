@@ -0,0 +1,65 @@
+//! Helpers shared by the tests of the other notations.
+
+use crate::Lexer;
+use crate::Notations;
+use crate::Result;
+use crate::Scope;
+use crate::Statement;
+use crate::parse_end;
+use crate::parse_program;
+use crate::parse_term;
+
+use la_term::Term;
+use la_term::symbol::Symbols;
+
+/// Parser of another notation, such as [`parse_latex`].
+///
+/// [`parse_latex`]: `crate::parse_latex`
+pub type Import = fn(&Symbols, &Scope, &str) -> Result<Term>;
+
+/// Parse a term in native notation.
+pub fn native(symbols: &Symbols, source: &str) -> Term
+{
+    let scope = Scope::new(None, []);
+    let mut lex = Lexer::new(source);
+    let term = parse_term(symbols, &scope, &mut lex).unwrap();
+    parse_end(&mut lex).unwrap();
+    term
+}
+
+/// Parse a program in native notation that ends in a term.
+///
+/// Returns that term and the operators declared by the program.
+pub fn program(symbols: &Symbols, source: &str) -> (Term, Notations)
+{
+    let scope = Scope::new(None, []);
+    let mut lex = Lexer::new(source);
+    let mut statements = parse_program(symbols, &scope, &mut lex).unwrap();
+    match statements.pop() {
+        Some(Statement::Term(term)) => (term, lex.notations().clone()),
+        other => panic!("{:?}", other),
+    }
+}
+
+/// Parse a term in another notation, with nothing in scope.
+pub fn import(parse: Import, symbols: &Symbols, source: &str) -> Result<Term>
+{
+    parse(symbols, &Scope::new(None, []), source)
+}
+
+/// The message of the error in a term in another notation.
+pub fn message(parse: Import, source: &str) -> String
+{
+    import(parse, &Symbols::new(), source).unwrap_err().message
+}
+
+/// Assert that a term in another notation
+/// means the same as a term in native notation.
+pub fn assert_imports(parse: Import, source: &str, expected: &str)
+{
+    let symbols = Symbols::new();
+    let term = import(parse, &symbols, source).unwrap();
+    let expected = native(&symbols, expected);
+    assert_eq!(format!("{:?}", term), format!("{:?}", expected),
+               "{}", source);
+}
@@ -0,0 +1,66 @@
+use crate::Context;
+use crate::lambda;
+
+use la_term::Term;
+use la_term::View;
+use la_term::lambda::Parameter;
+use la_term::lambda::Strictness;
+use std::rc::Rc;
+
+/// Simplify an application of `LetRec`.
+///
+/// `LetRec(|x, y| List(a, b), |x, y| c)` stands for `c`
+/// in which `x` and `y` are the fixed point of `a` and `b`.
+/// It is unfolded once, by substituting
+/// `LetRec(|x, y| List(a, b), |x, y| a)` for `x`
+/// and `LetRec(|x, y| List(a, b), |x, y| b)` for `y` in `c`.
+/// These are not simplified before they are substituted,
+/// so they are unfolded only where they are needed.
+pub fn simplify(c: &Context, arguments: &[Term]) -> Option<Term>
+{
+    if arguments.len() != 2 {
+        // TODO: Warn about arity of LetRec.
+        return None;
+    }
+
+    let (parameters, values) = match arguments[0].view() {
+        View::Lambda(parameters, values) => (parameters, values),
+        _ => return None,
+    };
+
+    let (body_parameters, body) = match arguments[1].view() {
+        View::Lambda(parameters, body) => (parameters, body),
+        _ => return None,
+    };
+
+    let values = match values.view() {
+        View::Application(list, values)
+            if list.eq_symbol(&c.constants.List) => values,
+        _ => return None,
+    };
+
+    if values.len() != parameters.len()
+        || body_parameters.len() != parameters.len()
+    {
+        return None;
+    }
+
+    let LetRec = c.constants.LetRec.term();
+    let unfolded: Vec<Term> =
+        values.iter()
+        .map(|value| {
+            let value = Term::lambda(parameters.clone(), value.clone());
+            Term::application(LetRec.clone(), [arguments[0].clone(), value])
+        })
+        .collect();
+
+    let non_strict: Rc<[Parameter]> =
+        body_parameters.iter()
+        .map(|parameter| Parameter{
+            strictness: Strictness::NonStrict,
+            name: parameter.name.clone(),
+        })
+        .collect();
+
+    lambda::apply(c, &non_strict, body, &unfolded)
+}
@@ -5,7 +5,7 @@ use crate::Context;
 
 use la_term::Term;
 use la_term::symbol::Symbol;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 
 macro_rules! builtins
 {
@@ -27,11 +27,12 @@ macro_rules! builtins
 
         /// Lookup table for implementations of builtins.
         ///
-        /// This is a hash table that associates some symbols
+        /// This is a table that associates some symbols
         /// with functions that simplify applications of those symbols.
+        /// It is ordered by symbol so that iteration is deterministic.
         pub struct Builtins
         {
-            jump_table: HashMap<Symbol, Builtin>,
+            jump_table: BTreeMap<Symbol, Builtin>,
         }
 
         impl Builtins
@@ -39,7 +40,7 @@ macro_rules! builtins
             /// Collect all builtins into the table.
             pub fn new(constants: &Constants) -> Self
             {
-                let mut jump_table = HashMap::<_, Builtin>::new();
+                let mut jump_table = BTreeMap::<_, Builtin>::new();
                 $(jump_table.insert(constants.$name.clone(), $name::simplify);)*
                 Self{jump_table}
             }
@@ -52,6 +53,12 @@ macro_rules! builtins
             {
                 self.jump_table.get(symbol).copied()
             }
+
+            /// Iterate over the builtins, in order of symbol IDs.
+            pub fn iter(&self) -> impl Iterator<Item=(&Symbol, Builtin)>
+            {
+                self.jump_table.iter().map(|(s, b)| (s, *b))
+            }
         }
 
     };
@@ -60,4 +67,5 @@ macro_rules! builtins
 builtins! {
     Derivative
     Cos Sin
+    LetRec
 }
@@ -123,6 +123,12 @@ macro_rules! constants
                     $($custom_name,)*
                 }
             }
+
+            /// The symbols in the table, in the order they are declared.
+            pub fn symbols(&self) -> impl Iterator<Item=&Symbol>
+            {
+                [$(&self.$symbol_name,)*].into_iter()
+            }
         }
 
     };
@@ -146,6 +152,7 @@ constants! {
         Add Ln Multiply Power
         Cos Sin Tan
         E Pi
+        LetRec List
         x
     }
 
@@ -0,0 +1,122 @@
+//! Simplification of applications of lambdas.
+
+use crate::Context;
+use crate::recurse;
+
+use la_term::Term;
+use la_term::View;
+use la_term::lambda::Parameter;
+use la_term::lambda::Strictness;
+use la_term::variable::DeBruijn;
+
+/// Apply a lambda to arguments by substituting them into its body.
+///
+/// Returns [`None`] if the number of arguments
+/// does not match the number of parameters.
+pub fn apply(
+    c: &Context,
+    parameters: &[Parameter],
+    body: &Term,
+    arguments: &[Term],
+) -> Option<Term>
+{
+    if parameters.len() != arguments.len() {
+        // TODO: Emit warning about wrong number of arguments.
+        return None;
+    }
+
+    let arguments: Vec<Term> =
+        Iterator::zip(parameters.iter(), arguments.iter())
+        .map(|(parameter, argument)| match parameter.strictness {
+            Strictness::Strict => recurse(c, argument.clone()),
+            Strictness::NonStrict => argument.clone(),
+        })
+        .collect();
+
+    let result = substitute(body, 0, &arguments);
+    Some(recurse(c, result))
+}
+
+/// Replace the variables bound by a lambda in its body by the arguments.
+///
+/// `depth` is the number of variables bound
+/// between the lambda and the given subterm of its body.
+/// Variables bound by the lambda are replaced by the arguments,
+/// and variables bound outside of the lambda are renumbered,
+/// as the lambda itself disappears.
+fn substitute(term: &Term, depth: u32, arguments: &[Term]) -> Term
+{
+    // Terms without free variables are unaffected.
+    if term.header().de_bruijn_cache.is_empty() {
+        return term.clone();
+    }
+
+    let count = arguments.len() as u32;
+    match term.view() {
+
+        View::Variable(DeBruijn(index)) if index < depth =>
+            term.clone(),
+
+        View::Variable(DeBruijn(index)) if index - depth < count =>
+            shift(&arguments[(index - depth) as usize], depth, 0),
+
+        View::Variable(DeBruijn(index)) =>
+            Term::variable(DeBruijn(index - count)),
+
+        View::Application(function, function_arguments) =>
+            Term::application(
+                substitute(function, depth, arguments),
+                function_arguments.iter()
+                    .map(|a| substitute(a, depth, arguments)),
+            ),
+
+        View::Lambda(parameters, body) => {
+            let depth = depth + parameters.len() as u32;
+            let body = substitute(body, depth, arguments);
+            Term::lambda(parameters.clone(), body)
+        },
+
+        View::Integer(..) => term.clone(),
+        View::String(..) => term.clone(),
+        View::Symbol(..) => term.clone(),
+
+    }
+}
+
+/// Renumber the free variables of a term that is moved
+/// underneath `amount` additional variable bindings.
+///
+/// Variables with De Bruijn indices below `cutoff`
+/// are bound within the term and are left alone.
+fn shift(term: &Term, amount: u32, cutoff: u32) -> Term
+{
+    if amount == 0 || term.header().de_bruijn_cache.is_empty() {
+        return term.clone();
+    }
+
+    match term.view() {
+
+        View::Variable(DeBruijn(index)) if index < cutoff =>
+            term.clone(),
+
+        View::Variable(DeBruijn(index)) =>
+            Term::variable(DeBruijn(index + amount)),
+
+        View::Application(function, arguments) =>
+            Term::application(
+                shift(function, amount, cutoff),
+                arguments.iter().map(|a| shift(a, amount, cutoff)),
+            ),
+
+        View::Lambda(parameters, body) => {
+            let cutoff = cutoff + parameters.len() as u32;
+            let body = shift(body, amount, cutoff);
+            Term::lambda(parameters.clone(), body)
+        },
+
+        View::Integer(..) => term.clone(),
+        View::String(..) => term.clone(),
+        View::Symbol(..) => term.clone(),
+
+    }
+}
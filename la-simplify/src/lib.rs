@@ -15,10 +15,11 @@ use self::builtins::Builtins;
 use la_term::Guard;
 use la_term::Term;
 use la_term::View;
+use la_term::generation::Generation;
 use la_term::symbol::Symbol;
 use la_term::symbol::Symbols;
 use std::cell::Cell;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::panic::panic_any;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::SeqCst;
@@ -26,6 +27,7 @@ use std::sync::atomic::Ordering::SeqCst;
 pub mod builtins;
 
 mod constants;
+mod lambda;
 
 /// Information threaded through the simplifier.
 ///
@@ -40,6 +42,12 @@ pub struct Context<'a>
     /// Once zero, simplification no longer descends.
     pub recursion_limit: Cell<usize>,
 
+    /// Set to true when [`simplify`] gives up
+    /// because the recursion limit was reached.
+    /// Terms simplified while this is set are not in normal form,
+    /// so they are not marked as such.
+    pub recursion_limit_reached: Cell<bool>,
+
     /// Set to true when the simplifier should stop.
     /// This will cause the simplifier to panic
     /// with [`StopRequested`] as the exception.
@@ -66,7 +74,16 @@ pub struct StopRequested;
 pub struct Session
 {
     /// Global definitions, as created with `:=`.
-    pub definitions: HashMap<Symbol, Term>,
+    /// Ordered by symbol so that iteration is deterministic.
+    definitions: BTreeMap<Symbol, Term>,
+
+    /// Help text for defined symbols,
+    /// taken from documentation comments preceding their definitions.
+    documentation: BTreeMap<Symbol, String>,
+
+    /// Generation in which simplified terms are marked as normal form.
+    /// A new generation is started whenever a definition changes.
+    generation: Generation,
 }
 
 impl Session
@@ -74,7 +91,58 @@ impl Session
     /// Create a session with no definitions.
     pub fn new() -> Self
     {
-        Self{definitions: HashMap::new()}
+        let definitions = BTreeMap::new();
+        let documentation = BTreeMap::new();
+        let generation = Generation::fresh();
+        Self{definitions, documentation, generation}
+    }
+
+    /// Get the definition of a symbol, if any.
+    pub fn definition(&self, symbol: &Symbol) -> Option<&Term>
+    {
+        self.definitions.get(symbol)
+    }
+
+    /// Iterate over the definitions, in order of symbol IDs.
+    pub fn definitions(&self) -> impl Iterator<Item=(&Symbol, &Term)>
+    {
+        self.definitions.iter()
+    }
+
+    /// Define or redefine a symbol.
+    ///
+    /// This starts a new generation, as terms that were in normal form
+    /// may no longer be in normal form under the new definition.
+    pub fn define(&mut self, symbol: Symbol, definition: Term)
+    {
+        self.definitions.insert(symbol, definition);
+        self.generation = Generation::fresh();
+    }
+
+    /// Get the help text of a symbol, if any.
+    pub fn documentation(&self, symbol: &Symbol) -> Option<&str>
+    {
+        self.documentation.get(symbol).map(String::as_str)
+    }
+
+    /// Set or remove the help text of a symbol.
+    ///
+    /// Unlike [`define`], this does not start a new generation,
+    /// as help text does not affect simplification.
+    ///
+    /// [`define`]: `Session::define`
+    pub fn document(&mut self, symbol: Symbol, documentation: Option<String>)
+    {
+        match documentation {
+            Some(text) => { self.documentation.insert(symbol, text); },
+            None => { self.documentation.remove(&symbol); },
+        }
+    }
+
+    /// The generation in which simplified terms are marked as normal form.
+    pub fn generation(&self) -> &Generation
+    {
+        &self.generation
     }
 }
 
@@ -105,8 +173,15 @@ pub fn recurse(c: &Context, term: Term) -> Term
 /// The exact semantics can be found in the Libre Algebra manual.
 pub fn simplify(c: &Context, term: Term) -> Term
 {
+    let generation = c.session.generation();
+
+    if term.is_normal_form(generation) {
+        return term;
+    }
+
     if c.recursion_limit.get() == 0 {
         // TODO: Emit warning about recursion depth reached.
+        c.recursion_limit_reached.set(true);
         return term;
     }
 
@@ -114,6 +189,27 @@ pub fn simplify(c: &Context, term: Term) -> Term
         panic_any(StopRequested);
     }
 
+    // Only the flag for this term is of interest,
+    // but the caller may need it for its own term too.
+    let reached_before = c.recursion_limit_reached.replace(false);
+    let _guard = Guard::new(|| {
+        c.recursion_limit_reached.set(
+            reached_before || c.recursion_limit_reached.get(),
+        );
+    });
+
+    let result = simplify_uncached(c, term);
+
+    if !c.recursion_limit_reached.get() {
+        result.set_normal_form(generation);
+    }
+
+    result
+}
+
+/// Like [`simplify`], but does not consult or update normal form marks.
+fn simplify_uncached(c: &Context, term: Term) -> Term
+{
     match term.view() {
 
         View::Application(function, arguments) =>
@@ -152,7 +248,13 @@ pub fn simplify_application(c: &Context, function: &Term, arguments: &[Term])
             c.builtins.get(builtin)
                 .and_then(|b| b(c, arguments)),
 
-        _ => todo!(),
+        // Lambdas are applied by substituting the arguments.
+        View::Lambda(parameters, body) =>
+            lambda::apply(c, parameters, body, arguments),
+
+        // Other terms cannot be applied,
+        // so the application remains as is.
+        _ => None,
 
     }
 }
@@ -161,7 +263,7 @@ pub fn simplify_application(c: &Context, function: &Term, arguments: &[Term])
 pub fn simplify_symbol(c: &Context, symbol: &Symbol) -> Term
 {
     // Look up the definition of the symbol.
-    let definition = c.session.definitions.get(symbol);
+    let definition = c.session.definition(symbol);
 
     match definition {
 
@@ -178,3 +280,223 @@ pub fn simplify_symbol(c: &Context, symbol: &Symbol) -> Term
 
     }
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use la_parse::Lexer;
+    use la_parse::Scope;
+    use la_parse::Statement;
+    use la_parse::parse_program;
+    use la_term::lambda::Parameter;
+    use la_term::lambda::Strictness;
+    use la_term::variable::DeBruijn;
+    use std::rc::Rc;
+
+    struct IgnoreWarnings;
+
+    impl Warner for IgnoreWarnings
+    {
+    }
+
+    /// Simplify a term in the given session.
+    fn run(symbols: &Symbols, session: &Session, term: Term) -> Term
+    {
+        let constants = Constants::new(symbols);
+        let builtins = Builtins::new(&constants);
+        let stop_requested = AtomicBool::new(false);
+        let context = Context{
+            recursion_limit: Cell::new(16),
+            recursion_limit_reached: Cell::new(false),
+            stop_requested: &stop_requested,
+            builtins: &builtins,
+            constants: &constants,
+            session,
+            symbols,
+            warner: &IgnoreWarnings,
+        };
+        simplify(&context, term)
+    }
+
+    /// Parse a program and run its statements,
+    /// returning the result of the last one, which must be a term,
+    /// and that term as it would be parsed on its own.
+    fn run_source(source: &str, expected: &str) -> (String, String)
+    {
+        let symbols = Symbols::new();
+        let scope = Scope::new(None, []);
+        let mut session = Session::new();
+        let mut result = None;
+        let mut lex = Lexer::new(source);
+        for statement in parse_program(&symbols, &scope, &mut lex).unwrap() {
+            match statement {
+                Statement::Definition{name, definition, ..} =>
+                    session.define(name, definition),
+                Statement::Term(term) =>
+                    result = Some(run(&symbols, &session, term)),
+                Statement::Notation(..) =>
+                    (),
+            }
+        }
+        let mut lex = Lexer::new(expected);
+        let expected = match parse_program(&symbols, &scope, &mut lex) {
+            Ok(mut statements) => match statements.pop() {
+                Some(Statement::Term(term)) => term,
+                other => panic!("{:?}", other),
+            },
+            Err(error) => panic!("{:?}", error),
+        };
+        (format!("{:?}", result.unwrap()), format!("{:?}", expected))
+    }
+
+    #[test]
+    fn normal_form_marks()
+    {
+        let symbols = Symbols::new();
+        let mut session = Session::new();
+        let a = symbols.get(b"a");
+        session.define(a.clone(), Term::integer_i32(1));
+
+        // A term marked as normal form is returned as is.
+        let term = Term::symbol(a.clone());
+        term.set_normal_form(session.generation());
+        assert!(run(&symbols, &session, term.clone()).ptr_eq(&term));
+
+        // Defining a symbol makes the marks stale.
+        session.define(a, Term::integer_i32(2));
+        let result = run(&symbols, &session, term);
+        assert!(matches!(result.view(), View::Integer(2)));
+        assert!(result.is_normal_form(session.generation()));
+    }
+
+    #[test]
+    fn normal_form_marks_after_many_definitions()
+    {
+        let symbols = Symbols::new();
+        let mut session = Session::new();
+        let a = symbols.get(b"a");
+        for i in 0 .. 1000 {
+            session.define(a.clone(), Term::integer_i32(i));
+        }
+        let result = run(&symbols, &session, Term::symbol(a));
+        assert!(matches!(result.view(), View::Integer(999)));
+        assert!(result.is_normal_form(session.generation()));
+    }
+
+    #[test]
+    fn lambda_application()
+    {
+        let symbols = Symbols::new();
+        let mut session = Session::new();
+        let f = symbols.get(b"f");
+        let g = symbols.get(b"g");
+        let x = symbols.get(b"x");
+        let y = symbols.get(b"y");
+        let parameter = |name: &Symbol| Parameter{
+            strictness: Strictness::Strict,
+            name: name.clone(),
+        };
+
+        // f := |x, y| g(y, x)
+        let body = Term::application(
+            Term::symbol(g.clone()),
+            [Term::variable(DeBruijn(1)), Term::variable(DeBruijn(0))],
+        );
+        let parameters: Rc<[Parameter]> =
+            vec![parameter(&x), parameter(&y)].into();
+        session.define(f.clone(), Term::lambda(parameters, body));
+
+        // f(1, 2) simplifies to g(2, 1).
+        let term = Term::application(
+            Term::symbol(f.clone()),
+            [Term::integer_i32(1), Term::integer_i32(2)],
+        );
+        let result = run(&symbols, &session, term);
+        match result.view() {
+            View::Application(function, arguments) => {
+                assert!(function.eq_symbol(&g));
+                assert!(matches!(arguments[0].view(), View::Integer(2)));
+                assert!(matches!(arguments[1].view(), View::Integer(1)));
+            },
+            _ => panic!("expected application"),
+        }
+
+        // Applications with the wrong number of arguments remain.
+        let term = Term::application(
+            Term::symbol(f),
+            [Term::integer_i32(1)],
+        );
+        let result = run(&symbols, &session, term.clone());
+        assert!(result.ptr_eq(&term));
+    }
+
+    #[test]
+    fn function_definition()
+    {
+        let (result, expected) =
+            run_source("f(x, y) := g(y, x); f(1, 2)", "g(2, 1)");
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn bindings()
+    {
+        let cases = [
+            ("let x = 1 in x", "1"),
+            ("x where x = 2", "2"),
+            ("let x = 1, y = g(x) in f(x, y)", "f(1, g(1))"),
+            ("let x = Sin(0), y = f(x) in y", "f(0)"),
+            ("with x = 1, y = 2 in f(y, x)", "f(2, 1)"),
+            ("x := 1; with x = 2, y = x in f(x, y)", "f(2, 1)"),
+            ("f(x, y) where x = 1, y = 2", "f(1, 2)"),
+            ("h(a) := g(a, b) where b = a; h(1)", "g(1, 1)"),
+            ("let rec x = Sin(y), y = 0 in x", "0"),
+            ("let rec f = |n| Sin(n), g = |n| f(n) in g(Pi)", "0"),
+            ("(let rec x = 0 in |y| Sin(x))(1)", "0"),
+        ];
+        for (source, expected) in cases {
+            let (result, expected) = run_source(source, expected);
+            assert_eq!(result, expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn lambda_application_shifts_free_variables()
+    {
+        let symbols = Symbols::new();
+        let session = Session::new();
+        let h = symbols.get(b"h");
+        let x = symbols.get(b"x");
+        let y = symbols.get(b"y");
+        let parameter = |name: &Symbol| Parameter{
+            strictness: Strictness::NonStrict,
+            name: name.clone(),
+        };
+
+        // (|x| |y| h(x, y))(#0) simplifies to |y| h(#1, #0).
+        let inner = Term::lambda(
+            vec![parameter(&y)].into(),
+            Term::application(
+                Term::symbol(h.clone()),
+                [Term::variable(DeBruijn(1)), Term::variable(DeBruijn(0))],
+            ),
+        );
+        let outer = Term::lambda(vec![parameter(&x)].into(), inner);
+        let term = Term::application(outer, [Term::variable(DeBruijn(0))]);
+        let result = run(&symbols, &session, term);
+        let (_, body) = match result.view() {
+            View::Lambda(parameters, body) => (parameters, body),
+            _ => panic!("expected lambda"),
+        };
+        match body.view() {
+            View::Application(function, arguments) => {
+                assert!(function.eq_symbol(&h));
+                assert!(arguments[0].eq_variable(DeBruijn(1)));
+                assert!(arguments[1].eq_variable(DeBruijn(0)));
+            },
+            _ => panic!("expected application"),
+        }
+    }
+}
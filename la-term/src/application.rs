@@ -94,3 +94,28 @@ impl Term
         (function, arguments)
     }
 }
+
+/// Drop the payload of an application term.
+///
+/// Returns the number of words the payload occupied.
+/// Terms that the payload refers to are moved into `children`
+/// instead of being dropped, so that the caller can release them
+/// without recursion.
+///
+/// # Safety
+///
+/// The payload must be that of an application term,
+/// and it must not be used after this call.
+pub(crate) unsafe fn drop_payload(
+    payload: *mut Payload,
+    children: &mut Vec<Term>,
+) -> usize
+{
+    let view = UnsafeView::new(payload);
+    let argument_count = *view.argument_count;
+    children.push(view.function.read());
+    for i in 0 .. argument_count {
+        children.push(view.arguments.add(i).read());
+    }
+    add(2, argument_count)
+}
@@ -0,0 +1,269 @@
+//! Property-based testing strategies for terms.
+//!
+//! This module is only available with the `proptest` feature.
+//! The strategies generate well-scoped terms of every kind:
+//! every variable in a generated term is bound by an enclosing lambda,
+//! or is one of the variables declared in scope by [`TermParameters`].
+//!
+//! Terms are generated in two steps.
+//! First a shape is generated, which is a plain tree
+//! that proptest knows how to shrink.
+//! Then the shape is turned into a term,
+//! at which point variables are assigned De Bruijn indices
+//! that refer to variables in scope.
+
+use crate::Term;
+use crate::View;
+use crate::lambda::Parameter;
+use crate::lambda::Strictness;
+use crate::symbol::Symbols;
+use crate::variable::DeBruijn;
+
+use proptest::collection::vec;
+use proptest::prop_oneof;
+use proptest::strategy::Just;
+use proptest::strategy::Strategy;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+/// Parameters for [`arbitrary_term`].
+#[derive(Clone, Copy, Debug)]
+pub struct TermParameters
+{
+    /// Maximum nesting depth of applications and lambdas.
+    pub depth: u32,
+
+    /// Desired number of nodes in the generated term.
+    pub size: u32,
+
+    /// Maximum number of arguments of an application,
+    /// and maximum number of parameters of a lambda.
+    pub breadth: usize,
+
+    /// Number of variables in scope of the generated term.
+    /// When zero, the generated terms are closed.
+    pub bound: u32,
+}
+
+impl Default for TermParameters
+{
+    fn default() -> Self
+    {
+        Self{depth: 4, size: 32, breadth: 4, bound: 0}
+    }
+}
+
+/// Names of symbols and lambda parameters in generated terms.
+///
+/// There are few of them so that shadowing and repetition are common.
+const NAMES: [&str; 4] = ["a", "b", "x", "y"];
+
+/// Tree from which a term is built.
+#[derive(Clone, Debug)]
+enum Shape
+{
+    Application(Box<Shape>, Vec<Shape>),
+    Integer(i32),
+    Lambda(Vec<(Strictness, usize)>, Box<Shape>),
+    String(Vec<u8>),
+    Symbol(usize),
+
+    /// The number is reduced modulo the number of variables in scope.
+    /// If there are no variables in scope, this becomes a symbol.
+    Variable(u32),
+}
+
+fn arbitrary_strictness() -> impl Strategy<Value=Strictness>
+{
+    prop_oneof![Just(Strictness::Strict), Just(Strictness::NonStrict)]
+}
+
+fn arbitrary_name() -> impl Strategy<Value=usize>
+{
+    0 .. NAMES.len()
+}
+
+fn arbitrary_shape(parameters: TermParameters) -> impl Strategy<Value=Shape>
+{
+    let leaf = prop_oneof![
+        proptest::num::i32::ANY.prop_map(Shape::Integer),
+        vec(proptest::num::u8::ANY, 0 .. 8).prop_map(Shape::String),
+        arbitrary_name().prop_map(Shape::Symbol),
+        proptest::num::u32::ANY.prop_map(Shape::Variable),
+    ];
+
+    let breadth = parameters.breadth;
+    leaf.prop_recursive(
+        parameters.depth,
+        parameters.size,
+        breadth as u32,
+        move |inner| prop_oneof![
+            (inner.clone(), vec(inner.clone(), 0 ..= breadth))
+                .prop_map(|(f, xs)| Shape::Application(Box::new(f), xs)),
+            (vec((arbitrary_strictness(), arbitrary_name()), 0 ..= breadth),
+             inner)
+                .prop_map(|(ps, b)| Shape::Lambda(ps, Box::new(b))),
+        ],
+    )
+}
+
+fn build(symbols: &Symbols, bound: u32, shape: &Shape) -> Term
+{
+    match shape {
+
+        Shape::Application(function, arguments) => {
+            let function = build(symbols, bound, function);
+            let arguments =
+                arguments.iter()
+                .map(|argument| build(symbols, bound, argument))
+                .collect::<Vec<_>>();
+            Term::application(function, arguments)
+        },
+
+        Shape::Integer(value) =>
+            Term::integer_i32(*value),
+
+        Shape::Lambda(parameters, body) => {
+            let parameters =
+                parameters.iter()
+                .map(|&(strictness, name)| {
+                    let name = symbols.get(NAMES[name].as_bytes());
+                    Parameter{strictness, name}
+                })
+                .collect::<Rc<[_]>>();
+            let bound = bound + parameters.len() as u32;
+            let body = build(symbols, bound, body);
+            Term::lambda(parameters, body)
+        },
+
+        Shape::String(bytes) =>
+            Term::string(bytes.iter().copied()),
+
+        Shape::Symbol(name) =>
+            symbols.get(NAMES[*name].as_bytes()).term(),
+
+        Shape::Variable(_) if bound == 0 =>
+            symbols.get(NAMES[0].as_bytes()).term(),
+
+        Shape::Variable(index) =>
+            Term::variable(DeBruijn(index % bound)),
+
+    }
+}
+
+/// Strategy that generates well-scoped terms.
+///
+/// Symbols and parameter names are interned in the given table.
+/// Each generated term is checked with [`assert_de_bruijn_cache`].
+pub fn arbitrary_term(symbols: Rc<Symbols>, parameters: TermParameters)
+    -> impl Strategy<Value=Term>
+{
+    arbitrary_shape(parameters).prop_map(move |shape| {
+        let term = build(&symbols, parameters.bound, &shape);
+        assert_de_bruijn_cache(&term);
+        term
+    })
+}
+
+/// Find the variables that appear free in a term, by traversing it.
+///
+/// This is the brute-force counterpart to the De Bruijn cache.
+pub fn free_variables(term: &Term) -> BTreeSet<DeBruijn>
+{
+    fn go(term: &Term, binders: u32, free: &mut BTreeSet<DeBruijn>)
+    {
+        match term.view() {
+            View::Application(function, arguments) => {
+                go(function, binders, free);
+                for argument in arguments {
+                    go(argument, binders, free);
+                }
+            },
+            View::Lambda(parameters, body) =>
+                go(body, binders + parameters.len() as u32, free),
+            View::Variable(de_bruijn) if de_bruijn.0 >= binders => {
+                free.insert(DeBruijn(de_bruijn.0 - binders));
+            },
+            View::Integer(..) => (),
+            View::String(..) => (),
+            View::Symbol(..) => (),
+            View::Variable(..) => (),
+        }
+    }
+
+    let mut free = BTreeSet::new();
+    go(term, 0, &mut free);
+    free
+}
+
+/// Assert that the De Bruijn cache of the term and each of its subterms
+/// agrees with the brute-force computation by [`free_variables`].
+///
+/// A cache in the “unknown” state agrees with any set of free variables.
+pub fn assert_de_bruijn_cache(term: &Term)
+{
+    let cache = term.header().de_bruijn_cache;
+    let free = free_variables(term);
+
+    // Indices beyond 16 cannot be in a cache that is not “unknown”.
+    let max = free.iter().map(|v| v.0 + 1).max().unwrap_or(0).max(17);
+    for de_bruijn in (0 .. max).map(DeBruijn) {
+        if let Some(contains) = cache.contains(de_bruijn) {
+            assert_eq!(
+                contains, free.contains(&de_bruijn),
+                "De Bruijn cache disagrees about {:?} in {:?}",
+                de_bruijn, term,
+            );
+        }
+    }
+
+    match term.view() {
+        View::Application(function, arguments) => {
+            assert_de_bruijn_cache(function);
+            arguments.iter().for_each(assert_de_bruijn_cache);
+        },
+        View::Lambda(_, body) => assert_de_bruijn_cache(body),
+        View::Integer(..) => (),
+        View::String(..) => (),
+        View::Symbol(..) => (),
+        View::Variable(..) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use proptest::test_runner::TestRunner;
+
+    fn run(parameters: TermParameters)
+    {
+        let symbols = Rc::new(Symbols::new());
+        let strategy = arbitrary_term(symbols, parameters);
+        let mut runner = TestRunner::default();
+        runner.run(&strategy, |term| {
+            let free = free_variables(&term);
+            assert!(free.iter().all(|v| v.0 < parameters.bound));
+            Ok(())
+        }).unwrap();
+    }
+
+    #[test]
+    fn closed_terms_are_closed()
+    {
+        run(TermParameters::default());
+    }
+
+    #[test]
+    fn open_terms_are_well_scoped()
+    {
+        run(TermParameters{bound: 3, ..TermParameters::default()});
+    }
+
+    #[test]
+    fn deep_terms_are_well_scoped()
+    {
+        run(TermParameters{depth: 12, size: 64, breadth: 2, bound: 20});
+    }
+}
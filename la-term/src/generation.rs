@@ -0,0 +1,265 @@
+//! Tracking which terms are known to be in normal form.
+//!
+//! Whether a term is in normal form depends on the definitions
+//! and builtins in effect when it was simplified.
+//! Each time these change, a new generation is started,
+//! and the normal form marks of the previous generation become stale.
+//!
+//! The header of each term records the epoch of the generation
+//! in which the term was last found to be in normal form.
+//! Epochs are a single byte, so they must be reused.
+//! Each thread counts the users of each epoch,
+//! that is the generations that have it and the terms marked with it.
+//! An epoch is handed out again only once it has no users left,
+//! so a stale mark is never mistaken for a current one.
+
+use crate::Term;
+
+use std::cell::Cell;
+use std::marker::PhantomData;
+
+/// Identifies a set of definitions and builtins.
+///
+/// Each generation has an epoch that no other generation on the thread has.
+/// If all epochs are in use, [`Generation::fresh`] returns
+/// [`Generation::NONE`], under which no terms are marked.
+#[derive(Debug, Eq, PartialEq)]
+pub struct Generation
+{
+    epoch: u8,
+
+    /// Epochs are counted per thread.
+    _thread: PhantomData<*const ()>,
+}
+
+/// Number of users of each epoch on a thread.
+struct Epochs
+{
+    /// Number of users of each epoch, indexed by the epoch.
+    /// Epoch zero is that of [`Generation::NONE`] and is not counted.
+    users: [Cell<usize>; 256],
+
+    /// Epoch from which to search for an unused epoch,
+    /// so that epochs are reused as late as possible.
+    next: Cell<u8>,
+
+    /// Number of live generations other than [`Generation::NONE`],
+    /// so that the marked terms can be told from them among the users.
+    generations: Cell<usize>,
+}
+
+impl Epochs
+{
+    /// Create the counts for a thread on which no epoch is in use.
+    fn new() -> Self
+    {
+        #[allow(clippy::declare_interior_mutable_const)]
+        const UNUSED: Cell<usize> = Cell::new(0);
+        let users = [UNUSED; 256];
+        Self{users, next: Cell::new(1), generations: Cell::new(0)}
+    }
+}
+
+thread_local!
+{
+    static EPOCHS: Epochs = Epochs::new();
+}
+
+impl Generation
+{
+    /// The generation of terms not known to be in normal form.
+    pub const NONE: Self = Self{epoch: 0, _thread: PhantomData};
+
+    /// Obtain a generation with an epoch that is not in use.
+    ///
+    /// Returns [`Generation::NONE`] if every epoch is in use.
+    pub fn fresh() -> Self
+    {
+        EPOCHS.with(|epochs| {
+            let start = epochs.next.get() as usize;
+            let count = u8::MAX as usize;
+            let unused =
+                (0 .. count)
+                .map(|offset| (start - 1 + offset) % count + 1)
+                .find(|&epoch| epochs.users[epoch].get() == 0);
+            match unused {
+                None => Self::NONE,
+                Some(epoch) => {
+                    epochs.users[epoch].set(1);
+                    epochs.generations.set(epochs.generations.get() + 1);
+                    epochs.next.set((epoch % count + 1) as u8);
+                    Self{epoch: epoch as u8, _thread: PhantomData}
+                },
+            }
+        })
+    }
+}
+
+impl Drop for Generation
+{
+    fn drop(&mut self)
+    {
+        if self.epoch != 0 {
+            let _ = EPOCHS.try_with(|epochs| {
+                epochs.generations.set(epochs.generations.get() - 1);
+            });
+        }
+        release(self.epoch);
+    }
+}
+
+impl Term
+{
+    /// Whether the term is known to be in normal form
+    /// in the given generation.
+    pub fn is_normal_form(&self, generation: &Generation) -> bool
+    {
+        generation.epoch != 0
+            && self.header().normal_form == generation.epoch
+    }
+
+    /// Record that the term is in normal form in the given generation.
+    ///
+    /// This replaces any generation previously recorded for the term.
+    /// Recording [`Generation::NONE`] removes the mark.
+    pub fn set_normal_form(&self, generation: &Generation)
+    {
+        let previous = self.header().normal_form;
+        if previous != generation.epoch {
+            acquire(generation.epoch);
+            release(previous);
+            unsafe {
+                (*self.as_ptr()).header.normal_form = generation.epoch;
+            }
+        }
+    }
+}
+
+/// Record a new user of the given epoch.
+fn acquire(epoch: u8)
+{
+    if epoch != 0 {
+        EPOCHS.with(|epochs| {
+            let users = &epochs.users[epoch as usize];
+            users.set(users.get() + 1);
+        });
+    }
+}
+
+/// Record that a user of the given epoch is gone.
+///
+/// This is a no-op once the thread is being torn down.
+fn release(epoch: u8)
+{
+    if epoch != 0 {
+        let _ = EPOCHS.try_with(|epochs| {
+            let users = &epochs.users[epoch as usize];
+            users.set(users.get() - 1);
+        });
+    }
+}
+
+/// Remove the mark of a term that is being deallocated,
+/// so that its epoch can be reused.
+pub(crate) fn forget(epoch: u8)
+{
+    release(epoch);
+}
+
+/// Number of terms on this thread that are marked as being in normal form,
+/// in any generation.
+#[cfg(feature = "statistics")]
+pub(crate) fn marked_terms() -> usize
+{
+    EPOCHS.with(|epochs| {
+        let users: usize = epochs.users.iter().map(Cell::get).sum();
+        users - epochs.generations.get()
+    })
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use std::iter::repeat_with;
+
+    /// Obtain generations until every epoch is in use.
+    fn exhaust() -> Vec<Generation>
+    {
+        repeat_with(Generation::fresh)
+            .take_while(|generation| *generation != Generation::NONE)
+            .collect()
+    }
+
+    #[test]
+    fn fresh_generations_are_distinct()
+    {
+        let a = Generation::fresh();
+        let b = Generation::fresh();
+        assert_ne!(a, b);
+        assert_ne!(a, Generation::NONE);
+        assert_ne!(b, Generation::NONE);
+    }
+
+    #[test]
+    fn new_term_is_not_normal_form()
+    {
+        let generation = Generation::fresh();
+        let term = Term::integer_i32(0);
+        assert!(!term.is_normal_form(&generation));
+        assert!(!term.is_normal_form(&Generation::NONE));
+    }
+
+    #[test]
+    fn set_normal_form_is_per_generation()
+    {
+        let a = Generation::fresh();
+        let b = Generation::fresh();
+        let term = Term::integer_i32(0);
+        term.set_normal_form(&a);
+        assert!(term.is_normal_form(&a));
+        assert!(!term.is_normal_form(&b));
+        term.set_normal_form(&b);
+        assert!(!term.is_normal_form(&a));
+        assert!(term.is_normal_form(&b));
+        term.set_normal_form(&Generation::NONE);
+        assert!(!term.is_normal_form(&b));
+    }
+
+    #[test]
+    fn stale_marks_are_never_current()
+    {
+        // Many more generations than there are epochs.
+        let term = Term::integer_i32(0);
+        term.set_normal_form(&Generation::fresh());
+        for _ in 0 .. 1000 {
+            let generation = Generation::fresh();
+            assert_ne!(generation, Generation::NONE);
+            assert!(!term.is_normal_form(&generation));
+        }
+    }
+
+    #[test]
+    fn epochs_are_reused_once_unused()
+    {
+        let mut generations = exhaust();
+        assert!(!generations.is_empty());
+        assert_eq!(Generation::fresh(), Generation::NONE);
+
+        // The marked term keeps the epoch in use.
+        let term = Term::integer_i32(0);
+        let generation = generations.pop().unwrap();
+        term.set_normal_form(&generation);
+        drop(generation);
+        assert_eq!(Generation::fresh(), Generation::NONE);
+
+        drop(term);
+        let generation = Generation::fresh();
+        assert_ne!(generation, Generation::NONE);
+        assert_eq!(exhaust().len(), 0);
+
+        drop(generations);
+        assert_eq!(exhaust().len(), usize::from(u8::MAX) - 1);
+    }
+}
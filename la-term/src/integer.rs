@@ -66,6 +66,22 @@ impl Term
     }
 }
 
+/// Drop the payload of an integer term.
+///
+/// Returns the number of words the payload occupied.
+///
+/// # Safety
+///
+/// The payload must be that of an integer term,
+/// and it must not be used after this call.
+pub(crate) unsafe fn drop_payload(
+    _payload: *mut Payload,
+    _children: &mut Vec<Term>,
+) -> usize
+{
+    1
+}
+
 #[cfg(test)]
 mod tests
 {
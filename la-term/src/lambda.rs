@@ -10,6 +10,7 @@ use crate::Payload;
 use crate::Term;
 use crate::symbol::Symbol;
 
+use std::ptr::drop_in_place;
 use std::rc::Rc;
 
 /// Information about a lambda parameter.
@@ -100,3 +101,25 @@ impl Term
         (parameters, body)
     }
 }
+
+/// Drop the payload of a lambda term.
+///
+/// Returns the number of words the payload occupied.
+/// Terms that the payload refers to are moved into `children`
+/// instead of being dropped, so that the caller can release them
+/// without recursion.
+///
+/// # Safety
+///
+/// The payload must be that of a lambda term,
+/// and it must not be used after this call.
+pub(crate) unsafe fn drop_payload(
+    payload: *mut Payload,
+    children: &mut Vec<Term>,
+) -> usize
+{
+    let view = UnsafeView::new(payload);
+    drop_in_place(view.parameters);
+    children.push(view.body.read());
+    3
+}
@@ -16,6 +16,7 @@ use std::alloc::alloc;
 use std::alloc::dealloc;
 use std::alloc::handle_alloc_error;
 use std::fmt;
+use std::mem::ManuallyDrop;
 use std::mem::align_of;
 use std::mem::size_of;
 use std::process::abort;
@@ -23,12 +24,19 @@ use std::ptr::NonNull;
 use std::rc::Rc;
 
 pub mod application;
+pub mod generation;
 pub mod integer;
 pub mod lambda;
 pub mod string;
 pub mod symbol;
 pub mod variable;
 
+#[cfg(any(test, feature = "proptest"))]
+pub mod arbitrary;
+
+#[cfg(feature = "statistics")]
+pub mod statistics;
+
 mod guard;
 
 #[cold]
@@ -109,6 +117,9 @@ impl Term
         (*ptr.as_ptr()).header = init(&mut (*ptr.as_ptr()).payload);
         guard.skip(); // ... but not if init returns.
 
+        #[cfg(feature = "statistics")]
+        statistics::record_alloc((*ptr.as_ptr()).header.kind, layout.size());
+
         Self{ptr}
     }
 
@@ -181,12 +192,72 @@ impl Clone for Term
     }
 }
 
+impl Term
+{
+    /// Decrement the reference count of the term.
+    ///
+    /// Returns whether this was the last reference,
+    /// in which case the term must be deallocated.
+    unsafe fn release(&self) -> bool
+    {
+        let ref_count: *mut u32 = &mut (*self.as_ptr()).header.ref_count;
+        *ref_count -= 1;
+        *ref_count == 0
+    }
+
+    /// Deallocate a term whose last reference was released.
+    ///
+    /// The terms it refers to are moved into `children` to be released,
+    /// rather than released here.
+    unsafe fn deallocate(&self, children: &mut Vec<Term>)
+    {
+        let normal_form = self.header().normal_form;
+        if normal_form != 0 {
+            generation::forget(normal_form);
+        }
+
+        // SAFETY: This was the last reference to the term,
+        //         so nothing uses the payload after it is dropped.
+        let kind = self.header().kind;
+        let payload = self.payload();
+        let payload_words = match kind {
+            Kind::Application => application::drop_payload(payload, children),
+            Kind::Integer => integer::drop_payload(payload, children),
+            Kind::Lambda => lambda::drop_payload(payload, children),
+            Kind::String => string::drop_payload(payload, children),
+            Kind::Symbol => symbol::drop_payload(payload, children),
+            Kind::Variable => variable::drop_payload(payload, children),
+        };
+
+        let layout = Self::layout(payload_words);
+
+        #[cfg(feature = "statistics")]
+        statistics::record_dealloc(kind, layout.size());
+
+        dealloc(self.as_ptr() as *mut u8, layout);
+    }
+}
+
+/// Dropping a term releases the terms it refers to using a work list,
+/// rather than by recursion, so that deeply nested terms
+/// do not overflow the stack when they are dropped.
 impl Drop for Term
 {
     fn drop(&mut self)
     {
-        // TODO: Call correct destructor depending on kind.
-        // TODO: Deallocate memory after obtaining layout.
+        unsafe {
+            if !self.release() {
+                return;
+            }
+            let mut children = Vec::new();
+            self.deallocate(&mut children);
+            while let Some(child) = children.pop() {
+                let child = ManuallyDrop::new(child);
+                if child.release() {
+                    child.deallocate(&mut children);
+                }
+            }
+        }
     }
 }
 
@@ -226,6 +297,11 @@ pub mod object
         /// Which kind of term this is.
         pub kind: Kind,
 
+        /// Epoch of the generation in which the term is marked
+        /// as being in normal form, or zero if it is not marked;
+        /// see [`generation`](`crate::generation`).
+        pub normal_form: u8,
+
         /// See [`DeBruijnCache`].
         pub de_bruijn_cache: DeBruijnCache,
     }
@@ -233,9 +309,12 @@ pub mod object
     impl Header
     {
         /// Create a header with a reference count of one.
+        ///
+        /// The term is not marked as being in normal form.
         pub fn new(kind: Kind, de_bruijn_cache: DeBruijnCache) -> Self
         {
-            Self{ref_count: 1, kind, de_bruijn_cache}
+            let normal_form = 0;
+            Self{ref_count: 1, kind, normal_form, de_bruijn_cache}
         }
     }
 
@@ -256,6 +335,19 @@ pub mod object
         Variable,
     }
 
+    impl Kind
+    {
+        /// Every kind of term, in declaration order.
+        pub const ALL: [Kind; 6] = [
+            Kind::Application,
+            Kind::Integer,
+            Kind::Lambda,
+            Kind::String,
+            Kind::Symbol,
+            Kind::Variable,
+        ];
+    }
+
     extern
     {
         /// Kind-specific data for the term.
@@ -277,6 +369,18 @@ mod tests
         assert_eq!(align_of::<Term>(), align_of::<usize>());
     }
 
+    /// Test that dropping a deeply nested term does not overflow the stack.
+    #[test]
+    fn drop_deep_term()
+    {
+        let f = Term::integer_i32(0);
+        let mut term = Term::integer_i32(1);
+        for _ in 0 .. 1_000_000 {
+            term = Term::application(f.clone(), [term]);
+        }
+        drop(term);
+    }
+
     /// Test that the `Header` type has a size of 8.
     /// This is the expected size of the header type,
     /// and if it is larger then something went wrong.
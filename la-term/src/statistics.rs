@@ -0,0 +1,165 @@
+//! Memory usage statistics for terms.
+//!
+//! This module is only available with the `statistics` feature.
+//! When enabled, each allocation and deallocation of a term
+//! updates a set of per-thread counters.
+//! As terms cannot be sent across threads,
+//! the counters of a thread describe all the terms it owns.
+
+use crate::Kind;
+use crate::symbol::Symbols;
+
+use std::cell::Cell;
+
+/// Snapshot of the memory usage statistics of the current thread.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Statistics
+{
+    /// Number of live terms of each kind, indexed by `Kind as usize`.
+    live_terms: [usize; Kind::ALL.len()],
+
+    /// Number of bytes occupied by live terms.
+    pub live_bytes: usize,
+
+    /// Highest value that `live_bytes` has had.
+    pub peak_bytes: usize,
+
+    /// Number of bytes ever allocated for terms,
+    /// including those that have since been deallocated.
+    pub total_bytes: usize,
+
+    /// Number of live terms that are marked as being in normal form;
+    /// see [`generation`](`crate::generation`).
+    pub normal_form_marks: usize,
+
+    /// Number of symbols in the table given to [`snapshot`].
+    pub symbols: usize,
+}
+
+impl Statistics
+{
+    /// Number of live terms of the given kind.
+    pub fn live_terms(&self, kind: Kind) -> usize
+    {
+        self.live_terms[kind as usize]
+    }
+
+    /// Number of live terms of any kind.
+    pub fn live_terms_total(&self) -> usize
+    {
+        self.live_terms.iter().sum()
+    }
+}
+
+thread_local!
+{
+    static STATISTICS: Cell<Statistics> = Cell::default();
+}
+
+/// Take a snapshot of the statistics of the current thread,
+/// and of the given symbol table.
+pub fn snapshot(symbols: &Symbols) -> Statistics
+{
+    let statistics = STATISTICS.with(Cell::get);
+    let normal_form_marks = crate::generation::marked_terms();
+    let symbols = symbols.len();
+    Statistics{normal_form_marks, symbols, ..statistics}
+}
+
+/// Record that a term of the given kind and size was allocated.
+pub(crate) fn record_alloc(kind: Kind, bytes: usize)
+{
+    STATISTICS.with(|statistics| {
+        let mut s = statistics.get();
+        s.live_terms[kind as usize] += 1;
+        s.live_bytes += bytes;
+        s.peak_bytes = s.peak_bytes.max(s.live_bytes);
+        s.total_bytes = s.total_bytes.saturating_add(bytes);
+        statistics.set(s);
+    });
+}
+
+/// Record that a term of the given kind and size was deallocated.
+pub(crate) fn record_dealloc(kind: Kind, bytes: usize)
+{
+    STATISTICS.with(|statistics| {
+        let mut s = statistics.get();
+        s.live_terms[kind as usize] -= 1;
+        s.live_bytes -= bytes;
+        statistics.set(s);
+    });
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use crate::Term;
+    use crate::generation::Generation;
+
+    #[test]
+    fn alloc_and_dealloc()
+    {
+        let symbols = Symbols::new();
+        let before = snapshot(&symbols);
+
+        let term = Term::integer_i32(0);
+        let during = snapshot(&symbols);
+        assert_eq!(during.live_terms(Kind::Integer),
+                   before.live_terms(Kind::Integer) + 1);
+        assert!(during.live_bytes > before.live_bytes);
+        assert!(during.peak_bytes >= during.live_bytes);
+
+        drop(term);
+        let after = snapshot(&symbols);
+        assert_eq!(after.live_terms(Kind::Integer),
+                   before.live_terms(Kind::Integer));
+        assert_eq!(after.live_bytes, before.live_bytes);
+        assert_eq!(after.peak_bytes, during.peak_bytes);
+        assert_eq!(after.total_bytes, during.total_bytes);
+    }
+
+    #[test]
+    fn dealloc_only_when_unreferenced()
+    {
+        let symbols = Symbols::new();
+        let before = snapshot(&symbols);
+
+        let function = Term::integer_i32(0);
+        let term = Term::application(function.clone(), [function.clone()]);
+        drop(function);
+        assert_eq!(snapshot(&symbols).live_terms_total(),
+                   before.live_terms_total() + 2);
+
+        drop(term);
+        assert_eq!(snapshot(&symbols).live_terms_total(),
+                   before.live_terms_total());
+    }
+
+    #[test]
+    fn normal_form_marks()
+    {
+        let symbols = Symbols::new();
+        let before = snapshot(&symbols);
+
+        let generation = Generation::fresh();
+        let term = Term::integer_i32(0);
+        term.set_normal_form(&generation);
+        assert_eq!(snapshot(&symbols).normal_form_marks,
+                   before.normal_form_marks + 1);
+
+        drop(term);
+        assert_eq!(snapshot(&symbols).normal_form_marks,
+                   before.normal_form_marks);
+    }
+
+    #[test]
+    fn symbols()
+    {
+        let symbols = Symbols::new();
+        let before = snapshot(&symbols).symbols;
+        let _symbol = symbols.get(b"x");
+        assert_eq!(snapshot(&symbols).symbols, before + 1);
+    }
+}
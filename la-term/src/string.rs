@@ -83,3 +83,20 @@ impl Term
         slice::from_raw_parts(view.bytes, byte_count)
     }
 }
+
+/// Drop the payload of a string term.
+///
+/// Returns the number of words the payload occupied.
+///
+/// # Safety
+///
+/// The payload must be that of a string term,
+/// and it must not be used after this call.
+pub(crate) unsafe fn drop_payload(
+    payload: *mut Payload,
+    _children: &mut Vec<Term>,
+) -> usize
+{
+    let view = UnsafeView::new(payload);
+    add(1, round_to_words(*view.byte_count))
+}
@@ -1,8 +1,9 @@
 //! Working with symbol terms.
 //!
-//! The payload of a symbol term contains 1 + ⌈_n_ / _s_⌉ words,
+//! The payload of a symbol term contains 2 + ⌈_n_ / _s_⌉ words,
 //! where _n_ is the number of bytes in the name, and _s_ is the word size.
-//! The first word records the number of bytes in the name.
+//! The first word records the ID of the symbol; see [`Symbol::id`].
+//! The second word records the number of bytes in the name.
 //! The remaining words record the bytes of the name.
 
 use crate::Header;
@@ -14,7 +15,9 @@ use crate::add;
 use crate::variable::DeBruijnCache;
 
 use std::borrow::Borrow;
+use std::cell::Cell;
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::collections::HashSet;
 use std::fmt;
 use std::hash::Hash;
@@ -22,6 +25,7 @@ use std::hash::Hasher;
 use std::mem::size_of;
 use std::ptr::copy;
 use std::slice;
+use std::vec;
 
 ////////////////////////////////////////////////////////////////////////////////
 // Symbol terms
@@ -31,6 +35,7 @@ use std::slice;
 #[derive(Clone, Copy)]
 pub struct UnsafeView
 {
+    pub id: *mut usize,
     pub name_len: *mut usize,
     pub name: *mut u8,
 }
@@ -46,8 +51,9 @@ impl UnsafeView
     {
         let payload = payload as *mut usize;
         Self{
-            name_len: payload,
-            name: payload.add(1) as *mut u8,
+            id: payload,
+            name_len: payload.add(1),
+            name: payload.add(2) as *mut u8,
         }
     }
 }
@@ -67,13 +73,14 @@ impl Term
     }
 
     /// Create a symbol term.
-    fn symbol_uninterned(name: &[u8]) -> Term
+    fn symbol_uninterned(id: usize, name: &[u8]) -> Term
     {
         let name_words = round_to_words(name.len());
-        let payload_words = add(1, name_words);
+        let payload_words = add(2, name_words);
         unsafe {
             Self::new(payload_words, |payload| {
                 let view = UnsafeView::new(payload);
+                view.id.write(id);
                 view.name_len.write(name.len());
                 copy(name.as_ptr(), view.name, name.len());
                 Header::new(Kind::Symbol, DeBruijnCache::EMPTY)
@@ -114,17 +121,38 @@ impl Term
     }
 }
 
+/// Drop the payload of a symbol term.
+///
+/// Returns the number of words the payload occupied.
+///
+/// # Safety
+///
+/// The payload must be that of a symbol term,
+/// and it must not be used after this call.
+pub(crate) unsafe fn drop_payload(
+    payload: *mut Payload,
+    _children: &mut Vec<Term>,
+) -> usize
+{
+    let view = UnsafeView::new(payload);
+    add(2, round_to_words(*view.name_len))
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // Symbol type
 
 /// Handle to a symbol term.
 ///
-/// As symbols are interned, the `PartialEq` and `Hash` impls for this type
-/// operate on the addresses of the terms (as opposed to their payloads).
+/// As symbols are interned, the `PartialEq` impl for this type
+/// operates on the addresses of the terms (as opposed to their names).
 /// As a result we can check symbol equality in _O(1)_ time.
+/// The `Hash` and `Ord` impls use the ID of the symbol instead,
+/// so that hashing and ordering are the same in each run of the program.
+/// Only symbols from the same table should be compared.
 ///
 /// You cannot create symbols directly.
-/// Symbol creation happens only through [`Symbols::get`].
+/// Symbol creation happens only through [`Symbols::get`]
+/// and [`Symbols::fresh`].
 #[derive(Clone)]
 #[repr(transparent)]
 pub struct Symbol
@@ -153,6 +181,21 @@ impl Symbol
             slice::from_raw_parts(view.name, *view.name_len)
         }
     }
+
+    /// The ID of the symbol.
+    ///
+    /// Symbols are numbered sequentially in the order they are interned,
+    /// starting at zero for the first symbol in the table.
+    /// Interning the same names in the same order
+    /// thus always assigns the same IDs.
+    pub fn id(&self) -> usize
+    {
+        let payload = self.inner.payload();
+        unsafe {
+            let view = UnsafeView::new(payload);
+            *view.id
+        }
+    }
 }
 
 impl PartialEq for Symbol
@@ -172,7 +215,30 @@ impl Hash for Symbol
     fn hash<H>(&self, state: &mut H)
         where H: Hasher
     {
-        self.inner.as_ptr().hash(state)
+        self.id().hash(state)
+    }
+}
+
+impl PartialOrd for Symbol
+{
+    fn partial_cmp(&self, rhs: &Symbol) -> Option<Ordering>
+    {
+        Some(self.cmp(rhs))
+    }
+}
+
+impl Ord for Symbol
+{
+    fn cmp(&self, rhs: &Symbol) -> Ordering
+    {
+        // Symbols from different tables may have the same ID.
+        // Break the tie so that the ordering is consistent with `Eq`.
+        self.id().cmp(&rhs.id())
+            .then_with(|| {
+                let lhs = self.inner.as_ptr() as *const u8;
+                let rhs = rhs.inner.as_ptr() as *const u8;
+                lhs.cmp(&rhs)
+            })
     }
 }
 
@@ -190,9 +256,25 @@ impl fmt::Debug for Symbol
 // Symbol interning
 
 /// Table of interned symbols.
+///
+/// Symbols that are referenced only by the table are evicted
+/// by [`Symbols::collect_garbage`].
+/// This happens automatically when the table has doubled in size
+/// since the previous collection.
+/// Evicting a symbol is not observable, as nothing references it;
+/// getting a symbol with the same name again creates a new symbol.
 pub struct Symbols
 {
     symbols: RefCell<HashSet<Entry>>,
+
+    /// Size the table must reach before the next automatic collection.
+    collect_at: Cell<usize>,
+
+    /// Number appended to the name of the next fresh symbol.
+    next_fresh: Cell<u64>,
+
+    /// ID of the next symbol to be created.
+    next_id: Cell<usize>,
 }
 
 /// Entry of the table of interned symbols.
@@ -233,24 +315,192 @@ impl Borrow<[u8]> for Entry
     }
 }
 
+impl Entry
+{
+    /// Whether the table holds the only reference to the symbol.
+    fn is_garbage(&self) -> bool
+    {
+        self.inner.inner.header().ref_count == 1
+    }
+}
+
+/// Size below which the table is not automatically collected.
+const MIN_COLLECT_AT: usize = 1024;
+
 impl Symbols
 {
     /// Create a new table with no symbols.
     pub fn new() -> Self
     {
-        Self{symbols: RefCell::new(HashSet::new())}
+        Self{
+            symbols: RefCell::new(HashSet::new()),
+            collect_at: Cell::new(MIN_COLLECT_AT),
+            next_fresh: Cell::new(0),
+            next_id: Cell::new(0),
+        }
     }
 
     /// Get or create the symbol with the given name.
     ///
     /// When a symbol with the same name was already created,
+    /// and it was not since evicted,
     /// this method will return that same symbol term.
     pub fn get(&self, name: &[u8]) -> Symbol
     {
+        if self.len() >= self.collect_at.get() {
+            self.collect_garbage();
+        }
         let mut symbols = self.symbols.borrow_mut();
         symbols.get_or_insert_with(name, |name| {
-            let symbol_term = Term::symbol_uninterned(name);
+            let id = self.next_id.get();
+            self.next_id.set(id + 1);
+            let symbol_term = Term::symbol_uninterned(id, name);
             Entry{inner: Symbol{inner: symbol_term}}
         }).inner.clone()
     }
+
+    /// Create a symbol whose name is not that of any symbol in the table.
+    ///
+    /// The name is the prefix followed by `$` and a number, e.g. `x$17`.
+    /// As identifiers in source code cannot contain `$`,
+    /// fresh symbols also do not clash with symbols written by the user.
+    pub fn fresh(&self, prefix: &[u8]) -> Symbol
+    {
+        loop {
+            let number = self.next_fresh.get();
+            self.next_fresh.set(number + 1);
+
+            let mut name = prefix.to_vec();
+            name.push(b'$');
+            name.extend_from_slice(number.to_string().as_bytes());
+
+            if !self.symbols.borrow().contains(&name[..]) {
+                break self.get(&name);
+            }
+        }
+    }
+
+    /// Evict symbols that are referenced only by the table.
+    ///
+    /// Returns the number of symbols that were evicted.
+    pub fn collect_garbage(&self) -> usize
+    {
+        let mut symbols = self.symbols.borrow_mut();
+        let before = symbols.len();
+        symbols.retain(|entry| !entry.is_garbage());
+        let after = symbols.len();
+        self.collect_at.set(MIN_COLLECT_AT.max(2 * after));
+        before - after
+    }
+
+    /// The number of symbols in the table.
+    pub fn len(&self) -> usize
+    {
+        self.symbols.borrow().len()
+    }
+
+    /// Whether the table has no symbols.
+    pub fn is_empty(&self) -> bool
+    {
+        self.symbols.borrow().is_empty()
+    }
+
+    /// Iterate over the symbols in the table, in order of their IDs.
+    ///
+    /// The iterator is a snapshot of the table;
+    /// symbols created during iteration are not included.
+    pub fn iter(&self) -> vec::IntoIter<Symbol>
+    {
+        let symbols = self.symbols.borrow();
+        let mut symbols: Vec<Symbol> =
+            symbols.iter()
+            .map(|entry| entry.inner.clone())
+            .collect();
+        symbols.sort_unstable_by_key(Symbol::id);
+        symbols.into_iter()
+    }
+}
+
+impl IntoIterator for &Symbols
+{
+    type Item = Symbol;
+    type IntoIter = vec::IntoIter<Symbol>;
+
+    fn into_iter(self) -> Self::IntoIter
+    {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn get_returns_same_symbol()
+    {
+        let symbols = Symbols::new();
+        let a = symbols.get(b"a");
+        assert_eq!(symbols.get(b"a"), a);
+        assert_ne!(symbols.get(b"b"), a);
+    }
+
+    #[test]
+    fn collect_garbage_evicts_unreferenced()
+    {
+        let symbols = Symbols::new();
+        let a = symbols.get(b"a");
+        let b = symbols.get(b"b").term();
+        symbols.get(b"c");
+        assert_eq!(symbols.collect_garbage(), 1);
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols.get(b"a"), a);
+        assert!(b.eq_symbol(&symbols.get(b"b")));
+        drop(a);
+        assert_eq!(symbols.collect_garbage(), 1);
+        assert_eq!(symbols.len(), 1);
+    }
+
+    #[test]
+    fn get_collects_garbage_automatically()
+    {
+        let symbols = Symbols::new();
+        for i in 0 .. 10 * MIN_COLLECT_AT {
+            symbols.get(i.to_string().as_bytes());
+        }
+        assert!(symbols.len() <= MIN_COLLECT_AT);
+    }
+
+    #[test]
+    fn ids_are_sequential()
+    {
+        let symbols = Symbols::new();
+        let b = symbols.get(b"b");
+        let a = symbols.get(b"a");
+        assert_eq!(symbols.get(b"b").id(), 0);
+        assert_eq!(a.id(), 1);
+        assert!(b < a);
+    }
+
+    #[test]
+    fn iter_is_in_id_order()
+    {
+        let symbols = Symbols::new();
+        let names: [&[u8]; 5] = [b"e", b"b", b"d", b"a", b"c"];
+        let kept: Vec<Symbol> = names.iter().map(|n| symbols.get(n)).collect();
+        let iterated: Vec<Symbol> = symbols.iter().collect();
+        assert_eq!(iterated, kept);
+    }
+
+    #[test]
+    fn fresh_is_new()
+    {
+        let symbols = Symbols::new();
+        let x0 = symbols.get(b"x$0");
+        let fresh = symbols.fresh(b"x");
+        assert_ne!(fresh, x0);
+        assert_eq!(fresh.name(), b"x$1");
+        assert_ne!(symbols.fresh(b"x"), fresh);
+    }
 }
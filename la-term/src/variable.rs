@@ -79,11 +79,27 @@ impl Term
     }
 }
 
+/// Drop the payload of a variable term.
+///
+/// Returns the number of words the payload occupied.
+///
+/// # Safety
+///
+/// The payload must be that of a variable term,
+/// and it must not be used after this call.
+pub(crate) unsafe fn drop_payload(
+    _payload: *mut Payload,
+    _children: &mut Vec<Term>,
+) -> usize
+{
+    1
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // De Bruijn indices
 
 /// A De Bruijn index references a variable.
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct DeBruijn(pub u32);
 
 impl Add<u32> for DeBruijn
@@ -134,6 +150,13 @@ impl DeBruijnCache
         self.bits == Self::UNKNOWN.bits
     }
 
+    /// Whether the De Bruijn cache is known to contain no variables,
+    /// in which case the term has no free variables.
+    pub fn is_empty(self) -> bool
+    {
+        self.bits == Self::EMPTY.bits
+    }
+
     /// Whether the De Bruijn cache contains a given variable.
     ///
     /// If this method returns `Some`, the answer is correct.
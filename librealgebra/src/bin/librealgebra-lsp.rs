@@ -0,0 +1,38 @@
+use la_lsp::Server;
+use la_lsp::run;
+use std::env::var_os;
+use std::io::stdin;
+use std::io::stdout;
+use std::path::PathBuf;
+use std::process::exit;
+
+/// Run the language server on standard input and output.
+///
+/// The reference documentation shown on hover is read from the directory
+/// named by `LIBREALGEBRA_REFERENCE`, which defaults to the one
+/// in the source tree this binary was built from.
+fn main()
+{
+    let reference = var_os("LIBREALGEBRA_REFERENCE").map_or_else(
+        || {
+            [env!("CARGO_MANIFEST_DIR"), "..", "la-reference", "definition"]
+            .iter().collect()
+        },
+        PathBuf::from,
+    );
+
+    let mut server = Server::new();
+    if let Err(error) = server.load_reference(&reference) {
+        eprintln!("{}: {}", reference.display(), error);
+    }
+
+    let stdin = stdin();
+    let stdout = stdout();
+    match run(&mut server, stdin.lock(), stdout.lock()) {
+        Ok(status) => exit(status),
+        Err(error) => {
+            eprintln!("{}", error);
+            exit(1);
+        },
+    }
+}
@@ -1,48 +1,383 @@
-use la_parse::Logos;
+use la_parse::FormatOptions;
+use la_parse::Lexer;
+use la_parse::MathematicaNames;
+use la_parse::Notations;
+use la_parse::Partial;
 use la_parse::Scope;
-use la_parse::Token;
+use la_parse::Statement;
+use la_parse::format;
+use la_parse::parse_content_mathml;
+use la_parse::parse_end;
+use la_parse::parse_latex;
+use la_parse::parse_mathematica;
+use la_parse::parse_partial;
+use la_parse::parse_program;
+use la_parse::parse_srepr;
 use la_parse::parse_term;
+use la_parse::render_content_mathml;
+use la_parse::render_full_form;
+use la_parse::render_input_form;
+use la_parse::render_latex;
+use la_parse::render_presentation_mathml;
+use la_parse::render_srepr;
+use la_parse::render_term;
 use la_simplify::Constants;
 use la_simplify::Context;
 use la_simplify::Session;
 use la_simplify::Warner;
 use la_simplify::builtins::Builtins;
 use la_simplify::simplify;
+use la_term::Term;
 use la_term::symbol::Symbols;
 use std::cell::Cell;
+use std::env::args;
+use std::fs;
+use std::io::BufRead;
 use std::io::Read;
 use std::io::stdin;
+use std::process::exit;
 use std::sync::atomic::AtomicBool;
 
 fn main()
 {
-    let stdin = stdin();
-    let mut stdin = stdin.lock();
-    let mut input = String::new();
-    stdin.read_to_string(&mut input).unwrap();
+    let arguments: Vec<String> = args().skip(1).collect();
+    match arguments.first().map(String::as_str) {
+        None => repl(),
+        Some("fmt") => exit(fmt(&arguments[1 ..])),
+        Some(other) => {
+            eprintln!("Unknown subcommand: {}", other);
+            exit(2);
+        },
+    }
+}
+
+/// Implementation of `librealgebra fmt [--check] [FILE...]`.
+///
+/// Formats the given files in place, or standard input to standard output
+/// if no files are given. With `--check`, files are not written,
+/// and the exit status is nonzero if any file is not formatted.
+/// Returns the exit status.
+fn fmt(arguments: &[String]) -> i32
+{
+    let check = arguments.iter().any(|a| a == "--check");
+    let paths: Vec<&String> =
+        arguments.iter().filter(|a| *a != "--check").collect();
+    let options = FormatOptions::default();
+
+    if paths.is_empty() {
+        let mut source = String::new();
+        stdin().read_to_string(&mut source).unwrap();
+        return match format(&source, options) {
+            Ok(formatted) if check => (formatted != source) as i32,
+            Ok(formatted) => { print!("{}", formatted); 0 },
+            Err(error) => { eprint!("{}", error.render(&source)); 1 },
+        };
+    }
 
-    let session = Session::new();
+    let mut status = 0;
+    for path in paths {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => {
+                eprintln!("{}: {}", path, error);
+                status = 1;
+                continue;
+            },
+        };
+        match format(&source, options) {
+            Ok(formatted) if formatted == source =>
+                (),
+            Ok(_) if check => {
+                println!("{} is not formatted", path);
+                status = 1;
+            },
+            Ok(formatted) => if let Err(error) = fs::write(path, formatted) {
+                eprintln!("{}: {}", path, error);
+                status = 1;
+            },
+            Err(error) => {
+                eprint!("{}: {}", path, error.render(&source));
+                status = 1;
+            },
+        }
+    }
+    status
+}
+
+/// Read and execute source code and commands from standard input.
+fn repl()
+{
     let symbols = Symbols::new();
     let constants = Constants::new(&symbols);
     let builtins = Builtins::new(&constants);
-    let warner = StderrWarner;
-
-    let scope = Scope::new(None, []);
-    let mut lexer = Token::lexer(&input).peekable();
-    let term = parse_term(&symbols, &scope, &mut lexer).unwrap();
-
-    let context = Context{
-        recursion_limit: Cell::new(16),
-        stop_requested: &AtomicBool::new(false),
-        builtins: &builtins,
-        constants: &constants,
-        session: &session,
-        symbols: &symbols,
-        warner: &warner,
+    let mut interpreter = Interpreter{
+        symbols,
+        constants,
+        builtins,
+        session: Session::new(),
+        notations: Notations::new(),
+        stop_requested: AtomicBool::new(false),
     };
 
-    let term = simplify(&context, term);
-    println!("{:#?}", term);
+    // Lines that start with a colon are commands,
+    // unless they continue incomplete source code.
+    // All other lines are source code, which is
+    // executed as soon as it forms a complete program.
+    let stdin = stdin();
+    let mut source = String::new();
+    for line in stdin.lock().lines() {
+        let line = line.unwrap();
+        if source.trim().is_empty() {
+            if let Some(command) = line.strip_prefix(':') {
+                interpreter.run_command(command.trim());
+                continue;
+            }
+        }
+        source.push_str(&line);
+        source.push('\n');
+        if interpreter.run_partial_source(&source) {
+            source.clear();
+        }
+    }
+    interpreter.run_source(&source);
+}
+
+/// State that persists across statements.
+struct Interpreter
+{
+    symbols: Symbols,
+    constants: Constants,
+    builtins: Builtins,
+    session: Session,
+    notations: Notations,
+    stop_requested: AtomicBool,
+}
+
+impl Interpreter
+{
+    /// Like [`run_source`], but do nothing if the program is incomplete.
+    ///
+    /// Returns whether the program was complete,
+    /// in which case it was executed or an error was reported.
+    ///
+    /// [`run_source`]: `Interpreter::run_source`
+    fn run_partial_source(&mut self, source: &str) -> bool
+    {
+        let scope = Scope::new(None, []);
+        let partial =
+            parse_partial(&self.symbols, &scope, &self.notations, source);
+        match partial {
+            Partial::Complete(statements) => {
+                for statement in statements {
+                    self.run_statement(statement);
+                }
+                true
+            },
+            Partial::Incomplete{..} => false,
+            Partial::Invalid(error) => {
+                eprint!("{}", error.render(source));
+                true
+            },
+        }
+    }
+
+    /// Parse a program and execute its statements in order.
+    fn run_source(&mut self, source: &str)
+    {
+        let scope = Scope::new(None, []);
+        let mut lexer = Lexer::with_notations(source, self.notations.clone());
+        let statements = parse_program(&self.symbols, &scope, &mut lexer);
+        let statements = match statements {
+            Ok(statements) => statements,
+            Err(error) => {
+                eprint!("{}", error.render(source));
+                return;
+            },
+        };
+
+        for statement in statements {
+            self.run_statement(statement);
+        }
+    }
+
+    fn run_statement(&mut self, statement: Statement)
+    {
+        match statement {
+            Statement::Definition{name, definition, doc} => {
+                self.session.document(name.clone(), doc);
+                self.session.define(name, definition);
+            },
+            Statement::Term(term) => {
+                let term = self.simplify(term);
+                println!("{}", render_term(&term, &self.notations));
+            },
+            Statement::Notation(notation) =>
+                self.notations.declare(notation),
+        }
+    }
+
+    fn simplify(&self, term: Term) -> Term
+    {
+        let context = Context{
+            recursion_limit: Cell::new(16),
+            recursion_limit_reached: Cell::new(false),
+            stop_requested: &self.stop_requested,
+            builtins: &self.builtins,
+            constants: &self.constants,
+            session: &self.session,
+            symbols: &self.symbols,
+            warner: &StderrWarner,
+        };
+        simplify(&context, term)
+    }
+
+    fn run_command(&self, command: &str)
+    {
+        let (name, argument) = match command.split_once(' ') {
+            Some((name, argument)) => (name, argument.trim()),
+            None => (command, ""),
+        };
+        match name {
+            "export" => self.run_export(argument),
+            "help" => self.print_help(argument),
+            "import" => self.run_import(argument),
+            "memory" => print!("{}", memory_report(&self.symbols)),
+            _ => eprintln!("Unknown command: :{}", command),
+        }
+    }
+
+    /// Parse a term written in another notation, and print it simplified.
+    fn run_import(&self, argument: &str)
+    {
+        let (notation, source) = split_notation(argument);
+        let parse: fn(&Symbols, &Scope, &str) -> la_parse::Result<Term> =
+            match notation {
+                "latex" if !source.is_empty() => parse_latex,
+                "content-mathml" if !source.is_empty() => parse_content_mathml,
+                "mathematica" if !source.is_empty() =>
+                    |symbols, scope, source| {
+                        let names = MathematicaNames::default();
+                        parse_mathematica(symbols, scope, &names, source)
+                    },
+                "sympy" if !source.is_empty() => parse_srepr,
+                _ => {
+                    eprintln!("Usage: :import \
+                               latex|content-mathml|mathematica|sympy \
+                               <source>");
+                    return;
+                },
+            };
+        let scope = Scope::new(None, []);
+        let term = parse(&self.symbols, &scope, source);
+        match term {
+            Ok(term) => {
+                let term = self.simplify(term);
+                println!("{}", render_term(&term, &self.notations));
+            },
+            Err(error) => eprint!("{}", error.render(source)),
+        }
+    }
+
+    /// Simplify a term, and print it in another notation.
+    fn run_export(&self, argument: &str)
+    {
+        let (notation, source) = split_notation(argument);
+        let render: fn(&Term, &Notations) -> String = match notation {
+            "latex" if !source.is_empty() => render_latex,
+            "mathml" if !source.is_empty() => render_presentation_mathml,
+            "content-mathml" if !source.is_empty() =>
+                |term, _| render_content_mathml(term),
+            "fullform" if !source.is_empty() =>
+                |term, _| {
+                    let names = MathematicaNames::default();
+                    render_full_form(term, &names)
+                },
+            "inputform" if !source.is_empty() =>
+                |term, notations| {
+                    let names = MathematicaNames::default();
+                    render_input_form(term, &names, notations)
+                },
+            "sympy" if !source.is_empty() => |term, _| render_srepr(term),
+            _ => {
+                eprintln!("Usage: :export \
+                           latex|mathml|content-mathml|fullform|inputform|\
+                           sympy <term>");
+                return;
+            },
+        };
+        let scope = Scope::new(None, []);
+        let mut lexer = Lexer::with_notations(source, self.notations.clone());
+        let term = parse_term(&self.symbols, &scope, &mut lexer)
+            .and_then(|term| parse_end(&mut lexer).map(|()| term));
+        match term {
+            Ok(term) => {
+                let term = self.simplify(term);
+                println!("{}", render(&term, &self.notations));
+            },
+            Err(error) => eprint!("{}", error.render(source)),
+        }
+    }
+
+    /// Print the help text of a user-defined symbol.
+    fn print_help(&self, name: &str)
+    {
+        if name.is_empty() {
+            eprintln!("Usage: :help <symbol>");
+            return;
+        }
+        let symbol = self.symbols.get(name.as_bytes());
+        match self.session.documentation(&symbol) {
+            Some(documentation) => println!("{}", documentation),
+            None if self.session.definition(&symbol).is_some() =>
+                println!("{} is not documented.", name),
+            None => println!("{} is not defined.", name),
+        }
+    }
+}
+
+/// Split the argument of `:import` or `:export`
+/// into the name of the notation and the source code.
+fn split_notation(argument: &str) -> (&str, &str)
+{
+    match argument.split_once(' ') {
+        Some((notation, source)) => (notation, source.trim()),
+        None => (argument, ""),
+    }
+}
+
+/// The output of `:memory`.
+#[cfg(feature = "statistics")]
+fn memory_report(symbols: &Symbols) -> String
+{
+    use la_term::object::Kind;
+    use std::fmt::Write;
+
+    let statistics = la_term::statistics::snapshot(symbols);
+    let mut report = String::from("Live terms:\n");
+    let mut line = |label: &str, value: usize| {
+        writeln!(report, "{:<14} {:>10}", label, value).unwrap();
+    };
+    for kind in Kind::ALL {
+        line(&format!("  {:?}", kind), statistics.live_terms(kind));
+    }
+    line("  Total", statistics.live_terms_total());
+    line("Live bytes:", statistics.live_bytes);
+    line("Peak bytes:", statistics.peak_bytes);
+    line("Total bytes:", statistics.total_bytes);
+    line("Normal forms:", statistics.normal_form_marks);
+    line("Symbols:", statistics.symbols);
+    report
+}
+
+/// The output of `:memory`.
+#[cfg(not(feature = "statistics"))]
+fn memory_report(symbols: &Symbols) -> String
+{
+    format!(
+        "{:<14} {:>10}\n\
+         Term statistics require the `statistics` feature.\n",
+        "Symbols:", symbols.len(),
+    )
 }
 
 struct StderrWarner;
@@ -50,3 +385,34 @@ struct StderrWarner;
 impl Warner for StderrWarner
 {
 }
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn memory_report_counts_symbols()
+    {
+        let symbols = Symbols::new();
+        let _symbol = symbols.get(b"x");
+        let report = memory_report(&symbols);
+        let expected = format!("{:<14} {:>10}\n", "Symbols:", symbols.len());
+        assert!(report.contains(&expected), "{}", report);
+    }
+
+    #[cfg(feature = "statistics")]
+    #[test]
+    fn memory_report_counts_terms()
+    {
+        let symbols = Symbols::new();
+        let _term = Term::integer_i32(0);
+        let report = memory_report(&symbols);
+        let total = report.lines().find(|line| line.starts_with("  Total"));
+        let total: usize = total.unwrap()[14 ..].trim().parse().unwrap();
+        assert!(total >= 1, "{}", report);
+        assert!(report.starts_with("Live terms:\n"), "{}", report);
+        assert!(report.contains("\n  Integer "), "{}", report);
+        assert!(report.contains("Normal forms:"), "{}", report);
+    }
+}
@@ -5,4 +5,6 @@ set -o xtrace
 
 cargo build
 cargo test
+cargo test --package la-term --features statistics
+cargo test --package librealgebra --features statistics
 cargo doc